@@ -0,0 +1,476 @@
+//! A minimal allocator interface for running structures in arenas or fixed
+//! memory pools, together with `AllocVec`, a growable array that allocates
+//! through it.
+
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+/// A source of raw memory.
+///
+/// # Safety
+///
+/// Blocks returned by `allocate` and `grow` must be valid for reads and
+/// writes of `layout.size()` bytes, aligned to `layout.align()`, and must not
+/// overlap any other live block until they are passed to `deallocate`.
+pub unsafe trait Allocator {
+    /// Allocate a block for `layout`, which never has size zero. Returns
+    /// `None` if the memory is exhausted.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this allocator with `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Resize a block to `new` bytes, keeping its contents. On failure the
+    /// old block stays valid.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this allocator with `old`, and
+    /// `new` must have the same alignment and must not be smaller.
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        let block = self.allocate(new)?;
+        // SAFETY: both blocks are valid for `old.size()` bytes and distinct
+        unsafe {
+            ptr::copy_nonoverlapping(ptr.as_ptr(), block.as_ptr(), old.size());
+            self.deallocate(ptr, old);
+        }
+        Some(block)
+    }
+}
+
+// SAFETY: forwards to `A`
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { (**self).deallocate(ptr, layout) }
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        unsafe { (**self).grow(ptr, old, new) }
+    }
+}
+
+/// The global heap, as used by `Vec` and `Box`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Global;
+
+// SAFETY: the global allocator upholds the contract
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        // SAFETY: callers never pass zero-sized layouts
+        NonNull::new(unsafe { alloc::alloc::alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { alloc::alloc::dealloc(ptr.as_ptr(), layout) }
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { alloc::alloc::realloc(ptr.as_ptr(), old, new.size()) })
+    }
+}
+
+/// Hands out memory from a fixed buffer by bumping an offset.
+///
+/// Memory is only reclaimed when the most recent block is freed or when the
+/// allocator is reset, which makes it a good fit for short-lived structures
+/// and for systems without a heap. Use it through a shared reference, e.g.
+/// `Heap::new_in(&bump)`.
+pub struct BumpAllocator<'a> {
+    start: NonNull<u8>,
+    size: usize,
+    offset: Cell<usize>,
+    // offset of the most recent block, which can be freed or grown in place
+    last: Cell<usize>,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+impl<'a> BumpAllocator<'a> {
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        BumpAllocator {
+            start: NonNull::new(buffer.as_mut_ptr().cast()).unwrap(),
+            size: buffer.len(),
+            offset: Cell::new(0),
+            last: Cell::new(0),
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Number of bytes handed out, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.offset.get()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+
+    /// Make the whole buffer available again. Requires `&mut self`, so no
+    /// block can still be in use.
+    pub fn reset(&mut self) {
+        self.offset.set(0);
+        self.last.set(0);
+    }
+
+    fn block_at(&self, offset: usize) -> NonNull<u8> {
+        // SAFETY: `offset` lies within the buffer
+        unsafe { self.start.add(offset) }
+    }
+}
+
+// SAFETY: blocks are carved from disjoint parts of the buffer, which outlives
+// the allocator; a block is only handed out again after it was freed
+unsafe impl Allocator for BumpAllocator<'_> {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let addr = self.start.as_ptr() as usize + self.offset.get();
+        let padding = addr.wrapping_neg() & (layout.align() - 1);
+        let begin = self.offset.get().checked_add(padding)?;
+        let end = begin.checked_add(layout.size())?;
+        if end > self.size {
+            return None;
+        }
+        self.offset.set(end);
+        self.last.set(begin);
+        Some(self.block_at(begin))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if ptr == self.block_at(self.last.get())
+            && self.last.get() + layout.size() == self.offset.get()
+        {
+            self.offset.set(self.last.get());
+        }
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        let last = self.last.get();
+        if ptr == self.block_at(last) && last + old.size() == self.offset.get() {
+            let end = last.checked_add(new.size())?;
+            if end > self.size {
+                return None;
+            }
+            self.offset.set(end);
+            return Some(ptr);
+        }
+        let block = self.allocate(new)?;
+        // SAFETY: both blocks are valid for `old.size()` bytes and distinct
+        unsafe { ptr::copy_nonoverlapping(ptr.as_ptr(), block.as_ptr(), old.size()) };
+        Some(block)
+    }
+}
+
+impl fmt::Debug for BumpAllocator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BumpAllocator")
+            .field("used", &self.used())
+            .field("capacity", &self.size)
+            .finish()
+    }
+}
+
+/// A growable array like `Vec` whose buffer comes from an `Allocator`.
+pub struct AllocVec<T, A: Allocator = Global> {
+    ptr: NonNull<T>,
+    cap: usize,
+    len: usize,
+    alloc: A,
+    _items: PhantomData<T>,
+}
+
+// SAFETY: `AllocVec` owns its items like `Vec` does
+unsafe impl<T: Send, A: Allocator + Send> Send for AllocVec<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for AllocVec<T, A> {}
+
+impl<T> AllocVec<T> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    /// Take over the buffer of a `Vec` without copying.
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        AllocVec {
+            // SAFETY: a `Vec`'s pointer is never null
+            ptr: unsafe { NonNull::new_unchecked(vec.as_mut_ptr()) },
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                vec.capacity()
+            },
+            len: vec.len(),
+            alloc: Global,
+            _items: PhantomData,
+        }
+    }
+
+    /// Hand the buffer over to a `Vec` without copying.
+    pub fn into_vec(self) -> Vec<T> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: the buffer was allocated by the global allocator with
+        // `Layout::array::<T>(cap)`, exactly like `Vec` does
+        unsafe { Vec::from_raw_parts(this.ptr.as_ptr(), this.len, this.cap) }
+    }
+}
+
+impl<T, A: Allocator> AllocVec<T, A> {
+    pub fn new_in(alloc: A) -> Self {
+        AllocVec {
+            ptr: NonNull::dangling(),
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            len: 0,
+            alloc,
+            _items: PhantomData,
+        }
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Panics if the allocator is out of memory.
+    pub fn push(&mut self, item: T) {
+        if self.try_push(item).is_err() {
+            panic!("allocation failed");
+        }
+    }
+
+    /// Like `push`, but hands the item back if the allocator is out of memory.
+    pub fn try_push(&mut self, item: T) -> Result<(), T> {
+        if self.len == self.cap && !self.grow() {
+            return Err(item);
+        }
+        // SAFETY: `len < cap`, so the slot is inside the buffer
+        unsafe { self.ptr.add(self.len).write(item) };
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the slot held an initialized item that is no longer counted
+        Some(unsafe { self.ptr.add(self.len).read() })
+    }
+
+    /// Remove the item at `index` and put the last item in its place.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        let last = self.len - 1;
+        self.swap(index, last);
+        self.pop().unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        let items: *mut [T] = &mut **self;
+        self.len = 0;
+        // SAFETY: the items are no longer reachable through `self`
+        unsafe { ptr::drop_in_place(items) };
+    }
+
+    fn grow(&mut self) -> bool {
+        let new_cap = (self.cap * 2).max(4);
+        let Ok(new) = Layout::array::<T>(new_cap) else {
+            return false;
+        };
+        let block = if self.cap == 0 {
+            self.alloc.allocate(new)
+        } else {
+            let old = Layout::array::<T>(self.cap).unwrap();
+            // SAFETY: the buffer was allocated with `old` and `new` is larger
+            unsafe { self.alloc.grow(self.ptr.cast(), old, new) }
+        };
+        match block {
+            Some(block) => {
+                self.ptr = block.cast();
+                self.cap = new_cap;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T, A: Allocator> Drop for AllocVec<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+        if self.cap != 0 && mem::size_of::<T>() != 0 {
+            let layout = Layout::array::<T>(self.cap).unwrap();
+            // SAFETY: the buffer was allocated with this layout
+            unsafe { self.alloc.deallocate(self.ptr.cast(), layout) };
+        }
+    }
+}
+
+impl<T, A: Allocator> Deref for AllocVec<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first `len` slots are initialized
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: Allocator> DerefMut for AllocVec<T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: the first `len` slots are initialized
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for AllocVec<T, A> {
+    fn clone(&self) -> Self {
+        let mut copy = AllocVec::new_in(self.alloc.clone());
+        for item in self.iter() {
+            copy.push(item.clone());
+        }
+        copy
+    }
+}
+
+impl<T> Default for AllocVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for AllocVec<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for AllocVec<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for AllocVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = AllocVec::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T> From<Vec<T>> for AllocVec<T> {
+    fn from(vec: Vec<T>) -> Self {
+        AllocVec::from_vec(vec)
+    }
+}
+
+impl<T> From<AllocVec<T>> for Vec<T> {
+    fn from(vec: AllocVec<T>) -> Self {
+        vec.into_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn global_round_trip_through_vec() {
+        let mut v = AllocVec::from_vec(vec![1, 2, 3]);
+        v.push(4);
+        assert_eq!(v.swap_remove(0), 1);
+        assert_eq!(&*v, &[4, 2, 3]);
+        assert_eq!(v.into_vec(), vec![4, 2, 3]);
+
+        let mut units = AllocVec::new();
+        for _ in 0..1000 {
+            units.push(());
+        }
+        assert_eq!(units.into_vec().len(), 1000);
+    }
+
+    #[test]
+    fn bump_allocator_runs_out() {
+        let mut buffer = [MaybeUninit::uninit(); 64];
+        let bump = BumpAllocator::new(&mut buffer);
+        let mut v = AllocVec::new_in(&bump);
+        for i in 0..16u32 {
+            v.push(i);
+        }
+        assert_eq!(bump.used(), 64);
+        assert_eq!(v.try_push(16), Err(16));
+        assert_eq!(v.len(), 16);
+        assert_eq!(v.iter().sum::<u32>(), 120);
+    }
+
+    #[test]
+    fn bump_allocator_grows_last_block_in_place() {
+        let mut buffer = [MaybeUninit::uninit(); 256];
+        let bump = BumpAllocator::new(&mut buffer);
+        let mut v = AllocVec::new_in(&bump);
+        v.extend(0..40u16);
+        assert_eq!(bump.used(), 128);
+
+        let mut w = AllocVec::new_in(&bump);
+        w.push(1u64);
+        drop(w);
+        drop(v);
+        assert!(bump.used() <= 128);
+    }
+
+    #[test]
+    fn bump_allocator_respects_alignment() {
+        let mut buffer = [MaybeUninit::uninit(); 128];
+        let mut bump = BumpAllocator::new(&mut buffer);
+        let a = bump.allocate(Layout::new::<u8>()).unwrap();
+        let b = bump.allocate(Layout::new::<u64>()).unwrap();
+        assert_eq!(b.as_ptr() as usize % 8, 0);
+        assert!(b.as_ptr() as usize > a.as_ptr() as usize);
+        bump.reset();
+        assert_eq!(bump.used(), 0);
+    }
+
+    #[test]
+    fn items_are_dropped() {
+        let marker = Rc::new(());
+        let mut buffer = [MaybeUninit::uninit(); 512];
+        let bump = BumpAllocator::new(&mut buffer);
+        let mut v = AllocVec::new_in(&bump);
+        for _ in 0..10 {
+            v.push(marker.clone());
+        }
+        let copy = v.clone();
+        drop(v.pop());
+        assert_eq!(Rc::strong_count(&marker), 20);
+        drop(v);
+        drop(copy);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+}
@@ -0,0 +1,125 @@
+//! `proptest::arbitrary::Arbitrary` implementations, enabled by the
+//! `arbitrary` feature.
+//!
+//! Every structure is generated from a random `Vec` of its items and built
+//! through its public API, so generated values always satisfy the
+//! structure's invariants. Shrinking works on that `Vec` and rebuilds the
+//! structure after each step, which keeps the invariants intact as well.
+
+use crate::binary_search_tree::BinarySearchTree;
+use crate::heap::Heap;
+use crate::interval_map::IntervalMap;
+use crate::range_set::RangeSet;
+use crate::sorted_vec::{SortedVec, SortedVecMap};
+use crate::unrolled_list::UnrolledList;
+use core::fmt::Debug;
+use core::ops::Range;
+use proptest::arbitrary::{any_with, Arbitrary};
+use proptest::collection::{vec, SizeRange, VecStrategy};
+use proptest::strategy::{Map, Strategy};
+
+/// Strategy that generates a `Vec<T>` and converts it into `C`.
+pub type FromVec<T, C> = Map<VecStrategy<<T as Arbitrary>::Strategy>, fn(Vec<T>) -> C>;
+
+fn from_vec<T: Arbitrary, C: Debug>(
+    (size, params): (SizeRange, T::Parameters),
+    build: fn(Vec<T>) -> C,
+) -> FromVec<T, C> {
+    vec(any_with::<T>(params), size).prop_map(build)
+}
+
+macro_rules! arbitrary_from_vec {
+    ($([$($bounds:tt)*] $target:ty, $item:ty, $build:expr;)*) => {$(
+        impl<$($bounds)*> Arbitrary for $target {
+            type Parameters = (SizeRange, <$item as Arbitrary>::Parameters);
+            type Strategy = FromVec<$item, Self>;
+
+            fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+                from_vec(args, $build)
+            }
+        }
+    )*};
+}
+
+arbitrary_from_vec! {
+    [T: Arbitrary + PartialOrd] Heap<T>, T, Heap::from_vec;
+    [T: Arbitrary + Ord + Clone] BinarySearchTree<T>, T, |items| {
+        items.into_iter().fold(BinarySearchTree::new(), |tree, x| tree.insert(x))
+    };
+    [T: Arbitrary + Ord] SortedVec<T>, T, SortedVec::from_unsorted;
+    [K: Arbitrary + Ord, V: Arbitrary] SortedVecMap<K, V>, (K, V), SortedVecMap::from_unsorted;
+    [T: Arbitrary] UnrolledList<T>, T, |items| items.into_iter().collect();
+    [K: Arbitrary + Ord + Clone, V: Arbitrary + Clone + Eq] IntervalMap<K, V>, (Range<K>, V),
+        |items| items.into_iter().collect();
+    [K: Arbitrary + Ord + Clone] RangeSet<K>, Range<K>, |items| items.into_iter().collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::arbitrary::any;
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+
+    fn in_order(tree: &BinarySearchTree<i32>, out: &mut Vec<i32>) {
+        if let Some(item) = tree.item() {
+            in_order(tree.left().unwrap(), out);
+            out.push(*item);
+            in_order(tree.right().unwrap(), out);
+        }
+    }
+
+    fn is_heap(items: &[i32]) -> bool {
+        (1..items.len()).all(|i| items[(i - 1) / 2] >= items[i])
+    }
+
+    fn is_sorted_set(items: &[i32]) -> bool {
+        items.windows(2).all(|w| w[0] < w[1])
+    }
+
+    proptest! {
+        #[test]
+        fn generated_heaps_are_heaps(heap in any::<Heap<i32>>()) {
+            prop_assert!(is_heap(&heap.into_vec()));
+        }
+
+        #[test]
+        fn generated_trees_are_ordered(tree in any::<BinarySearchTree<i32>>()) {
+            let mut items = vec![];
+            in_order(&tree, &mut items);
+            prop_assert!(is_sorted_set(&items));
+        }
+
+        #[test]
+        fn generated_range_sets_are_disjoint(set in any::<RangeSet<u8>>()) {
+            let ranges: Vec<_> = set.iter().collect();
+            prop_assert!(ranges.windows(2).all(|w| w[0].end < w[1].start));
+        }
+    }
+
+    #[test]
+    fn shrinking_preserves_invariants() {
+        let mut runner = TestRunner::deterministic();
+        for _ in 0..20 {
+            let mut tree = any::<Heap<i32>>().new_tree(&mut runner).unwrap();
+            while tree.simplify() {
+                assert!(is_heap(&tree.current().into_vec()));
+            }
+            let mut tree = any::<SortedVec<i32>>().new_tree(&mut runner).unwrap();
+            while tree.simplify() {
+                assert!(is_sorted_set(tree.current().as_slice()));
+            }
+        }
+    }
+
+    #[test]
+    fn size_parameter_bounds_the_structure() {
+        let mut runner = TestRunner::deterministic();
+        let strategy = any_with::<UnrolledList<u8>>(((3..=5).into(), ()));
+        for _ in 0..20 {
+            let list = strategy.new_tree(&mut runner).unwrap().current();
+            assert!((3..=5).contains(&list.len()));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Zero-copy archiving with rkyv, enabled by the `rkyv` feature.
+//!
+//! `Heap`, `SortedVec` and `BitVec` archive as their backing buffers. The
+//! archived forms are read in place from the serialized bytes, for example
+//! a memory-mapped file, and keep the read-only queries of the originals.
+//! Validating an archive with `rkyv::access` checks that it is memory safe
+//! to read, not that a heap is in heap order or a sorted vec is sorted;
+//! deserializing restores those invariants.
+
+use crate::allocator::Allocator;
+use crate::bit_vec::BitVec;
+use crate::compare::Compare;
+use crate::heap::Heap;
+use crate::sorted_vec::SortedVec;
+use alloc::vec::Vec;
+use rkyv::bytecheck::CheckBytes;
+use rkyv::munge::munge;
+use rkyv::rancor::Fallible;
+use rkyv::ser::{Allocator as SerAllocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Archived, Deserialize, Place, Portable, Serialize};
+
+const WORD_BITS: usize = 64;
+
+/// An archived `Heap`: its items in heap order.
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(transparent)]
+pub struct ArchivedHeap<T> {
+    items: ArchivedVec<T>,
+}
+
+impl<T> ArchivedHeap<T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The top item under the comparator the heap was built with.
+    pub fn peek(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    /// The items in heap order.
+    pub fn as_slice(&self) -> &[T] {
+        self.items.as_slice()
+    }
+}
+
+impl<T: Archive, C: Compare<T>, A: Allocator> Archive for Heap<T, C, A> {
+    type Archived = ArchivedHeap<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: VecResolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedHeap { items } = out);
+        ArchivedVec::resolve_from_slice(self.as_slice(), resolver, items);
+    }
+}
+
+impl<T, C, A, S> Serialize<S> for Heap<T, C, A>
+where
+    T: Serialize<S>,
+    C: Compare<T>,
+    A: Allocator,
+    S: Fallible + SerAllocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+/// Deserializes with the default comparator, restoring heap order if the
+/// archive was not in it.
+impl<T, C, D> Deserialize<Heap<T, C>, D> for ArchivedHeap<T::Archived>
+where
+    T: Archive,
+    C: Compare<T> + Default,
+    ArchivedVec<T::Archived>: Deserialize<Vec<T>, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<Heap<T, C>, D::Error> {
+        let items = self.items.deserialize(deserializer)?;
+        Ok(Heap::from_vec_with(items, C::default()))
+    }
+}
+
+/// An archived `SortedVec`: its items in ascending order.
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(transparent)]
+pub struct ArchivedSortedVec<T> {
+    items: ArchivedVec<T>,
+}
+
+impl<T> ArchivedSortedVec<T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Binary search for `key`, which may be a native value compared with
+    /// the archived items.
+    pub fn contains<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        T: PartialOrd<Q>,
+    {
+        let i = self.rank(key);
+        self.items.get(i).is_some_and(|item| item == key)
+    }
+
+    /// The number of items less than `key`.
+    pub fn rank<Q: ?Sized>(&self, key: &Q) -> usize
+    where
+        T: PartialOrd<Q>,
+    {
+        self.items.partition_point(|item| item < key)
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        self.items.as_slice()
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+}
+
+impl<T: Archive + Ord> Archive for SortedVec<T> {
+    type Archived = ArchivedSortedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: VecResolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedSortedVec { items } = out);
+        ArchivedVec::resolve_from_slice(self.as_slice(), resolver, items);
+    }
+}
+
+impl<T, S> Serialize<S> for SortedVec<T>
+where
+    T: Serialize<S> + Ord,
+    S: Fallible + SerAllocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+/// Deserializes in O(n) for a sorted archive; an unsorted one is sorted
+/// and deduplicated.
+impl<T, D> Deserialize<SortedVec<T>, D> for ArchivedSortedVec<T::Archived>
+where
+    T: Archive + Ord,
+    ArchivedVec<T::Archived>: Deserialize<Vec<T>, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<SortedVec<T>, D::Error> {
+        let items = self.items.deserialize(deserializer)?;
+        Ok(SortedVec::from_unsorted(items))
+    }
+}
+
+/// An archived `BitVec`.
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(C)]
+pub struct ArchivedBitVec {
+    words: ArchivedVec<Archived<u64>>,
+    len: Archived<u64>,
+}
+
+impl ArchivedBitVec {
+    pub fn len(&self) -> usize {
+        self.len.to_native() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.word(index / WORD_BITS) & (1 << (index % WORD_BITS)) != 0)
+    }
+
+    pub fn count_ones(&self) -> usize {
+        (0..self.len().div_ceil(WORD_BITS))
+            .map(|i| self.word(i).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len()).map(move |i| self.get(i).unwrap())
+    }
+
+    /// The `i`th word with the bits past the end cleared. Missing words
+    /// read as zeros.
+    fn word(&self, i: usize) -> u64 {
+        let word = self.words.get(i).map_or(0, |w| w.to_native());
+        match self.len() - i * WORD_BITS {
+            n if n < WORD_BITS => word & ((1 << n) - 1),
+            _ => word,
+        }
+    }
+}
+
+/// Resolver for an archived `BitVec`.
+pub struct BitVecResolver {
+    words: VecResolver,
+}
+
+impl Archive for BitVec {
+    type Archived = ArchivedBitVec;
+    type Resolver = BitVecResolver;
+
+    fn resolve(&self, resolver: BitVecResolver, out: Place<ArchivedBitVec>) {
+        munge!(let ArchivedBitVec { words, len } = out);
+        ArchivedVec::resolve_from_slice(self.as_words(), resolver.words, words);
+        (self.len() as u64).resolve((), len);
+    }
+}
+
+impl<S> Serialize<S> for BitVec
+where
+    S: Fallible + SerAllocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<BitVecResolver, S::Error> {
+        Ok(BitVecResolver {
+            words: ArchivedVec::serialize_from_slice(self.as_words(), serializer)?,
+        })
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<BitVec, D> for ArchivedBitVec {
+    fn deserialize(&self, _: &mut D) -> Result<BitVec, D::Error> {
+        let words = self.words.iter().map(|w| w.to_native()).collect();
+        Ok(BitVec::from_words(words, self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rkyv::rancor::Error;
+
+    #[test]
+    fn heap_is_read_in_place() {
+        let heap = Heap::from_vec(vec![3, 9, 1, 7, 5]);
+        let bytes = rkyv::to_bytes::<Error>(&heap).unwrap();
+        let archived = rkyv::access::<ArchivedHeap<Archived<i32>>, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 5);
+        assert_eq!(archived.peek().map(|x| x.to_native()), Some(9));
+        let mut copy: Heap<i32> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(copy.pop(), Some(9));
+        assert_eq!(copy.into_vec().len(), 4);
+    }
+
+    #[test]
+    fn sorted_vec_is_searched_in_place() {
+        let words = SortedVec::from_unsorted(
+            vec!["pear", "apple", "fig"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        );
+        let bytes = rkyv::to_bytes::<Error>(&words).unwrap();
+        let archived = rkyv::access::<Archived<SortedVec<String>>, Error>(&bytes).unwrap();
+        assert!(archived.contains("fig"));
+        assert!(!archived.contains("kiwi"));
+        assert_eq!(archived.rank("banana"), 1);
+        let copy: SortedVec<String> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(copy, words);
+    }
+
+    #[test]
+    fn bit_vec_round_trip() {
+        let mut bits = BitVec::from_elem(70, false);
+        for i in [0, 3, 64, 69] {
+            bits.set(i, true);
+        }
+        let bytes = rkyv::to_bytes::<Error>(&bits).unwrap();
+        let archived = rkyv::access::<ArchivedBitVec, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 70);
+        assert_eq!(archived.count_ones(), 4);
+        assert_eq!(archived.get(64), Some(true));
+        assert_eq!(archived.get(70), None);
+        assert!(archived.iter().eq(bits.iter()));
+        let copy: BitVec = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(copy, bits);
+    }
+
+    #[test]
+    fn corrupt_archives_are_rejected() {
+        let bytes = rkyv::to_bytes::<Error>(&SortedVec::from_unsorted(vec![1u32, 2, 3])).unwrap();
+        let truncated = &bytes[..bytes.len() - 4];
+        assert!(rkyv::access::<ArchivedSortedVec<Archived<u32>>, Error>(truncated).is_err());
+    }
+}
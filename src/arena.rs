@@ -0,0 +1,192 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+
+const INITIAL_CAPACITY: usize = 8;
+
+/// A typed arena: values are allocated one by one and all freed together when
+/// the arena is dropped or reset.
+///
+/// Values are stored in chunks that are never reallocated, so references
+/// handed out by `alloc` stay valid for as long as the arena is borrowed.
+pub struct Arena<T> {
+    chunks: RefCell<Chunks<T>>,
+}
+
+struct Chunks<T> {
+    current: Vec<T>,
+    full: Vec<Vec<T>>,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(INITIAL_CAPACITY)
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        Arena {
+            chunks: RefCell::new(Chunks {
+                current: Vec::with_capacity(n.max(1)),
+                full: vec![],
+            }),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.current.len() == chunks.current.capacity() {
+            chunks.grow();
+        }
+        let index = chunks.current.len();
+        chunks.current.push(value);
+        // SAFETY: the chunk is never reallocated while the arena is shared,
+        // and each slot is handed out exactly once. Going through the raw
+        // pointer avoids creating a reference to the other slots.
+        unsafe { &mut *chunks.current.as_mut_ptr().add(index) }
+    }
+
+    /// Allocate all values of an iterator in one contiguous slice.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_extend(&self, values: impl IntoIterator<Item = T>) -> &mut [T] {
+        let values: Vec<T> = values.into_iter().collect();
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.current.capacity() - chunks.current.len() < values.len() {
+            chunks.grow_to(values.len());
+        }
+        let start = chunks.current.len();
+        let n = values.len();
+        chunks.current.extend(values);
+        // SAFETY: as in `alloc`
+        unsafe { core::slice::from_raw_parts_mut(chunks.current.as_mut_ptr().add(start), n) }
+    }
+
+    pub fn len(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.current.len() + chunks.full.iter().map(Vec::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop all values but keep the largest chunk for reuse.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        chunks.full.clear();
+        chunks.current.clear();
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let chunks = self.chunks.get_mut();
+        chunks
+            .full
+            .iter_mut()
+            .flat_map(|c| c.iter_mut())
+            .chain(chunks.current.iter_mut())
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        let chunks = self.chunks.into_inner();
+        let mut result: Vec<T> = chunks.full.into_iter().flatten().collect();
+        result.extend(chunks.current);
+        result
+    }
+}
+
+impl<T> Chunks<T> {
+    fn grow(&mut self) {
+        self.grow_to(1);
+    }
+
+    fn grow_to(&mut self, min: usize) {
+        let capacity = (self.current.capacity() * 2).max(min);
+        let old = core::mem::replace(&mut self.current, Vec::with_capacity(capacity));
+        if !old.is_empty() {
+            self.full.push(old);
+        }
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Arena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Arena").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn references_stay_valid_across_growth() {
+        let arena = Arena::with_capacity(2);
+        let refs: Vec<&mut usize> = (0..100).map(|i| arena.alloc(i)).collect();
+        for (i, r) in refs.iter().enumerate() {
+            assert_eq!(**r, i);
+        }
+        assert_eq!(arena.len(), 100);
+    }
+
+    #[test]
+    fn allocated_values_can_be_mutated() {
+        let arena = Arena::new();
+        let a = arena.alloc(String::from("a"));
+        let b = arena.alloc(String::from("b"));
+        a.push('!');
+        b.push('?');
+        assert_eq!(a, "a!");
+        assert_eq!(b, "b?");
+    }
+
+    #[test]
+    fn alloc_extend_is_contiguous() {
+        let arena = Arena::with_capacity(4);
+        arena.alloc(0);
+        let slice = arena.alloc_extend(1..=10);
+        assert_eq!(slice, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(arena.into_vec(), (0..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cyclic_references_between_nodes() {
+        struct Node<'a> {
+            value: i32,
+            next: Cell<Option<&'a Node<'a>>>,
+        }
+        let arena = Arena::new();
+        let a = arena.alloc(Node {
+            value: 1,
+            next: Cell::new(None),
+        });
+        let b = arena.alloc(Node {
+            value: 2,
+            next: Cell::new(Some(a)),
+        });
+        a.next.set(Some(b));
+        assert_eq!(a.next.get().unwrap().next.get().unwrap().value, 1);
+    }
+
+    #[test]
+    fn reset_drops_values() {
+        let counter = std::rc::Rc::new(());
+        let mut arena = Arena::new();
+        for _ in 0..20 {
+            arena.alloc(counter.clone());
+        }
+        assert_eq!(std::rc::Rc::strong_count(&counter), 21);
+        arena.reset();
+        assert!(arena.is_empty());
+        assert_eq!(std::rc::Rc::strong_count(&counter), 1);
+        arena.alloc(counter.clone());
+        assert_eq!(arena.iter_mut().count(), 1);
+    }
+}
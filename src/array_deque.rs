@@ -0,0 +1,266 @@
+//! A double-ended queue with a fixed capacity, stored inline without
+//! allocating. Pushing onto a full deque hands the item back.
+
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::{Index, IndexMut};
+
+/// A ring buffer of up to `N` items stored in an array.
+pub struct ArrayDeque<T, const N: usize> {
+    head: usize,
+    len: usize,
+    items: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> ArrayDeque<T, N> {
+    pub const fn new() -> Self {
+        ArrayDeque {
+            head: 0,
+            len: 0,
+            items: [const { MaybeUninit::uninit() }; N],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Hands the item back if the deque is full.
+    pub fn push_back(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        let slot = self.slot(self.len);
+        self.items[slot].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Hands the item back if the deque is full.
+    pub fn push_front(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        self.head = (self.head + N - 1) % N;
+        self.items[self.head].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: the front item is initialized and no longer counted
+        let value = unsafe { self.items[self.head].assume_init_read() };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the back item is initialized and no longer counted
+        Some(unsafe { self.items[self.slot(self.len)].assume_init_read() })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        // SAFETY: the first `len` items from `head` are initialized
+        Some(unsafe { self.items[self.slot(index)].assume_init_ref() })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let slot = self.slot(index);
+        // SAFETY: as in `get`
+        Some(unsafe { self.items[slot].assume_init_mut() })
+    }
+
+    /// The items in order, as the part up to the end of the array and the
+    /// part that wrapped around to its start.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let first = self.len.min(N - self.head);
+        let items = self.items.as_ptr() as *const T;
+        // SAFETY: both ranges hold initialized items
+        unsafe {
+            (
+                core::slice::from_raw_parts(items.add(self.head), first),
+                core::slice::from_raw_parts(items, self.len - first),
+            )
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        let (a, b) = self.as_slices();
+        (0..self.len).map(move |i| if i < a.len() { &a[i] } else { &b[i - a.len()] })
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_back().is_some() {}
+        self.head = 0;
+    }
+
+    fn slot(&self, index: usize) -> usize {
+        let slot = self.head + index;
+        if slot >= N {
+            slot - N
+        } else {
+            slot
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayDeque<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Default for ArrayDeque<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Index<usize> for ArrayDeque<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for ArrayDeque<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayDeque<T, N> {
+    fn clone(&self) -> Self {
+        let mut d = ArrayDeque::new();
+        for item in self.iter() {
+            // cannot fail, the clone has the same capacity
+            let _ = d.push_back(item.clone());
+        }
+        d
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayDeque<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<ArrayDeque<T, M>>
+    for ArrayDeque<T, N>
+{
+    fn eq(&self, other: &ArrayDeque<T, M>) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayDeque<T, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    #[test]
+    fn both_ends() {
+        let mut d = ArrayDeque::<i32, 4>::new();
+        d.push_back(1).unwrap();
+        d.push_front(0).unwrap();
+        d.push_back(2).unwrap();
+        assert_eq!(d.push_front(-1), Ok(()));
+        assert_eq!(d.push_back(3), Err(3));
+        assert_eq!(d.push_front(-2), Err(-2));
+        assert_eq!((d.front(), d.back()), (Some(&-1), Some(&2)));
+        assert_eq!(d[1], 0);
+        d[1] = 10;
+        assert_eq!(format!("{d:?}"), "[-1, 10, 1, 2]");
+        assert_eq!(d.pop_front(), Some(-1));
+        assert_eq!(d.pop_back(), Some(2));
+        assert_eq!(d.len(), 2);
+    }
+
+    #[test]
+    fn wraps_around_like_vec_deque() {
+        let mut d = ArrayDeque::<u32, 5>::new();
+        let mut model = VecDeque::new();
+        for i in 0..200u32 {
+            match i * 7 % 5 {
+                0 | 1 => {
+                    if d.push_back(i).is_ok() {
+                        model.push_back(i);
+                    }
+                }
+                2 => {
+                    if d.push_front(i).is_ok() {
+                        model.push_front(i);
+                    }
+                }
+                3 => assert_eq!(d.pop_front(), model.pop_front()),
+                _ => assert_eq!(d.pop_back(), model.pop_back()),
+            }
+            assert!(d.iter().eq(model.iter()));
+            assert!(d.iter().rev().eq(model.iter().rev()));
+        }
+        let (a, b) = d.as_slices();
+        assert_eq!(a.len() + b.len(), d.len());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut d = ArrayDeque::<u8, 0>::new();
+        assert_eq!(d.push_front(1), Err(1));
+        assert_eq!(d.pop_back(), None);
+        assert_eq!(d.as_slices(), (&[][..], &[][..]));
+    }
+
+    #[test]
+    fn drops_remaining_items() {
+        let counter = Rc::new(());
+        let mut d = ArrayDeque::<_, 3>::new();
+        for _ in 0..3 {
+            d.push_front(counter.clone()).unwrap();
+        }
+        d.pop_back();
+        let copy = d.clone();
+        assert_eq!(Rc::strong_count(&counter), 5);
+        drop(d);
+        drop(copy);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! A binary heap with a fixed capacity, stored inline without allocating.
+
+use crate::array_vec::ArrayVec;
+use crate::compare::{Compare, Natural};
+use crate::traits::PriorityQueue;
+use core::fmt;
+
+/// A binary max-heap of up to `N` items. Like `Heap`, the comparator `C`
+/// decides which item is the largest.
+#[derive(Clone)]
+pub struct ArrayHeap<T, const N: usize, C = Natural> {
+    data: ArrayVec<T, N>,
+    cmp: C,
+}
+
+impl<T: PartialOrd, const N: usize> ArrayHeap<T, N> {
+    pub const fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, const N: usize, C: Compare<T>> ArrayHeap<T, N, C> {
+    pub const fn with_comparator(cmp: C) -> Self {
+        ArrayHeap {
+            data: ArrayVec::new(),
+            cmp,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.data.is_full()
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The items in heap order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Hands the item back if the heap is full.
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        self.data.push(item)?;
+        self.trickle_up(self.data.len() - 1);
+        Ok(())
+    }
+
+    /// Push `item` if there is room; otherwise keep whichever of `item` and
+    /// the current top is smaller by the comparator, and return the other.
+    /// The heap then holds the `N` smallest items seen, or with a `Reverse`
+    /// comparator the `N` largest.
+    pub fn push_or_replace(&mut self, item: T) -> Option<T> {
+        let item = match self.data.push(item) {
+            Ok(()) => {
+                self.trickle_up(self.data.len() - 1);
+                return None;
+            }
+            Err(item) => item,
+        };
+        if N == 0 || !self.cmp.less(&item, &self.data[0]) {
+            return Some(item);
+        }
+        let top = core::mem::replace(&mut self.data[0], item);
+        self.trickle_down(0);
+        Some(top)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let value = self.data.swap_remove(0);
+        self.trickle_down(0);
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    fn trickle_up(&mut self, mut node: usize) {
+        while node > 0 {
+            let p = (node - 1) / 2;
+            if !self.cmp.less(&self.data[p], &self.data[node]) {
+                break;
+            }
+            self.data.swap(p, node);
+            node = p;
+        }
+    }
+
+    fn trickle_down(&mut self, mut node: usize) {
+        loop {
+            let (l, r) = (node * 2 + 1, node * 2 + 2);
+            if l >= self.data.len() {
+                return;
+            }
+            let child = if r >= self.data.len() || self.cmp.greater(&self.data[l], &self.data[r]) {
+                l
+            } else {
+                r
+            };
+            if !self.cmp.less(&self.data[node], &self.data[child]) {
+                return;
+            }
+            self.data.swap(child, node);
+            node = child;
+        }
+    }
+}
+
+impl<T: PartialOrd, const N: usize> Default for ArrayHeap<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize, C> fmt::Debug for ArrayHeap<T, N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArrayHeap")
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// `push` panics when the heap is full.
+impl<T, const N: usize, C: Compare<T>> PriorityQueue<T> for ArrayHeap<T, N, C> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push(&mut self, item: T) {
+        if ArrayHeap::push(self, item).is_err() {
+            panic!("capacity exceeded");
+        }
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.peek()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Reverse;
+    #[cfg(feature = "alloc")]
+    use crate::heap::Heap;
+
+    #[test]
+    fn pops_largest_first() {
+        let mut heap = ArrayHeap::<u32, 8>::new();
+        for x in [4, 9, 1, 7, 3] {
+            heap.push(x).unwrap();
+        }
+        assert_eq!(heap.peek(), Some(&9));
+        let mut popped = Vec::new();
+        while let Some(x) = heap.pop() {
+            popped.push(x);
+        }
+        assert_eq!(popped, vec![9, 7, 4, 3, 1]);
+    }
+
+    #[test]
+    fn rejects_items_when_full() {
+        let mut heap = ArrayHeap::<u32, 2>::new();
+        assert_eq!(heap.push(1), Ok(()));
+        assert_eq!(heap.push(2), Ok(()));
+        assert!(heap.is_full());
+        assert_eq!(heap.push(3), Err(3));
+        assert_eq!(heap.len(), 2);
+    }
+
+    #[test]
+    fn keeps_the_largest_items() {
+        let mut top = ArrayHeap::<u32, 3, Reverse>::with_comparator(Reverse::default());
+        for x in [5, 1, 8, 3, 9, 2, 7] {
+            top.push_or_replace(x);
+        }
+        let mut kept = top.as_slice().to_vec();
+        kept.sort();
+        assert_eq!(kept, vec![7, 8, 9]);
+        assert_eq!(top.push_or_replace(0), Some(0));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn matches_heap() {
+        let mut fixed = ArrayHeap::<u64, 64>::new();
+        let mut heap = Heap::new();
+        let mut x: u64 = 3;
+        for _ in 0..1000 {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1);
+            if x >> 62 == 0 {
+                assert_eq!(fixed.pop(), heap.pop());
+            } else if fixed.push(x >> 40).is_ok() {
+                heap.push(x >> 40);
+            }
+            assert_eq!(fixed.peek(), heap.peek());
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! A vector with a fixed capacity, stored inline without allocating.
+//!
+//! This module only uses `core`, like `array_deque` and `array_heap`. `push`
+//! and `insert` hand the item back instead of growing when the vector is
+//! full.
+
+use core::fmt;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// A vector of up to `N` items stored in an array.
+pub struct ArrayVec<T, const N: usize> {
+    len: usize,
+    items: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    pub const fn new() -> Self {
+        ArrayVec {
+            len: 0,
+            items: [const { MaybeUninit::uninit() }; N],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: the first `len` items are initialized
+        unsafe { core::slice::from_raw_parts(self.items.as_ptr() as *const T, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: the first `len` items are initialized
+        unsafe { core::slice::from_raw_parts_mut(self.items.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Hands the item back if the vector is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        self.items[self.len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the item was initialized and is now outside `len`
+        Some(unsafe { self.items[self.len].assume_init_read() })
+    }
+
+    /// Hands the item back if the vector is full. Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+        assert!(index <= self.len, "insertion index out of bounds");
+        if self.len == N {
+            return Err(value);
+        }
+        // SAFETY: shifts the initialized items `index..len` up by one slot,
+        // which exists because `len < N`
+        unsafe {
+            let p = self.items.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), self.len - index);
+            (*p).write(value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+        // SAFETY: moves the item out and closes the gap
+        unsafe {
+            let p = self.items.as_mut_ptr().add(index);
+            let value = (*p).assume_init_read();
+            ptr::copy(p.add(1), p, self.len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    /// Remove the item at `index` and put the last item in its place.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop().unwrap()
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        while self.len > new_len {
+            self.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: drops exactly the initialized items
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+    fn clone(&self) -> Self {
+        ArrayVec::try_from(self.as_slice()).expect("clones have the same capacity")
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<ArrayVec<T, M>> for ArrayVec<T, N> {
+    fn eq(&self, other: &ArrayVec<T, M>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+impl<T: Clone, const N: usize> TryFrom<&[T]> for ArrayVec<T, N> {
+    type Error = crate::errors::DataStructureError;
+
+    /// Fails with `CapacityExceeded` if the slice is longer than `N`.
+    fn try_from(items: &[T]) -> Result<Self, Self::Error> {
+        if items.len() > N {
+            return Err(crate::errors::DataStructureError::CapacityExceeded);
+        }
+        let mut v = ArrayVec::new();
+        for item in items {
+            v.push(item.clone())
+                .map_err(|_| crate::errors::DataStructureError::CapacityExceeded)?;
+        }
+        Ok(v)
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for ArrayVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> IntoIter<T, N> {
+        let this = ManuallyDrop::new(self);
+        IntoIter {
+            next: 0,
+            end: this.len,
+            // SAFETY: `this` is not dropped, so the items are moved exactly once
+            items: unsafe { ptr::read(&this.items) },
+        }
+    }
+}
+
+/// Iterator returned by `ArrayVec::into_iter`.
+pub struct IntoIter<T, const N: usize> {
+    next: usize,
+    end: usize,
+    items: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        self.next += 1;
+        // SAFETY: items in `next..end` are initialized and not yet moved out
+        Some(unsafe { self.items[self.next - 1].assume_init_read() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.end - self.next;
+        (n, Some(n))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        self.end -= 1;
+        // SAFETY: as in `next`
+        Some(unsafe { self.items[self.end].assume_init_read() })
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        for item in &mut self.items[self.next..self.end] {
+            // SAFETY: the remaining items are initialized
+            unsafe { item.assume_init_drop() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::DataStructureError;
+    use std::rc::Rc;
+
+    #[test]
+    fn fills_up_and_rejects_items() {
+        let mut v = ArrayVec::<u8, 3>::new();
+        assert_eq!(v.push(1), Ok(()));
+        assert_eq!(v.insert(0, 0), Ok(()));
+        assert_eq!(v.push(2), Ok(()));
+        assert!(v.is_full());
+        assert_eq!(v.push(3), Err(3));
+        assert_eq!(v.insert(1, 9), Err(9));
+        assert_eq!(v.as_slice(), &[0, 1, 2]);
+        assert_eq!(v.remove(0), 0);
+        assert_eq!(v.swap_remove(0), 1);
+        assert_eq!(v.pop(), Some(2));
+        assert_eq!(v.pop(), None);
+    }
+
+    #[test]
+    fn drops_every_item_once() {
+        let counter = Rc::new(());
+        let mut v = ArrayVec::<_, 8>::new();
+        for _ in 0..6 {
+            v.push(counter.clone()).unwrap();
+        }
+        v.truncate(4);
+        assert_eq!(Rc::strong_count(&counter), 5);
+        let mut iter = v.clone().into_iter();
+        iter.next();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&counter), 5);
+        drop(v);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn conversions_and_iteration() {
+        let v = ArrayVec::<i32, 4>::try_from(&[3, 1, 2][..]).unwrap();
+        assert_eq!(format!("{v:?}"), "[3, 1, 2]");
+        assert_eq!(v.iter().max(), Some(&3));
+        assert_eq!(
+            v.clone().into_iter().rev().collect::<Vec<_>>(),
+            vec![2, 1, 3]
+        );
+        assert_eq!(v, ArrayVec::<i32, 8>::try_from(&[3, 1, 2][..]).unwrap());
+        assert_eq!(
+            ArrayVec::<i32, 2>::try_from(&[1, 2, 3][..]),
+            Err(DataStructureError::CapacityExceeded)
+        );
+    }
+}
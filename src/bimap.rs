@@ -0,0 +1,240 @@
+use alloc::collections::BTreeMap;
+use core::borrow::Borrow;
+use core::fmt;
+
+/// A one-to-one mapping between left and right values with lookups in both
+/// directions.
+#[derive(Clone)]
+pub struct BiMap<L, R> {
+    left_to_right: BTreeMap<L, R>,
+    right_to_left: BTreeMap<R, L>,
+}
+
+/// Pairs that were removed by `BiMap::insert` to keep the mapping one-to-one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Nothing was removed.
+    Neither,
+    /// The left value was already mapped; this is its old pair.
+    Left(L, R),
+    /// The right value was already mapped; this is its old pair.
+    Right(L, R),
+    /// Exactly this pair was already present.
+    Pair(L, R),
+    /// Both values were mapped, to different partners. The old pairs of the
+    /// left and of the right value, respectively.
+    Both((L, R), (L, R)),
+}
+
+impl<L, R> BiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    pub fn new() -> Self {
+        BiMap {
+            left_to_right: BTreeMap::new(),
+            right_to_left: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.left_to_right.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left_to_right.is_empty()
+    }
+
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right.get(left)
+    }
+
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left.get(right)
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right.contains_key(left)
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left.contains_key(right)
+    }
+
+    /// Insert a pair, removing any existing pairs that contain either value.
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let old_left = self.remove_by_left(&left);
+        let old_right = self.remove_by_right(&right);
+        self.insert_unchecked(left, right);
+        match (old_left, old_right) {
+            (None, None) => Overwritten::Neither,
+            (Some((l, r)), None) => {
+                if r == *self.get_by_left(&l).unwrap() {
+                    Overwritten::Pair(l, r)
+                } else {
+                    Overwritten::Left(l, r)
+                }
+            }
+            (None, Some((l, r))) => Overwritten::Right(l, r),
+            (Some(a), Some(b)) => Overwritten::Both(a, b),
+        }
+    }
+
+    /// Insert a pair only if neither value is mapped yet; otherwise hand the
+    /// pair back.
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        if self.contains_left(&left) || self.contains_right(&right) {
+            return Err((left, right));
+        }
+        self.insert_unchecked(left, right);
+        Ok(())
+    }
+
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (l, r) = self.left_to_right.remove_entry(left)?;
+        self.right_to_left.remove(&r);
+        Some((l, r))
+    }
+
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (r, l) = self.right_to_left.remove_entry(right)?;
+        self.left_to_right.remove(&l);
+        Some((l, r))
+    }
+
+    pub fn clear(&mut self) {
+        self.left_to_right.clear();
+        self.right_to_left.clear();
+    }
+
+    /// The pairs in ascending order of their left values.
+    pub fn iter(&self) -> impl Iterator<Item = (&L, &R)> {
+        self.left_to_right.iter()
+    }
+
+    pub fn left_values(&self) -> impl Iterator<Item = &L> {
+        self.left_to_right.keys()
+    }
+
+    pub fn right_values(&self) -> impl Iterator<Item = &R> {
+        self.right_to_left.keys()
+    }
+
+    fn insert_unchecked(&mut self, left: L, right: R) {
+        self.left_to_right.insert(left.clone(), right.clone());
+        self.right_to_left.insert(right, left);
+    }
+}
+
+impl<L, R> Default for BiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: PartialEq, R: PartialEq> PartialEq for BiMap<L, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.left_to_right == other.left_to_right
+    }
+}
+
+impl<L: Eq, R: Eq> Eq for BiMap<L, R> {}
+
+impl<L: fmt::Debug, R: fmt::Debug> fmt::Debug for BiMap<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.left_to_right.iter()).finish()
+    }
+}
+
+impl<L, R> FromIterator<(L, R)> for BiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (L, R)>>(iter: I) -> Self {
+        let mut map = BiMap::new();
+        for (l, r) in iter {
+            map.insert(l, r);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_in_both_directions() {
+        let mut map = BiMap::new();
+        assert_eq!(map.insert(1, "one"), Overwritten::Neither);
+        assert_eq!(map.insert(2, "two"), Overwritten::Neither);
+        assert_eq!(map.get_by_left(&1), Some(&"one"));
+        assert_eq!(map.get_by_right("two"), Some(&2));
+        assert!(map.contains_left(&2));
+        assert!(!map.contains_right("three"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn insert_reports_overwritten_pairs() {
+        let mut map: BiMap<_, _> = [(1, 'a'), (2, 'b')].into_iter().collect();
+        assert_eq!(map.insert(1, 'a'), Overwritten::Pair(1, 'a'));
+        assert_eq!(map.insert(1, 'c'), Overwritten::Left(1, 'a'));
+        assert_eq!(map.insert(3, 'c'), Overwritten::Right(1, 'c'));
+        assert_eq!(map.insert(2, 'c'), Overwritten::Both((2, 'b'), (3, 'c')));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_by_left(&2), Some(&'c'));
+        assert_eq!(map.get_by_right(&'b'), None);
+    }
+
+    #[test]
+    fn insert_no_overwrite_rejects_conflicts() {
+        let mut map = BiMap::new();
+        assert_eq!(map.insert_no_overwrite("x", 1), Ok(()));
+        assert_eq!(map.insert_no_overwrite("x", 2), Err(("x", 2)));
+        assert_eq!(map.insert_no_overwrite("y", 1), Err(("y", 1)));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn removal_keeps_both_sides_in_sync() {
+        let mut map: BiMap<String, u32> = [("a".to_string(), 1), ("b".to_string(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(map.remove_by_left("a"), Some(("a".to_string(), 1)));
+        assert!(!map.contains_right(&1));
+        assert_eq!(map.remove_by_right(&2), Some(("b".to_string(), 2)));
+        assert!(!map.contains_left("b"));
+        assert!(map.is_empty());
+    }
+}
@@ -1,144 +1,713 @@
-use std::cmp::Ordering;
-use std::rc::Rc;
+use crate::compare::{ByKey, Compare, Natural};
+use crate::errors::DataStructureError;
+use crate::frozen_map::{FrozenMap, FrozenSet};
+use crate::memory::{
+    compare_nodes, rc_bytes, MemoryFootprint, MemoryStats, SharedFootprint, SharingStats,
+};
+use crate::traits::{Map, Set};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Index, IndexMut};
 
-#[derive(Debug, PartialEq)]
-pub enum BinarySearchTree<T> {
-    Empty,
-    Node(Rc<Node<T>>),
+/// A persistent binary search tree.
+///
+/// Items are ordered by the comparator `C`. Every subtree keeps a clone of
+/// it, so comparators should be cheap to clone; the built-in ones and
+/// closures without captures take no space at all.
+pub struct BinarySearchTree<T, C = Natural> {
+    root: Option<Rc<Node<T, C>>>,
+    cmp: C,
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Node<T> {
+#[derive(Clone)]
+struct Node<T, C> {
     item: T,
-    left: BinarySearchTree<T>,
-    right: BinarySearchTree<T>,
+    left: BinarySearchTree<T, C>,
+    right: BinarySearchTree<T, C>,
 }
 
 impl<T: Clone + PartialOrd> BinarySearchTree<T> {
     pub fn new() -> Self {
-        BinarySearchTree::Empty
+        Self::with_comparator(Natural)
     }
+}
+
+impl<T: Clone, K: PartialOrd, F: Fn(&T) -> K + Clone> BinarySearchTree<T, ByKey<F>> {
+    /// An empty tree ordered by the key that `f` extracts from each item.
+    pub fn by_key(f: F) -> Self {
+        Self::with_comparator(ByKey(f))
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone + Default> BinarySearchTree<T, C> {
     pub fn make_leaf(item: T) -> Self {
-        BinarySearchTree::Node(Rc::new(Node {
-            item,
-            left: BinarySearchTree::Empty,
-            right: BinarySearchTree::Empty,
-        }))
+        Self::default().leaf(item)
+    }
+
+    /// Build a balanced tree from sorted, unique items in O(n).
+    ///
+    /// Fails with `InvalidStructure` if the items are out of order or
+    /// repeated, and with `IncomparableItems` if two neighbours cannot be
+    /// compared.
+    pub fn from_sorted(items: Vec<T>) -> Result<Self, DataStructureError> {
+        Self::from_sorted_with(items, C::default())
     }
+}
 
+impl<T, C> BinarySearchTree<T, C> {
     pub fn is_empty(&self) -> bool {
-        match self {
-            BinarySearchTree::Empty => true,
-            _ => false,
+        self.root.is_none()
+    }
+
+    /// Number of items. Takes O(n) because the tree does not cache it.
+    pub fn len(&self) -> usize {
+        match &self.root {
+            None => 0,
+            Some(node) => 1 + node.left.len() + node.right.len(),
         }
     }
 
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
     pub fn item(&self) -> Option<&T> {
-        match self {
-            BinarySearchTree::Empty => None,
-            BinarySearchTree::Node(item) => Some(&item.item),
-        }
+        self.root.as_ref().map(|node| &node.item)
     }
 
     pub fn left(&self) -> Option<&Self> {
-        match self {
-            BinarySearchTree::Empty => None,
-            BinarySearchTree::Node(item) => Some(&item.left),
-        }
+        self.root.as_ref().map(|node| &node.left)
     }
 
     pub fn right(&self) -> Option<&Self> {
-        match self {
-            BinarySearchTree::Empty => None,
-            BinarySearchTree::Node(item) => Some(&item.right),
+        self.root.as_ref().map(|node| &node.right)
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BinarySearchTree<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BinarySearchTree { root: None, cmp }
+    }
+
+    /// Like `from_sorted`, with items ordered by `cmp`.
+    pub fn from_sorted_with(items: Vec<T>, cmp: C) -> Result<Self, DataStructureError> {
+        for pair in items.windows(2) {
+            match cmp.compare(&pair[0], &pair[1]) {
+                None => return Err(DataStructureError::IncomparableItems),
+                Some(Ordering::Less) => {}
+                Some(_) => return Err(DataStructureError::InvalidStructure),
+            }
         }
+        Ok(Self::with_comparator(cmp).balanced(&items))
     }
 
-    pub fn find<K>(&self, key: &K) -> Option<&T>
+    /// A copy of the tree that shares no nodes with `self`.
+    pub fn clone_deep(&self) -> Self {
+        match &self.root {
+            None => self.empty(),
+            Some(node) => self.branch(
+                node.item.clone(),
+                node.left.clone_deep(),
+                node.right.clone_deep(),
+            ),
+        }
+    }
+
+    pub fn find<Q>(&self, key: &Q) -> Option<&T>
     where
-        T: PartialOrd<K>,
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
-        match self {
-            BinarySearchTree::Node(node) => match node.item.partial_cmp(key) {
-                Some(Ordering::Equal) => Some(&node.item),
-                Some(Ordering::Greater) => node.left.find(key),
-                Some(Ordering::Less) => node.right.find(key),
-                None => None,
-            },
-            _ => None,
+        let node = self.root.as_ref()?;
+        match self.cmp.compare(node.item.borrow(), key)? {
+            Ordering::Equal => Some(&node.item),
+            Ordering::Greater => node.left.find(key),
+            Ordering::Less => node.right.find(key),
         }
     }
 
+    /// Panics if `item` cannot be compared with an item in the tree.
     pub fn insert(&self, item: T) -> Self {
-        match self {
-            BinarySearchTree::Empty => BinarySearchTree::make_leaf(item),
-            BinarySearchTree::Node(node) => match item.partial_cmp(&node.item) {
-                None => todo!(),
-                Some(Ordering::Equal) => BinarySearchTree::Node(Rc::new(Node {
-                    item,
-                    left: node.left.clone(),
-                    right: node.right.clone(),
-                })),
-                Some(Ordering::Less) => BinarySearchTree::Node(Rc::new(Node {
-                    item: node.item.clone(),
-                    left: node.left.insert(item),
-                    right: node.right.clone(),
-                })),
-                Some(Ordering::Greater) => BinarySearchTree::Node(Rc::new(Node {
-                    item: node.item.clone(),
-                    left: node.left.clone(),
-                    right: node.right.insert(item),
-                })),
-            },
+        self.try_insert(item).expect("incomparable items")
+    }
+
+    pub fn try_insert(&self, item: T) -> Result<Self, DataStructureError> {
+        let Some(node) = &self.root else {
+            return Ok(self.leaf(item));
+        };
+        match self.cmp.compare(&item, &node.item) {
+            None => Err(DataStructureError::IncomparableItems),
+            Some(Ordering::Equal) => Ok(self.branch(item, node.left.clone(), node.right.clone())),
+            Some(Ordering::Less) => Ok(self.branch(
+                node.item.clone(),
+                node.left.try_insert(item)?,
+                node.right.clone(),
+            )),
+            Some(Ordering::Greater) => Ok(self.branch(
+                node.item.clone(),
+                node.left.clone(),
+                node.right.try_insert(item)?,
+            )),
         }
     }
 
-    pub fn delete<K>(&self, key: &K) -> Option<Self>
+    /// The tree without `key`, or `None` if it does not contain `key`.
+    ///
+    /// Panics if `key` cannot be compared with an item in the tree.
+    pub fn delete<Q>(&self, key: &Q) -> Option<Self>
     where
-        T: PartialOrd<K>,
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
-        match self {
-            BinarySearchTree::Empty => None,
-            BinarySearchTree::Node(node) => match node.item.partial_cmp(key) {
-                None => todo!(),
-                Some(Ordering::Equal) if node.left.is_empty() => Some(node.right.clone()),
-                Some(Ordering::Equal) if node.right.is_empty() => Some(node.left.clone()),
-                Some(Ordering::Equal) => {
-                    let x = self.right().unwrap().smallest().unwrap();
-                    Some(BinarySearchTree::Node(Rc::new(Node {
-                        item: x.clone(),
-                        left: node.left.clone(),
-                        right: node.right.delete(x)?,
-                    })))
-                }
-                Some(Ordering::Less) => Some(BinarySearchTree::Node(Rc::new(Node {
-                    item: node.item.clone(),
-                    left: node.left.clone(),
-                    right: node.right.delete(key)?,
-                }))),
-                Some(Ordering::Greater) => Some(BinarySearchTree::Node(Rc::new(Node {
-                    item: node.item.clone(),
-                    left: node.left.delete(key)?,
-                    right: node.right.clone(),
-                }))),
-            },
+        match self.try_delete(key) {
+            Ok(tree) => Some(tree),
+            Err(DataStructureError::KeyNotFound) => None,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    pub fn try_delete<Q>(&self, key: &Q) -> Result<Self, DataStructureError>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let Some(node) = &self.root else {
+            return Err(DataStructureError::KeyNotFound);
+        };
+        match self.cmp.compare(node.item.borrow(), key) {
+            None => Err(DataStructureError::IncomparableItems),
+            Some(Ordering::Equal) if node.left.is_empty() => Ok(node.right.clone()),
+            Some(Ordering::Equal) if node.right.is_empty() => Ok(node.left.clone()),
+            Some(Ordering::Equal) => {
+                let x = node.right.smallest().unwrap();
+                Ok(self.branch(x.clone(), node.left.clone(), node.right.try_delete::<T>(x)?))
+            }
+            Some(Ordering::Less) => Ok(self.branch(
+                node.item.clone(),
+                node.left.clone(),
+                node.right.try_delete(key)?,
+            )),
+            Some(Ordering::Greater) => Ok(self.branch(
+                node.item.clone(),
+                node.left.try_delete(key)?,
+                node.right.clone(),
+            )),
+        }
+    }
+
+    /// An empty tree with the same comparator.
+    fn empty(&self) -> Self {
+        Self::with_comparator(self.cmp.clone())
+    }
+
+    fn leaf(&self, item: T) -> Self {
+        self.branch(item, self.empty(), self.empty())
+    }
+
+    fn branch(&self, item: T, left: Self, right: Self) -> Self {
+        BinarySearchTree {
+            root: Some(Rc::new(Node { item, left, right })),
+            cmp: self.cmp.clone(),
+        }
+    }
+
+    /// Build a balanced tree from items that are sorted and unique.
+    fn balanced(&self, items: &[T]) -> Self {
+        if items.is_empty() {
+            return self.empty();
         }
+        let mid = items.len() / 2;
+        self.branch(
+            items[mid].clone(),
+            self.balanced(&items[..mid]),
+            self.balanced(&items[mid + 1..]),
+        )
     }
 
     fn smallest(&self) -> Option<&T> {
-        match self {
-            BinarySearchTree::Empty => None,
-            BinarySearchTree::Node(node) if node.left.is_empty() => Some(&node.item),
-            BinarySearchTree::Node(node) => node.left.smallest(),
+        let node = self.root.as_ref()?;
+        node.left.smallest().or(Some(&node.item))
+    }
+}
+
+/// The mutating methods replace `self` with the updated persistent tree.
+impl<T: Clone, C: Compare<T> + Clone> Set<T> for BinarySearchTree<T, C> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        self.find(item).is_some()
+    }
+
+    fn insert(&mut self, item: T) -> bool {
+        let new = !Set::contains(self, &item);
+        *self = BinarySearchTree::insert(self, item);
+        new
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        match self.delete(item) {
+            Some(tree) => {
+                *self = tree;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T, C> BinarySearchTree<T, C> {
+    /// Call `f` on every item in order.
+    fn for_each_in_order(&self, f: &mut impl FnMut(&T)) {
+        if let Some(node) = &self.root {
+            node.left.for_each_in_order(f);
+            f(&node.item);
+            node.right.for_each_in_order(f);
+        }
+    }
+
+    /// Addresses of all nodes, for comparing versions.
+    fn node_addresses(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut stack = vec![self];
+        core::iter::from_fn(move || loop {
+            if let Some(node) = &stack.pop()?.root {
+                stack.push(&node.left);
+                stack.push(&node.right);
+                return Some(Rc::as_ptr(node) as usize);
+            }
+        })
+    }
+}
+
+impl<T, C> MemoryFootprint for BinarySearchTree<T, C> {
+    fn memory_stats(&self) -> MemoryStats {
+        fn depth<T, C>(tree: &BinarySearchTree<T, C>) -> usize {
+            match &tree.root {
+                None => 0,
+                Some(node) => 1 + depth(&node.left).max(depth(&node.right)),
+            }
         }
+        let nodes = self.node_addresses().count();
+        MemoryStats {
+            nodes,
+            heap_bytes: nodes * rc_bytes::<Node<T, C>>(),
+            depth: depth(self),
+            used_slots: nodes,
+            total_slots: nodes,
+        }
+    }
+}
+
+impl<T, C> SharedFootprint for BinarySearchTree<T, C> {
+    fn shares_structure_with(&self, other: &Self) -> SharingStats {
+        compare_nodes(self.node_addresses(), other.node_addresses())
+    }
+}
+
+/// Builds a balanced tree without comparing any items.
+impl<T: Clone + Ord> From<BTreeSet<T>> for BinarySearchTree<T> {
+    fn from(set: BTreeSet<T>) -> Self {
+        let items: Vec<T> = set.into_iter().collect();
+        BinarySearchTree::new().balanced(&items)
+    }
+}
+
+impl<T: Clone + Ord, C> From<&BinarySearchTree<T, C>> for BTreeSet<T> {
+    fn from(tree: &BinarySearchTree<T, C>) -> Self {
+        let mut items = Vec::new();
+        tree.for_each_in_order(&mut |item| items.push(item.clone()));
+        items.into_iter().collect()
+    }
+}
+
+impl<T: Clone + Ord> BinarySearchTree<T> {
+    /// The items as an immutable sorted array, in O(n).
+    pub fn freeze(&self) -> FrozenSet<T> {
+        let mut items = Vec::new();
+        self.for_each_in_order(&mut |item| items.push(item.clone()));
+        FrozenSet::from_sorted(items).expect("tree items are sorted")
     }
 }
 
-impl<T> Clone for BinarySearchTree<T> {
+impl<T, C: Default> Default for BinarySearchTree<T, C> {
+    fn default() -> Self {
+        BinarySearchTree {
+            root: None,
+            cmp: C::default(),
+        }
+    }
+}
+
+impl<T, C: Clone> Clone for BinarySearchTree<T, C> {
     fn clone(&self) -> Self {
-        match self {
-            BinarySearchTree::Empty => BinarySearchTree::Empty,
-            BinarySearchTree::Node(item) => BinarySearchTree::Node(item.clone()),
+        BinarySearchTree {
+            root: self.root.clone(),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for BinarySearchTree<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.root {
+            None => f.write_str("Empty"),
+            Some(node) => f.debug_tuple("Node").field(node).finish(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for Node<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("item", &self.item)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for BinarySearchTree<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (None, None) => true,
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for Node<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item && self.left == other.left && self.right == other.right
+    }
+}
+
+/// A persistent sorted map: a binary search tree of entries ordered by key.
+#[derive(Clone)]
+pub struct TreeMap<K, V> {
+    tree: BinarySearchTree<Entry<K, V>>,
+}
+
+/// A key and its value, compared by the key alone.
+#[derive(Debug, Clone)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K: PartialEq, V> PartialEq for Entry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for Entry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl<K, V> Borrow<K> for Entry<K, V> {
+    fn borrow(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Clone + Ord, V: Clone> TreeMap<K, V> {
+    pub fn new() -> Self {
+        TreeMap {
+            tree: BinarySearchTree::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Number of entries. Takes O(n) because the tree does not cache it.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.lookup(key).map(|entry| &entry.value)
+    }
+
+    /// Mutable access to the value under `key`. Like `insert`, this copies
+    /// the nodes on the path to `key` that are shared with other versions.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.lookup(key)?;
+        let mut tree = &mut self.tree;
+        loop {
+            let Some(node) = &mut tree.root else {
+                return None;
+            };
+            let node = Rc::make_mut(node);
+            tree = match node.item.key.borrow().cmp(key) {
+                Ordering::Equal => return Some(&mut node.item.value),
+                Ordering::Greater => &mut node.left,
+                Ordering::Less => &mut node.right,
+            };
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.lookup(key).is_some()
+    }
+
+    /// The map with `key` set to `value`.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        TreeMap {
+            tree: self.tree.insert(Entry { key, value }),
+        }
+    }
+
+    /// The map without `key`.
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match self.lookup(key) {
+            Some(entry) => TreeMap {
+                tree: self.tree.delete(&entry.key).unwrap(),
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// The entries as an immutable sorted map, in O(n).
+    pub fn freeze(&self) -> FrozenMap<K, V> {
+        let entries = self.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        FrozenMap::from_sorted(entries).expect("tree keys are sorted")
+    }
+
+    /// A balanced map of entries that are sorted by key and unique.
+    pub(crate) fn from_sorted_entries(entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| Entry { key, value })
+            .collect();
+        TreeMap {
+            tree: BinarySearchTree::new().balanced(&entries),
+        }
+    }
+
+    fn lookup<Q>(&self, key: &Q) -> Option<&Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let mut tree = &self.tree;
+        while let Some(node) = &tree.root {
+            tree = match node.item.key.borrow().cmp(key) {
+                Ordering::Equal => return Some(&node.item),
+                Ordering::Greater => &node.left,
+                Ordering::Less => &node.right,
+            };
+        }
+        None
+    }
+}
+
+impl<K, V> TreeMap<K, V> {
+    /// The entries in ascending key order.
+    pub fn iter(&self) -> vec::IntoIter<(&K, &V)> {
+        fn walk<'a, K, V>(tree: &'a BinarySearchTree<Entry<K, V>>, out: &mut Vec<(&'a K, &'a V)>) {
+            if let Some(node) = &tree.root {
+                walk(&node.left, out);
+                out.push((&node.item.key, &node.item.value));
+                walk(&node.right, out);
+            }
+        }
+        let mut entries = vec![];
+        walk(&self.tree, &mut entries);
+        entries.into_iter()
+    }
+}
+
+impl<K: Clone + Ord, V: Clone> Default for TreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The mutating methods replace `self` with the updated persistent map.
+impl<K: Clone + Ord, V: Clone> Map<K, V> for TreeMap<K, V> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.get(&key).cloned();
+        *self = TreeMap::insert(self, key, value);
+        old
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let old = self.get(key).cloned()?;
+        *self = TreeMap::remove(self, key);
+        Some(old)
+    }
+}
+
+impl<K, V, Q> Index<&Q> for TreeMap<K, V>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone,
+    Q: ?Sized + Ord,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found")
+    }
+}
+
+impl<K, V, Q> IndexMut<&Q> for TreeMap<K, V>
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone,
+    Q: ?Sized + Ord,
+{
+    fn index_mut(&mut self, key: &Q) -> &mut V {
+        self.get_mut(key).expect("key not found")
+    }
+}
+
+/// Maps are equal if they hold the same entries, whatever the shape of
+/// their trees.
+impl<K: PartialEq, V: PartialEq> PartialEq for TreeMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+/// Builds a balanced tree without comparing any keys.
+impl<K: Clone + Ord, V: Clone> From<BTreeMap<K, V>> for TreeMap<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        TreeMap::from_sorted_entries(map)
+    }
+}
+
+impl<K: Clone + Ord, V: Clone> From<&TreeMap<K, V>> for BTreeMap<K, V> {
+    fn from(map: &TreeMap<K, V>) -> Self {
+        map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for TreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// One difference between two versions of a tree map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<K, V> {
+    Added(K, V),
+    Removed(K, V),
+    /// The key maps to a different value: old, then new.
+    Modified(K, V, V),
+}
+
+/// The changes that turn `old` into `new`, in ascending key order.
+///
+/// Subtrees that both versions share are skipped without being visited, so
+/// the cost grows with the number of changes times the depth of the trees
+/// rather than with their size.
+pub fn diff<K, V>(old: &TreeMap<K, V>, new: &TreeMap<K, V>) -> vec::IntoIter<Change<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + PartialEq,
+{
+    let mut changes = vec![];
+    diff_trees(&old.tree, &new.tree, &mut changes);
+    changes.into_iter()
+}
+
+type EntryTree<K, V> = BinarySearchTree<Entry<K, V>>;
+
+/// The entries below a key, the entry at the key, and the entries above it.
+type Split<K, V> = (EntryTree<K, V>, Option<Entry<K, V>>, EntryTree<K, V>);
+
+fn diff_trees<K, V>(old: &EntryTree<K, V>, new: &EntryTree<K, V>, changes: &mut Vec<Change<K, V>>)
+where
+    K: Clone + Ord,
+    V: Clone + PartialEq,
+{
+    let Some(node) = &new.root else {
+        old.for_each_in_order(&mut |entry| {
+            changes.push(Change::Removed(entry.key.clone(), entry.value.clone()))
+        });
+        return;
+    };
+    if matches!(&old.root, Some(root) if Rc::ptr_eq(root, node)) {
+        return;
+    }
+    let (left, entry, right) = split(old, &node.item.key);
+    diff_trees(&left, &node.left, changes);
+    match entry {
+        None => changes.push(Change::Added(
+            node.item.key.clone(),
+            node.item.value.clone(),
+        )),
+        Some(old) if old.value != node.item.value => changes.push(Change::Modified(
+            old.key,
+            old.value,
+            node.item.value.clone(),
+        )),
+        Some(_) => {}
+    }
+    diff_trees(&right, &node.right, changes);
+}
+
+/// Split `tree` around `key`. Only the nodes on the path to `key` are
+/// copied; all other subtrees stay shared with `tree`.
+fn split<K: Clone + Ord, V: Clone>(tree: &EntryTree<K, V>, key: &K) -> Split<K, V> {
+    let Some(node) = &tree.root else {
+        return (tree.empty(), None, tree.empty());
+    };
+    match node.item.key.cmp(key) {
+        Ordering::Equal => (
+            node.left.clone(),
+            Some(node.item.clone()),
+            node.right.clone(),
+        ),
+        Ordering::Greater => {
+            let (less, entry, greater) = split(&node.left, key);
+            let greater = tree.branch(node.item.clone(), greater, node.right.clone());
+            (less, entry, greater)
+        }
+        Ordering::Less => {
+            let (less, entry, greater) = split(&node.right, key);
+            let less = tree.branch(node.item.clone(), node.left.clone(), less);
+            (less, entry, greater)
         }
     }
 }
@@ -300,4 +869,166 @@ mod tests {
             .insert(90);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn borrowed_key_lookup() {
+        let bst = BinarySearchTree::new()
+            .insert(String::from("b"))
+            .insert(String::from("a"));
+        assert_eq!(bst.find("a").map(String::as_str), Some("a"));
+        assert_eq!(bst.find("c"), None);
+        let bst = bst.delete("b").unwrap();
+        assert_eq!(bst.item().map(String::as_str), Some("a"));
+        assert!(bst.delete("b").is_none());
+    }
+
+    #[test]
+    fn incomparable_items_are_reported() {
+        let bst = BinarySearchTree::new().insert(1.0).insert(2.0);
+        assert_eq!(
+            bst.try_insert(f64::NAN),
+            Err(DataStructureError::IncomparableItems)
+        );
+        assert_eq!(
+            bst.try_delete(&f64::NAN),
+            Err(DataStructureError::IncomparableItems)
+        );
+        assert_eq!(bst.try_delete(&3.0), Err(DataStructureError::KeyNotFound));
+        assert_eq!(bst.try_delete(&1.0).unwrap().item(), Some(&2.0));
+    }
+
+    #[test]
+    fn tree_map_versions_are_independent() {
+        let old = TreeMap::new().insert(2, 'b').insert(1, 'a');
+        let new = old.insert(2, 'c').remove(&1);
+        assert_eq!(old.get(&2), Some(&'b'));
+        assert_eq!(new.get(&2), Some(&'c'));
+        assert!(!new.contains_key(&1));
+        assert_eq!(old.iter().collect::<Vec<_>>(), vec![(&1, &'a'), (&2, &'b')]);
+        assert_eq!(format!("{new:?}"), "{2: 'c'}");
+        assert_eq!(new.remove(&5), new);
+        assert_ne!(new, TreeMap::new().insert(2, 'b'));
+    }
+
+    #[test]
+    fn tree_map_get_mut_copies_only_the_path() {
+        let old = [8, 4, 12, 2, 6]
+            .into_iter()
+            .fold(TreeMap::new(), |map, k| map.insert(k, k));
+        let mut new = old.clone();
+        new[&6] += 100;
+        *new.get_mut(&2).unwrap() += 100;
+        assert!(new.get_mut(&99).is_none());
+        assert_eq!((old[&6], old[&2]), (6, 2));
+        assert_eq!((new[&6], new[&2], new[&12]), (106, 102, 12));
+        // the right subtree of the root is on neither path
+        let (Some(a), Some(b)) = (
+            &old.tree.right().unwrap().root,
+            &new.tree.right().unwrap().root,
+        ) else {
+            panic!("both maps have a right subtree");
+        };
+        assert!(Rc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn tree_map_borrowed_key_lookup() {
+        let mut map = TreeMap::new()
+            .insert(String::from("b"), 1)
+            .insert(String::from("a"), 2);
+        assert_eq!(map.get("a"), Some(&2));
+        assert!(!map.contains_key("c"));
+        map["b"] += 10;
+        assert_eq!(map.remove("a").get("b"), Some(&11));
+        assert_eq!(map.remove("c"), map);
+    }
+
+    #[test]
+    fn custom_comparator() {
+        use crate::compare::Reverse;
+        let bst: BinarySearchTree<i32, Reverse> = BinarySearchTree::default();
+        let bst = bst.insert(2).insert(1).insert(3);
+        assert_eq!(bst.left().unwrap().item(), Some(&3));
+        assert_eq!(bst.right().unwrap().item(), Some(&1));
+        assert_eq!(bst.find(&1), Some(&1));
+        assert_eq!(bst.delete(&2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn key_extractor_comparator() {
+        let bst = BinarySearchTree::by_key(|s: &&str| s.len());
+        let bst = bst.insert("ccc").insert("a").insert("bb");
+        assert_eq!(bst.find(&"xx"), Some(&"bb"));
+        assert_eq!(bst.insert("zz").find(&"bb"), Some(&"zz"));
+        assert_eq!(bst.delete(&"yyy").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn comparator_with_runtime_state() {
+        let modulus = 10;
+        let by_last_digit = move |a: &u32, b: &u32| (a % modulus).cmp(&(b % modulus));
+        let bst = BinarySearchTree::with_comparator(by_last_digit)
+            .insert(21)
+            .insert(13)
+            .insert(5);
+        assert_eq!(bst.find(&31), Some(&21));
+        assert_eq!(bst.find(&7), None);
+        let items = BinarySearchTree::from_sorted_with(vec![10, 31, 42], by_last_digit).unwrap();
+        assert_eq!(items.find(&2), Some(&42));
+    }
+
+    #[test]
+    fn btree_set_conversions() {
+        let tree = BinarySearchTree::from((0..15).collect::<BTreeSet<_>>());
+        assert_eq!(tree.len(), 15);
+        assert_eq!(tree.memory_stats().depth, 4);
+        assert_eq!(BTreeSet::from(&tree), (0..15).collect());
+        assert!(BinarySearchTree::from(BTreeSet::<u8>::new()).is_empty());
+    }
+
+    #[test]
+    fn btree_map_conversions() {
+        let btree: BTreeMap<_, _> = (0..15).map(|i| (i, i * i)).collect();
+        let map = TreeMap::from(btree.clone());
+        assert_eq!(map.len(), 15);
+        assert_eq!(map.tree.memory_stats().depth, 4);
+        assert_eq!(map[&7], 49);
+        assert_eq!(BTreeMap::from(&map), btree);
+        assert!(TreeMap::from(BTreeMap::<u8, u8>::new()).is_empty());
+    }
+
+    #[test]
+    fn bulk_load_sorted_items() {
+        let tree = BinarySearchTree::<_>::from_sorted((0..1000).collect()).unwrap();
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.memory_stats().depth, 10);
+        assert_eq!(tree.find(&999), Some(&999));
+        assert_eq!(
+            BinarySearchTree::<_>::from_sorted(vec![1, 3, 2]),
+            Err(DataStructureError::InvalidStructure)
+        );
+        assert_eq!(
+            BinarySearchTree::<_>::from_sorted(vec![1.0, f64::NAN]),
+            Err(DataStructureError::IncomparableItems)
+        );
+    }
+
+    #[test]
+    fn tree_map_diff() {
+        let old = TreeMap::from((0..100).map(|i| (i, i)).collect::<BTreeMap<_, _>>());
+        let new = old.insert(5, 500).remove(&50).insert(200, 2).remove(&99);
+        assert_eq!(
+            diff(&old, &new).collect::<Vec<_>>(),
+            vec![
+                Change::Modified(5, 5, 500),
+                Change::Removed(50, 50),
+                Change::Removed(99, 99),
+                Change::Added(200, 2),
+            ]
+        );
+        assert_eq!(diff(&new, &old).count(), 4);
+        assert_eq!(diff(&new, &new.clone()).count(), 0);
+        assert_eq!(diff(&TreeMap::new(), &old).count(), 100);
+        assert_eq!(diff(&old, &TreeMap::new()).count(), 100);
+    }
 }
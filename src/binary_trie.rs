@@ -0,0 +1,404 @@
+//! A bitwise trie over fixed-width integer keys.
+//!
+//! Every node branches on one bit, most significant first, so a key of `B`
+//! bits is found in `B` steps regardless of the number of entries. Besides
+//! full keys the trie stores prefixes (the first `len` bits of a key), which
+//! makes it a routing table: `longest_prefix_match` finds the most specific
+//! prefix covering an address. `max_xor_with` walks towards the opposite
+//! bit wherever a key exists, giving the key that maximizes `key ^ query`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// An unsigned integer that can be used as a trie key.
+pub trait TrieKey: Copy + Eq + fmt::Debug {
+    const BITS: u32;
+
+    fn zero() -> Self;
+
+    /// The bit at `index`, counted from the most significant bit.
+    fn bit(self, index: u32) -> bool;
+
+    /// A copy with the bit at `index` set.
+    fn with_bit(self, index: u32) -> Self;
+
+    /// Keep the first `len` bits and clear the rest.
+    fn truncate(self, len: u32) -> Self;
+}
+
+macro_rules! trie_key {
+    ($($t:ty),*) => {$(
+        impl TrieKey for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            fn zero() -> Self {
+                0
+            }
+
+            fn bit(self, index: u32) -> bool {
+                self >> (Self::BITS - 1 - index) & 1 == 1
+            }
+
+            fn with_bit(self, index: u32) -> Self {
+                self | 1 << (Self::BITS - 1 - index)
+            }
+
+            fn truncate(self, len: u32) -> Self {
+                self.checked_shr(Self::BITS - len)
+                    .and_then(|x| x.checked_shl(Self::BITS - len))
+                    .unwrap_or(0)
+            }
+        }
+    )*};
+}
+
+trie_key!(u8, u16, u32, u64, u128);
+
+/// Index of the root; never anyone's child, so it doubles as "no child".
+const ROOT: usize = 0;
+
+#[derive(Clone)]
+struct Node<V> {
+    children: [usize; 2],
+    value: Option<V>,
+    /// Number of full-length keys in this subtree.
+    keys: usize,
+}
+
+impl<V> Node<V> {
+    fn new() -> Self {
+        Node {
+            children: [ROOT; 2],
+            value: None,
+            keys: 0,
+        }
+    }
+}
+
+/// A map from keys and key prefixes of type `K` to values. With `V = ()`
+/// it is a set of integers.
+#[derive(Clone)]
+pub struct BinaryTrie<K, V = ()> {
+    nodes: Vec<Node<V>>,
+    free: Vec<usize>,
+    len: usize,
+    _key: core::marker::PhantomData<K>,
+}
+
+impl<K: TrieKey, V> BinaryTrie<K, V> {
+    pub fn new() -> Self {
+        BinaryTrie {
+            nodes: vec![Node::new()],
+            free: vec![],
+            len: 0,
+            _key: core::marker::PhantomData,
+        }
+    }
+
+    /// Number of stored keys and prefixes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of nodes in use, including the root.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_prefix(key, K::BITS, value)
+    }
+
+    pub fn get(&self, key: K) -> Option<&V> {
+        self.get_prefix(key, K::BITS)
+    }
+
+    pub fn contains(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        self.remove_prefix(key, K::BITS)
+    }
+
+    /// Store a value for the first `len` bits of `prefix`; the remaining
+    /// bits are ignored. Panics if `len` exceeds the key width.
+    pub fn insert_prefix(&mut self, prefix: K, len: u32, value: V) -> Option<V> {
+        assert!(len <= K::BITS, "prefix longer than the key");
+        let mut path = Vec::with_capacity(len as usize + 1);
+        let mut node = ROOT;
+        path.push(node);
+        for i in 0..len {
+            let side = prefix.bit(i) as usize;
+            node = match self.nodes[node].children[side] {
+                ROOT => {
+                    let child = self.alloc();
+                    self.nodes[node].children[side] = child;
+                    child
+                }
+                child => child,
+            };
+            path.push(node);
+        }
+        let old = self.nodes[node].value.replace(value);
+        if old.is_none() {
+            self.len += 1;
+            if len == K::BITS {
+                for n in path {
+                    self.nodes[n].keys += 1;
+                }
+            }
+        }
+        old
+    }
+
+    pub fn get_prefix(&self, prefix: K, len: u32) -> Option<&V> {
+        if len > K::BITS {
+            return None;
+        }
+        let mut node = ROOT;
+        for i in 0..len {
+            node = self.nodes[node].children[prefix.bit(i) as usize];
+            if node == ROOT {
+                return None;
+            }
+        }
+        self.nodes[node].value.as_ref()
+    }
+
+    /// Remove the value stored for the first `len` bits of `prefix`.
+    pub fn remove_prefix(&mut self, prefix: K, len: u32) -> Option<V> {
+        let path = self.path(prefix, len)?;
+        let old = self.nodes[*path.last().unwrap()].value.take()?;
+        self.len -= 1;
+        if len == K::BITS {
+            for &n in &path {
+                self.nodes[n].keys -= 1;
+            }
+        }
+        // free the nodes that no longer lead anywhere
+        for depth in (1..path.len()).rev() {
+            let node = &self.nodes[path[depth]];
+            if node.value.is_some() || node.children != [ROOT; 2] {
+                break;
+            }
+            let side = prefix.bit(depth as u32 - 1) as usize;
+            self.nodes[path[depth - 1]].children[side] = ROOT;
+            self.free.push(path[depth]);
+        }
+        Some(old)
+    }
+
+    /// The longest stored prefix of `key`, as `(prefix, len, value)`.
+    pub fn longest_prefix_match(&self, key: K) -> Option<(K, u32, &V)> {
+        let mut node = ROOT;
+        let mut best = self.nodes[ROOT].value.as_ref().map(|v| (0, v));
+        for i in 0..K::BITS {
+            node = self.nodes[node].children[key.bit(i) as usize];
+            if node == ROOT {
+                break;
+            }
+            if let Some(v) = &self.nodes[node].value {
+                best = Some((i + 1, v));
+            }
+        }
+        best.map(|(len, v)| (key.truncate(len), len, v))
+    }
+
+    /// The stored full-length key `k` that maximizes `k ^ query`. Prefixes
+    /// are not considered.
+    pub fn max_xor_with(&self, query: K) -> Option<K> {
+        if self.nodes[ROOT].keys == 0 {
+            return None;
+        }
+        let mut node = ROOT;
+        let mut key = K::zero();
+        for i in 0..K::BITS {
+            let want = !query.bit(i) as usize;
+            let children = self.nodes[node].children;
+            let side = if children[want] != ROOT && self.nodes[children[want]].keys > 0 {
+                want
+            } else {
+                1 - want
+            };
+            if side == 1 {
+                key = key.with_bit(i);
+            }
+            node = children[side];
+        }
+        Some(key)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Nodes from the root to the node of the prefix, if it exists.
+    fn path(&self, prefix: K, len: u32) -> Option<Vec<usize>> {
+        if len > K::BITS {
+            return None;
+        }
+        let mut path = Vec::with_capacity(len as usize + 1);
+        let mut node = ROOT;
+        path.push(node);
+        for i in 0..len {
+            node = self.nodes[node].children[prefix.bit(i) as usize];
+            if node == ROOT {
+                return None;
+            }
+            path.push(node);
+        }
+        Some(path)
+    }
+
+    fn alloc(&mut self) -> usize {
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Node::new();
+                i
+            }
+            None => {
+                self.nodes.push(Node::new());
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+impl<K: TrieKey, V> Default for BinaryTrie<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: TrieKey> FromIterator<K> for BinaryTrie<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut trie = Self::new();
+        trie.extend(iter);
+        trie
+    }
+}
+
+impl<K: TrieKey> Extend<K> for BinaryTrie<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key, ());
+        }
+    }
+}
+
+impl<K: TrieKey, V> fmt::Debug for BinaryTrie<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BinaryTrie")
+            .field("len", &self.len)
+            .field("nodes", &self.node_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(a: u8, b: u8, c: u8, d: u8) -> u32 {
+        u32::from_be_bytes([a, b, c, d])
+    }
+
+    #[test]
+    fn set_of_integers() {
+        let mut trie: BinaryTrie<u32> = [5, 17, 3, 1 << 31].into_iter().collect();
+        assert_eq!(trie.len(), 4);
+        assert!(trie.contains(17) && trie.contains(1 << 31));
+        assert!(!trie.contains(4));
+        assert_eq!(trie.insert(5, ()), Some(()));
+        assert_eq!(trie.len(), 4);
+        assert_eq!(trie.remove(17), Some(()));
+        assert_eq!(trie.remove(17), None);
+        assert!(!trie.contains(17));
+        assert_eq!(trie.len(), 3);
+    }
+
+    #[test]
+    fn removing_prunes_nodes() {
+        let mut trie = BinaryTrie::<u16, &str>::new();
+        trie.insert(0x1234, "a");
+        let single = trie.node_count();
+        assert_eq!(single, 17);
+        trie.insert(0x1235, "b");
+        assert_eq!(trie.node_count(), single + 1);
+        trie.remove(0x1235);
+        assert_eq!(trie.node_count(), single);
+        trie.remove(0x1234);
+        assert_eq!(trie.node_count(), 1);
+        assert!(trie.is_empty());
+        // freed nodes are reused
+        trie.insert(0xffff, "c");
+        assert_eq!(trie.node_count(), single);
+        assert_eq!(trie.get(0xffff), Some(&"c"));
+    }
+
+    #[test]
+    fn max_xor_matches_brute_force() {
+        let mut x: u64 = 7;
+        let mut next = || {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            x >> 16
+        };
+        let keys: Vec<u64> = (0..300).map(|_| next()).collect();
+        let mut trie: BinaryTrie<u64> = keys.iter().copied().collect();
+        // prefixes do not take part
+        trie.insert_prefix(u64::MAX, 1, ());
+        for _ in 0..200 {
+            let q = next();
+            let best = keys.iter().map(|k| k ^ q).max().unwrap();
+            assert_eq!(trie.max_xor_with(q).map(|k| k ^ q), Some(best));
+        }
+        assert_eq!(BinaryTrie::<u8>::new().max_xor_with(3), None);
+    }
+
+    #[test]
+    fn routing_table_longest_prefix_match() {
+        let mut routes = BinaryTrie::<u32, &str>::new();
+        routes.insert_prefix(0, 0, "default");
+        routes.insert_prefix(ip(10, 0, 0, 0), 8, "internal");
+        routes.insert_prefix(ip(10, 1, 0, 0), 16, "lab");
+        routes.insert(ip(10, 1, 2, 3), "printer");
+        fn lookup<'a>(routes: &BinaryTrie<u32, &'a str>, a: u32) -> Option<(u32, &'a str)> {
+            routes.longest_prefix_match(a).map(|(_, len, v)| (len, *v))
+        }
+        assert_eq!(lookup(&routes, ip(8, 8, 8, 8)), Some((0, "default")));
+        assert_eq!(lookup(&routes, ip(10, 9, 9, 9)), Some((8, "internal")));
+        assert_eq!(lookup(&routes, ip(10, 1, 2, 4)), Some((16, "lab")));
+        assert_eq!(lookup(&routes, ip(10, 1, 2, 3)), Some((32, "printer")));
+        assert_eq!(
+            routes.longest_prefix_match(ip(10, 1, 200, 1)).unwrap().0,
+            ip(10, 1, 0, 0)
+        );
+
+        routes.remove_prefix(ip(10, 1, 99, 99), 16);
+        assert_eq!(lookup(&routes, ip(10, 1, 2, 4)), Some((8, "internal")));
+        routes.remove_prefix(0, 0);
+        assert_eq!(lookup(&routes, ip(8, 8, 8, 8)), None);
+    }
+
+    #[test]
+    fn wide_keys() {
+        let mut trie = BinaryTrie::<u128, u8>::new();
+        trie.insert(u128::MAX, 1);
+        trie.insert(0, 2);
+        trie.insert_prefix(0x2001_0db8 << 96, 32, 3);
+        assert_eq!(trie.get(u128::MAX), Some(&1));
+        assert_eq!(trie.max_xor_with(1), Some(u128::MAX));
+        assert_eq!(trie.max_xor_with(u128::MAX), Some(0));
+        let (prefix, len, v) = trie.longest_prefix_match((0x2001_0db8 << 96) | 42).unwrap();
+        assert_eq!((prefix, len, *v), (0x2001_0db8 << 96, 32, 3));
+        assert_eq!(trie.get_prefix(0x2001_0db8 << 96, 32), Some(&3));
+    }
+}
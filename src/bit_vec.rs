@@ -0,0 +1,596 @@
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::size_of;
+use core::ops::{BitAnd, BitOr, BitXor};
+
+const WORD_BITS: usize = 64;
+
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct BitSet {
+    bits: BitVec,
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        BitVec {
+            words: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn from_elem(len: usize, value: bool) -> Self {
+        let fill = if value { !0 } else { 0 };
+        let mut bv = BitVec {
+            words: vec![fill; len.div_ceil(WORD_BITS)],
+            len,
+        };
+        bv.clear_unused_bits();
+        bv
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "bit index out of bounds");
+        let mask = 1 << (index % WORD_BITS);
+        if value {
+            self.words[index / WORD_BITS] |= mask;
+        } else {
+            self.words[index / WORD_BITS] &= !mask;
+        }
+    }
+
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    pub fn pop(&mut self) -> Option<bool> {
+        let value = self.get(self.len.checked_sub(1)?)?;
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.pop();
+        }
+        Some(value)
+    }
+
+    pub fn resize(&mut self, len: usize, value: bool) {
+        while self.len < len && !self.len.is_multiple_of(WORD_BITS) {
+            self.push(value);
+        }
+        if len > self.len {
+            let fill = if value { !0 } else { 0 };
+            self.words.resize(len.div_ceil(WORD_BITS), fill);
+        } else {
+            self.words.truncate(len.div_ceil(WORD_BITS));
+        }
+        self.len = len;
+        self.clear_unused_bits();
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.count_ones()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.get(i).unwrap())
+    }
+
+    pub fn iter_ones(&self) -> Ones<'_> {
+        Ones {
+            words: &self.words,
+            index: 0,
+            current: self.words.first().copied().unwrap_or(0),
+        }
+    }
+
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Take over `words` as the bits of a vector of length `len`. Missing
+    /// words read as zeros, and bits past `len` are dropped.
+    pub fn from_words(mut words: Vec<u64>, len: usize) -> Self {
+        words.resize(len.div_ceil(WORD_BITS), 0);
+        let mut bv = BitVec { words, len };
+        bv.clear_unused_bits();
+        bv
+    }
+
+    fn clear_unused_bits(&mut self) {
+        let used = self.len % WORD_BITS;
+        if used != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << used) - 1;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bv = BitVec::new();
+        bv.extend(iter);
+        bv
+    }
+}
+
+impl Extend<bool> for BitVec {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+
+pub struct Ones<'a> {
+    words: &'a [u64],
+    index: usize,
+    current: u64,
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.index * WORD_BITS + bit)
+    }
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        BitSet {
+            bits: BitVec::new(),
+        }
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        BitSet {
+            bits: BitVec::from_elem(n, false),
+        }
+    }
+
+    /// Number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.bits.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn insert(&mut self, value: usize) -> bool {
+        if value >= self.bits.len() {
+            self.bits.resize(value + 1, false);
+        }
+        let present = self.contains(value);
+        self.bits.set(value, true);
+        !present
+    }
+
+    pub fn remove(&mut self, value: usize) -> bool {
+        let present = self.contains(value);
+        if present {
+            self.bits.set(value, false);
+        }
+        present
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        self.bits.get(value).unwrap_or(false)
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.bits.count_ones()
+    }
+
+    pub fn iter(&self) -> Ones<'_> {
+        self.bits.iter_ones()
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        self.combine_with(other, |a, b| a | b);
+    }
+
+    pub fn intersect_with(&mut self, other: &BitSet) {
+        self.combine_with(other, |a, b| a & b);
+    }
+
+    pub fn difference_with(&mut self, other: &BitSet) {
+        self.combine_with(other, |a, b| a & !b);
+    }
+
+    pub fn symmetric_difference_with(&mut self, other: &BitSet) {
+        self.combine_with(other, |a, b| a ^ b);
+    }
+
+    pub fn is_subset(&self, other: &BitSet) -> bool {
+        let empty = 0;
+        self.bits.words.iter().enumerate().all(|(i, &w)| {
+            let o = *other.bits.words.get(i).unwrap_or(&empty);
+            w & !o == 0
+        })
+    }
+
+    fn combine_with(&mut self, other: &BitSet, op: impl Fn(u64, u64) -> u64) {
+        if other.bits.len() > self.bits.len() {
+            self.bits.resize(other.bits.len(), false);
+        }
+        for (i, w) in self.bits.words.iter_mut().enumerate() {
+            *w = op(*w, other.bits.words.get(i).copied().unwrap_or(0));
+        }
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        let n = self.bits.words.len().max(other.bits.words.len());
+        (0..n).all(|i| {
+            self.bits.words.get(i).copied().unwrap_or(0)
+                == other.bits.words.get(i).copied().unwrap_or(0)
+        })
+    }
+}
+
+impl Eq for BitSet {}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl BitAnd for &BitSet {
+    type Output = BitSet;
+
+    fn bitand(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.intersect_with(other);
+        result
+    }
+}
+
+impl BitOr for &BitSet {
+    type Output = BitSet;
+
+    fn bitor(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.union_with(other);
+        result
+    }
+}
+
+impl BitXor for &BitSet {
+    type Output = BitSet;
+
+    fn bitxor(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.symmetric_difference_with(other);
+        result
+    }
+}
+
+/// Every `SELECT_SAMPLE`-th one (and zero) has its word recorded, so that
+/// select only searches the few words between two samples.
+const SELECT_SAMPLE: usize = 256;
+
+/// An immutable `BitVec` with an index for rank and select queries.
+///
+/// Rank is O(1) from a count of the ones before each word. Select looks up
+/// a sample and then binary searches the words up to the next sample, which
+/// is O(1) unless the bits are very unevenly distributed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RankSelect {
+    bits: BitVec,
+    /// Number of ones in the words before each word, plus the total.
+    ranks: Vec<usize>,
+    one_samples: Vec<usize>,
+    zero_samples: Vec<usize>,
+}
+
+impl RankSelect {
+    pub fn new(bits: BitVec) -> Self {
+        let mut ranks = Vec::with_capacity(bits.words.len() + 1);
+        let mut one_samples = vec![];
+        let mut zero_samples = vec![];
+        let (mut ones, mut zeros) = (0, 0);
+        for (w, word) in bits.words.iter().enumerate() {
+            ranks.push(ones);
+            let valid = (bits.len - w * WORD_BITS).min(WORD_BITS);
+            ones += word.count_ones() as usize;
+            zeros += valid - word.count_ones() as usize;
+            while one_samples.len() * SELECT_SAMPLE < ones {
+                one_samples.push(w);
+            }
+            while zero_samples.len() * SELECT_SAMPLE < zeros {
+                zero_samples.push(w);
+            }
+        }
+        ranks.push(ones);
+        RankSelect {
+            bits,
+            ranks,
+            one_samples,
+            zero_samples,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.bits.get(index)
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.ranks[self.ranks.len() - 1]
+    }
+
+    pub fn count_zeros(&self) -> usize {
+        self.bits.len - self.count_ones()
+    }
+
+    pub fn as_bit_vec(&self) -> &BitVec {
+        &self.bits
+    }
+
+    /// Number of ones before position `pos`. Panics if `pos > len`.
+    pub fn rank1(&self, pos: usize) -> usize {
+        assert!(pos <= self.bits.len, "bit index out of bounds");
+        let (word, bit) = (pos / WORD_BITS, pos % WORD_BITS);
+        let mut rank = self.ranks[word];
+        if bit > 0 {
+            rank += (self.bits.words[word] & ((1 << bit) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+
+    /// Number of zeros before position `pos`. Panics if `pos > len`.
+    pub fn rank0(&self, pos: usize) -> usize {
+        pos - self.rank1(pos)
+    }
+
+    /// Position of the one with index `n` (counting from 0).
+    pub fn select1(&self, n: usize) -> Option<usize> {
+        if n >= self.count_ones() {
+            return None;
+        }
+        let word = self.find_word(&self.one_samples, n, |w| self.ranks[w]);
+        let rest = n - self.ranks[word];
+        Some(word * WORD_BITS + select_in_word(self.bits.words[word], rest))
+    }
+
+    /// Position of the zero with index `n` (counting from 0).
+    pub fn select0(&self, n: usize) -> Option<usize> {
+        if n >= self.count_zeros() {
+            return None;
+        }
+        let zeros_before = |w: usize| w * WORD_BITS - self.ranks[w];
+        let word = self.find_word(&self.zero_samples, n, zeros_before);
+        let rest = n - zeros_before(word);
+        Some(word * WORD_BITS + select_in_word(!self.bits.words[word], rest))
+    }
+
+    /// The last word with fewer than `n + 1` matching bits before it.
+    fn find_word(&self, samples: &[usize], n: usize, before: impl Fn(usize) -> usize) -> usize {
+        let mut lo = samples[n / SELECT_SAMPLE];
+        let mut hi = samples
+            .get(n / SELECT_SAMPLE + 1)
+            .map_or(self.bits.words.len(), |&w| w + 1);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if before(mid) <= n {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+/// Position of the set bit with index `n` in `word`.
+fn select_in_word(mut word: u64, n: usize) -> usize {
+    for _ in 0..n {
+        word &= word - 1;
+    }
+    word.trailing_zeros() as usize
+}
+
+impl From<BitVec> for RankSelect {
+    fn from(bits: BitVec) -> Self {
+        RankSelect::new(bits)
+    }
+}
+
+impl MemoryFootprint for RankSelect {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut stats = buffer_stats::<u64>(self.bits.words.len(), self.bits.words.capacity());
+        stats.heap_bytes += size_of::<usize>()
+            * (self.ranks.capacity() + self.one_samples.capacity() + self.zero_samples.capacity());
+        stats.nodes += 3;
+        stats
+    }
+}
+
+impl fmt::Debug for RankSelect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.bits.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_bitvec() {
+        let mut bv = BitVec::new();
+        assert!(bv.is_empty());
+        assert_eq!(bv.get(0), None);
+        assert_eq!(bv.pop(), None);
+    }
+
+    #[test]
+    fn push_get_pop() {
+        let mut bv = BitVec::new();
+        for i in 0..200 {
+            bv.push(i % 3 == 0);
+        }
+        assert_eq!(bv.len(), 200);
+        assert_eq!(bv.get(99), Some(true));
+        assert_eq!(bv.get(100), Some(false));
+        assert_eq!(bv.count_ones(), 67);
+        for i in (0..200).rev() {
+            assert_eq!(bv.pop(), Some(i % 3 == 0));
+        }
+        assert!(bv.is_empty());
+    }
+
+    #[test]
+    fn set_and_clear_bits() {
+        let mut bv = BitVec::from_elem(70, false);
+        bv.set(3, true);
+        bv.set(69, true);
+        assert_eq!(bv.iter_ones().collect::<Vec<_>>(), vec![3, 69]);
+        bv.set(3, false);
+        assert_eq!(bv.count_ones(), 1);
+        assert_eq!(bv.count_zeros(), 69);
+    }
+
+    #[test]
+    fn resize_fills_new_bits() {
+        let mut bv = BitVec::from_elem(3, false);
+        bv.resize(130, true);
+        assert_eq!(bv.count_ones(), 127);
+        bv.resize(10, true);
+        assert_eq!(bv.count_ones(), 7);
+        assert_eq!(format!("{:?}", bv), "0001111111");
+    }
+
+    #[test]
+    fn bitset_insert_remove_contains() {
+        let mut set = BitSet::new();
+        assert!(set.insert(5));
+        assert!(!set.insert(5));
+        assert!(set.insert(1000));
+        assert!(set.contains(5));
+        assert!(!set.contains(6));
+        assert!(!set.contains(100_000));
+        assert_eq!(set.len(), 2);
+        assert!(set.remove(5));
+        assert!(!set.remove(5));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1000]);
+    }
+
+    #[test]
+    fn bitset_algebra() {
+        let a: BitSet = [1, 2, 3, 100].into_iter().collect();
+        let b: BitSet = [2, 3, 4].into_iter().collect();
+        assert_eq!((&a | &b).iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 100]);
+        assert_eq!((&a & &b).iter().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!((&a ^ &b).iter().collect::<Vec<_>>(), vec![1, 4, 100]);
+        let mut d = a.clone();
+        d.difference_with(&b);
+        assert_eq!(d.iter().collect::<Vec<_>>(), vec![1, 100]);
+    }
+
+    #[test]
+    fn bitset_equality_ignores_capacity() {
+        let mut a = BitSet::with_capacity(1000);
+        a.insert(3);
+        let b: BitSet = [3].into_iter().collect();
+        assert_eq!(a, b);
+        assert!(b.is_subset(&a));
+        a.insert(999);
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn rank_and_select() {
+        let bits: BitVec = (0..1000).map(|i| i % 3 == 0 || i > 900).collect();
+        let rs = RankSelect::new(bits.clone());
+        let ones: Vec<_> = bits.iter_ones().collect();
+        assert_eq!(rs.count_ones(), ones.len());
+        for (n, &pos) in ones.iter().enumerate() {
+            assert_eq!(rs.select1(n), Some(pos));
+            assert_eq!(rs.rank1(pos), n);
+        }
+        assert_eq!(rs.select1(ones.len()), None);
+        let zeros: Vec<_> = (0..1000).filter(|&i| !bits.get(i).unwrap()).collect();
+        for (n, &pos) in zeros.iter().enumerate() {
+            assert_eq!(rs.select0(n), Some(pos));
+            assert_eq!(rs.rank0(pos), n);
+        }
+        assert_eq!(rs.select0(zeros.len()), None);
+        assert_eq!(rs.rank1(1000), ones.len());
+        assert_eq!(RankSelect::new(BitVec::new()).select0(0), None);
+    }
+}
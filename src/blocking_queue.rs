@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A bounded FIFO queue for passing items between threads.
+///
+/// `push` waits while the queue is full and `pop` waits while it is empty,
+/// so a fast producer is slowed down to the pace of its consumers. Share it
+/// between threads by reference or in an `Arc`.
+pub struct BlockingQueue<T> {
+    items: Mutex<VecDeque<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+}
+
+impl<T> BlockingQueue<T> {
+    /// A queue that holds up to `capacity` items (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        BlockingQueue {
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.lock().len() == self.capacity
+    }
+
+    /// Append an item, waiting for space if the queue is full.
+    pub fn push(&self, item: T) {
+        let mut items = self.lock();
+        while items.len() == self.capacity {
+            items = self.not_full.wait(items).unwrap_or_else(|e| e.into_inner());
+        }
+        self.push_locked(items, item);
+    }
+
+    /// Like `push`, but gives the item back if there is still no space
+    /// after `timeout`.
+    pub fn push_timeout(&self, item: T, timeout: Duration) -> Result<(), T> {
+        let deadline = Instant::now() + timeout;
+        let mut items = self.lock();
+        while items.len() == self.capacity {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return Err(item);
+            };
+            items = self
+                .not_full
+                .wait_timeout(items, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        self.push_locked(items, item);
+        Ok(())
+    }
+
+    /// Append an item if there is space, without waiting.
+    pub fn try_push(&self, item: T) -> Result<(), T> {
+        let items = self.lock();
+        if items.len() == self.capacity {
+            return Err(item);
+        }
+        self.push_locked(items, item);
+        Ok(())
+    }
+
+    /// Remove the oldest item, waiting for one if the queue is empty.
+    pub fn pop(&self) -> T {
+        let mut items = self.lock();
+        loop {
+            if let Some(item) = items.pop_front() {
+                return self.popped(items, item);
+            }
+            items = self
+                .not_empty
+                .wait(items)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Like `pop`, but returns `None` if the queue is still empty after
+    /// `timeout`.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut items = self.lock();
+        loop {
+            if let Some(item) = items.pop_front() {
+                return Some(self.popped(items, item));
+            }
+            let left = deadline.checked_duration_since(Instant::now())?;
+            items = self
+                .not_empty
+                .wait_timeout(items, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Remove the oldest item if there is one, without waiting.
+    pub fn try_pop(&self) -> Option<T> {
+        let mut items = self.lock();
+        let item = items.pop_front()?;
+        Some(self.popped(items, item))
+    }
+
+    fn push_locked(&self, mut items: MutexGuard<'_, VecDeque<T>>, item: T) {
+        items.push_back(item);
+        drop(items);
+        self.not_empty.notify_one();
+    }
+
+    fn popped(&self, items: MutexGuard<'_, VecDeque<T>>, item: T) -> T {
+        drop(items);
+        self.not_full.notify_one();
+        item
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<T>> {
+        self.items.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BlockingQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockingQueue")
+            .field("items", &*self.lock())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn fifo_order() {
+        let q = BlockingQueue::new(4);
+        for i in 0..4 {
+            q.push(i);
+        }
+        assert!(q.is_full());
+        assert_eq!(q.len(), 4);
+        assert_eq!(
+            (0..4).map(|_| q.pop()).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn try_variants_do_not_wait() {
+        let q = BlockingQueue::new(1);
+        assert_eq!(q.try_pop(), None);
+        assert_eq!(q.try_push('a'), Ok(()));
+        assert_eq!(q.try_push('b'), Err('b'));
+        assert_eq!(q.try_pop(), Some('a'));
+        assert_eq!(BlockingQueue::<u8>::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn timeouts_expire() {
+        let q = BlockingQueue::new(1);
+        let short = Duration::from_millis(10);
+        assert_eq!(q.pop_timeout(short), None);
+        q.push(1);
+        assert_eq!(q.push_timeout(2, short), Err(2));
+        assert_eq!(q.pop_timeout(short), Some(1));
+        assert_eq!(q.push_timeout(3, short), Ok(()));
+    }
+
+    #[test]
+    fn full_queue_blocks_producer() {
+        let q = BlockingQueue::new(2);
+        let pushed = AtomicUsize::new(0);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..10 {
+                    q.push(i);
+                    pushed.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+            thread::sleep(Duration::from_millis(50));
+            // the producer is stuck until something is popped
+            assert!(pushed.load(Ordering::SeqCst) <= 2);
+            let received: Vec<_> = (0..10).map(|_| q.pop()).collect();
+            assert_eq!(received, (0..10).collect::<Vec<_>>());
+        });
+        assert_eq!(pushed.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn many_producers_and_consumers() {
+        let q = BlockingQueue::new(8);
+        let total = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for p in 0..4 {
+                let q = &q;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        q.push(p * 1000 + i);
+                    }
+                });
+            }
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        total.fetch_add(q.pop(), Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        assert_eq!(total.load(Ordering::SeqCst), (0..4000).sum());
+        assert!(q.is_empty());
+    }
+}
@@ -0,0 +1,601 @@
+//! Bounded caches that evict entries to make room for new ones.
+//!
+//! All caches keep their entries in a `Slab` and thread their eviction
+//! queues through it as doubly linked lists, so no operation allocates once
+//! the cache has filled up.
+
+use crate::slab::Slab;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// A key-value store with a bounded size that decides by itself which
+/// entries to drop.
+pub trait Cache<K, V> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The size limit of the cache.
+    fn capacity(&self) -> usize;
+
+    /// Look up a key and count the lookup as a use of the entry.
+    fn get(&mut self, key: &K) -> Option<&V>;
+
+    /// Look up a key without affecting which entry is evicted next.
+    fn peek(&self, key: &K) -> Option<&V>;
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Insert an entry, evicting others if the cache is full. Returns the
+    /// value previously stored under `key`.
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+}
+
+/// Links of a node that is part of a `Queue`.
+#[derive(Debug, Default, Clone, Copy)]
+struct Links {
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+trait Linked {
+    fn links(&mut self) -> &mut Links;
+}
+
+/// A doubly linked list of slab entries, newest first.
+#[derive(Debug, Default, Clone, Copy)]
+struct Queue {
+    newest: Option<usize>,
+    oldest: Option<usize>,
+    len: usize,
+}
+
+impl Queue {
+    fn push_newest<N: Linked>(&mut self, nodes: &mut Slab<N>, node: usize) {
+        *nodes[node].links() = Links {
+            prev: None,
+            next: self.newest,
+        };
+        match self.newest {
+            Some(n) => nodes[n].links().prev = Some(node),
+            None => self.oldest = Some(node),
+        }
+        self.newest = Some(node);
+        self.len += 1;
+    }
+
+    fn unlink<N: Linked>(&mut self, nodes: &mut Slab<N>, node: usize) {
+        let Links { prev, next } = *nodes[node].links();
+        match prev {
+            Some(p) => nodes[p].links().next = next,
+            None => self.newest = next,
+        }
+        match next {
+            Some(n) => nodes[n].links().prev = prev,
+            None => self.oldest = prev,
+        }
+        self.len -= 1;
+    }
+
+    fn move_to_newest<N: Linked>(&mut self, nodes: &mut Slab<N>, node: usize) {
+        self.unlink(nodes, node);
+        self.push_newest(nodes, node);
+    }
+}
+
+/// Decides how much of a cache's capacity an entry uses.
+///
+/// Any `Fn(&K, &V) -> usize` is a weigher, e.g. one that returns the
+/// length of a byte buffer.
+pub trait Weigher<K, V> {
+    fn weigh(&self, key: &K, value: &V) -> usize;
+}
+
+/// Every entry weighs one, so the capacity is a number of entries.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Unweighted;
+
+impl<K, V> Weigher<K, V> for Unweighted {
+    fn weigh(&self, _: &K, _: &V) -> usize {
+        1
+    }
+}
+
+impl<K, V, F: Fn(&K, &V) -> usize> Weigher<K, V> for F {
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        self(key, value)
+    }
+}
+
+/// A cache that evicts the least recently used entries until the total
+/// weight of its entries fits into its capacity.
+pub struct LruCache<K, V, W = Unweighted> {
+    map: HashMap<K, usize>,
+    nodes: Slab<LruNode<K, V>>,
+    queue: Queue,
+    weight: usize,
+    capacity: usize,
+    weigher: W,
+}
+
+struct LruNode<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    links: Links,
+}
+
+impl<K, V> Linked for LruNode<K, V> {
+    fn links(&mut self) -> &mut Links {
+        &mut self.links
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// A cache for up to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_weigher(capacity, Unweighted)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, W: Weigher<K, V>> LruCache<K, V, W> {
+    /// A cache whose entries may weigh up to `capacity` in total.
+    pub fn with_weigher(capacity: usize, weigher: W) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        LruCache {
+            map: HashMap::new(),
+            nodes: Slab::new(),
+            queue: Queue::default(),
+            weight: 0,
+            capacity,
+            weigher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Total weight of the entries.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let node = *self.map.get(key)?;
+        self.queue.move_to_newest(&mut self.nodes, node);
+        Some(&self.nodes[node].value)
+    }
+
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        Some(&self.nodes[*self.map.get(key)?].value)
+    }
+
+    /// Insert an entry and evict least recently used entries until the
+    /// weights fit. An entry that weighs more than the whole capacity is
+    /// not stored at all.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.remove(&key);
+        let weight = self.weigher.weigh(&key, &value);
+        if weight > self.capacity {
+            return old;
+        }
+        while self.weight + weight > self.capacity {
+            self.pop_oldest();
+        }
+        let node = self.nodes.insert(LruNode {
+            key: key.clone(),
+            value,
+            weight,
+            links: Links::default(),
+        });
+        self.queue.push_newest(&mut self.nodes, node);
+        self.map.insert(key, node);
+        self.weight += weight;
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let node = self.map.remove(key)?;
+        Some(self.remove_node(node).1)
+    }
+
+    /// Remove and return the least recently used entry.
+    pub fn pop_oldest(&mut self) -> Option<(K, V)> {
+        let node = self.queue.oldest?;
+        let (key, value) = self.remove_node(node);
+        self.map.remove(&key);
+        Some((key, value))
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.queue = Queue::default();
+        self.weight = 0;
+    }
+
+    fn remove_node(&mut self, node: usize) -> (K, V) {
+        self.queue.unlink(&mut self.nodes, node);
+        let node = self.nodes.remove(node).unwrap();
+        self.weight -= node.weight;
+        (node.key, node.value)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, W: Weigher<K, V>> Cache<K, V> for LruCache<K, V, W> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn peek(&self, key: &K) -> Option<&V> {
+        self.peek(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, W> fmt::Debug for LruCache<K, V, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.nodes.iter().map(|(_, node)| (&node.key, &node.value)))
+            .finish()
+    }
+}
+
+/// A cache with the 2Q replacement policy.
+///
+/// New entries go to a small FIFO of probationary entries. Keys evicted
+/// from there are remembered in a ghost queue for a while; inserting such
+/// a key again proves it is used repeatedly and moves it to the protected
+/// LRU segment. A scan over many keys that are used only once therefore
+/// cannot flush the entries that are used often.
+pub struct TwoQueueCache<K, V> {
+    map: HashMap<K, usize>,
+    nodes: Slab<Node<K, V>>,
+    probation: Queue,
+    protected: Queue,
+    ghosts: Queue,
+    capacity: usize,
+    probation_capacity: usize,
+    ghost_capacity: usize,
+}
+
+struct Node<K, V> {
+    key: K,
+    // `None` for ghosts
+    value: Option<V>,
+    segment: Segment,
+    links: Links,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Probation,
+    Protected,
+    Ghost,
+}
+
+impl<K, V> Linked for Node<K, V> {
+    fn links(&mut self) -> &mut Links {
+        &mut self.links
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> TwoQueueCache<K, V> {
+    /// A cache for up to `capacity` entries, a quarter of which are
+    /// probationary, that remembers half as many evicted keys.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_sizes(capacity, capacity / 4, capacity / 2)
+    }
+
+    /// Like `new`, but with explicit sizes of the probationary segment and
+    /// of the ghost queue. Both are at least one.
+    pub fn with_sizes(capacity: usize, probation_capacity: usize, ghost_capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        TwoQueueCache {
+            map: HashMap::new(),
+            nodes: Slab::with_capacity(capacity + ghost_capacity),
+            probation: Queue::default(),
+            protected: Queue::default(),
+            ghosts: Queue::default(),
+            capacity,
+            probation_capacity: probation_capacity.max(1),
+            ghost_capacity: ghost_capacity.max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.probation.len + self.protected.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of evicted keys that are still remembered.
+    pub fn ghost_len(&self) -> usize {
+        self.ghosts.len
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let node = *self.map.get(key)?;
+        if self.nodes[node].segment == Segment::Protected {
+            self.protected.move_to_newest(&mut self.nodes, node);
+        }
+        self.nodes[node].value.as_ref()
+    }
+
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.nodes[*self.map.get(key)?].value.as_ref()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let segment = match self.map.get(&key) {
+            None => Segment::Probation,
+            Some(&node) if self.nodes[node].segment == Segment::Ghost => {
+                self.ghosts.unlink(&mut self.nodes, node);
+                self.nodes.remove(node);
+                self.map.remove(&key);
+                Segment::Protected
+            }
+            Some(&node) => {
+                if self.nodes[node].segment == Segment::Protected {
+                    self.protected.move_to_newest(&mut self.nodes, node);
+                }
+                return self.nodes[node].value.replace(value);
+            }
+        };
+        self.make_room();
+        let node = self.nodes.insert(Node {
+            key: key.clone(),
+            value: Some(value),
+            segment,
+            links: Links::default(),
+        });
+        let (queue, nodes) = self.queue(segment);
+        queue.push_newest(nodes, node);
+        self.map.insert(key, node);
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let node = self.map.remove(key)?;
+        let segment = self.nodes[node].segment;
+        let (queue, nodes) = self.queue(segment);
+        queue.unlink(nodes, node);
+        self.nodes.remove(node)?.value
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.probation = Queue::default();
+        self.protected = Queue::default();
+        self.ghosts = Queue::default();
+    }
+
+    fn make_room(&mut self) {
+        while self.len() >= self.capacity {
+            if self.probation.len > self.probation_capacity || self.protected.len == 0 {
+                let node = self.probation.oldest.unwrap();
+                self.probation.unlink(&mut self.nodes, node);
+                self.nodes[node].value = None;
+                self.nodes[node].segment = Segment::Ghost;
+                self.ghosts.push_newest(&mut self.nodes, node);
+                if self.ghosts.len > self.ghost_capacity {
+                    let ghost = self.ghosts.oldest.unwrap();
+                    self.forget(ghost);
+                }
+            } else {
+                let node = self.protected.oldest.unwrap();
+                self.forget(node);
+            }
+        }
+    }
+
+    fn forget(&mut self, node: usize) {
+        let segment = self.nodes[node].segment;
+        let (queue, nodes) = self.queue(segment);
+        queue.unlink(nodes, node);
+        let node = self.nodes.remove(node).unwrap();
+        self.map.remove(&node.key);
+    }
+
+    fn queue(&mut self, segment: Segment) -> (&mut Queue, &mut Slab<Node<K, V>>) {
+        let queue = match segment {
+            Segment::Probation => &mut self.probation,
+            Segment::Protected => &mut self.protected,
+            Segment::Ghost => &mut self.ghosts,
+        };
+        (queue, &mut self.nodes)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for TwoQueueCache<K, V> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn peek(&self, key: &K) -> Option<&V> {
+        self.peek(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for TwoQueueCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.nodes
+                    .iter()
+                    .filter_map(|(_, node)| Some((&node.key, node.value.as_ref()?))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut cache = TwoQueueCache::new(4);
+        assert!(cache.is_empty());
+        assert_eq!(cache.insert("a", 1), None);
+        assert_eq!(cache.insert("a", 2), Some(1));
+        assert_eq!(cache.get(&"a"), Some(&2));
+        assert_eq!(cache.peek(&"b"), None);
+        assert_eq!(cache.remove(&"a"), Some(2));
+        assert_eq!(cache.remove(&"a"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn never_exceeds_capacity() {
+        let mut cache = TwoQueueCache::new(8);
+        for i in 0..100 {
+            cache.insert(i % 13, i);
+            assert!(cache.len() <= 8);
+            assert!(cache.ghost_len() <= 4);
+        }
+        assert_eq!(cache.len(), 8);
+    }
+
+    #[test]
+    fn ghost_hit_promotes_to_protected() {
+        let mut cache = TwoQueueCache::with_sizes(3, 1, 2);
+        for k in 0..4 {
+            cache.insert(k, k);
+        }
+        // 0 was evicted but is remembered
+        assert!(!cache.contains_key(&0));
+        assert_eq!(cache.ghost_len(), 1);
+        cache.insert(0, 0);
+        // making room turned 1 into a ghost in turn
+        assert!(!cache.contains_key(&1));
+        assert_eq!(cache.ghost_len(), 1);
+        // a long scan of new keys only cycles through probation
+        for k in 100..200 {
+            cache.insert(k, k);
+        }
+        assert_eq!(cache.peek(&0), Some(&0));
+    }
+
+    #[test]
+    fn protected_segment_is_lru() {
+        let mut cache = TwoQueueCache::with_sizes(4, 1, 4);
+        for k in [0, 1, 2, 3, 10, 11, 12, 13] {
+            cache.insert(k, k);
+        }
+        // 0, 1 and 2 are ghosts now; inserting them again protects them
+        for k in 0..3 {
+            cache.insert(k, k);
+        }
+        assert_eq!(cache.get(&0), Some(&0));
+        // probation is at its minimum, so the least recently used protected
+        // entry goes
+        cache.insert(5, 5);
+        assert!(cache.contains_key(&0));
+        assert!(!cache.contains_key(&1));
+        assert!(cache.contains_key(&2));
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut cache = LruCache::new(3);
+        for k in 0..3 {
+            cache.insert(k, k);
+        }
+        assert_eq!(cache.get(&0), Some(&0));
+        cache.insert(3, 3);
+        assert!(!cache.contains_key(&1));
+        assert_eq!(cache.pop_oldest(), Some((2, 2)));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn weighted_lru_fits_budget() {
+        let mut cache = LruCache::with_weigher(10, |_: &&str, blob: &Vec<u8>| blob.len());
+        cache.insert("a", vec![0; 4]);
+        cache.insert("b", vec![0; 4]);
+        cache.insert("c", vec![0; 2]);
+        assert_eq!(cache.weight(), 10);
+        // needs room for 5, so both a and b go
+        cache.insert("d", vec![0; 5]);
+        assert_eq!(cache.weight(), 7);
+        assert!(!cache.contains_key(&"a") && !cache.contains_key(&"b"));
+        // replacing an entry replaces its weight
+        assert_eq!(cache.insert("c", vec![0; 1]).map(|v| v.len()), Some(2));
+        assert_eq!(cache.weight(), 6);
+        // too heavy to cache at all
+        cache.insert("e", vec![0; 11]);
+        assert!(!cache.contains_key(&"e"));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn usable_through_trait() {
+        fn fill(cache: &mut impl Cache<u32, u32>) {
+            for k in 0..10 {
+                cache.insert(k, k * k);
+            }
+        }
+        let mut cache = TwoQueueCache::new(4);
+        fill(&mut cache);
+        assert_eq!(Cache::len(&cache), 4);
+        assert_eq!(Cache::get(&mut cache, &9), Some(&81));
+        let mut cache = LruCache::new(4);
+        fill(&mut cache);
+        assert_eq!(Cache::peek(&cache, &6), Some(&36));
+    }
+}
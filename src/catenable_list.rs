@@ -0,0 +1,291 @@
+use crate::real_time_queue::RealTimeQueue;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{OnceCell, RefCell};
+use core::fmt;
+
+/// Okasaki's persistent catenable list. `push_front`, `push_back`,
+/// `concat` and `front` take O(1) time and `pop_front` amortized O(1), even
+/// when old versions are reused.
+///
+/// A non-empty list is its first item and a queue of sublists. Joining two
+/// lists puts the second at the end of the first one's queue. Popping the
+/// first item links the sublists back into one list; that linking is
+/// suspended and done at most once, no matter how many versions share it.
+pub struct CatList<T> {
+    len: usize,
+    root: Option<Rc<Node<T>>>,
+}
+
+struct Node<T> {
+    head: T,
+    children: RealTimeQueue<Lazy<T>>,
+}
+
+/// A list that may not have been built yet.
+struct Lazy<T> {
+    cell: Rc<LazyCell<T>>,
+}
+
+struct LazyCell<T> {
+    len: usize,
+    value: OnceCell<CatList<T>>,
+    /// The sublists that still need to be linked into `value`.
+    pending: RefCell<Option<RealTimeQueue<Lazy<T>>>>,
+}
+
+impl<T> Clone for CatList<T> {
+    fn clone(&self) -> Self {
+        CatList {
+            len: self.len,
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T: Clone> CatList<T> {
+    pub fn new() -> Self {
+        CatList { len: 0, root: None }
+    }
+
+    pub fn singleton(item: T) -> Self {
+        CatList {
+            len: 1,
+            root: Some(Rc::new(Node {
+                head: item,
+                children: RealTimeQueue::new(),
+            })),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.root.as_ref().map(|node| &node.head)
+    }
+
+    pub fn push_front(&self, item: T) -> Self {
+        Self::singleton(item).concat(self)
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        self.concat(&Self::singleton(item))
+    }
+
+    /// The items of `self` followed by those of `other`.
+    pub fn concat(&self, other: &Self) -> Self {
+        match (&self.root, &other.root) {
+            (None, _) => other.clone(),
+            (_, None) => self.clone(),
+            _ => self.link(Lazy::evaluated(other.clone())),
+        }
+    }
+
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        let node = self.root.as_ref()?;
+        let rest = if node.children.is_empty() {
+            CatList::new()
+        } else {
+            link_all(node.children.clone(), self.len - 1)
+        };
+        Some((node.head.clone(), rest))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        // forcing the sublists in order visits the items in order
+        let mut items = Vec::with_capacity(self.len);
+        let mut stack = vec![self];
+        while let Some(list) = stack.pop() {
+            if let Some(node) = &list.root {
+                items.push(&node.head);
+                let children: Vec<_> = node.children.iter().collect();
+                stack.extend(children.into_iter().rev().map(Lazy::force));
+            }
+        }
+        items.into_iter()
+    }
+
+    /// Append a non-empty list to the queue of this non-empty list.
+    fn link(&self, other: Lazy<T>) -> Self {
+        let node = self.root.as_ref().unwrap();
+        CatList {
+            len: self.len + other.cell.len,
+            root: Some(Rc::new(Node {
+                head: node.head.clone(),
+                children: node.children.push_back(other),
+            })),
+        }
+    }
+}
+
+/// Link a non-empty queue of sublists with `len` items in total: the first
+/// one gets the lazily linked rest appended.
+fn link_all<T: Clone>(queue: RealTimeQueue<Lazy<T>>, len: usize) -> CatList<T> {
+    let (first, rest) = queue.pop_front().unwrap();
+    let first = first.force();
+    if rest.is_empty() {
+        first.clone()
+    } else {
+        first.link(Lazy::suspended(rest, len - first.len))
+    }
+}
+
+impl<T: Clone> Lazy<T> {
+    fn evaluated(list: CatList<T>) -> Self {
+        Lazy {
+            cell: Rc::new(LazyCell {
+                len: list.len,
+                value: OnceCell::from(list),
+                pending: RefCell::new(None),
+            }),
+        }
+    }
+
+    fn suspended(queue: RealTimeQueue<Lazy<T>>, len: usize) -> Self {
+        Lazy {
+            cell: Rc::new(LazyCell {
+                len,
+                value: OnceCell::new(),
+                pending: RefCell::new(Some(queue)),
+            }),
+        }
+    }
+
+    fn force(&self) -> &CatList<T> {
+        self.cell.value.get_or_init(|| {
+            let queue = self.cell.pending.borrow_mut().take();
+            link_all(queue.expect("list is being linked"), self.cell.len)
+        })
+    }
+}
+
+impl<T: Clone> Default for CatList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for CatList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for CatList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for CatList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(CatList::new(), |list, item| list.push_back(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(list: &CatList<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn empty_list() {
+        let list = CatList::<i32>::new();
+        assert!(list.is_empty());
+        assert_eq!(list.front(), None);
+        assert!(list.pop_front().is_none());
+        assert_eq!(list.concat(&list), list);
+    }
+
+    #[test]
+    fn push_at_both_ends() {
+        let mut list = CatList::new();
+        for i in 0..10 {
+            list = list.push_back(i).push_front(-i - 1);
+        }
+        assert_eq!(list.len(), 20);
+        assert_eq!(items(&list), (-10..10).collect::<Vec<_>>());
+        assert_eq!(list.front(), Some(&-10));
+    }
+
+    #[test]
+    fn concat_and_drain_in_order() {
+        let a: CatList<_> = (0..5).collect();
+        let b: CatList<_> = (5..12).collect();
+        let mut joined = a.concat(&b).concat(&a.concat(&b));
+        assert_eq!(joined.len(), 24);
+        let mut popped = Vec::new();
+        while let Some((item, rest)) = joined.pop_front() {
+            popped.push(item);
+            assert_eq!(rest.len(), 24 - popped.len());
+            joined = rest;
+        }
+        assert_eq!(popped, (0..12).chain(0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn old_versions_are_unchanged() {
+        let base: CatList<_> = (0..4).collect();
+        let longer = base.push_back(4).concat(&base);
+        let (_, tail) = longer.pop_front().unwrap();
+        let (_, tail2) = tail.pop_front().unwrap();
+        assert_eq!(items(&base), vec![0, 1, 2, 3]);
+        assert_eq!(items(&longer), vec![0, 1, 2, 3, 4, 0, 1, 2, 3]);
+        assert_eq!(items(&tail), vec![1, 2, 3, 4, 0, 1, 2, 3]);
+        // the same suspended tail is shared by both pops
+        let (_, again) = longer.pop_front().unwrap();
+        assert_eq!(again.pop_front().unwrap().1, tail2);
+    }
+
+    #[test]
+    fn matches_vec_under_random_operations() {
+        let mut list = CatList::new();
+        let mut model: Vec<i32> = Vec::new();
+        let mut x: u32 = 3;
+        for i in 0..2000 {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            match (x >> 16) % 4 {
+                0 => {
+                    list = list.push_front(i);
+                    model.insert(0, i);
+                }
+                1 => {
+                    list = list.push_back(i);
+                    model.push(i);
+                }
+                2 => {
+                    let other: CatList<_> = (i..i + 3).collect();
+                    list = list.concat(&other);
+                    model.extend(i..i + 3);
+                }
+                _ => {
+                    if let Some((item, rest)) = list.pop_front() {
+                        assert_eq!(item, model.remove(0));
+                        list = rest;
+                    }
+                }
+            }
+            assert_eq!(list.front(), model.first());
+        }
+        assert_eq!(items(&list), model);
+    }
+}
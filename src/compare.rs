@@ -0,0 +1,79 @@
+//! Comparators that decide how ordered structures sort their items.
+//!
+//! Any `Fn(&T, &T) -> Ordering` is a comparator, so a heap can be ordered by
+//! a closure without wrapping the items in a newtype.
+
+use core::cmp::Ordering;
+
+/// A strategy for ordering items of type `T`.
+pub trait Compare<T: ?Sized> {
+    /// `None` if the items cannot be ordered relative to each other.
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering>;
+
+    fn less(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Some(Ordering::Less)
+    }
+
+    fn greater(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Some(Ordering::Greater)
+    }
+}
+
+/// The items' own `PartialOrd` order.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Natural;
+
+impl<T: PartialOrd + ?Sized> Compare<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering> {
+        a.partial_cmp(b)
+    }
+}
+
+/// The opposite order of the wrapped comparator.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Reverse<C = Natural>(pub C);
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reverse<C> {
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering> {
+        self.0.compare(b, a)
+    }
+}
+
+/// Orders items by the key that the function extracts from them.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ByKey<F>(pub F);
+
+impl<T: ?Sized, K: PartialOrd, F: Fn(&T) -> K> Compare<T> for ByKey<F> {
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering> {
+        (self.0)(a).partial_cmp(&(self.0)(b))
+    }
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering> {
+        Some(self(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_and_reverse() {
+        assert!(Natural.less(&1, &2));
+        assert!(Reverse(Natural).less(&2, &1));
+        assert_eq!(Natural.compare("a", "b"), Some(Ordering::Less));
+        assert_eq!(Natural.compare(&f64::NAN, &1.0), None);
+        assert!(!Natural.less(&f64::NAN, &1.0));
+    }
+
+    #[test]
+    fn key_extractors_and_closures() {
+        let by_len = ByKey(|s: &&str| s.len());
+        assert!(by_len.greater(&"abc", &"xy"));
+        assert!(Reverse(by_len).less(&"abc", &"xy"));
+        let modulo = |a: &u32, b: &u32| (a % 10).cmp(&(b % 10));
+        assert!(modulo.less(&21, &3));
+    }
+}
@@ -0,0 +1,308 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const DEFAULT_SHARDS: usize = 16;
+
+/// A hash map that can be shared between threads.
+///
+/// Keys are distributed over a fixed number of shards, each protected by its
+/// own lock, so operations on different shards do not contend. Since no
+/// references can escape a lock, lookups return clones of the values.
+pub struct ConcurrentHashMap<K, V, S = RandomState> {
+    shards: Box<[RwLock<HashMap<K, V, S>>]>,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V> ConcurrentHashMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(n: usize) -> Self {
+        Self::with_shards_and_hasher(n, RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> ConcurrentHashMap<K, V, S> {
+    pub fn with_shards_and_hasher(n: usize, hasher: S) -> Self {
+        assert!(n > 0, "need at least one shard");
+        let shards = (0..n)
+            .map(|_| RwLock::new(HashMap::with_hasher(hasher.clone())))
+            .collect();
+        ConcurrentHashMap { shards, hasher }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Number of entries. Concurrent updates may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| read(s).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| read(s).is_empty())
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(key, V::clone)
+    }
+
+    /// Look up a key and apply `f` to the value while the shard is locked.
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        read(self.shard(key)).get(key).map(f)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        read(self.shard(key)).contains_key(key)
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        write(self.shard(&key)).insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        write(self.shard(key)).remove(key)
+    }
+
+    /// Insert many entries, locking and growing every shard only once.
+    /// Much faster than inserting the entries one at a time.
+    pub fn insert_batch(&self, entries: impl IntoIterator<Item = (K, V)>) {
+        let mut batches: Vec<Vec<(K, V)>> = self.shards.iter().map(|_| Vec::new()).collect();
+        for (key, value) in entries {
+            batches[self.shard_index(&key)].push((key, value));
+        }
+        for (shard, batch) in self.shards.iter().zip(batches) {
+            if !batch.is_empty() {
+                let mut shard = write(shard);
+                shard.reserve(batch.len());
+                shard.extend(batch);
+            }
+        }
+    }
+
+    /// Atomically read and modify the entry for `key`.
+    ///
+    /// The closure receives the current value (or `None`) and may change,
+    /// insert or remove it by assigning to the option. No other thread can
+    /// access the entry while the closure runs.
+    pub fn update<R>(&self, key: K, f: impl FnOnce(&mut Option<V>) -> R) -> R {
+        let mut shard = write(self.shard(&key));
+        let mut value = shard.remove(&key);
+        let result = f(&mut value);
+        if let Some(value) = value {
+            shard.insert(key, value);
+        }
+        result
+    }
+
+    /// Visit all entries, one shard at a time.
+    ///
+    /// Each shard is locked while it is visited, so the iteration is
+    /// consistent per shard but not across shards.
+    pub fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+        for shard in self.shards.iter() {
+            for (k, v) in read(shard).iter() {
+                f(k, v);
+            }
+        }
+    }
+
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in self.shards.iter() {
+            write(shard).retain(|k, v| f(k, v));
+        }
+    }
+
+    /// Remove all entries, one shard at a time. The shards keep their
+    /// allocations.
+    pub fn drain(&self) -> std::vec::IntoIter<(K, V)> {
+        self.drain_filter(|_, _| true)
+    }
+
+    /// Remove the entries for which `pred` returns true.
+    pub fn drain_filter(
+        &self,
+        mut pred: impl FnMut(&K, &mut V) -> bool,
+    ) -> std::vec::IntoIter<(K, V)> {
+        let mut removed = Vec::new();
+        for shard in self.shards.iter() {
+            removed.extend(write(shard).extract_if(|k, v| pred(k, v)));
+        }
+        removed.into_iter()
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            write(shard).clear();
+        }
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &RwLock<HashMap<K, V, S>> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    fn shard_index<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        let h = self.hasher.hash_one(key);
+        (h % self.shards.len() as u64) as usize
+    }
+}
+
+// A panic while holding a shard lock cannot leave a `HashMap` in an invalid
+// state, so poisoning is ignored.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<K: Hash + Eq, V> Default for ConcurrentHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> fmt::Debug for ConcurrentHashMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut map = f.debug_map();
+        self.for_each(|k, v| {
+            map.entry(k, v);
+        });
+        map.finish()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for ConcurrentHashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let map = ConcurrentHashMap::new();
+        map.insert_batch(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn empty_map() {
+        let map = ConcurrentHashMap::<String, i32>::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get("x"), None);
+        assert_eq!(map.remove("x"), None);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let map = ConcurrentHashMap::new();
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("b".to_string(), 2), None);
+        assert_eq!(map.insert("a".to_string(), 3), Some(1));
+        assert_eq!(map.get("a"), Some(3));
+        assert_eq!(map.get_with("b", |v| v * 10), Some(20));
+        assert!(map.contains_key("b"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove("a"), Some(3));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn update_inserts_modifies_and_removes() {
+        let map = ConcurrentHashMap::new();
+        map.update(1, |v| *v = Some(10));
+        assert_eq!(map.get(&1), Some(10));
+        let old = map.update(1, |v| {
+            let old = *v;
+            *v.as_mut().unwrap() += 1;
+            old
+        });
+        assert_eq!(old, Some(10));
+        assert_eq!(map.get(&1), Some(11));
+        map.update(1, |v| *v = None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn for_each_and_retain() {
+        let map: ConcurrentHashMap<_, _> = (0..100).map(|i| (i, i * i)).collect();
+        let mut sum = 0;
+        map.for_each(|_, v| sum += v);
+        assert_eq!(sum, (0..100).map(|i| i * i).sum::<i32>());
+        map.retain(|k, _| k % 2 == 0);
+        assert_eq!(map.len(), 50);
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn batch_insert() {
+        let map = ConcurrentHashMap::with_shards(4);
+        map.insert(7, 0);
+        map.insert_batch((0..1000).map(|i| (i, i)));
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.get(&7), Some(7));
+        map.insert_batch(std::iter::empty());
+        assert_eq!(map.len(), 1000);
+    }
+
+    #[test]
+    fn drain_entries() {
+        let map: ConcurrentHashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        let mut odd: Vec<_> = map
+            .drain_filter(|k, _| k % 2 == 1)
+            .map(|(k, _)| k)
+            .collect();
+        odd.sort();
+        assert_eq!(odd, (1..100).step_by(2).collect::<Vec<_>>());
+        assert_eq!(map.len(), 50);
+        assert_eq!(map.drain().count(), 50);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn concurrent_counters() {
+        let map = ConcurrentHashMap::with_shards(4);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for i in 0..1000 {
+                        map.update(i % 10, |v| *v = Some(v.unwrap_or(0) + 1));
+                    }
+                });
+            }
+        });
+        for i in 0..10 {
+            assert_eq!(map.get(&i), Some(800));
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hint;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const MAX_LEVEL: usize = 24;
+
+/// An ordered map that can be shared between threads.
+///
+/// This is the "lazy" skip list of Herlihy et al.: lookups never take a node's
+/// update lock, only each link's mutex for as long as it takes to clone the
+/// pointer, while updates lock only the immediate predecessors of the
+/// affected node and validate them before linking. Nodes are reference counted, so a
+/// removed node stays valid for readers that are still looking at it.
+pub struct ConcurrentSkipListMap<K, V> {
+    head: Arc<Node<K, V>>,
+    len: AtomicUsize,
+    seed: AtomicU64,
+}
+
+type Link<K, V> = Option<Arc<Node<K, V>>>;
+
+struct Node<K, V> {
+    // `None` only for the head sentinel
+    entry: Option<(K, Mutex<V>)>,
+    next: Box<[Mutex<Link<K, V>>]>,
+    marked: AtomicBool,
+    fully_linked: AtomicBool,
+    locked: AtomicBool,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(entry: Option<(K, Mutex<V>)>, next: Vec<Link<K, V>>) -> Self {
+        Node {
+            entry,
+            next: next.into_iter().map(Mutex::new).collect(),
+            marked: AtomicBool::new(false),
+            fully_linked: AtomicBool::new(false),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    fn key(&self) -> &K {
+        &self.entry.as_ref().expect("head has no key").0
+    }
+
+    fn top_level(&self) -> usize {
+        self.next.len() - 1
+    }
+
+    fn load(&self, level: usize) -> Link<K, V> {
+        lock(&self.next[level]).clone()
+    }
+
+    fn store(&self, level: usize, link: Link<K, V>) {
+        *lock(&self.next[level]) = link;
+    }
+
+    fn is_marked(&self) -> bool {
+        self.marked.load(Ordering::Acquire)
+    }
+
+    fn lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            thread::yield_now();
+        }
+    }
+
+    fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+/// Waits in a retry loop: spins for a few exponentially growing rounds,
+/// then yields the thread to let the thread it waits for make progress.
+struct Backoff(u32);
+
+impl Backoff {
+    const SPIN_LIMIT: u32 = 6;
+
+    fn snooze(&mut self) {
+        if self.0 < Self::SPIN_LIMIT {
+            for _ in 0..1 << self.0 {
+                hint::spin_loop();
+            }
+            self.0 += 1;
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn same<K, V>(a: &Link<K, V>, b: &Link<K, V>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        _ => false,
+    }
+}
+
+struct Path<K, V> {
+    preds: Vec<Arc<Node<K, V>>>,
+    succs: Vec<Link<K, V>>,
+}
+
+impl<K, V> Path<K, V> {
+    /// Lock the predecessors on levels `0..=top`. The same node may be the
+    /// predecessor on several consecutive levels; it is locked only once.
+    fn lock(&self, top: usize) -> Vec<&Arc<Node<K, V>>> {
+        let mut locked: Vec<&Arc<Node<K, V>>> = vec![];
+        for pred in &self.preds[..=top] {
+            if locked.last().is_none_or(|last| !Arc::ptr_eq(last, pred)) {
+                pred.lock();
+                locked.push(pred);
+            }
+        }
+        locked
+    }
+}
+
+fn unlock_all<K, V>(nodes: Vec<&Arc<Node<K, V>>>) {
+    for node in nodes {
+        node.unlock();
+    }
+}
+
+impl<K: Ord, V> ConcurrentSkipListMap<K, V> {
+    pub fn new() -> Self {
+        ConcurrentSkipListMap {
+            head: Arc::new(Node::new(None, vec![None; MAX_LEVEL])),
+            len: AtomicUsize::new(0),
+            seed: AtomicU64::new(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    /// Number of entries. Concurrent updates may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(0).is_none()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: Clone,
+    {
+        let node = self.find_node(key)?;
+        let value = lock(&node.entry.as_ref().unwrap().1).clone();
+        Some(value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find_node(key).is_some()
+    }
+
+    /// Insert a key-value pair, returning the previous value of the key.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let top = self.random_level();
+        let mut value = Some(value);
+        let mut backoff = Backoff(0);
+        loop {
+            let (path, found) = self.find(&key);
+            if let Some(level) = found {
+                let node = path.succs[level].as_ref().unwrap();
+                if !node.is_marked() {
+                    while !node.fully_linked.load(Ordering::Acquire) {
+                        thread::yield_now();
+                    }
+                    let mut slot = lock(&node.entry.as_ref().unwrap().1);
+                    return Some(std::mem::replace(&mut *slot, value.take().unwrap()));
+                }
+                // the node is being removed; retry once it is unlinked
+                backoff.snooze();
+                continue;
+            }
+
+            let locked = path.lock(top);
+            let valid = (0..=top).all(|level| {
+                let pred = &path.preds[level];
+                let succ = &path.succs[level];
+                !pred.is_marked()
+                    && succ.as_ref().is_none_or(|s| !s.is_marked())
+                    && same(&pred.load(level), succ)
+            });
+            if !valid {
+                unlock_all(locked);
+                continue;
+            }
+
+            let entry = (key, Mutex::new(value.take().unwrap()));
+            let node = Arc::new(Node::new(Some(entry), path.succs[..=top].to_vec()));
+            for level in 0..=top {
+                path.preds[level].store(level, Some(node.clone()));
+            }
+            node.fully_linked.store(true, Ordering::Release);
+            self.len.fetch_add(1, Ordering::Relaxed);
+            unlock_all(locked);
+            return None;
+        }
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: Clone,
+    {
+        let mut victim: Option<Arc<Node<K, V>>> = None;
+        loop {
+            let (path, found) = self.find(key);
+            if victim.is_none() {
+                let level = found?;
+                let node = path.succs[level].clone().unwrap();
+                let ready = node.fully_linked.load(Ordering::Acquire)
+                    && node.top_level() == level
+                    && !node.is_marked();
+                if !ready {
+                    return None;
+                }
+                node.lock();
+                if node.is_marked() {
+                    node.unlock();
+                    return None;
+                }
+                node.marked.store(true, Ordering::Release);
+                victim = Some(node);
+            }
+
+            let node = victim.as_ref().unwrap();
+            let top = node.top_level();
+            let locked = path.lock(top);
+            let valid = (0..=top).all(|level| {
+                let pred = &path.preds[level];
+                !pred.is_marked() && same(&pred.load(level), &victim)
+            });
+            if !valid {
+                unlock_all(locked);
+                continue;
+            }
+
+            for level in (0..=top).rev() {
+                path.preds[level].store(level, node.load(level));
+            }
+            node.unlock();
+            unlock_all(locked);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            let value = lock(&node.entry.as_ref().unwrap().1).clone();
+            return Some(value);
+        }
+    }
+
+    pub fn first(&self) -> Option<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.iter().next()
+    }
+
+    /// Iterate over a range of entries in key order.
+    ///
+    /// The iterator is weakly consistent: it never yields an entry twice or
+    /// out of order, and it reflects some but not necessarily all updates made
+    /// concurrently with the iteration.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Clone,
+    {
+        let next = match range.start_bound() {
+            Bound::Unbounded => self.head.load(0),
+            Bound::Included(start) => self.seek(|k| k < start).succs[0].clone(),
+            Bound::Excluded(start) => self.seek(|k| k <= start).succs[0].clone(),
+        };
+        Range {
+            next,
+            end: range.end_bound().cloned(),
+            _map: self,
+        }
+    }
+
+    pub fn iter(&self) -> Range<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.range(..)
+    }
+
+    fn find_node<Q>(&self, key: &Q) -> Option<Arc<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (path, found) = self.find(key);
+        let node = path.succs[found?].clone().unwrap();
+        if node.fully_linked.load(Ordering::Acquire) && !node.is_marked() {
+            Some(node)
+        } else {
+            None
+        }
+    }
+
+    /// Find the predecessors and successors of `key` on every level, and the
+    /// highest level on which a node with that key was found.
+    fn find<Q>(&self, key: &Q) -> (Path<K, V>, Option<usize>)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let path = self.seek(|k| k.borrow() < key);
+        let found = (0..MAX_LEVEL).rev().find(|&level| {
+            let succ = path.succs[level].as_ref();
+            succ.is_some_and(|node| node.key().borrow() == key)
+        });
+        (path, found)
+    }
+
+    /// Walk down the list, advancing on each level while `before` holds for
+    /// the next key.
+    fn seek(&self, before: impl Fn(&K) -> bool) -> Path<K, V> {
+        let mut preds = vec![self.head.clone(); MAX_LEVEL];
+        let mut succs = vec![None; MAX_LEVEL];
+        let mut pred = self.head.clone();
+        for level in (0..MAX_LEVEL).rev() {
+            let mut curr = pred.load(level);
+            while let Some(node) = curr.as_ref().filter(|n| before(n.key())) {
+                pred = node.clone();
+                curr = pred.load(level);
+            }
+            preds[level] = pred.clone();
+            succs[level] = curr;
+        }
+        Path { preds, succs }
+    }
+
+    fn random_level(&self) -> usize {
+        // xorshift; a data race on the seed only affects randomness
+        let mut x = self.seed.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.seed.store(x, Ordering::Relaxed);
+        (x & ((1 << (MAX_LEVEL - 1)) - 1)).trailing_ones() as usize
+    }
+}
+
+impl<K, V> Drop for ConcurrentSkipListMap<K, V> {
+    fn drop(&mut self) {
+        // unlink iteratively to avoid deep recursion in the nodes' destructors
+        for level in 1..MAX_LEVEL {
+            self.head.store(level, None);
+        }
+        let mut curr = lock(&self.head.next[0]).take();
+        while let Some(node) = curr {
+            for level in 1..node.next.len() {
+                node.store(level, None);
+            }
+            curr = lock(&node.next[0]).take();
+        }
+    }
+}
+
+impl<K: Ord, V> Default for ConcurrentSkipListMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug
+    for ConcurrentSkipListMap<K, V>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for ConcurrentSkipListMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let map = ConcurrentSkipListMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+pub struct Range<'a, K, V> {
+    next: Link<K, V>,
+    end: Bound<K>,
+    _map: &'a ConcurrentSkipListMap<K, V>,
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for Range<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        loop {
+            let node = self.next.take()?;
+            let (key, value) = node.entry.as_ref().unwrap();
+            let in_range = match &self.end {
+                Bound::Unbounded => true,
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+            };
+            if !in_range {
+                return None;
+            }
+            self.next = node.load(0);
+            if !node.is_marked() {
+                return Some((key.clone(), lock(value).clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn empty_map() {
+        let map = ConcurrentSkipListMap::<i32, i32>::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(map.first(), None);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let map = ConcurrentSkipListMap::new();
+        assert_eq!(map.insert(5, "five"), None);
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(5, "FIVE"), Some("five"));
+        assert_eq!(map.get(&5), Some("FIVE"));
+        assert!(map.contains_key(&1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove(&1), Some("one"));
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn borrowed_lookup() {
+        let map = ConcurrentSkipListMap::new();
+        map.insert("key".to_string(), 1);
+        assert_eq!(map.get("key"), Some(1));
+        assert_eq!(map.remove("key"), Some(1));
+    }
+
+    #[test]
+    fn ordered_ranges() {
+        let map: ConcurrentSkipListMap<_, _> = (0..50).rev().map(|i| (i * 2, i)).collect();
+        assert_eq!(map.first(), Some((0, 0)));
+        let keys: Vec<_> = map.range(10..=16).map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![10, 12, 14, 16]);
+        let keys: Vec<_> = map.range(91..).map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![92, 94, 96, 98]);
+        use std::ops::Bound::{Excluded, Included};
+        let keys: Vec<_> = map
+            .range((Excluded(10), Included(13)))
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec![12]);
+    }
+
+    #[test]
+    fn matches_btreemap() {
+        let map = ConcurrentSkipListMap::new();
+        let mut reference = BTreeMap::new();
+        let mut x = 11u32;
+        for i in 0..3000 {
+            x = (x * 1103 + 12345) % 1000;
+            if i % 3 == 0 {
+                assert_eq!(map.remove(&x), reference.remove(&x));
+            } else {
+                assert_eq!(map.insert(x, i), reference.insert(x, i));
+            }
+        }
+        assert_eq!(map.len(), reference.len());
+        assert!(map.iter().eq(reference.into_iter()));
+    }
+
+    #[test]
+    fn concurrent_updates() {
+        let map = ConcurrentSkipListMap::new();
+        thread::scope(|s| {
+            for t in 0..4 {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..2000 {
+                        map.insert(i * 4 + t, t);
+                    }
+                    for i in 0..1000 {
+                        assert_eq!(map.remove(&(i * 8 + t)), Some(t));
+                    }
+                });
+            }
+            s.spawn(|| {
+                // a concurrent scan must stay sorted
+                let keys: Vec<_> = map.iter().map(|(k, _)| k).collect();
+                assert!(keys.windows(2).all(|w| w[0] < w[1]));
+            });
+        });
+        assert_eq!(map.len(), 4000);
+        let keys: Vec<_> = map.iter().map(|(k, _)| k).collect();
+        let expected: Vec<_> = (0..8000).filter(|k| k % 8 >= 4).collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn dropping_large_map_does_not_overflow_stack() {
+        let map = ConcurrentSkipListMap::new();
+        for i in 0..200_000 {
+            map.insert(i, ());
+        }
+        drop(map);
+    }
+}
@@ -0,0 +1,382 @@
+//! Two-phase graphs: build with `GraphBuilder`, traverse a `CsrGraph`.
+//!
+//! The builder keeps every node's edges as a linked list of slots in one
+//! shared arena, so adding and removing edges never moves other edges.
+//! `freeze` turns it into compressed sparse row form: the edges of each
+//! node are stored next to each other, sorted by target, in one array of
+//! targets and one of weights. A traversal then reads memory sequentially.
+//! Both implement `Graph`, so the same code runs on either phase.
+
+use crate::graph::{Graph, GraphMut};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{fmt, iter, slice};
+
+const NIL: usize = usize::MAX;
+
+/// A mutable graph whose edge lists live in a shared arena.
+#[derive(Clone)]
+pub struct GraphBuilder<W> {
+    /// First slot of the edge list of every node.
+    heads: Vec<usize>,
+    slots: Vec<Slot<W>>,
+    /// First slot of the list of removed slots.
+    free: usize,
+    edges: usize,
+}
+
+#[derive(Clone)]
+struct Slot<W> {
+    to: usize,
+    weight: W,
+    next: usize,
+}
+
+impl<W: Copy> GraphBuilder<W> {
+    /// A graph with `nodes` nodes and no edges.
+    pub fn new(nodes: usize) -> Self {
+        Self::with_capacity(nodes, 0)
+    }
+
+    /// Like `new`, with room for `edges` edges.
+    pub fn with_capacity(nodes: usize, edges: usize) -> Self {
+        GraphBuilder {
+            heads: vec![NIL; nodes],
+            slots: Vec::with_capacity(edges),
+            free: NIL,
+            edges: 0,
+        }
+    }
+
+    /// Convert into the compact read-only form in O(n + m log d), where
+    /// `d` is the largest out-degree.
+    pub fn freeze(self) -> CsrGraph<W> {
+        let mut offsets = Vec::with_capacity(self.heads.len() + 1);
+        let mut targets = Vec::with_capacity(self.edges);
+        let mut weights = Vec::with_capacity(self.edges);
+        let mut buffer = Vec::new();
+        offsets.push(0);
+        for u in 0..self.heads.len() {
+            buffer.extend(self.neighbors(u));
+            buffer.sort_unstable_by_key(|&(v, _)| v);
+            for (v, w) in buffer.drain(..) {
+                targets.push(v);
+                weights.push(w);
+            }
+            offsets.push(targets.len());
+        }
+        CsrGraph {
+            offsets,
+            targets,
+            weights,
+        }
+    }
+
+    /// The slot of the edge `from -> to` and the slot before it.
+    fn find(&self, from: usize, to: usize) -> Option<(usize, usize)> {
+        let mut prev = NIL;
+        let mut slot = *self.heads.get(from)?;
+        while slot != NIL {
+            if self.slots[slot].to == to {
+                return Some((prev, slot));
+            }
+            prev = slot;
+            slot = self.slots[slot].next;
+        }
+        None
+    }
+}
+
+impl<W: Copy> Graph for GraphBuilder<W> {
+    type Weight = W;
+    type Neighbors<'a>
+        = BuilderNeighbors<'a, W>
+    where
+        W: 'a;
+
+    fn node_count(&self) -> usize {
+        self.heads.len()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edges
+    }
+
+    fn edge(&self, from: usize, to: usize) -> Option<W> {
+        let (_, slot) = self.find(from, to)?;
+        Some(self.slots[slot].weight)
+    }
+
+    /// The most recently added edges come first.
+    fn neighbors(&self, node: usize) -> Self::Neighbors<'_> {
+        BuilderNeighbors {
+            slots: &self.slots,
+            next: self.heads[node],
+        }
+    }
+}
+
+impl<W: Copy> GraphMut for GraphBuilder<W> {
+    fn add_node(&mut self) -> usize {
+        self.heads.push(NIL);
+        self.heads.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: W) -> Option<W> {
+        assert!(to < self.node_count(), "node out of bounds");
+        if let Some((_, slot)) = self.find(from, to) {
+            return Some(core::mem::replace(&mut self.slots[slot].weight, weight));
+        }
+        let slot = Slot {
+            to,
+            weight,
+            next: self.heads[from],
+        };
+        let index = if self.free == NIL {
+            self.slots.push(slot);
+            self.slots.len() - 1
+        } else {
+            let index = self.free;
+            self.free = self.slots[index].next;
+            self.slots[index] = slot;
+            index
+        };
+        self.heads[from] = index;
+        self.edges += 1;
+        None
+    }
+
+    fn remove_edge(&mut self, from: usize, to: usize) -> Option<W> {
+        let (prev, slot) = self.find(from, to)?;
+        let next = self.slots[slot].next;
+        if prev == NIL {
+            self.heads[from] = next;
+        } else {
+            self.slots[prev].next = next;
+        }
+        self.slots[slot].next = self.free;
+        self.free = slot;
+        self.edges -= 1;
+        Some(self.slots[slot].weight)
+    }
+}
+
+impl<W: Copy + fmt::Debug> fmt::Debug for GraphBuilder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries((0..self.node_count()).map(|u| (u, self.neighbors(u))))
+            .finish()
+    }
+}
+
+pub struct BuilderNeighbors<'a, W> {
+    slots: &'a [Slot<W>],
+    next: usize,
+}
+
+impl<W: Copy> Iterator for BuilderNeighbors<'_, W> {
+    type Item = (usize, W);
+
+    fn next(&mut self) -> Option<(usize, W)> {
+        let slot = self.slots.get(self.next)?;
+        self.next = slot.next;
+        Some((slot.to, slot.weight))
+    }
+}
+
+impl<W: Copy + fmt::Debug> fmt::Debug for BuilderNeighbors<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(BuilderNeighbors {
+                slots: self.slots,
+                next: self.next,
+            })
+            .finish()
+    }
+}
+
+/// An immutable graph in compressed sparse row form.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CsrGraph<W> {
+    /// The edges of node `u` are at `offsets[u]..offsets[u + 1]`.
+    offsets: Vec<usize>,
+    targets: Vec<usize>,
+    weights: Vec<W>,
+}
+
+impl<W: Copy> CsrGraph<W> {
+    pub fn out_degree(&self, node: usize) -> usize {
+        self.offsets[node + 1] - self.offsets[node]
+    }
+
+    /// The targets of the edges leaving `node`, in ascending order.
+    pub fn targets(&self, node: usize) -> &[usize] {
+        &self.targets[self.offsets[node]..self.offsets[node + 1]]
+    }
+
+    /// The weights of the edges leaving `node`, matching `targets`.
+    pub fn weights(&self, node: usize) -> &[W] {
+        &self.weights[self.offsets[node]..self.offsets[node + 1]]
+    }
+
+    /// The same graph with every edge reversed, in O(n + m).
+    pub fn transpose(&self) -> Self {
+        let n = self.node_count();
+        let mut offsets = vec![0; n + 1];
+        for &v in &self.targets {
+            offsets[v + 1] += 1;
+        }
+        for u in 0..n {
+            offsets[u + 1] += offsets[u];
+        }
+        // sources are visited in ascending order, so every list ends up sorted
+        let mut fill = offsets.clone();
+        let mut targets = vec![0; self.targets.len()];
+        let mut weights = self.weights.clone();
+        for (u, v, w) in self.edges() {
+            targets[fill[v]] = u;
+            weights[fill[v]] = w;
+            fill[v] += 1;
+        }
+        CsrGraph {
+            offsets,
+            targets,
+            weights,
+        }
+    }
+
+    /// Convert back into a builder to make changes.
+    pub fn thaw(&self) -> GraphBuilder<W> {
+        let mut builder = GraphBuilder::with_capacity(self.node_count(), self.edge_count());
+        for (u, v, w) in self.edges() {
+            builder.add_edge(u, v, w);
+        }
+        builder
+    }
+}
+
+impl<W: Copy> Graph for CsrGraph<W> {
+    type Weight = W;
+    type Neighbors<'a>
+        = iter::Zip<iter::Copied<slice::Iter<'a, usize>>, iter::Copied<slice::Iter<'a, W>>>
+    where
+        W: 'a;
+
+    fn node_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// O(log d) by binary search.
+    fn edge(&self, from: usize, to: usize) -> Option<W> {
+        if from >= self.node_count() {
+            return None;
+        }
+        let i = self.targets(from).binary_search(&to).ok()?;
+        Some(self.weights(from)[i])
+    }
+
+    fn neighbors(&self, node: usize) -> Self::Neighbors<'_> {
+        let targets = self.targets(node).iter().copied();
+        targets.zip(self.weights(node).iter().copied())
+    }
+}
+
+impl<W: Copy + fmt::Debug> fmt::Debug for CsrGraph<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries((0..self.node_count()).map(|u| (u, self.targets(u))))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::AdjacencyList;
+    use crate::shortest_path::dijkstra;
+
+    fn sample() -> GraphBuilder<u32> {
+        let mut g = GraphBuilder::new(5);
+        for (u, v, w) in [
+            (0, 2, 1),
+            (0, 1, 4),
+            (2, 1, 2),
+            (1, 3, 1),
+            (2, 3, 7),
+            (3, 4, 3),
+        ] {
+            g.add_edge(u, v, w);
+        }
+        g
+    }
+
+    /// Sum of the targets of all out-edges of every node, through the
+    /// `Graph` trait only.
+    fn checksum<G: Graph>(g: &G) -> usize {
+        g.edges().map(|(u, v, _)| u * 31 + v).sum()
+    }
+
+    #[test]
+    fn builder_edits_in_place() {
+        let mut g = sample();
+        assert_eq!(g.add_edge(0, 1, 5), Some(4));
+        assert_eq!(g.remove_edge(2, 3), Some(7));
+        assert_eq!(g.remove_edge(2, 3), None);
+        assert_eq!(g.edge_count(), 5);
+        // the freed slot is reused
+        let slots = g.slots.len();
+        assert_eq!(g.add_node(), 5);
+        g.add_edge(5, 0, 9);
+        assert_eq!(g.slots.len(), slots);
+        assert_eq!(g.edge(5, 0), Some(9));
+        assert_eq!(g.neighbors(0).collect::<Vec<_>>(), vec![(1, 5), (2, 1)]);
+    }
+
+    #[test]
+    fn frozen_graph_has_sorted_rows() {
+        let csr = sample().freeze();
+        assert_eq!((csr.node_count(), csr.edge_count()), (5, 6));
+        assert_eq!(csr.targets(0), &[1, 2]);
+        assert_eq!(csr.weights(0), &[4, 1]);
+        assert_eq!(csr.out_degree(4), 0);
+        assert_eq!(csr.edge(2, 3), Some(7));
+        assert_eq!(csr.edge(3, 2), None);
+        assert_eq!(csr.edge(9, 0), None);
+        assert_eq!(
+            format!("{csr:?}"),
+            "{0: [1, 2], 1: [3], 2: [1, 3], 3: [4], 4: []}"
+        );
+    }
+
+    #[test]
+    fn both_phases_agree() {
+        let builder = sample();
+        let mut list = AdjacencyList::new(5);
+        for (u, v, w) in builder.edges() {
+            list.add_edge(u, v, w);
+        }
+        let csr = builder.clone().freeze();
+        assert_eq!(checksum(&builder), checksum(&csr));
+        assert_eq!(checksum(&list), checksum(&csr));
+        let a = dijkstra(5, 0, |u| builder.neighbors(u));
+        let b = dijkstra(5, 0, |u| csr.neighbors(u));
+        assert!((0..5).all(|v| a.distance(v) == b.distance(v)));
+        assert_eq!(csr.thaw().freeze(), csr);
+    }
+
+    #[test]
+    fn transpose_reverses_edges() {
+        let csr = sample().freeze();
+        let t = csr.transpose();
+        assert_eq!(t.edge_count(), csr.edge_count());
+        for (u, v, w) in csr.edges() {
+            assert_eq!(t.edge(v, u), Some(w));
+        }
+        assert_eq!(t.targets(1), &[0, 2]);
+        assert_eq!(t.transpose(), csr);
+    }
+}
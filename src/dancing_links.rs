@@ -0,0 +1,376 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+const ROOT: usize = 0;
+
+/// Knuth's dancing links: a sparse boolean matrix stored as circular doubly
+/// linked lists, for solving exact cover problems with Algorithm X.
+///
+/// Primary columns must be covered exactly once by a solution, secondary
+/// columns at most once. All nodes live in parallel vectors; index 0 is the
+/// root and column `c` has its header at index `c + 1`.
+#[derive(Clone)]
+pub struct DancingLinks {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    // column header of each node (headers point to themselves)
+    column: Vec<usize>,
+    // row id of each node; unused for headers
+    row: Vec<usize>,
+    size: Vec<usize>,
+    n_columns: usize,
+    n_rows: usize,
+}
+
+impl DancingLinks {
+    /// A matrix with `columns` primary columns and no rows.
+    pub fn new(columns: usize) -> Self {
+        Self::with_secondary(columns, 0)
+    }
+
+    /// A matrix whose first `primary` columns are primary and the following
+    /// `secondary` columns are secondary.
+    pub fn with_secondary(primary: usize, secondary: usize) -> Self {
+        let n = primary + secondary + 1;
+        let mut dlx = DancingLinks {
+            left: (0..n).collect(),
+            right: (0..n).collect(),
+            up: (0..n).collect(),
+            down: (0..n).collect(),
+            column: (0..n).collect(),
+            row: vec![usize::MAX; n],
+            size: vec![0; n],
+            n_columns: primary + secondary,
+            n_rows: 0,
+        };
+        // only primary headers are linked into the root's list
+        for h in 1..=primary {
+            dlx.left[h] = h - 1;
+            dlx.right[h - 1] = h;
+        }
+        dlx.left[ROOT] = primary;
+        dlx.right[primary] = ROOT;
+        dlx
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.n_columns
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.n_rows
+    }
+
+    /// Number of rows that currently have a 1 in `col`.
+    pub fn column_size(&self, col: usize) -> usize {
+        self.size[self.header(col)]
+    }
+
+    /// Add a row with ones in the given columns and return its id. Ids are
+    /// consecutive, starting at 0.
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        let id = self.n_rows;
+        self.n_rows += 1;
+        let mut first = None;
+        for &col in columns {
+            let h = self.header(col);
+            let x = self.left.len();
+            let (l, r) = match first {
+                None => (x, x),
+                Some(f) => (self.left[f], f),
+            };
+            self.left.push(l);
+            self.right.push(r);
+            self.right[l] = x;
+            self.left[r] = x;
+            self.up.push(self.up[h]);
+            self.down.push(h);
+            self.down[self.up[h]] = x;
+            self.up[h] = x;
+            self.column.push(h);
+            self.row.push(id);
+            self.size[h] += 1;
+            first.get_or_insert(x);
+        }
+        id
+    }
+
+    /// Remove a column and every row that has a 1 in it from the matrix.
+    ///
+    /// Covers must be undone with `uncover` in exactly the reverse order.
+    pub fn cover(&mut self, col: usize) {
+        self.cover_header(self.header(col));
+    }
+
+    pub fn uncover(&mut self, col: usize) {
+        self.uncover_header(self.header(col));
+    }
+
+    /// Iterate over all exact covers. Each solution lists the chosen row ids.
+    ///
+    /// The matrix is restored when the iterator is exhausted or dropped.
+    pub fn solutions(&mut self) -> Solutions<'_> {
+        Solutions {
+            dlx: self,
+            stack: vec![],
+            started: false,
+            done: false,
+        }
+    }
+
+    pub fn first_solution(&mut self) -> Option<Vec<usize>> {
+        self.solutions().next()
+    }
+
+    fn header(&self, col: usize) -> usize {
+        assert!(col < self.n_columns, "column out of range");
+        col + 1
+    }
+
+    fn cover_header(&mut self, c: usize) {
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = r;
+        self.left[r] = l;
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover_header(&mut self, c: usize) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = j;
+                self.up[d] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = c;
+        self.left[r] = c;
+    }
+
+    /// Cover the other columns of the row that `x` belongs to.
+    fn select(&mut self, x: usize) {
+        let mut j = self.right[x];
+        while j != x {
+            self.cover_header(self.column[j]);
+            j = self.right[j];
+        }
+    }
+
+    fn unselect(&mut self, x: usize) {
+        let mut j = self.left[x];
+        while j != x {
+            self.uncover_header(self.column[j]);
+            j = self.left[j];
+        }
+    }
+
+    /// The uncovered primary column with the fewest rows.
+    fn choose_column(&self) -> usize {
+        let mut best = self.right[ROOT];
+        let mut c = self.right[best];
+        while c != ROOT {
+            if self.size[c] < self.size[best] {
+                best = c;
+            }
+            c = self.right[c];
+        }
+        best
+    }
+}
+
+impl fmt::Debug for DancingLinks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DancingLinks")
+            .field("columns", &self.n_columns)
+            .field("rows", &self.n_rows)
+            .finish()
+    }
+}
+
+/// Iterator over exact covers, created by `DancingLinks::solutions`.
+pub struct Solutions<'a> {
+    dlx: &'a mut DancingLinks,
+    // the chosen node on each search level
+    stack: Vec<usize>,
+    started: bool,
+    done: bool,
+}
+
+impl Solutions<'_> {
+    /// Choose the row of `x` or, if its column is exhausted, backtrack to the
+    /// next candidate on a lower level. Returns false when the search is over.
+    fn try_from(&mut self, mut x: usize) -> bool {
+        loop {
+            let c = self.dlx.column[x];
+            if x == c {
+                self.dlx.uncover_header(c);
+                match self.stack.pop() {
+                    None => return false,
+                    Some(prev) => {
+                        self.dlx.unselect(prev);
+                        x = self.dlx.down[prev];
+                    }
+                }
+            } else {
+                self.dlx.select(x);
+                self.stack.push(x);
+                return true;
+            }
+        }
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done {
+            return None;
+        }
+        if self.started {
+            // resume the search behind the previous solution
+            let alive = match self.stack.pop() {
+                None => false,
+                Some(prev) => {
+                    self.dlx.unselect(prev);
+                    self.try_from(self.dlx.down[prev])
+                }
+            };
+            if !alive {
+                self.done = true;
+                return None;
+            }
+        }
+        self.started = true;
+        loop {
+            if self.dlx.right[ROOT] == ROOT {
+                return Some(self.stack.iter().map(|&x| self.dlx.row[x]).collect());
+            }
+            let c = self.dlx.choose_column();
+            self.dlx.cover_header(c);
+            if !self.try_from(self.dlx.down[c]) {
+                self.done = true;
+                return None;
+            }
+        }
+    }
+}
+
+impl Drop for Solutions<'_> {
+    fn drop(&mut self) {
+        while let Some(x) = self.stack.pop() {
+            self.dlx.unselect(x);
+            self.dlx.uncover_header(self.dlx.column[x]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut v: Vec<usize>) -> Vec<usize> {
+        v.sort();
+        v
+    }
+
+    fn knuth_example() -> DancingLinks {
+        let mut dlx = DancingLinks::new(7);
+        for row in [
+            &[2, 4, 5][..],
+            &[0, 3, 6],
+            &[1, 2, 5],
+            &[0, 3],
+            &[1, 6],
+            &[3, 4, 6],
+        ] {
+            dlx.add_row(row);
+        }
+        dlx
+    }
+
+    #[test]
+    fn unique_solution() {
+        let mut dlx = knuth_example();
+        let solutions: Vec<_> = dlx.solutions().map(sorted).collect();
+        assert_eq!(solutions, vec![vec![0, 3, 4]]);
+    }
+
+    #[test]
+    fn no_solution() {
+        let mut dlx = DancingLinks::new(3);
+        dlx.add_row(&[0, 1]);
+        dlx.add_row(&[1, 2]);
+        assert_eq!(dlx.solutions().count(), 0);
+    }
+
+    #[test]
+    fn set_partitions() {
+        // covering {0, 1, 2, 3} with any of its non-empty subsets enumerates
+        // its partitions; there are as many as the Bell number B4 = 15
+        let mut dlx = DancingLinks::new(4);
+        for mask in 1..16usize {
+            let cols: Vec<_> = (0..4).filter(|i| mask & (1 << i) != 0).collect();
+            dlx.add_row(&cols);
+        }
+        assert_eq!(dlx.solutions().count(), 15);
+    }
+
+    #[test]
+    fn matrix_is_restored() {
+        let mut dlx = knuth_example();
+        let sizes: Vec<_> = (0..7).map(|c| dlx.column_size(c)).collect();
+        // abandon an iteration early, then exhaust one
+        assert!(dlx.first_solution().is_some());
+        assert_eq!(
+            (0..7).map(|c| dlx.column_size(c)).collect::<Vec<_>>(),
+            sizes
+        );
+        assert_eq!(dlx.solutions().count(), 1);
+        assert_eq!(dlx.solutions().count(), 1);
+
+        dlx.cover(3);
+        assert_eq!(dlx.column_size(0), 0);
+        dlx.uncover(3);
+        assert_eq!(dlx.column_size(0), 2);
+    }
+
+    #[test]
+    fn n_queens_with_secondary_columns() {
+        fn queens(n: usize) -> usize {
+            // primary: ranks and files; secondary: both kinds of diagonals
+            let mut dlx = DancingLinks::with_secondary(2 * n, 2 * (2 * n - 1));
+            for r in 0..n {
+                for c in 0..n {
+                    let diag = 2 * n + r + c;
+                    let anti = 2 * n + (2 * n - 1) + (r + n - 1 - c);
+                    dlx.add_row(&[r, n + c, diag, anti]);
+                }
+            }
+            dlx.solutions().count()
+        }
+        assert_eq!(queens(1), 1);
+        assert_eq!(queens(3), 0);
+        assert_eq!(queens(6), 4);
+        assert_eq!(queens(8), 92);
+    }
+}
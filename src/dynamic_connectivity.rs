@@ -0,0 +1,299 @@
+//! Fully dynamic connectivity in undirected graphs.
+//!
+//! This is the structure of Holm, de Lichtenberg and Thorup. A spanning
+//! forest is kept as Euler tour trees, and every edge has a level that only
+//! ever rises. The forest of level `i` holds the tree edges of level `i` or
+//! higher, and its trees have at most `n / 2^i` vertices. When a tree edge
+//! is deleted, the smaller of the two halves pushes its edges one level up
+//! while a replacement edge is searched among its non-tree edges. This
+//! gives amortized O(log² n) updates and O(log n) queries.
+
+use crate::euler_tour_tree::EulerTourForest;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::fmt;
+
+pub struct DynamicConnectivity {
+    levels: Vec<Level>,
+    /// Every edge by its endpoints in ascending order.
+    edges: BTreeMap<(usize, usize), Edge>,
+    components: usize,
+}
+
+struct Level {
+    forest: EulerTourForest,
+    /// The non-tree edges of this level at every vertex. A vertex is marked
+    /// in `forest` if it has any; a tree edge is marked if this is its level.
+    non_tree: Vec<BTreeSet<usize>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    level: usize,
+    tree: bool,
+}
+
+impl DynamicConnectivity {
+    /// A graph of `n` vertices without edges.
+    pub fn new(n: usize) -> Self {
+        let levels = (usize::BITS - n.leading_zeros()).max(1) as usize;
+        DynamicConnectivity {
+            levels: (0..levels)
+                .map(|_| Level {
+                    forest: EulerTourForest::new(n),
+                    non_tree: (0..n).map(|_| BTreeSet::new()).collect(),
+                })
+                .collect(),
+            edges: BTreeMap::new(),
+            components: n,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.levels[0].forest.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Number of connected components.
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    pub fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.edges.contains_key(&key(u, v))
+    }
+
+    pub fn connected(&self, u: usize, v: usize) -> bool {
+        self.levels[0].forest.connected(u, v)
+    }
+
+    /// Number of vertices in the component of `v`.
+    pub fn component_size(&self, v: usize) -> usize {
+        self.levels[0].forest.component_size(v)
+    }
+
+    /// Add the edge between `u` and `v`. Returns false, and changes nothing,
+    /// for self loops and edges that already exist.
+    pub fn insert_edge(&mut self, u: usize, v: usize) -> bool {
+        if u == v || self.has_edge(u, v) {
+            return false;
+        }
+        let (a, b) = key(u, v);
+        let forest = &mut self.levels[0].forest;
+        let tree = forest.link(a, b);
+        if tree {
+            forest.set_edge_mark(a, b, true);
+            self.components -= 1;
+        } else {
+            self.add_non_tree(0, a, b);
+        }
+        self.edges.insert((a, b), Edge { level: 0, tree });
+        true
+    }
+
+    /// Remove the edge between `u` and `v`. Returns false if there is none.
+    pub fn remove_edge(&mut self, u: usize, v: usize) -> bool {
+        let (a, b) = key(u, v);
+        let Some(edge) = self.edges.remove(&(a, b)) else {
+            return false;
+        };
+        if !edge.tree {
+            self.remove_non_tree(edge.level, a, b);
+            return true;
+        }
+        for level in &mut self.levels[..=edge.level] {
+            level.forest.cut(a, b);
+        }
+        for i in (0..=edge.level).rev() {
+            if self.replace(i, a, b) {
+                return true;
+            }
+        }
+        self.components += 1;
+        true
+    }
+
+    /// Look for an edge of level `i` that reconnects the trees of `u` and
+    /// `v` in the forest of level `i`, and make it a tree edge. The edges of
+    /// the smaller tree that are looked at without success move up a level.
+    fn replace(&mut self, i: usize, u: usize, v: usize) -> bool {
+        let forest = &self.levels[i].forest;
+        let (small, other) = if forest.component_size(u) <= forest.component_size(v) {
+            (u, v)
+        } else {
+            (v, u)
+        };
+
+        while let Some((a, b)) = self.levels[i].forest.find_marked_edge(small) {
+            self.levels[i].forest.set_edge_mark(a, b, false);
+            let upper = &mut self.levels[i + 1].forest;
+            upper.link(a, b);
+            upper.set_edge_mark(a, b, true);
+            self.edges.get_mut(&(a, b)).unwrap().level = i + 1;
+        }
+
+        while let Some(x) = self.levels[i].forest.find_marked_vertex(small) {
+            let y = *self.levels[i].non_tree[x].first().unwrap();
+            let (a, b) = key(x, y);
+            self.remove_non_tree(i, a, b);
+            if self.levels[i].forest.connected(y, other) {
+                for level in &mut self.levels[..=i] {
+                    level.forest.link(a, b);
+                }
+                self.levels[i].forest.set_edge_mark(a, b, true);
+                self.edges.insert(
+                    (a, b),
+                    Edge {
+                        level: i,
+                        tree: true,
+                    },
+                );
+                return true;
+            }
+            self.add_non_tree(i + 1, a, b);
+            self.edges.get_mut(&(a, b)).unwrap().level = i + 1;
+        }
+        false
+    }
+
+    fn add_non_tree(&mut self, i: usize, u: usize, v: usize) {
+        let level = &mut self.levels[i];
+        for (x, y) in [(u, v), (v, u)] {
+            level.non_tree[x].insert(y);
+            level.forest.set_vertex_mark(x, true);
+        }
+    }
+
+    fn remove_non_tree(&mut self, i: usize, u: usize, v: usize) {
+        let level = &mut self.levels[i];
+        for (x, y) in [(u, v), (v, u)] {
+            level.non_tree[x].remove(&y);
+            if level.non_tree[x].is_empty() {
+                level.forest.set_vertex_mark(x, false);
+            }
+        }
+    }
+}
+
+fn key(u: usize, v: usize) -> (usize, usize) {
+    (u.min(v), u.max(v))
+}
+
+impl fmt::Debug for DynamicConnectivity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicConnectivity")
+            .field("nodes", &self.node_count())
+            .field("edges", &self.edge_count())
+            .field("components", &self.components)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn cycle_survives_one_deletion() {
+        let mut graph = DynamicConnectivity::new(5);
+        for (u, v) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+            assert!(graph.insert_edge(u, v));
+        }
+        assert_eq!(graph.component_count(), 2);
+        assert!(graph.remove_edge(1, 2));
+        assert!(graph.connected(1, 2));
+        assert!(graph.remove_edge(0, 3));
+        assert!(!graph.connected(1, 2));
+        assert!(graph.connected(0, 1));
+        assert_eq!(graph.component_count(), 3);
+        assert_eq!(graph.component_size(3), 2);
+    }
+
+    #[test]
+    fn duplicates_and_self_loops_are_rejected() {
+        let mut graph = DynamicConnectivity::new(3);
+        assert!(graph.insert_edge(0, 1));
+        assert!(!graph.insert_edge(1, 0));
+        assert!(!graph.insert_edge(2, 2));
+        assert!(graph.has_edge(1, 0));
+        assert!(!graph.remove_edge(0, 2));
+        assert_eq!(graph.edge_count(), 1);
+        assert!(graph.connected(2, 2));
+    }
+
+    #[test]
+    fn dense_graph_torn_down() {
+        let n = 12;
+        let mut graph = DynamicConnectivity::new(n);
+        for u in 0..n {
+            for v in u + 1..n {
+                graph.insert_edge(u, v);
+            }
+        }
+        assert_eq!(graph.component_count(), 1);
+        // remove every edge at vertex 0 except the last one
+        for v in 1..n - 1 {
+            graph.remove_edge(0, v);
+            assert!(graph.connected(0, v));
+        }
+        graph.remove_edge(0, n - 1);
+        assert!(!graph.connected(0, 1));
+        assert_eq!(graph.component_count(), 2);
+        assert_eq!(graph.component_size(5), n - 1);
+    }
+
+    #[test]
+    fn random_updates_match_flood_fill() {
+        let n = 40;
+        let mut graph = DynamicConnectivity::new(n);
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut x: u64 = 5;
+        let mut next = |m: usize| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as usize % m
+        };
+        for round in 0..4000 {
+            // alternate between filling up and thinning out the graph
+            let insert_bias = if (round / 500) % 2 == 0 { 3 } else { 1 };
+            if next(insert_bias + 1) != 0 || edges.is_empty() {
+                let (u, v) = (next(n), next(n));
+                if graph.insert_edge(u, v) {
+                    edges.push(key(u, v));
+                }
+            } else {
+                let (u, v) = edges.swap_remove(next(edges.len()));
+                assert!(graph.remove_edge(v, u));
+            }
+
+            let mut component = vec![usize::MAX; n];
+            let mut count = 0;
+            for s in 0..n {
+                if component[s] != usize::MAX {
+                    continue;
+                }
+                component[s] = s;
+                count += 1;
+                let mut stack = vec![s];
+                while let Some(a) = stack.pop() {
+                    for &(p, q) in &edges {
+                        for (from, to) in [(p, q), (q, p)] {
+                            if from == a && component[to] == usize::MAX {
+                                component[to] = s;
+                                stack.push(to);
+                            }
+                        }
+                    }
+                }
+            }
+            assert_eq!(graph.component_count(), count);
+            let (u, v) = (next(n), next(n));
+            assert_eq!(graph.connected(u, v), component[u] == component[v]);
+        }
+    }
+}
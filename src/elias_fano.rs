@@ -0,0 +1,228 @@
+//! Elias–Fano encoding of a non-decreasing sequence of integers.
+//!
+//! Each value is split into `l` low bits, stored verbatim in a
+//! `PackedIntVec`, and the remaining high bits, stored in unary in a
+//! bit vector: value `i` sets the bit at `(value >> l) + i`. With `l` chosen
+//! as `log2(universe / n)` this takes about `2 + log2(universe / n)` bits
+//! per value, close to the information-theoretic minimum. Access to the
+//! `i`-th value is a `select1` on the high bits, and `next_geq` starts from
+//! the bucket of the query found with `select0`.
+
+use crate::bit_vec::{BitVec, RankSelect};
+use crate::errors::DataStructureError;
+use crate::memory::{MemoryFootprint, MemoryStats};
+use crate::packed_int_vec::PackedIntVec;
+use core::fmt;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct EliasFano {
+    high: RankSelect,
+    /// Low bits of every value; one unused bit each if `low_bits` is 0.
+    low: PackedIntVec,
+    low_bits: u32,
+    len: usize,
+}
+
+impl EliasFano {
+    /// Encode a sorted (non-decreasing) sequence. Fails with
+    /// `InvalidStructure` if the values are not sorted.
+    pub fn from_sorted(values: &[u64]) -> Result<Self, DataStructureError> {
+        if !values.is_sorted() {
+            return Err(DataStructureError::InvalidStructure);
+        }
+        let n = values.len() as u64;
+        let universe = values.last().map_or(0, |&max| max.saturating_add(1));
+        let low_bits = if n == 0 || universe <= n {
+            0
+        } else {
+            (universe / n).ilog2()
+        };
+        let mask = (1u64 << low_bits) - 1;
+
+        let mut low = PackedIntVec::with_capacity(low_bits.max(1), values.len());
+        let high_len = values.len() + (universe >> low_bits) as usize + 1;
+        let mut high = BitVec::from_elem(high_len, false);
+        for (i, &value) in values.iter().enumerate() {
+            low.push(value & mask);
+            high.set((value >> low_bits) as usize + i, true);
+        }
+        Ok(EliasFano {
+            high: RankSelect::new(high),
+            low,
+            low_bits,
+            len: values.len(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value at `index`.
+    pub fn get(&self, index: usize) -> Option<u64> {
+        let pos = self.high.select1(index)?;
+        Some(self.value(index, pos))
+    }
+
+    /// The first value `>= x`, together with its index.
+    pub fn next_geq(&self, x: u64) -> Option<(usize, u64)> {
+        let bucket = x >> self.low_bits;
+        // skip to the first slot of the bucket: after its `bucket`-th zero
+        let start = match bucket {
+            0 => 0,
+            b => self.high.select0(b as usize - 1)? + 1,
+        };
+        let mut index = start - bucket as usize;
+        let mut pos = start;
+        while index < self.len {
+            if self.high.get(pos) == Some(true) {
+                let value = self.value(index, pos);
+                if value >= x {
+                    return Some((index, value));
+                }
+                index += 1;
+            }
+            pos += 1;
+        }
+        None
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        self.next_geq(x).is_some_and(|(_, v)| v == x)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            ef: self,
+            ones: self.high.as_bit_vec().iter_ones(),
+            index: 0,
+        }
+    }
+
+    fn value(&self, index: usize, pos: usize) -> u64 {
+        let high = (pos - index) as u64;
+        (high << self.low_bits) | self.low.get(index).unwrap()
+    }
+}
+
+impl MemoryFootprint for EliasFano {
+    fn memory_stats(&self) -> MemoryStats {
+        let high = self.high.memory_stats();
+        let low = self.low.memory_stats();
+        MemoryStats {
+            nodes: high.nodes + low.nodes,
+            heap_bytes: high.heap_bytes + low.heap_bytes,
+            depth: 1,
+            used_slots: self.len,
+            total_slots: self.len,
+        }
+    }
+}
+
+impl fmt::Debug for EliasFano {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a EliasFano {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a> {
+    ef: &'a EliasFano,
+    ones: crate::bit_vec::Ones<'a>,
+    index: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.index == self.ef.len {
+            return None;
+        }
+        let pos = self.ones.next()?;
+        self.index += 1;
+        Some(self.ef.value(self.index - 1, pos))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.ef.len - self.index;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn access_by_index() {
+        let values = [2, 3, 5, 7, 11, 13, 24, 24, 100];
+        let ef = EliasFano::from_sorted(&values).unwrap();
+        assert_eq!(ef.len(), 9);
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(ef.get(i), Some(v));
+        }
+        assert_eq!(ef.get(9), None);
+        assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn next_geq_queries() {
+        let values: Vec<u64> = (0..500).map(|i| i * i / 3).collect();
+        let ef = EliasFano::from_sorted(&values).unwrap();
+        for x in 0..=values[499] + 1 {
+            let expected = values.iter().position(|&v| v >= x).map(|i| (i, values[i]));
+            assert_eq!(ef.next_geq(x), expected, "x = {x}");
+        }
+        assert!(ef.contains(300) && !ef.contains(301));
+    }
+
+    #[test]
+    fn edge_cases() {
+        let empty = EliasFano::from_sorted(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+        assert_eq!(empty.next_geq(0), None);
+
+        let zeros = EliasFano::from_sorted(&[0, 0, 0]).unwrap();
+        assert_eq!(zeros.iter().collect::<Vec<_>>(), vec![0, 0, 0]);
+        assert_eq!(zeros.next_geq(0), Some((0, 0)));
+        assert_eq!(zeros.next_geq(1), None);
+
+        let big = EliasFano::from_sorted(&[1, u64::MAX / 2, u64::MAX]).unwrap();
+        assert_eq!(big.get(1), Some(u64::MAX / 2));
+        assert_eq!(big.next_geq(u64::MAX - 1), Some((2, u64::MAX)));
+
+        assert_eq!(
+            EliasFano::from_sorted(&[3, 1]),
+            Err(DataStructureError::InvalidStructure)
+        );
+    }
+
+    #[test]
+    fn compresses_sparse_ids() {
+        let values: Vec<u64> = (0..10_000).map(|i| i * 1000 + i % 7).collect();
+        let ef = EliasFano::from_sorted(&values).unwrap();
+        // about 2 + log2(1000) ~ 12 bits per value plus the rank and select
+        // index, instead of 64
+        assert!(ef.memory_stats().heap_bytes < values.len() * 3);
+        assert_eq!(ef.get(1234), Some(values[1234]));
+        assert_eq!(ef.next_geq(5_000_003), Some((5001, 5_001_000 + 5001 % 7)));
+    }
+}
@@ -0,0 +1,53 @@
+use core::fmt;
+
+/// Why a fallible (`try_*`) operation failed.
+///
+/// The panicking counterparts of these operations fail in the same
+/// situations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DataStructureError {
+    /// Two items could not be ordered, e.g. because one of them is NaN.
+    IncomparableItems,
+    /// The structure cannot hold any more items, or the item does not fit.
+    CapacityExceeded,
+    /// The key, item or position does not exist in the structure.
+    KeyNotFound,
+    /// The structure cannot be built with the given parameters.
+    InvalidStructure,
+}
+
+impl fmt::Display for DataStructureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DataStructureError::IncomparableItems => "incomparable items",
+            DataStructureError::CapacityExceeded => "capacity exceeded",
+            DataStructureError::KeyNotFound => "key not found",
+            DataStructureError::InvalidStructure => "invalid structure",
+        })
+    }
+}
+
+impl core::error::Error for DataStructureError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_messages() {
+        assert_eq!(
+            DataStructureError::IncomparableItems.to_string(),
+            "incomparable items"
+        );
+        assert_eq!(DataStructureError::KeyNotFound.to_string(), "key not found");
+    }
+
+    #[test]
+    fn usable_as_boxed_error() {
+        fn fails() -> Result<(), Box<dyn std::error::Error>> {
+            Err(DataStructureError::CapacityExceeded)?
+        }
+        assert_eq!(fails().unwrap_err().to_string(), "capacity exceeded");
+    }
+}
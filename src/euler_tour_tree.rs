@@ -0,0 +1,477 @@
+//! Euler tour trees: a forest under links and cuts, with component queries.
+//!
+//! Each tree is stored as its Euler tour, with one node per vertex and one
+//! per direction of every edge, in a treap ordered by tour position.
+//! Linking and cutting split and join tours in expected O(log n). Every
+//! treap node also counts the vertices below it and whether any of them is
+//! marked, so component sizes and marked vertices or edges are found in
+//! expected O(log n) as well.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+const NIL: usize = usize::MAX;
+
+const VERTEX: usize = 0;
+const EDGE: usize = 1;
+
+pub struct EulerTourForest {
+    /// The first `n` nodes are the vertices; edge nodes follow.
+    nodes: Vec<Node>,
+    /// Node of each direction of every edge.
+    arcs: BTreeMap<(usize, usize), usize>,
+    free: Vec<usize>,
+    vertices: usize,
+    seed: u32,
+}
+
+#[derive(Clone)]
+struct Node {
+    children: [usize; 2],
+    parent: usize,
+    priority: u32,
+    /// A vertex if `from == to`, otherwise the edge `from -> to`.
+    from: usize,
+    to: usize,
+    size: usize,
+    vertex_count: usize,
+    mark: bool,
+    /// Whether any vertex or edge node below is marked.
+    marked: [bool; 2],
+}
+
+impl Node {
+    fn kind(&self) -> usize {
+        if self.from == self.to {
+            VERTEX
+        } else {
+            EDGE
+        }
+    }
+}
+
+impl EulerTourForest {
+    /// A forest of `n` isolated vertices.
+    pub fn new(n: usize) -> Self {
+        let mut forest = EulerTourForest {
+            nodes: Vec::with_capacity(n),
+            arcs: BTreeMap::new(),
+            free: Vec::new(),
+            vertices: n,
+            seed: 0x9e37_79b9,
+        };
+        for v in 0..n {
+            let node = forest.new_node(v, v);
+            forest.nodes.push(node);
+        }
+        forest
+    }
+
+    /// Number of vertices.
+    pub fn len(&self) -> usize {
+        self.vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices == 0
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.arcs.len() / 2
+    }
+
+    pub fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.arcs.contains_key(&(u, v))
+    }
+
+    pub fn connected(&self, u: usize, v: usize) -> bool {
+        u == v || self.root(u) == self.root(v)
+    }
+
+    /// Number of vertices in the tree containing `v`.
+    pub fn component_size(&self, v: usize) -> usize {
+        self.nodes[self.root(v)].vertex_count
+    }
+
+    /// The vertices of the tree containing `v`, in the order of its tour.
+    pub fn component(&self, v: usize) -> Vec<usize> {
+        let mut result = Vec::with_capacity(self.component_size(v));
+        let mut stack = Vec::new();
+        let mut x = self.root(v);
+        while x != NIL || !stack.is_empty() {
+            while x != NIL {
+                stack.push(x);
+                x = self.nodes[x].children[0];
+            }
+            let top = stack.pop().unwrap();
+            if self.nodes[top].kind() == VERTEX {
+                result.push(top);
+            }
+            x = self.nodes[top].children[1];
+        }
+        result
+    }
+
+    /// Connect `u` and `v` with an edge. Returns false, and changes nothing,
+    /// if they are already in the same tree.
+    pub fn link(&mut self, u: usize, v: usize) -> bool {
+        if self.connected(u, v) {
+            return false;
+        }
+        let uv = self.alloc(u, v);
+        let vu = self.alloc(v, u);
+        let tu = self.reroot(u);
+        let tv = self.reroot(v);
+        let left = self.merge(tu, uv);
+        let right = self.merge(tv, vu);
+        self.merge(left, right);
+        true
+    }
+
+    /// Remove the edge between `u` and `v`. Returns false if there is none.
+    pub fn cut(&mut self, u: usize, v: usize) -> bool {
+        let (Some(&uv), Some(&vu)) = (self.arcs.get(&(u, v)), self.arcs.get(&(v, u))) else {
+            return false;
+        };
+        let (mut first, mut second) = (uv, vu);
+        let (mut i, mut j) = (self.position(uv), self.position(vu));
+        if i > j {
+            core::mem::swap(&mut first, &mut second);
+            core::mem::swap(&mut i, &mut j);
+        }
+        // the tour is `left first middle second right`, where `middle` is
+        // the tour of the tree on the far side of the edge
+        let (left, rest) = self.split(self.root(first), i);
+        let (_, rest) = self.split(rest, 1);
+        let (_, rest) = self.split(rest, j - i - 1);
+        let (_, right) = self.split(rest, 1);
+        self.merge(left, right);
+        self.arcs.remove(&(u, v));
+        self.arcs.remove(&(v, u));
+        self.free.push(first);
+        self.free.push(second);
+        true
+    }
+
+    pub fn set_vertex_mark(&mut self, v: usize, mark: bool) {
+        self.set_mark(v, mark);
+    }
+
+    pub fn vertex_mark(&self, v: usize) -> bool {
+        self.nodes[v].mark
+    }
+
+    /// Mark the edge `u -> v`; `find_marked_edge` reports it in that
+    /// direction. Returns false if there is no such edge.
+    pub fn set_edge_mark(&mut self, u: usize, v: usize, mark: bool) -> bool {
+        match self.arcs.get(&(u, v)) {
+            Some(&x) => {
+                self.set_mark(x, mark);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn edge_mark(&self, u: usize, v: usize) -> bool {
+        self.arcs.get(&(u, v)).is_some_and(|&x| self.nodes[x].mark)
+    }
+
+    /// Some marked vertex in the tree containing `v`.
+    pub fn find_marked_vertex(&self, v: usize) -> Option<usize> {
+        self.find_marked(self.root(v), VERTEX)
+    }
+
+    /// Some marked edge in the tree containing `v`.
+    pub fn find_marked_edge(&self, v: usize) -> Option<(usize, usize)> {
+        let x = self.find_marked(self.root(v), EDGE)?;
+        Some((self.nodes[x].from, self.nodes[x].to))
+    }
+
+    fn new_node(&mut self, from: usize, to: usize) -> Node {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        Node {
+            children: [NIL, NIL],
+            parent: NIL,
+            priority: self.seed,
+            from,
+            to,
+            size: 1,
+            vertex_count: usize::from(from == to),
+            mark: false,
+            marked: [false; 2],
+        }
+    }
+
+    fn alloc(&mut self, from: usize, to: usize) -> usize {
+        let node = self.new_node(from, to);
+        let x = match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.arcs.insert((from, to), x);
+        x
+    }
+
+    fn set_mark(&mut self, x: usize, mark: bool) {
+        self.nodes[x].mark = mark;
+        let mut x = x;
+        while x != NIL {
+            self.update(x);
+            x = self.nodes[x].parent;
+        }
+    }
+
+    fn find_marked(&self, root: usize, kind: usize) -> Option<usize> {
+        if !self.nodes[root].marked[kind] {
+            return None;
+        }
+        let mut x = root;
+        loop {
+            let node = &self.nodes[x];
+            let [left, right] = node.children;
+            if left != NIL && self.nodes[left].marked[kind] {
+                x = left;
+            } else if node.mark && node.kind() == kind {
+                return Some(x);
+            } else {
+                x = right;
+            }
+        }
+    }
+
+    /// Rotate the tour containing vertex `v` so that it starts at `v`, and
+    /// return its root.
+    fn reroot(&mut self, v: usize) -> usize {
+        let k = self.position(v);
+        let (before, after) = self.split(self.root(v), k);
+        self.merge(after, before)
+    }
+
+    fn root(&self, mut x: usize) -> usize {
+        while self.nodes[x].parent != NIL {
+            x = self.nodes[x].parent;
+        }
+        x
+    }
+
+    fn position(&self, mut x: usize) -> usize {
+        let mut pos = self.size(self.nodes[x].children[0]);
+        while self.nodes[x].parent != NIL {
+            let p = self.nodes[x].parent;
+            if self.nodes[p].children[1] == x {
+                pos += self.size(self.nodes[p].children[0]) + 1;
+            }
+            x = p;
+        }
+        pos
+    }
+
+    fn size(&self, x: usize) -> usize {
+        if x == NIL {
+            0
+        } else {
+            self.nodes[x].size
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let node = &self.nodes[x];
+        let mut size = 1;
+        let mut vertex_count = usize::from(node.kind() == VERTEX);
+        let mut marked = [false; 2];
+        marked[node.kind()] = node.mark;
+        for child in node.children {
+            if child != NIL {
+                let c = &self.nodes[child];
+                size += c.size;
+                vertex_count += c.vertex_count;
+                marked[VERTEX] |= c.marked[VERTEX];
+                marked[EDGE] |= c.marked[EDGE];
+            }
+        }
+        let node = &mut self.nodes[x];
+        node.size = size;
+        node.vertex_count = vertex_count;
+        node.marked = marked;
+    }
+
+    fn set_child(&mut self, p: usize, side: usize, c: usize) {
+        self.nodes[p].children[side] = c;
+        if c != NIL {
+            self.nodes[c].parent = p;
+        }
+    }
+
+    /// Join two tours; both must be roots or `NIL`.
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        if self.nodes[a].priority > self.nodes[b].priority {
+            let right = self.merge(self.nodes[a].children[1], b);
+            self.set_child(a, 1, right);
+            self.update(a);
+            a
+        } else {
+            let left = self.merge(a, self.nodes[b].children[0]);
+            self.set_child(b, 0, left);
+            self.update(b);
+            b
+        }
+    }
+
+    /// Split the tour rooted at `t` after its first `k` nodes.
+    fn split(&mut self, t: usize, k: usize) -> (usize, usize) {
+        if t == NIL {
+            return (NIL, NIL);
+        }
+        let [left, right] = self.nodes[t].children;
+        let left_size = self.size(left);
+        if k <= left_size {
+            let (a, b) = self.split(left, k);
+            self.set_child(t, 0, b);
+            self.update(t);
+            self.detach(a);
+            (a, t)
+        } else {
+            let (a, b) = self.split(right, k - left_size - 1);
+            self.set_child(t, 1, a);
+            self.update(t);
+            self.detach(b);
+            (t, b)
+        }
+    }
+
+    fn detach(&mut self, x: usize) {
+        if x != NIL {
+            self.nodes[x].parent = NIL;
+        }
+    }
+}
+
+impl fmt::Debug for EulerTourForest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EulerTourForest")
+            .field("vertices", &self.vertices)
+            .field("edges", &self.edge_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn link_and_cut() {
+        let mut forest = EulerTourForest::new(5);
+        assert!(forest.link(0, 1));
+        assert!(forest.link(1, 2));
+        assert!(forest.link(3, 4));
+        assert!(!forest.link(2, 0));
+        assert!(forest.connected(0, 2));
+        assert!(!forest.connected(0, 3));
+        assert_eq!(forest.component_size(2), 3);
+        assert_eq!(forest.edge_count(), 3);
+
+        assert!(forest.cut(2, 1));
+        assert!(!forest.cut(2, 1));
+        assert!(!forest.connected(0, 2));
+        assert_eq!(forest.component_size(0), 2);
+        assert_eq!(forest.component(2), vec![2]);
+    }
+
+    #[test]
+    fn tour_visits_every_vertex_once() {
+        let mut forest = EulerTourForest::new(8);
+        for (u, v) in [(0, 1), (0, 2), (2, 3), (2, 4), (5, 4), (6, 5)] {
+            forest.link(u, v);
+        }
+        let mut component = forest.component(3);
+        component.sort();
+        assert_eq!(component, vec![0, 1, 2, 3, 4, 5, 6]);
+        forest.cut(2, 4);
+        let mut component = forest.component(6);
+        component.sort();
+        assert_eq!(component, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn marks_are_found_per_tree() {
+        let mut forest = EulerTourForest::new(6);
+        for (u, v) in [(0, 1), (1, 2), (3, 4), (4, 5)] {
+            forest.link(u, v);
+        }
+        forest.set_vertex_mark(2, true);
+        assert!(forest.set_edge_mark(4, 3, true));
+        assert!(!forest.set_edge_mark(0, 5, true));
+        assert_eq!(forest.find_marked_vertex(0), Some(2));
+        assert_eq!(forest.find_marked_vertex(5), None);
+        assert_eq!(forest.find_marked_edge(5), Some((4, 3)));
+        assert_eq!(forest.find_marked_edge(1), None);
+
+        forest.cut(1, 2);
+        assert_eq!(forest.find_marked_vertex(0), None);
+        forest.set_vertex_mark(2, false);
+        assert!(!forest.vertex_mark(2));
+        assert!(forest.edge_mark(4, 3));
+        forest.cut(3, 4);
+        assert_eq!(forest.find_marked_edge(3), None);
+    }
+
+    #[test]
+    fn random_forest_matches_union_of_edges() {
+        let n = 60;
+        let mut forest = EulerTourForest::new(n);
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut x: u32 = 1;
+        let mut next = |m: usize| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as usize % m
+        };
+        for _ in 0..3000 {
+            if next(3) != 0 || edges.is_empty() {
+                let (u, v) = (next(n), next(n));
+                if forest.link(u, v) {
+                    edges.push((u, v));
+                }
+            } else {
+                let (u, v) = edges.swap_remove(next(edges.len()));
+                assert!(forest.cut(v, u));
+            }
+            // compare component sizes against a flood fill of `edges`
+            let v = next(n);
+            let mut seen = vec![false; n];
+            let mut stack = vec![v];
+            seen[v] = true;
+            while let Some(a) = stack.pop() {
+                for &(p, q) in &edges {
+                    for (s, t) in [(p, q), (q, p)] {
+                        if s == a && !seen[t] {
+                            seen[t] = true;
+                            stack.push(t);
+                        }
+                    }
+                }
+            }
+            let size = seen.iter().filter(|&&s| s).count();
+            assert_eq!(forest.component_size(v), size);
+        }
+    }
+}
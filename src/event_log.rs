@@ -0,0 +1,313 @@
+//! An append-only log of timestamped events with increasing sequence
+//! numbers.
+//!
+//! Records are stored in segments of a fixed size. The segments form a
+//! sparse index: seeking by sequence number or timestamp binary searches
+//! the segments by their last record, then the records of one segment, so
+//! it takes O(log n). Dropping old records removes whole segments from the
+//! front of a deque.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+const DEFAULT_SEGMENT_LEN: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<T> {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub event: T,
+}
+
+/// A position in the log: a segment and an offset into it.
+type Pos = (usize, usize);
+
+/// Sequence numbers are strictly increasing, timestamps non-decreasing.
+pub struct EventLog<T> {
+    segments: VecDeque<Vec<Record<T>>>,
+    segment_len: usize,
+    len: usize,
+    next_seq: u64,
+}
+
+impl<T> EventLog<T> {
+    pub fn new() -> Self {
+        Self::with_segment_len(DEFAULT_SEGMENT_LEN)
+    }
+
+    /// Panics if `segment_len` is 0.
+    pub fn with_segment_len(segment_len: usize) -> Self {
+        assert!(segment_len > 0, "segments must hold at least one record");
+        EventLog {
+            segments: VecDeque::new(),
+            segment_len,
+            len: 0,
+            next_seq: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The sequence number `append` assigns next.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn first(&self) -> Option<&Record<T>> {
+        self.segments.front()?.first()
+    }
+
+    pub fn last(&self) -> Option<&Record<T>> {
+        self.segments.back()?.last()
+    }
+
+    /// Append an event with the next sequence number and return that
+    /// number. Panics if `timestamp` is before the last record's.
+    pub fn append(&mut self, timestamp: u64, event: T) -> u64 {
+        let seq = self.next_seq;
+        self.append_at(seq, timestamp, event);
+        seq
+    }
+
+    /// Append an event with a given sequence number, which may skip
+    /// numbers. Panics if `seq` is not greater than the last sequence
+    /// number or `timestamp` is before the last record's.
+    pub fn append_at(&mut self, seq: u64, timestamp: u64, event: T) {
+        if let Some(last) = self.last() {
+            assert!(seq > last.seq, "sequence numbers must increase");
+            assert!(timestamp >= last.timestamp, "timestamps must not decrease");
+        }
+        assert!(seq >= self.next_seq, "sequence numbers must increase");
+        match self.segments.back_mut() {
+            Some(segment) if segment.len() < self.segment_len => segment.push(Record {
+                seq,
+                timestamp,
+                event,
+            }),
+            _ => {
+                let mut segment = Vec::with_capacity(self.segment_len);
+                segment.push(Record {
+                    seq,
+                    timestamp,
+                    event,
+                });
+                self.segments.push_back(segment);
+            }
+        }
+        self.len += 1;
+        self.next_seq = seq + 1;
+    }
+
+    pub fn get(&self, seq: u64) -> Option<&Record<T>> {
+        let (s, i) = self.position(|r| r.seq < seq);
+        self.segments.get(s)?.get(i).filter(|r| r.seq == seq)
+    }
+
+    /// The records from the first with a sequence number of at least `seq`.
+    pub fn seek_seq(&self, seq: u64) -> Iter<'_, T> {
+        self.range(seq..)
+    }
+
+    /// The records from the first with a timestamp of at least `timestamp`.
+    pub fn seek_time(&self, timestamp: u64) -> Iter<'_, T> {
+        self.time_range(timestamp..)
+    }
+
+    /// The records whose sequence numbers lie in `seqs`.
+    pub fn range(&self, seqs: impl RangeBounds<u64>) -> Iter<'_, T> {
+        self.iter_between(seqs, |r| r.seq)
+    }
+
+    /// The records whose timestamps lie in `timestamps`.
+    pub fn time_range(&self, timestamps: impl RangeBounds<u64>) -> Iter<'_, T> {
+        self.iter_between(timestamps, |r| r.timestamp)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(..)
+    }
+
+    /// Drop the records with sequence numbers below `seq`. The next
+    /// sequence number stays the same.
+    pub fn truncate_before(&mut self, seq: u64) {
+        while let Some(front) = self.segments.front_mut() {
+            if front.last().unwrap().seq < seq {
+                self.len -= front.len();
+                self.segments.pop_front();
+            } else {
+                let n = front.partition_point(|r| r.seq < seq);
+                front.drain(..n);
+                self.len -= n;
+                break;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.len = 0;
+    }
+
+    /// The first position whose record does not satisfy `before`, which
+    /// must hold for a prefix of the log.
+    fn position(&self, before: impl Fn(&Record<T>) -> bool) -> Pos {
+        let s = self
+            .segments
+            .partition_point(|segment| before(segment.last().unwrap()));
+        match self.segments.get(s) {
+            Some(segment) => (s, segment.partition_point(&before)),
+            None => (s, 0),
+        }
+    }
+
+    fn iter_between(
+        &self,
+        range: impl RangeBounds<u64>,
+        key: impl Fn(&Record<T>) -> u64,
+    ) -> Iter<'_, T> {
+        let pos = match range.start_bound() {
+            Bound::Included(&x) => self.position(|r| key(r) < x),
+            Bound::Excluded(&x) => self.position(|r| key(r) <= x),
+            Bound::Unbounded => (0, 0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&x) => self.position(|r| key(r) <= x),
+            Bound::Excluded(&x) => self.position(|r| key(r) < x),
+            Bound::Unbounded => (self.segments.len(), 0),
+        };
+        Iter {
+            log: self,
+            pos,
+            end: end.max(pos),
+        }
+    }
+}
+
+impl<T> Default for EventLog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for EventLog<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a EventLog<T> {
+    type Item = &'a Record<T>;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Iterator over a range of records, oldest first.
+pub struct Iter<'a, T> {
+    log: &'a EventLog<T>,
+    pos: Pos,
+    end: Pos,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a Record<T>;
+
+    fn next(&mut self) -> Option<&'a Record<T>> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let (s, i) = self.pos;
+        let segment = &self.log.segments[s];
+        self.pos = if i + 1 < segment.len() {
+            (s, i + 1)
+        } else {
+            (s + 1, 0)
+        };
+        Some(&segment[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs<'a>(iter: impl Iterator<Item = &'a Record<char>>) -> Vec<u64> {
+        iter.map(|r| r.seq).collect()
+    }
+
+    #[test]
+    fn append_and_get() {
+        let mut log = EventLog::with_segment_len(3);
+        for (i, c) in "abcdefgh".chars().enumerate() {
+            assert_eq!(log.append(i as u64 * 10, c), i as u64);
+        }
+        assert_eq!(log.len(), 8);
+        assert_eq!(log.get(5).map(|r| r.event), Some('f'));
+        assert_eq!(log.get(8), None);
+        assert_eq!(log.first().map(|r| r.timestamp), Some(0));
+        assert_eq!(log.last().map(|r| r.event), Some('h'));
+        assert_eq!(log.iter().map(|r| r.event).collect::<String>(), "abcdefgh");
+    }
+
+    #[test]
+    fn seek_and_range_by_sequence_with_gaps() {
+        let mut log = EventLog::with_segment_len(2);
+        for seq in [1, 2, 5, 6, 7, 10, 20] {
+            log.append_at(seq, seq, 'x');
+        }
+        assert_eq!(log.next_seq(), 21);
+        assert_eq!(log.get(3), None);
+        assert_eq!(seqs(log.seek_seq(3)), vec![5, 6, 7, 10, 20]);
+        assert_eq!(seqs(log.range(6..=10)), vec![6, 7, 10]);
+        assert_eq!(seqs(log.range(8..10)), Vec::<u64>::new());
+        assert_eq!(seqs(log.range(..)), vec![1, 2, 5, 6, 7, 10, 20]);
+        assert_eq!(seqs(log.seek_seq(21)), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn seek_by_timestamp_with_ties() {
+        let mut log = EventLog::with_segment_len(3);
+        for t in [0, 5, 5, 5, 5, 9, 12, 12] {
+            log.append(t, 'x');
+        }
+        assert_eq!(seqs(log.seek_time(5)), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(seqs(log.seek_time(6)), vec![5, 6, 7]);
+        assert_eq!(seqs(log.time_range(5..=9)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(seqs(log.time_range(..12)), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(seqs(log.time_range(13..)), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn truncate_keeps_numbering() {
+        let mut log = EventLog::with_segment_len(4);
+        for i in 0..10 {
+            log.append(i, 'x');
+        }
+        log.truncate_before(6);
+        assert_eq!(log.len(), 4);
+        assert_eq!(seqs(log.iter()), vec![6, 7, 8, 9]);
+        assert_eq!(log.append(10, 'y'), 10);
+        assert_eq!(log.get(5), None);
+        log.truncate_before(100);
+        assert!(log.is_empty());
+        assert_eq!(log.append(11, 'z'), 11);
+    }
+
+    #[test]
+    #[should_panic(expected = "timestamps must not decrease")]
+    fn rejects_older_timestamps() {
+        let mut log = EventLog::new();
+        log.append(5, ());
+        log.append(4, ());
+    }
+}
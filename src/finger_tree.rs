@@ -0,0 +1,820 @@
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub trait Monoid: Clone {
+    fn zero() -> Self;
+    fn combine(&self, other: &Self) -> Self;
+}
+
+pub trait Measured {
+    type Measure: Monoid;
+    fn measure(&self) -> Self::Measure;
+}
+
+type Measure<T> = <T as Measured>::Measure;
+
+pub struct FingerTree<T: Measured> {
+    tree: Tree<T>,
+}
+
+enum Tree<T: Measured> {
+    Empty,
+    Single(Rc<Node<T>>),
+    Deep(Rc<Deep<T>>),
+}
+
+struct Deep<T: Measured> {
+    measure: Measure<T>,
+    prefix: Vec<Rc<Node<T>>>,
+    middle: Tree<T>,
+    suffix: Vec<Rc<Node<T>>>,
+}
+
+enum Node<T: Measured> {
+    Leaf(T),
+    Branch(Measure<T>, Vec<Rc<Node<T>>>),
+}
+
+impl<T: Measured> Clone for Tree<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Tree::Empty => Tree::Empty,
+            Tree::Single(x) => Tree::Single(x.clone()),
+            Tree::Deep(d) => Tree::Deep(d.clone()),
+        }
+    }
+}
+
+impl<T: Measured> Clone for FingerTree<T> {
+    fn clone(&self) -> Self {
+        FingerTree {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Measured> Node<T> {
+    fn measure(&self) -> Measure<T> {
+        match self {
+            Node::Leaf(item) => item.measure(),
+            Node::Branch(m, _) => m.clone(),
+        }
+    }
+
+    fn children(&self) -> &[Rc<Node<T>>] {
+        match self {
+            Node::Leaf(_) => unreachable!(),
+            Node::Branch(_, children) => children,
+        }
+    }
+
+    fn item(&self) -> &T {
+        match self {
+            Node::Leaf(item) => item,
+            Node::Branch(_, _) => unreachable!(),
+        }
+    }
+}
+
+impl<T: Measured + Clone> FingerTree<T> {
+    pub fn new() -> Self {
+        FingerTree { tree: Tree::Empty }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self.tree, Tree::Empty)
+    }
+
+    pub fn measure(&self) -> Measure<T> {
+        self.tree.measure()
+    }
+
+    pub fn push_front(&self, item: T) -> Self {
+        FingerTree {
+            tree: self.tree.push_front(Rc::new(Node::Leaf(item))),
+        }
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        FingerTree {
+            tree: self.tree.push_back(Rc::new(Node::Leaf(item))),
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        match &self.tree {
+            Tree::Empty => None,
+            Tree::Single(x) => Some(first_leaf(x)),
+            Tree::Deep(d) => Some(first_leaf(&d.prefix[0])),
+        }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        match &self.tree {
+            Tree::Empty => None,
+            Tree::Single(x) => Some(last_leaf(x)),
+            Tree::Deep(d) => Some(last_leaf(d.suffix.last().unwrap())),
+        }
+    }
+
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        let (head, rest) = self.tree.view_front()?;
+        Some((head.item().clone(), FingerTree { tree: rest }))
+    }
+
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        let (rest, last) = self.tree.view_back()?;
+        Some((FingerTree { tree: rest }, last.item().clone()))
+    }
+
+    pub fn concat(&self, other: &Self) -> Self {
+        FingerTree {
+            tree: app3(&self.tree, vec![], &other.tree),
+        }
+    }
+
+    /// Split the sequence before the first element at which `predicate`,
+    /// applied to the accumulated measure up to and including that element,
+    /// becomes true. The predicate must be monotone.
+    pub fn split(&self, predicate: impl Fn(&Measure<T>) -> bool) -> (Self, Self) {
+        if self.is_empty() || !predicate(&self.measure()) {
+            return (self.clone(), FingerTree::new());
+        }
+        let (left, x, right) = split_tree(&predicate, &Monoid::zero(), &self.tree);
+        (
+            FingerTree { tree: left },
+            FingerTree {
+                tree: right.push_front(x),
+            },
+        )
+    }
+
+    /// Find the first element at which `predicate`, applied to the
+    /// accumulated measure, becomes true.
+    pub fn find(&self, predicate: impl Fn(&Measure<T>) -> bool) -> Option<&T> {
+        if self.is_empty() || !predicate(&self.measure()) {
+            return None;
+        }
+        let (mut node, mut acc) = lookup_tree(&predicate, Monoid::zero(), &self.tree);
+        while let Node::Branch(_, children) = &**node {
+            (node, acc) = lookup_digit(&predicate, acc, children);
+        }
+        Some(node.item())
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: vec![IterItem::Tree(&self.tree)],
+        }
+    }
+}
+
+fn first_leaf<T: Measured>(mut node: &Rc<Node<T>>) -> &T {
+    loop {
+        match &**node {
+            Node::Leaf(item) => return item,
+            Node::Branch(_, children) => node = &children[0],
+        }
+    }
+}
+
+fn last_leaf<T: Measured>(mut node: &Rc<Node<T>>) -> &T {
+    loop {
+        match &**node {
+            Node::Leaf(item) => return item,
+            Node::Branch(_, children) => node = children.last().unwrap(),
+        }
+    }
+}
+
+fn measure_digit<T: Measured>(digit: &[Rc<Node<T>>]) -> Measure<T> {
+    digit.iter().fold(Monoid::zero(), |acc: Measure<T>, x| {
+        acc.combine(&x.measure())
+    })
+}
+
+fn branch<T: Measured>(children: Vec<Rc<Node<T>>>) -> Rc<Node<T>> {
+    Rc::new(Node::Branch(measure_digit(&children), children))
+}
+
+fn deep<T: Measured>(
+    prefix: Vec<Rc<Node<T>>>,
+    middle: Tree<T>,
+    suffix: Vec<Rc<Node<T>>>,
+) -> Tree<T> {
+    let measure = measure_digit(&prefix)
+        .combine(&middle.measure())
+        .combine(&measure_digit(&suffix));
+    Tree::Deep(Rc::new(Deep {
+        measure,
+        prefix,
+        middle,
+        suffix,
+    }))
+}
+
+fn digit_to_tree<T: Measured>(digit: &[Rc<Node<T>>]) -> Tree<T> {
+    digit
+        .iter()
+        .fold(Tree::Empty, |tree, x| tree.push_back(x.clone()))
+}
+
+/// Build a deep tree from a possibly empty prefix.
+fn deep_l<T: Measured>(
+    prefix: Vec<Rc<Node<T>>>,
+    middle: &Tree<T>,
+    suffix: &[Rc<Node<T>>],
+) -> Tree<T> {
+    if !prefix.is_empty() {
+        return deep(prefix, middle.clone(), suffix.to_vec());
+    }
+    match middle.view_front() {
+        None => digit_to_tree(suffix),
+        Some((node, rest)) => deep(node.children().to_vec(), rest, suffix.to_vec()),
+    }
+}
+
+/// Build a deep tree from a possibly empty suffix.
+fn deep_r<T: Measured>(
+    prefix: &[Rc<Node<T>>],
+    middle: &Tree<T>,
+    suffix: Vec<Rc<Node<T>>>,
+) -> Tree<T> {
+    if !suffix.is_empty() {
+        return deep(prefix.to_vec(), middle.clone(), suffix);
+    }
+    match middle.view_back() {
+        None => digit_to_tree(prefix),
+        Some((rest, node)) => deep(prefix.to_vec(), rest, node.children().to_vec()),
+    }
+}
+
+impl<T: Measured> Tree<T> {
+    fn measure(&self) -> Measure<T> {
+        match self {
+            Tree::Empty => Monoid::zero(),
+            Tree::Single(x) => x.measure(),
+            Tree::Deep(d) => d.measure.clone(),
+        }
+    }
+
+    fn push_front(&self, x: Rc<Node<T>>) -> Self {
+        match self {
+            Tree::Empty => Tree::Single(x),
+            Tree::Single(y) => deep(vec![x], Tree::Empty, vec![y.clone()]),
+            Tree::Deep(d) if d.prefix.len() == 4 => {
+                let p = &d.prefix;
+                let node = branch(vec![p[1].clone(), p[2].clone(), p[3].clone()]);
+                deep(
+                    vec![x, p[0].clone()],
+                    d.middle.push_front(node),
+                    d.suffix.clone(),
+                )
+            }
+            Tree::Deep(d) => {
+                let mut prefix = Vec::with_capacity(d.prefix.len() + 1);
+                prefix.push(x);
+                prefix.extend_from_slice(&d.prefix);
+                deep(prefix, d.middle.clone(), d.suffix.clone())
+            }
+        }
+    }
+
+    fn push_back(&self, x: Rc<Node<T>>) -> Self {
+        match self {
+            Tree::Empty => Tree::Single(x),
+            Tree::Single(y) => deep(vec![y.clone()], Tree::Empty, vec![x]),
+            Tree::Deep(d) if d.suffix.len() == 4 => {
+                let s = &d.suffix;
+                let node = branch(vec![s[0].clone(), s[1].clone(), s[2].clone()]);
+                deep(
+                    d.prefix.clone(),
+                    d.middle.push_back(node),
+                    vec![s[3].clone(), x],
+                )
+            }
+            Tree::Deep(d) => {
+                let mut suffix = d.suffix.clone();
+                suffix.push(x);
+                deep(d.prefix.clone(), d.middle.clone(), suffix)
+            }
+        }
+    }
+
+    fn view_front(&self) -> Option<(Rc<Node<T>>, Self)> {
+        match self {
+            Tree::Empty => None,
+            Tree::Single(x) => Some((x.clone(), Tree::Empty)),
+            Tree::Deep(d) => {
+                let rest = deep_l(d.prefix[1..].to_vec(), &d.middle, &d.suffix);
+                Some((d.prefix[0].clone(), rest))
+            }
+        }
+    }
+
+    fn view_back(&self) -> Option<(Self, Rc<Node<T>>)> {
+        match self {
+            Tree::Empty => None,
+            Tree::Single(x) => Some((Tree::Empty, x.clone())),
+            Tree::Deep(d) => {
+                let n = d.suffix.len();
+                let rest = deep_r(&d.prefix, &d.middle, d.suffix[..n - 1].to_vec());
+                Some((rest, d.suffix[n - 1].clone()))
+            }
+        }
+    }
+}
+
+fn app3<T: Measured>(left: &Tree<T>, middle: Vec<Rc<Node<T>>>, right: &Tree<T>) -> Tree<T> {
+    match (left, right) {
+        (Tree::Empty, _) => middle
+            .into_iter()
+            .rev()
+            .fold(right.clone(), |tree, x| tree.push_front(x)),
+        (_, Tree::Empty) => middle
+            .into_iter()
+            .fold(left.clone(), |tree, x| tree.push_back(x)),
+        (Tree::Single(x), _) => app3(&Tree::Empty, middle, right).push_front(x.clone()),
+        (_, Tree::Single(y)) => app3(left, middle, &Tree::Empty).push_back(y.clone()),
+        (Tree::Deep(l), Tree::Deep(r)) => {
+            let mut seam = l.suffix.clone();
+            seam.extend(middle);
+            seam.extend_from_slice(&r.prefix);
+            deep(
+                l.prefix.clone(),
+                app3(&l.middle, nodes(seam), &r.middle),
+                r.suffix.clone(),
+            )
+        }
+    }
+}
+
+/// Group between 2 and 12 nodes into nodes of two or three children.
+fn nodes<T: Measured>(mut xs: Vec<Rc<Node<T>>>) -> Vec<Rc<Node<T>>> {
+    let mut result = vec![];
+    while !xs.is_empty() {
+        let take = match xs.len() {
+            2 | 4 => 2,
+            _ => 3,
+        };
+        let rest = xs.split_off(take);
+        result.push(branch(xs));
+        xs = rest;
+    }
+    result
+}
+
+fn lookup_digit<'a, T: Measured>(
+    predicate: &impl Fn(&Measure<T>) -> bool,
+    mut acc: Measure<T>,
+    digit: &'a [Rc<Node<T>>],
+) -> (&'a Rc<Node<T>>, Measure<T>) {
+    for x in &digit[..digit.len() - 1] {
+        let next = acc.combine(&x.measure());
+        if predicate(&next) {
+            return (x, acc);
+        }
+        acc = next;
+    }
+    (digit.last().unwrap(), acc)
+}
+
+/// Like `split_tree`, but only locate the node without rebuilding anything.
+/// Returns the node together with the measure accumulated before it.
+fn lookup_tree<'a, T: Measured>(
+    predicate: &impl Fn(&Measure<T>) -> bool,
+    acc: Measure<T>,
+    tree: &'a Tree<T>,
+) -> (&'a Rc<Node<T>>, Measure<T>) {
+    match tree {
+        Tree::Empty => unreachable!(),
+        Tree::Single(x) => (x, acc),
+        Tree::Deep(d) => {
+            let acc_prefix = acc.combine(&measure_digit(&d.prefix));
+            if predicate(&acc_prefix) {
+                return lookup_digit(predicate, acc, &d.prefix);
+            }
+            let acc_middle = acc_prefix.combine(&d.middle.measure());
+            if predicate(&acc_middle) {
+                let (node, acc_node) = lookup_tree(predicate, acc_prefix, &d.middle);
+                return lookup_digit(predicate, acc_node, node.children());
+            }
+            lookup_digit(predicate, acc_middle, &d.suffix)
+        }
+    }
+}
+
+type Split<T> = (Vec<Rc<Node<T>>>, Rc<Node<T>>, Vec<Rc<Node<T>>>);
+
+fn split_digit<T: Measured>(
+    predicate: &impl Fn(&Measure<T>) -> bool,
+    acc: &Measure<T>,
+    digit: &[Rc<Node<T>>],
+) -> Split<T> {
+    let mut acc = acc.clone();
+    for (i, x) in digit.iter().enumerate() {
+        acc = acc.combine(&x.measure());
+        if predicate(&acc) {
+            return (digit[..i].to_vec(), x.clone(), digit[i + 1..].to_vec());
+        }
+    }
+    let n = digit.len();
+    (digit[..n - 1].to_vec(), digit[n - 1].clone(), vec![])
+}
+
+fn split_tree<T: Measured>(
+    predicate: &impl Fn(&Measure<T>) -> bool,
+    acc: &Measure<T>,
+    tree: &Tree<T>,
+) -> (Tree<T>, Rc<Node<T>>, Tree<T>) {
+    match tree {
+        Tree::Empty => unreachable!(),
+        Tree::Single(x) => (Tree::Empty, x.clone(), Tree::Empty),
+        Tree::Deep(d) => {
+            let acc_prefix = acc.combine(&measure_digit(&d.prefix));
+            if predicate(&acc_prefix) {
+                let (l, x, r) = split_digit(predicate, acc, &d.prefix);
+                return (digit_to_tree(&l), x, deep_l(r, &d.middle, &d.suffix));
+            }
+            let acc_middle = acc_prefix.combine(&d.middle.measure());
+            if predicate(&acc_middle) {
+                let (ml, node, mr) = split_tree(predicate, &acc_prefix, &d.middle);
+                let acc_node = acc_prefix.combine(&ml.measure());
+                let (l, x, r) = split_digit(predicate, &acc_node, node.children());
+                return (deep_r(&d.prefix, &ml, l), x, deep_l(r, &mr, &d.suffix));
+            }
+            let (l, x, r) = split_digit(predicate, &acc_middle, &d.suffix);
+            (deep_r(&d.prefix, &d.middle, l), x, digit_to_tree(&r))
+        }
+    }
+}
+
+impl<T: Measured + Clone> Default for FingerTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Measured + Clone + fmt::Debug> fmt::Debug for FingerTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Measured + Clone> FromIterator<T> for FingerTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(FingerTree::new(), |tree, item| tree.push_back(item))
+    }
+}
+
+enum IterItem<'a, T: Measured> {
+    Tree(&'a Tree<T>),
+    Node(&'a Node<T>),
+}
+
+pub struct Iter<'a, T: Measured> {
+    stack: Vec<IterItem<'a, T>>,
+}
+
+impl<'a, T: Measured> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                IterItem::Tree(Tree::Empty) => {}
+                IterItem::Tree(Tree::Single(x)) => self.stack.push(IterItem::Node(x)),
+                IterItem::Tree(Tree::Deep(d)) => {
+                    self.stack
+                        .extend(d.suffix.iter().rev().map(|x| IterItem::Node(x)));
+                    self.stack.push(IterItem::Tree(&d.middle));
+                    self.stack
+                        .extend(d.prefix.iter().rev().map(|x| IterItem::Node(x)));
+                }
+                IterItem::Node(Node::Leaf(item)) => return Some(item),
+                IterItem::Node(Node::Branch(_, children)) => {
+                    self.stack
+                        .extend(children.iter().rev().map(|x| IterItem::Node(x)));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub usize);
+
+impl Monoid for Size {
+    fn zero() -> Self {
+        Size(0)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Size(self.0 + other.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Max<T>(pub Option<T>);
+
+impl<T: Ord + Clone> Monoid for Max<T> {
+    fn zero() -> Self {
+        Max(None)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Max(core::cmp::max(&self.0, &other.0).clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Indexed<T>(T);
+
+impl<T> Measured for Indexed<T> {
+    type Measure = Size;
+    fn measure(&self) -> Size {
+        Size(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Prioritized<T>(T);
+
+impl<T: Ord + Clone> Measured for Prioritized<T> {
+    type Measure = Max<T>;
+    fn measure(&self) -> Max<T> {
+        Max(Some(self.0.clone()))
+    }
+}
+
+pub struct IndexedSeq<T> {
+    tree: FingerTree<Indexed<T>>,
+}
+
+impl<T> Clone for IndexedSeq<T> {
+    fn clone(&self) -> Self {
+        IndexedSeq {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Clone> IndexedSeq<T> {
+    pub fn new() -> Self {
+        IndexedSeq {
+            tree: FingerTree::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.measure().0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.tree.find(|s| s.0 > index).map(|x| &x.0)
+    }
+
+    pub fn push_front(&self, item: T) -> Self {
+        IndexedSeq {
+            tree: self.tree.push_front(Indexed(item)),
+        }
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        IndexedSeq {
+            tree: self.tree.push_back(Indexed(item)),
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.tree.front().map(|x| &x.0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tree.back().map(|x| &x.0)
+    }
+
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        let (item, tree) = self.tree.pop_front()?;
+        Some((item.0, IndexedSeq { tree }))
+    }
+
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        let (tree, item) = self.tree.pop_back()?;
+        Some((IndexedSeq { tree }, item.0))
+    }
+
+    pub fn concat(&self, other: &Self) -> Self {
+        IndexedSeq {
+            tree: self.tree.concat(&other.tree),
+        }
+    }
+
+    pub fn split_at(&self, index: usize) -> (Self, Self) {
+        let (left, right) = self.tree.split(|s| s.0 > index);
+        (IndexedSeq { tree: left }, IndexedSeq { tree: right })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.iter().map(|x| &x.0)
+    }
+}
+
+impl<T: Clone> Default for IndexedSeq<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for IndexedSeq<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> FromIterator<T> for IndexedSeq<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        IndexedSeq {
+            tree: iter.into_iter().map(Indexed).collect(),
+        }
+    }
+}
+
+pub struct MaxPriorityQueue<T: Ord + Clone> {
+    tree: FingerTree<Prioritized<T>>,
+}
+
+impl<T: Ord + Clone> Clone for MaxPriorityQueue<T> {
+    fn clone(&self) -> Self {
+        MaxPriorityQueue {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> MaxPriorityQueue<T> {
+    pub fn new() -> Self {
+        MaxPriorityQueue {
+            tree: FingerTree::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn peek(&self) -> Option<T> {
+        self.tree.measure().0
+    }
+
+    pub fn push(&self, item: T) -> Self {
+        MaxPriorityQueue {
+            tree: self.tree.push_back(Prioritized(item)),
+        }
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        MaxPriorityQueue {
+            tree: self.tree.concat(&other.tree),
+        }
+    }
+
+    pub fn pop(&self) -> Option<(T, Self)> {
+        let max = self.tree.measure();
+        max.0.as_ref()?;
+        let (left, x, right) = split_tree(&|m: &Max<T>| m.0 >= max.0, &Max(None), &self.tree.tree);
+        let tree = FingerTree {
+            tree: app3(&left, vec![], &right),
+        };
+        Some((x.item().0.clone(), MaxPriorityQueue { tree }))
+    }
+}
+
+impl<T: Ord + Clone> Default for MaxPriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for MaxPriorityQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        MaxPriorityQueue {
+            tree: iter.into_iter().map(Prioritized).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tree() {
+        let tree = FingerTree::<Indexed<i32>>::new();
+        assert!(tree.is_empty());
+        assert_eq!(tree.measure(), Size(0));
+        assert!(tree.pop_front().is_none());
+        assert!(tree.pop_back().is_none());
+        assert_eq!(tree.iter().count(), 0);
+    }
+
+    #[test]
+    fn push_both_ends() {
+        let mut seq = IndexedSeq::new();
+        for i in 0..100 {
+            seq = seq.push_back(i).push_front(-i - 1);
+        }
+        assert_eq!(seq.len(), 200);
+        assert!(seq.iter().copied().eq(-100..100));
+        assert_eq!(seq.front(), Some(&-100));
+        assert_eq!(seq.back(), Some(&99));
+    }
+
+    #[test]
+    fn use_as_deque() {
+        let mut seq: IndexedSeq<_> = (0..50).collect();
+        for i in 0..25 {
+            let (x, rest) = seq.pop_front().unwrap();
+            let (rest, y) = rest.pop_back().unwrap();
+            assert_eq!(x, i);
+            assert_eq!(y, 49 - i);
+            seq = rest;
+        }
+        assert!(seq.is_empty());
+    }
+
+    #[test]
+    fn indexed_access() {
+        let seq: IndexedSeq<_> = (0..1000).collect();
+        for i in 0..1000 {
+            assert_eq!(seq.get(i), Some(&i));
+        }
+        assert_eq!(seq.get(1000), None);
+    }
+
+    #[test]
+    fn split_at_every_position() {
+        let seq: IndexedSeq<_> = (0..100).collect();
+        for i in 0..=100 {
+            let (left, right) = seq.split_at(i);
+            assert!(left.iter().copied().eq(0..i));
+            assert!(right.iter().copied().eq(i..100));
+        }
+    }
+
+    #[test]
+    fn concat_sequences() {
+        for n in [0, 1, 5, 30, 200] {
+            for m in [0, 1, 7, 100] {
+                let a: IndexedSeq<_> = (0..n).collect();
+                let b: IndexedSeq<_> = (n..n + m).collect();
+                let c = a.concat(&b);
+                assert_eq!(c.len(), n + m);
+                assert!(c.iter().copied().eq(0..n + m));
+            }
+        }
+    }
+
+    #[test]
+    fn operations_are_persistent() {
+        let a: IndexedSeq<_> = (0..10).collect();
+        let b = a.push_back(10);
+        let (c, _) = a.split_at(5);
+        assert_eq!(a.len(), 10);
+        assert_eq!(b.len(), 11);
+        assert_eq!(c.len(), 5);
+    }
+
+    #[test]
+    fn priority_queue_pops_in_descending_order() {
+        let items = [5, 1, 9, 3, 7, 9, 2, 8];
+        let mut pq: MaxPriorityQueue<_> = items.iter().copied().collect();
+        let mut popped = vec![];
+        while let Some((x, rest)) = pq.pop() {
+            popped.push(x);
+            pq = rest;
+        }
+        assert_eq!(popped, vec![9, 9, 8, 7, 5, 3, 2, 1]);
+    }
+
+    #[test]
+    fn merge_priority_queues() {
+        let a: MaxPriorityQueue<_> = [1, 4].into_iter().collect();
+        let b = MaxPriorityQueue::new().push(3).push(6);
+        let merged = a.merge(&b);
+        assert_eq!(merged.peek(), Some(6));
+        let (_, rest) = merged.pop().unwrap();
+        assert_eq!(rest.peek(), Some(4));
+        assert_eq!(a.peek(), Some(4));
+    }
+}
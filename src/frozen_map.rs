@@ -0,0 +1,478 @@
+//! Immutable sorted sets and maps stored in a single array.
+//!
+//! They are the read-optimized form of the persistent `BinarySearchTree`
+//! and `TreeMap`: `freeze` on a tree and `thaw` on a frozen collection
+//! convert between the two in O(n). Lookups are binary searches, and the set operations merge
+//! both arrays in one pass, so they take O(n + m).
+
+use crate::binary_search_tree::{BinarySearchTree, TreeMap};
+use crate::errors::DataStructureError;
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use crate::sorted_vec::range_indices;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::ops::RangeBounds;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrozenSet<T> {
+    items: Box<[T]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrozenMap<K, V> {
+    entries: Box<[(K, V)]>,
+}
+
+/// Walk two sorted slices in step. `f` sees every item of `a` or `b`, and
+/// both at once if their keys are equal.
+fn merge_walk<'a, T, K: Ord + 'a>(
+    a: &'a [T],
+    b: &'a [T],
+    key: impl Fn(&'a T) -> &'a K,
+    mut f: impl FnMut(Option<&'a T>, Option<&'a T>),
+) {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match key(&a[i]).cmp(key(&b[j])) {
+            Ordering::Less => {
+                f(Some(&a[i]), None);
+                i += 1;
+            }
+            Ordering::Greater => {
+                f(None, Some(&b[j]));
+                j += 1;
+            }
+            Ordering::Equal => {
+                f(Some(&a[i]), Some(&b[j]));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    a[i..].iter().for_each(|x| f(Some(x), None));
+    b[j..].iter().for_each(|y| f(None, Some(y)));
+}
+
+impl<T: Ord> FrozenSet<T> {
+    pub fn from_unsorted(mut items: Vec<T>) -> Self {
+        items.sort();
+        items.dedup();
+        FrozenSet {
+            items: items.into_boxed_slice(),
+        }
+    }
+
+    /// Take over items that are already sorted and unique, in O(n). Fails
+    /// with `InvalidStructure` otherwise.
+    pub fn from_sorted(items: Vec<T>) -> Result<Self, DataStructureError> {
+        if items.is_sorted_by(|a, b| a < b) {
+            Ok(FrozenSet {
+                items: items.into_boxed_slice(),
+            })
+        } else {
+            Err(DataStructureError::InvalidStructure)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get<Q>(&self, item: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let idx = self.items.binary_search_by(|x| x.borrow().cmp(item)).ok()?;
+        Some(&self.items[idx])
+    }
+
+    pub fn contains<Q>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get(item).is_some()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.items.last()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> &[T]
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = range_indices(&self.items, |x| x, range);
+        &self.items[start..end]
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        let mut subset = self.len() <= other.len();
+        if subset {
+            merge_walk(
+                &self.items,
+                &other.items,
+                |x| x,
+                |a, b| subset &= a.is_none() || b.is_some(),
+            );
+        }
+        subset
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        let mut disjoint = true;
+        merge_walk(
+            &self.items,
+            &other.items,
+            |x| x,
+            |a, b| disjoint &= a.is_none() || b.is_none(),
+        );
+        disjoint
+    }
+}
+
+impl<T: Ord + Clone> FrozenSet<T> {
+    pub fn union(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.or(b))
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.and(b))
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.filter(|_| b.is_none()))
+    }
+
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.xor(b))
+    }
+
+    /// A balanced persistent tree with the same items.
+    pub fn thaw(&self) -> BinarySearchTree<T> {
+        BinarySearchTree::from_sorted(self.items.to_vec()).expect("frozen sets are sorted")
+    }
+
+    fn merge<'a>(
+        &'a self,
+        other: &'a Self,
+        pick: impl Fn(Option<&'a T>, Option<&'a T>) -> Option<&'a T>,
+    ) -> Self {
+        let mut items = Vec::new();
+        merge_walk(
+            &self.items,
+            &other.items,
+            |x| x,
+            |a, b| items.extend(pick(a, b).cloned()),
+        );
+        FrozenSet {
+            items: items.into_boxed_slice(),
+        }
+    }
+}
+
+impl<T: Ord> Default for FrozenSet<T> {
+    fn default() -> Self {
+        FrozenSet::from_unsorted(Vec::new())
+    }
+}
+
+impl<T: Ord> FromIterator<T> for FrozenSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        FrozenSet::from_unsorted(iter.into_iter().collect())
+    }
+}
+
+impl<T> MemoryFootprint for FrozenSet<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<T>(self.items.len(), self.items.len())
+    }
+}
+
+impl<'a, T> IntoIterator for &'a FrozenSet<T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+impl<K: Ord, V> FrozenMap<K, V> {
+    /// Build a map from unsorted pairs. If a key occurs multiple times, the
+    /// last value wins.
+    pub fn from_unsorted(mut entries: Vec<(K, V)>) -> Self {
+        entries.reverse();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.dedup_by(|a, b| a.0 == b.0);
+        FrozenMap {
+            entries: entries.into_boxed_slice(),
+        }
+    }
+
+    /// Take over entries whose keys are already sorted and unique, in O(n).
+    /// Fails with `InvalidStructure` otherwise.
+    pub fn from_sorted(entries: Vec<(K, V)>) -> Result<Self, DataStructureError> {
+        if entries.is_sorted_by(|a, b| a.0 < b.0) {
+            Ok(FrozenMap {
+                entries: entries.into_boxed_slice(),
+            })
+        } else {
+            Err(DataStructureError::InvalidStructure)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let idx = self
+            .entries
+            .binary_search_by(|(k, _)| k.borrow().cmp(key))
+            .ok()?;
+        let (k, v) = &self.entries[idx];
+        Some((k, v))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.entries.first().map(|(k, v)| (k, v))
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.entries.last().map(|(k, v)| (k, v))
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> &[(K, V)]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = range_indices(&self.entries, |(k, _)| k, range);
+        &self.entries[start..end]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn as_slice(&self) -> &[(K, V)] {
+        &self.entries
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> FrozenMap<K, V> {
+    /// All keys of both maps. Where both have a key, the value of `other`
+    /// wins.
+    pub fn union(&self, other: &Self) -> Self {
+        self.union_with(other, |_, _, b| b.clone())
+    }
+
+    /// All keys of both maps, combining the values of shared keys with `f`.
+    pub fn union_with(&self, other: &Self, mut f: impl FnMut(&K, &V, &V) -> V) -> Self {
+        let mut entries = Vec::new();
+        merge_walk(
+            &self.entries,
+            &other.entries,
+            |(k, _)| k,
+            |a, b| {
+                entries.push(match (a, b) {
+                    (Some((k, x)), Some((_, y))) => (k.clone(), f(k, x, y)),
+                    (Some(e), None) | (None, Some(e)) => e.clone(),
+                    (None, None) => unreachable!(),
+                })
+            },
+        );
+        FrozenMap {
+            entries: entries.into_boxed_slice(),
+        }
+    }
+
+    /// The entries of `self` whose keys are also in `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.filter(|_| b.is_some()))
+    }
+
+    /// The entries of `self` whose keys are not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.filter(|_| b.is_none()))
+    }
+
+    /// The entries whose keys are in exactly one of the maps.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.merge(other, |a, b| a.xor(b))
+    }
+
+    /// A balanced persistent tree map of the same entries.
+    pub fn thaw(&self) -> TreeMap<K, V> {
+        TreeMap::from_sorted_entries(self.entries.iter().cloned())
+    }
+
+    fn merge<'a>(
+        &'a self,
+        other: &'a Self,
+        pick: impl Fn(Option<&'a (K, V)>, Option<&'a (K, V)>) -> Option<&'a (K, V)>,
+    ) -> Self {
+        let mut entries = Vec::new();
+        merge_walk(
+            &self.entries,
+            &other.entries,
+            |(k, _)| k,
+            |a, b| entries.extend(pick(a, b).cloned()),
+        );
+        FrozenMap {
+            entries: entries.into_boxed_slice(),
+        }
+    }
+}
+
+impl<K: Ord, V> Default for FrozenMap<K, V> {
+    fn default() -> Self {
+        FrozenMap::from_unsorted(Vec::new())
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for FrozenMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        FrozenMap::from_unsorted(iter.into_iter().collect())
+    }
+}
+
+impl<K, V> MemoryFootprint for FrozenMap<K, V> {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<(K, V)>(self.entries.len(), self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_lookups() {
+        let set: FrozenSet<_> = [5, 1, 9, 3, 5, 7].into_iter().collect();
+        assert_eq!(set.as_slice(), &[1, 3, 5, 7, 9]);
+        assert!(set.contains(&7) && !set.contains(&4));
+        assert_eq!(set.range(3..8), &[3, 5, 7]);
+        assert_eq!((set.first(), set.last()), (Some(&1), Some(&9)));
+        assert_eq!(
+            FrozenSet::from_sorted(vec![1, 1]),
+            Err(DataStructureError::InvalidStructure)
+        );
+    }
+
+    #[test]
+    fn set_algebra() {
+        let a: FrozenSet<u32> = (0..20).filter(|x| x % 2 == 0).collect();
+        let b: FrozenSet<u32> = (0..20).filter(|x| x % 3 == 0).collect();
+        let naive = |f: fn(bool, bool) -> bool| -> Vec<u32> {
+            (0..20).filter(|x| f(x % 2 == 0, x % 3 == 0)).collect()
+        };
+        assert_eq!(a.union(&b).as_slice(), naive(|x, y| x || y));
+        assert_eq!(a.intersection(&b).as_slice(), naive(|x, y| x && y));
+        assert_eq!(a.difference(&b).as_slice(), naive(|x, y| x && !y));
+        assert_eq!(a.symmetric_difference(&b).as_slice(), naive(|x, y| x != y));
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert!(a.difference(&b).is_disjoint(&b));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn map_lookups_and_last_value_wins() {
+        let map: FrozenMap<_, _> = [(3, "c"), (1, "a"), (3, "C"), (2, "b")]
+            .into_iter()
+            .collect();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&3), Some(&"C"));
+        assert_eq!(map.get(&4), None);
+        assert_eq!(map.range(2..), &[(2, "b"), (3, "C")]);
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn map_algebra() {
+        let a: FrozenMap<_, _> = (0..6).map(|k| (k, k * 10)).collect();
+        let b: FrozenMap<_, _> = (4..8).map(|k| (k, k * 100)).collect();
+        let sum = a.union_with(&b, |_, x, y| x + y);
+        assert_eq!(sum.get(&5), Some(&550));
+        assert_eq!(sum.len(), 8);
+        assert_eq!(a.union(&b).get(&4), Some(&400));
+        assert_eq!(a.intersection(&b).as_slice(), &[(4, 40), (5, 50)]);
+        assert_eq!(a.difference(&b).len(), 4);
+        let sym = a.symmetric_difference(&b);
+        assert_eq!(
+            sym.keys().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 6, 7]
+        );
+    }
+
+    #[test]
+    fn freeze_and_thaw_round_trip() {
+        let tree = (0..50).fold(BinarySearchTree::new(), |t, x| t.insert(x * 7 % 50));
+        let frozen = tree.freeze();
+        assert!(frozen.iter().copied().eq(0..50));
+        let thawed = frozen.thaw();
+        assert_eq!(thawed.len(), 50);
+        assert_eq!(thawed.freeze(), frozen);
+
+        let map: FrozenMap<_, _> = (0..10).map(|k| (k, k * k)).collect();
+        let tree = map.thaw().insert(10, 100);
+        assert_eq!(tree.get(&3), Some(&9));
+        assert_eq!(tree.freeze().get(&10), Some(&100));
+    }
+}
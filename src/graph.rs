@@ -0,0 +1,415 @@
+//! Weighted directed graphs on nodes numbered `0..n`.
+//!
+//! `AdjacencyList` suits sparse graphs; `AdjacencyMatrix` stores a slot for
+//! every ordered pair of nodes, which costs O(n²) memory but answers edge
+//! lookups in O(1) and is the natural input for `floyd_warshall`. Both
+//! implement `GraphMut`, and `neighbors` plugs directly into the searches in
+//! `shortest_path`. Read-only algorithms only need `Graph`.
+
+use crate::errors::DataStructureError;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Add;
+use core::{fmt, iter, slice};
+
+/// A directed graph with edge weights of type `Weight`. There is at most
+/// one edge from any node to another.
+pub trait Graph {
+    type Weight: Copy;
+    type Neighbors<'a>: Iterator<Item = (usize, Self::Weight)>
+    where
+        Self: 'a;
+
+    fn node_count(&self) -> usize;
+
+    fn edge_count(&self) -> usize;
+
+    fn edge(&self, from: usize, to: usize) -> Option<Self::Weight>;
+
+    /// The targets of all edges leaving `node`, with their weights.
+    fn neighbors(&self, node: usize) -> Self::Neighbors<'_>;
+
+    fn has_edge(&self, from: usize, to: usize) -> bool {
+        self.edge(from, to).is_some()
+    }
+
+    /// All edges as `(from, to, weight)`, ordered by `from`.
+    fn edges(&self) -> impl Iterator<Item = (usize, usize, Self::Weight)> + '_ {
+        (0..self.node_count()).flat_map(move |u| self.neighbors(u).map(move |(v, w)| (u, v, w)))
+    }
+}
+
+/// A graph that can be changed in place.
+pub trait GraphMut: Graph {
+    /// Add an isolated node and return its index.
+    fn add_node(&mut self) -> usize;
+
+    /// Add or reweight the edge `from -> to`, returning the old weight.
+    /// Panics if a node does not exist.
+    fn add_edge(&mut self, from: usize, to: usize, weight: Self::Weight) -> Option<Self::Weight>;
+
+    fn remove_edge(&mut self, from: usize, to: usize) -> Option<Self::Weight>;
+}
+
+/// A graph storing the outgoing edges of every node in a `Vec`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdjacencyList<W> {
+    adjacency: Vec<Vec<(usize, W)>>,
+    edges: usize,
+}
+
+impl<W: Copy> AdjacencyList<W> {
+    /// A graph with `nodes` nodes and no edges.
+    pub fn new(nodes: usize) -> Self {
+        AdjacencyList {
+            adjacency: (0..nodes).map(|_| Vec::new()).collect(),
+            edges: 0,
+        }
+    }
+}
+
+impl<W: Copy> Graph for AdjacencyList<W> {
+    type Weight = W;
+    type Neighbors<'a>
+        = iter::Copied<slice::Iter<'a, (usize, W)>>
+    where
+        W: 'a;
+
+    fn node_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edges
+    }
+
+    fn edge(&self, from: usize, to: usize) -> Option<W> {
+        self.adjacency
+            .get(from)?
+            .iter()
+            .find(|(v, _)| *v == to)
+            .map(|&(_, w)| w)
+    }
+
+    fn neighbors(&self, node: usize) -> Self::Neighbors<'_> {
+        self.adjacency[node].iter().copied()
+    }
+}
+
+impl<W: Copy> GraphMut for AdjacencyList<W> {
+    fn add_node(&mut self) -> usize {
+        self.adjacency.push(Vec::new());
+        self.adjacency.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: W) -> Option<W> {
+        assert!(to < self.node_count(), "node out of bounds");
+        let edges = &mut self.adjacency[from];
+        match edges.iter_mut().find(|(v, _)| *v == to) {
+            Some((_, w)) => Some(core::mem::replace(w, weight)),
+            None => {
+                edges.push((to, weight));
+                self.edges += 1;
+                None
+            }
+        }
+    }
+
+    fn remove_edge(&mut self, from: usize, to: usize) -> Option<W> {
+        let edges = self.adjacency.get_mut(from)?;
+        let i = edges.iter().position(|(v, _)| *v == to)?;
+        self.edges -= 1;
+        Some(edges.swap_remove(i).1)
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for AdjacencyList<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.adjacency.iter().enumerate())
+            .finish()
+    }
+}
+
+/// A graph storing a slot for every ordered pair of nodes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdjacencyMatrix<W> {
+    weights: Vec<Option<W>>,
+    nodes: usize,
+    edges: usize,
+}
+
+impl<W: Copy> AdjacencyMatrix<W> {
+    /// A graph with `nodes` nodes and no edges.
+    pub fn new(nodes: usize) -> Self {
+        AdjacencyMatrix {
+            weights: vec![None; nodes * nodes],
+            nodes,
+            edges: 0,
+        }
+    }
+
+    fn slot(&self, from: usize, to: usize) -> Option<usize> {
+        (from < self.nodes && to < self.nodes).then(|| from * self.nodes + to)
+    }
+}
+
+impl<W: Copy> Graph for AdjacencyMatrix<W> {
+    type Weight = W;
+    type Neighbors<'a>
+        = MatrixNeighbors<'a, W>
+    where
+        W: 'a;
+
+    fn node_count(&self) -> usize {
+        self.nodes
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edges
+    }
+
+    fn edge(&self, from: usize, to: usize) -> Option<W> {
+        self.weights[self.slot(from, to)?]
+    }
+
+    fn neighbors(&self, node: usize) -> Self::Neighbors<'_> {
+        let start = node * self.nodes;
+        MatrixNeighbors {
+            row: self.weights[start..start + self.nodes].iter().enumerate(),
+        }
+    }
+}
+
+impl<W: Copy> GraphMut for AdjacencyMatrix<W> {
+    /// O(n²): every row gets a new column.
+    fn add_node(&mut self) -> usize {
+        let n = self.nodes;
+        let mut weights = Vec::with_capacity((n + 1) * (n + 1));
+        for row in self.weights.chunks(n.max(1)).take(n) {
+            weights.extend_from_slice(row);
+            weights.push(None);
+        }
+        weights.resize((n + 1) * (n + 1), None);
+        self.weights = weights;
+        self.nodes += 1;
+        n
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: W) -> Option<W> {
+        let slot = self.slot(from, to).expect("node out of bounds");
+        let old = self.weights[slot].replace(weight);
+        if old.is_none() {
+            self.edges += 1;
+        }
+        old
+    }
+
+    fn remove_edge(&mut self, from: usize, to: usize) -> Option<W> {
+        let slot = self.slot(from, to)?;
+        let old = self.weights[slot].take()?;
+        self.edges -= 1;
+        Some(old)
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for AdjacencyMatrix<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.weights.chunks(self.nodes.max(1)).take(self.nodes))
+            .finish()
+    }
+}
+
+pub struct MatrixNeighbors<'a, W> {
+    row: iter::Enumerate<slice::Iter<'a, Option<W>>>,
+}
+
+impl<W: Copy> Iterator for MatrixNeighbors<'_, W> {
+    type Item = (usize, W);
+
+    fn next(&mut self) -> Option<(usize, W)> {
+        self.row.find_map(|(v, w)| w.map(|w| (v, w)))
+    }
+}
+
+/// Shortest paths between every pair of nodes, as computed by
+/// `floyd_warshall`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AllPairsShortestPaths<W> {
+    dist: Vec<Option<W>>,
+    /// The node after `from` on the shortest path `from -> to`.
+    next: Vec<usize>,
+    nodes: usize,
+}
+
+/// All-pairs shortest paths in O(n³) time and O(n²) memory. Negative
+/// weights are allowed; a negative cycle fails with `InvalidStructure`.
+pub fn floyd_warshall<G>(graph: &G) -> Result<AllPairsShortestPaths<G::Weight>, DataStructureError>
+where
+    G: Graph,
+    G::Weight: Ord + Add<Output = G::Weight> + Default,
+{
+    let n = graph.node_count();
+    let mut dist = vec![None; n * n];
+    let mut next = vec![usize::MAX; n * n];
+    for u in 0..n {
+        dist[u * n + u] = Some(G::Weight::default());
+        next[u * n + u] = u;
+        for (v, w) in graph.neighbors(u) {
+            if dist[u * n + v].is_none_or(|d| w < d) {
+                dist[u * n + v] = Some(w);
+                next[u * n + v] = v;
+            }
+        }
+    }
+    for k in 0..n {
+        for i in 0..n {
+            let Some(ik) = dist[i * n + k] else { continue };
+            for j in 0..n {
+                let Some(kj) = dist[k * n + j] else { continue };
+                if dist[i * n + j].is_none_or(|d| ik + kj < d) {
+                    dist[i * n + j] = Some(ik + kj);
+                    next[i * n + j] = next[i * n + k];
+                }
+            }
+        }
+        if (0..n).any(|i| dist[i * n + i].is_some_and(|d| d < G::Weight::default())) {
+            return Err(DataStructureError::InvalidStructure);
+        }
+    }
+    Ok(AllPairsShortestPaths {
+        dist,
+        next,
+        nodes: n,
+    })
+}
+
+impl<W: Copy> AllPairsShortestPaths<W> {
+    /// Length of the shortest path `from -> to`, if `to` is reachable.
+    pub fn distance(&self, from: usize, to: usize) -> Option<W> {
+        if from < self.nodes && to < self.nodes {
+            self.dist[from * self.nodes + to]
+        } else {
+            None
+        }
+    }
+
+    /// The nodes on the shortest path `from -> to`, both ends included.
+    pub fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        self.distance(from, to)?;
+        let mut path = vec![from];
+        let mut current = from;
+        while current != to {
+            current = self.next[current * self.nodes + to];
+            path.push(current);
+        }
+        Some(path)
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for AllPairsShortestPaths<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.dist.chunks(self.nodes.max(1)).take(self.nodes))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::dijkstra;
+
+    /// The graph from the `shortest_path` tests:
+    ///
+    /// ```text
+    /// 0 -4-> 1 -1-> 3
+    /// 0 -1-> 2 -2-> 1
+    /// 2 -7-> 3 -3-> 4
+    /// 5 (isolated)
+    /// ```
+    fn build<G: GraphMut<Weight = i32>>(mut g: G) -> G {
+        for (u, v, w) in [
+            (0, 1, 4),
+            (0, 2, 1),
+            (1, 3, 1),
+            (2, 1, 2),
+            (2, 3, 7),
+            (3, 4, 3),
+        ] {
+            g.add_edge(u, v, w);
+        }
+        g
+    }
+
+    fn neighbors_of<G: Graph>(g: &G, node: usize) -> Vec<(usize, G::Weight)> {
+        let mut n: Vec<_> = g.neighbors(node).collect();
+        n.sort_by_key(|&(v, _)| v);
+        n
+    }
+
+    #[test]
+    fn list_and_matrix_agree() {
+        let list = build(AdjacencyList::new(6));
+        let matrix = build(AdjacencyMatrix::new(6));
+        assert_eq!((list.edge_count(), matrix.edge_count()), (6, 6));
+        for u in 0..6 {
+            assert_eq!(neighbors_of(&list, u), neighbors_of(&matrix, u));
+            for v in 0..6 {
+                assert_eq!(list.edge(u, v), matrix.edge(u, v));
+            }
+        }
+        assert_eq!(matrix.edge(0, 17), None);
+    }
+
+    #[test]
+    fn edges_are_replaced_and_removed() {
+        let mut g = AdjacencyMatrix::new(3);
+        assert_eq!(g.add_edge(0, 1, 5), None);
+        assert_eq!(g.add_edge(0, 1, 2), Some(5));
+        assert_eq!(g.edge_count(), 1);
+        assert_eq!(g.remove_edge(0, 1), Some(2));
+        assert_eq!(g.remove_edge(0, 1), None);
+        assert!(!g.has_edge(0, 1));
+
+        g.add_edge(2, 1, 9);
+        assert_eq!(g.add_node(), 3);
+        g.add_edge(3, 2, 1);
+        assert_eq!(
+            (g.edge(2, 1), g.edge(3, 2), g.edge(2, 3)),
+            (Some(9), Some(1), None)
+        );
+        assert_eq!(format!("{g:?}").matches("Some").count(), 2);
+    }
+
+    #[test]
+    fn floyd_warshall_matches_dijkstra() {
+        let g = build(AdjacencyMatrix::new(6));
+        let all = floyd_warshall(&g).unwrap();
+        for source in 0..6 {
+            let tree = dijkstra(6, source, |u| g.neighbors(u));
+            for target in 0..6 {
+                assert_eq!(all.distance(source, target), tree.distance(target));
+            }
+        }
+        assert_eq!(all.path(0, 4), Some(vec![0, 2, 1, 3, 4]));
+        assert_eq!(all.path(3, 3), Some(vec![3]));
+        assert_eq!(all.path(4, 0), None);
+    }
+
+    #[test]
+    fn negative_weights_and_cycles() {
+        let mut g = AdjacencyList::new(3);
+        g.add_edge(0, 1, 5);
+        g.add_edge(1, 2, -3);
+        g.add_edge(0, 2, 4);
+        let all = floyd_warshall(&g).unwrap();
+        assert_eq!(all.distance(0, 2), Some(2));
+        g.add_edge(2, 0, -3);
+        assert_eq!(
+            floyd_warshall(&g).unwrap_err(),
+            DataStructureError::InvalidStructure
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+/// A dense two-dimensional array stored row by row in a single `Vec`.
+///
+/// Cells are addressed as `(row, col)`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    rows: usize,
+    cols: usize,
+}
+
+const OFFSETS_4: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const OFFSETS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+impl<T> Grid<T> {
+    pub fn new(rows: usize, cols: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid {
+            cells: vec![fill; rows * cols],
+            rows,
+            cols,
+        }
+    }
+
+    pub fn from_fn(rows: usize, cols: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let cells = (0..rows * cols).map(|i| f(i / cols, i % cols)).collect();
+        Grid { cells, rows, cols }
+    }
+
+    /// Build a grid from cells in row-major order. Returns `None` if the
+    /// number of cells does not match.
+    pub fn from_vec(rows: usize, cols: usize, cells: Vec<T>) -> Option<Self> {
+        (cells.len() == rows * cols).then_some(Grid { cells, rows, cols })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        self.offset(row, col).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        self.offset(row, col).map(|i| &mut self.cells[i])
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        assert!(row < self.rows, "row out of range");
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [T] {
+        assert!(row < self.rows, "row out of range");
+        &mut self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &T> {
+        assert!(col < self.cols, "column out of range");
+        self.cells.iter().skip(col).step_by(self.cols)
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[T]> {
+        // `max(1)` keeps `chunks` happy for grids without columns
+        self.cells.chunks(self.cols.max(1))
+    }
+
+    /// All cells in row-major order.
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    /// All cells with their positions, in row-major order.
+    pub fn indexed_iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let cols = self.cols;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| ((i / cols, i % cols), v))
+    }
+
+    /// Positions of the up to 4 orthogonally adjacent cells.
+    pub fn neighbors4(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbors(row, col, &OFFSETS_4)
+    }
+
+    /// Positions of the up to 8 orthogonally or diagonally adjacent cells.
+    pub fn neighbors8(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbors(row, col, &OFFSETS_8)
+    }
+
+    /// A read-only window of `rows` x `cols` cells whose top left corner is
+    /// at `(row, col)`. Panics if the window does not fit.
+    pub fn view(&self, row: usize, col: usize, rows: usize, cols: usize) -> GridView<'_, T> {
+        assert!(
+            row + rows <= self.rows && col + cols <= self.cols,
+            "view out of range"
+        );
+        GridView {
+            grid: self,
+            row,
+            col,
+            rows,
+            cols,
+        }
+    }
+
+    /// Change the dimensions, keeping the cells that are still inside the
+    /// grid and filling new ones with `fill`.
+    pub fn resize(&mut self, rows: usize, cols: usize, fill: T)
+    where
+        T: Clone,
+    {
+        let mut old = core::mem::take(&mut self.cells).into_iter();
+        let mut cells = Vec::with_capacity(rows * cols);
+        let keep = cols.min(self.cols);
+        for r in 0..rows.min(self.rows) {
+            cells.extend(old.by_ref().take(keep));
+            old.by_ref().take(self.cols - keep).for_each(drop);
+            cells.resize((r + 1) * cols, fill.clone());
+        }
+        cells.resize(rows * cols, fill);
+        *self = Grid { cells, rows, cols };
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            cells: self.cells.iter().map(f).collect(),
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    /// A new grid with rows and columns swapped.
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        Grid::from_fn(self.cols, self.rows, |r, c| self[(c, r)].clone())
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.cells
+    }
+
+    fn offset(&self, row: usize, col: usize) -> Option<usize> {
+        (row < self.rows && col < self.cols).then(|| row * self.cols + col)
+    }
+
+    fn neighbors<'a>(
+        &self,
+        row: usize,
+        col: usize,
+        offsets: &'a [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (rows, cols) = (self.rows, self.cols);
+        offsets.iter().filter_map(move |&(dr, dc)| {
+            let r = row.checked_add_signed(dr).filter(|&r| r < rows)?;
+            let c = col.checked_add_signed(dc).filter(|&c| c < cols)?;
+            Some((r, c))
+        })
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        self.get(row, col).expect("index out of range")
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        self.get_mut(row, col).expect("index out of range")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter_rows()).finish()
+    }
+}
+
+/// A rectangular window into a `Grid`, created by `Grid::view`.
+#[derive(Clone, Copy)]
+pub struct GridView<'a, T> {
+    grid: &'a Grid<T>,
+    row: usize,
+    col: usize,
+    rows: usize,
+    cols: usize,
+}
+
+impl<'a, T> GridView<'a, T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The cell at `(row, col)` relative to the window's corner.
+    pub fn get(&self, row: usize, col: usize) -> Option<&'a T> {
+        if row < self.rows && col < self.cols {
+            self.grid.get(self.row + row, self.col + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, row: usize) -> &'a [T] {
+        assert!(row < self.rows, "row out of range");
+        &self.grid.row(self.row + row)[self.col..self.col + self.cols]
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        (0..self.rows).map(|r| self.row(r))
+    }
+
+    /// All cells of the window in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.iter_rows().flatten()
+    }
+
+    pub fn to_grid(&self) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid::from_fn(self.rows, self.cols, |r, c| self.get(r, c).unwrap().clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for GridView<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter_rows()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(rows: usize, cols: usize) -> Grid<usize> {
+        Grid::from_fn(rows, cols, |r, c| r * 10 + c)
+    }
+
+    #[test]
+    fn indexing() {
+        let mut grid = numbered(3, 4);
+        assert_eq!(grid.len(), 12);
+        assert_eq!(grid[(2, 3)], 23);
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.get(0, 4), None);
+        grid[(1, 1)] = 99;
+        assert_eq!(grid.row(1), &[10, 99, 12, 13]);
+        assert_eq!(grid.column(2).copied().collect::<Vec<_>>(), vec![2, 12, 22]);
+        assert_eq!(Grid::from_vec(2, 2, vec![1, 2, 3]), None);
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = numbered(3, 3);
+        let n4: Vec<_> = grid.neighbors4(0, 0).collect();
+        assert_eq!(n4, vec![(0, 1), (1, 0)]);
+        assert_eq!(grid.neighbors4(1, 1).count(), 4);
+        assert_eq!(grid.neighbors8(1, 1).count(), 8);
+        let n8: Vec<_> = grid.neighbors8(2, 2).collect();
+        assert_eq!(n8, vec![(1, 1), (1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn sub_grid_views() {
+        let grid = numbered(4, 5);
+        let view = grid.view(1, 2, 2, 3);
+        assert_eq!(view.row(0), &[12, 13, 14]);
+        assert_eq!(view.get(1, 0), Some(&22));
+        assert_eq!(view.get(2, 0), None);
+        assert_eq!(
+            view.iter().copied().collect::<Vec<_>>(),
+            vec![12, 13, 14, 22, 23, 24]
+        );
+        assert_eq!(view.to_grid().transpose()[(2, 1)], 24);
+    }
+
+    #[test]
+    fn resizing_keeps_overlapping_cells() {
+        let mut grid = numbered(3, 3);
+        grid.resize(2, 4, 0);
+        assert_eq!(grid.into_vec(), vec![0, 1, 2, 0, 10, 11, 12, 0]);
+
+        let mut grid = numbered(3, 3);
+        grid.resize(4, 2, 7);
+        assert_eq!(grid.into_vec(), vec![0, 1, 10, 11, 20, 21, 7, 7]);
+
+        let mut grid = numbered(2, 2);
+        grid.resize(0, 5, 0);
+        assert!(grid.is_empty());
+        grid.resize(1, 1, 3);
+        assert_eq!(grid[(0, 0)], 3);
+    }
+
+    #[test]
+    fn map_and_iteration() {
+        let grid = numbered(2, 2).map(|v| v * 2);
+        let cells: Vec<_> = grid.indexed_iter().map(|(p, &v)| (p, v)).collect();
+        assert_eq!(
+            cells,
+            vec![((0, 0), 0), ((0, 1), 2), ((1, 0), 20), ((1, 1), 22)]
+        );
+        assert_eq!(grid.iter_rows().count(), 2);
+    }
+}
@@ -0,0 +1,363 @@
+//! A hash map behind a Bloom filter, for workloads where most lookups miss.
+//!
+//! Every key in the map has its bits set in the filter, so a key whose
+//! bits are not all set cannot be in the map and the lookup ends after a
+//! few bit tests, without walking the trie. Removed keys leave their bits
+//! behind and make the filter less selective until `rebuild_filter` is
+//! called; the filter also rebuilds itself with twice the capacity when the
+//! map outgrows it.
+
+use crate::bit_vec::BitVec;
+use crate::hamt::{Iter, PHashMap, TransientHashMap};
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+const DEFAULT_CAPACITY: usize = 1024;
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+pub struct GuardedMap<K, V, S = RandomState> {
+    map: TransientHashMap<K, V, S>,
+    filter: BloomFilter,
+    hasher: S,
+    /// Keys that were removed since the filter was last rebuilt.
+    stale: usize,
+    lookups: Cell<u64>,
+    rejected: Cell<u64>,
+    false_positives: Cell<u64>,
+}
+
+/// How well the filter did since the statistics were last reset.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GuardStats {
+    /// Lookups by key.
+    pub lookups: u64,
+    /// Lookups answered by the filter alone.
+    pub rejected: u64,
+    /// Lookups the filter let through for keys that were not in the map.
+    pub false_positives: u64,
+}
+
+impl GuardStats {
+    /// Fraction of absent keys that the filter failed to reject.
+    pub fn false_positive_rate(&self) -> f64 {
+        let misses = self.rejected + self.false_positives;
+        if misses == 0 {
+            0.0
+        } else {
+            self.false_positives as f64 / misses as f64
+        }
+    }
+}
+
+struct BloomFilter {
+    bits: BitVec,
+    hashes: u32,
+    capacity: usize,
+    rate: f64,
+    /// Keys whose bits were set, including stale ones.
+    inserted: usize,
+}
+
+impl BloomFilter {
+    /// The optimal size for `capacity` keys at false positive `rate`:
+    /// `m = -n ln p / ln² 2` bits and `k = m / n ln 2` hash functions.
+    fn new(capacity: usize, rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(capacity as f64) * rate.ln() / (ln2 * ln2)).ceil() as usize;
+        let bits = bits.max(64);
+        let hashes = ((bits as f64 / capacity as f64) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: BitVec::from_elem(bits, false),
+            hashes,
+            capacity,
+            rate,
+            inserted: 0,
+        }
+    }
+
+    /// Bit positions for a 64-bit hash, by double hashing its two halves.
+    fn positions(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let m = self.bits.len() as u64;
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        for i in self.positions(hash).collect::<Vec<_>>() {
+            self.bits.set(i, true);
+        }
+        self.inserted += 1;
+    }
+
+    fn may_contain(&self, hash: u64) -> bool {
+        self.positions(hash).all(|i| self.bits.get(i) == Some(true))
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone> GuardedMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+
+    /// A map whose filter is sized for `capacity` keys at a false positive
+    /// rate of `rate`. Panics unless `0 < rate < 1`.
+    pub fn with_capacity(capacity: usize, rate: f64) -> Self {
+        Self::with_capacity_and_hasher(capacity, rate, RandomState::new())
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> GuardedMap<K, V, S> {
+    pub fn with_capacity_and_hasher(capacity: usize, rate: f64, hasher: S) -> Self {
+        assert!(
+            rate > 0.0 && rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+        GuardedMap {
+            map: PHashMap::with_hasher(hasher.clone()).transient(),
+            filter: BloomFilter::new(capacity, rate),
+            hasher,
+            stale: 0,
+            lookups: Cell::new(0),
+            rejected: Cell::new(0),
+            false_positives: Cell::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.lookups.set(self.lookups.get() + 1);
+        if !self.filter.may_contain(self.hasher.hash_one(key)) {
+            self.rejected.set(self.rejected.get() + 1);
+            return None;
+        }
+        let value = self.map.get(key);
+        if value.is_none() {
+            self.false_positives.set(self.false_positives.get() + 1);
+        }
+        value
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        if !self.filter.may_contain(self.hasher.hash_one(key)) {
+            return None;
+        }
+        self.map.get_mut(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the value previously stored under `key`.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&key);
+        let old = self.map.insert(key, value);
+        if old.is_none() {
+            if self.filter.inserted >= self.filter.capacity {
+                self.filter = BloomFilter::new(self.filter.capacity * 2, self.filter.rate);
+                self.rebuild_filter();
+            } else {
+                self.filter.insert(hash);
+            }
+        }
+        old
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        if !self.filter.may_contain(self.hasher.hash_one(key)) {
+            return None;
+        }
+        let old = self.map.remove(key);
+        if old.is_some() {
+            self.stale += 1;
+        }
+        old
+    }
+
+    /// Clear the filter and add the keys currently in the map, dropping
+    /// the bits of removed keys.
+    pub fn rebuild_filter(&mut self) {
+        self.filter = BloomFilter::new(self.filter.capacity, self.filter.rate);
+        for (key, _) in self.map.iter() {
+            self.filter.insert(self.hasher.hash_one(key));
+        }
+        self.stale = 0;
+    }
+
+    /// Number of keys removed since the filter was last rebuilt; their
+    /// bits still make lookups of other keys pass the filter.
+    pub fn stale_keys(&self) -> usize {
+        self.stale
+    }
+
+    /// Size of the filter in bits.
+    pub fn filter_bits(&self) -> usize {
+        self.filter.bits.len()
+    }
+
+    /// The expected false positive rate of the filter in its current state,
+    /// `(1 - e^(-k n / m))^k`.
+    pub fn expected_false_positive_rate(&self) -> f64 {
+        let k = self.filter.hashes as f64;
+        let n = self.filter.inserted as f64;
+        let m = self.filter.bits.len() as f64;
+        (1.0 - (-k * n / m).exp()).powf(k)
+    }
+
+    pub fn stats(&self) -> GuardStats {
+        GuardStats {
+            lookups: self.lookups.get(),
+            rejected: self.rejected.get(),
+            false_positives: self.false_positives.get(),
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        self.lookups.set(0);
+        self.rejected.set(0);
+        self.false_positives.set(0);
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    pub fn into_inner(self) -> TransientHashMap<K, V, S> {
+        self.map
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone> Default for GuardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone> FromIterator<(K, V)> for GuardedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> Extend<(K, V)>
+    for GuardedMap<K, V, S>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for GuardedMap<K, V, S>
+where
+    K: Clone + Hash + Eq + fmt::Debug,
+    V: Clone + fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn behaves_like_a_map() {
+        let mut map = GuardedMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("b", 2), None);
+        assert_eq!(map.insert("a", 3), Some(1));
+        assert_eq!(map.get(&"a"), Some(&3));
+        *map.get_mut(&"b").unwrap() += 10;
+        assert_eq!(map.remove(&"b"), Some(12));
+        assert_eq!(map.remove(&"b"), None);
+        assert!(!map.contains_key(&"c"));
+        assert_eq!(map.len(), 1);
+        assert_eq!(format!("{map:?}"), r#"{"a": 3}"#);
+    }
+
+    #[test]
+    fn borrowed_key_lookup() {
+        let mut map = GuardedMap::new();
+        map.insert(String::from("a"), 1);
+        assert_eq!(map.get("a"), Some(&1));
+        assert!(map.contains_key("a") && !map.contains_key("b"));
+        *map.get_mut("a").unwrap() += 1;
+        assert_eq!(map.remove("a"), Some(2));
+    }
+
+    #[test]
+    fn most_misses_are_rejected_by_the_filter() {
+        let map: GuardedMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+        for i in 0..1000 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        map.reset_stats();
+        for i in 1000..11_000 {
+            assert_eq!(map.get(&i), None);
+        }
+        let stats = map.stats();
+        assert_eq!(stats.lookups, 10_000);
+        assert_eq!(stats.rejected + stats.false_positives, 10_000);
+        assert!(stats.false_positive_rate() < 0.03, "{stats:?}");
+        assert!(map.expected_false_positive_rate() < 0.02);
+    }
+
+    #[test]
+    fn filter_grows_with_the_map() {
+        let mut map = GuardedMap::with_capacity(10, 0.01);
+        let bits = map.filter_bits();
+        map.extend((0..100).map(|i| (i, ())));
+        assert!(map.filter_bits() >= 8 * bits);
+        assert!((0..100).all(|i| map.contains_key(&i)));
+        assert!(map.expected_false_positive_rate() < 0.02);
+    }
+
+    #[test]
+    fn rebuild_drops_removed_keys() {
+        let mut map: GuardedMap<u32, ()> = GuardedMap::with_capacity(2000, 0.01);
+        map.extend((0..2000).map(|i| (i, ())));
+        for i in 0..1990 {
+            map.remove(&i);
+        }
+        assert_eq!(map.stale_keys(), 1990);
+        let before = map.expected_false_positive_rate();
+        map.rebuild_filter();
+        assert_eq!(map.stale_keys(), 0);
+        assert!(map.expected_false_positive_rate() < before / 100.0);
+        assert!((1990..2000).all(|i| map.contains_key(&i)));
+        assert_eq!(map.into_inner().len(), 10);
+    }
+}
@@ -0,0 +1,1571 @@
+use crate::memory::{
+    compare_nodes, rc_bytes, MemoryFootprint, MemoryStats, SharedFootprint, SharingStats,
+};
+use crate::traits::Map;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem::size_of;
+use core::ops::{Index, IndexMut};
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+const HASH_BITS: u32 = 64;
+
+#[cfg(feature = "std")]
+pub struct PHashMap<K, V, S = RandomState> {
+    root: Rc<Node<K, V>>,
+    len: usize,
+    hasher: S,
+}
+
+// without std there is no default hasher
+#[cfg(not(feature = "std"))]
+pub struct PHashMap<K, V, S> {
+    root: Rc<Node<K, V>>,
+    len: usize,
+    hasher: S,
+}
+
+/// A persistent hash set: a `PHashMap` without values.
+#[cfg(feature = "std")]
+pub struct PHashSet<T, S = RandomState> {
+    map: PHashMap<T, (), S>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct PHashSet<T, S> {
+    map: PHashMap<T, (), S>,
+}
+
+#[cfg(feature = "std")]
+pub struct TransientHashMap<K, V, S = RandomState> {
+    map: PHashMap<K, V, S>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct TransientHashMap<K, V, S> {
+    map: PHashMap<K, V, S>,
+}
+
+#[derive(Clone)]
+enum Node<K, V> {
+    Branch {
+        bitmap: u32,
+        entries: Vec<Slot<K, V>>,
+    },
+    Collision {
+        hash: u64,
+        pairs: Vec<(K, V)>,
+    },
+}
+
+#[derive(Clone)]
+enum Slot<K, V> {
+    Leaf { hash: u64, key: K, value: V },
+    Sub(Rc<Node<K, V>>),
+}
+
+impl<K, V> Node<K, V> {
+    fn empty() -> Self {
+        Node::Branch {
+            bitmap: 0,
+            entries: vec![],
+        }
+    }
+}
+
+impl<K: Clone, V: Clone> Node<K, V> {
+    fn clone_deep(&self) -> Self {
+        match self {
+            Node::Branch { bitmap, entries } => Node::Branch {
+                bitmap: *bitmap,
+                entries: entries
+                    .iter()
+                    .map(|entry| match entry {
+                        Slot::Sub(child) => Slot::Sub(Rc::new(child.clone_deep())),
+                        leaf => leaf.clone(),
+                    })
+                    .collect(),
+            },
+            collision => collision.clone(),
+        }
+    }
+}
+
+impl<K, V, S: Clone> Clone for PHashMap<K, V, S> {
+    fn clone(&self) -> Self {
+        PHashMap {
+            root: self.root.clone(),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> PHashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> PHashMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        PHashMap {
+            root: Rc::new(Node::empty()),
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A copy of the map that shares no nodes with `self`.
+    pub fn clone_deep(&self) -> Self {
+        PHashMap {
+            root: Rc::new(self.root.clone_deep()),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hasher.hash_one(key);
+        self.find(hash, |k| k.borrow() == key).map(|(_, _, v)| v)
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let mut map = self.clone();
+        map.insert_mut(key, value);
+        map
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        if !self.contains_key(key) {
+            return self.clone();
+        }
+        let mut map = self.clone();
+        map.remove_mut(key);
+        map
+    }
+
+    pub fn transient(&self) -> TransientHashMap<K, V, S> {
+        TransientHashMap { map: self.clone() }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: vec![NodeIter::new(&self.root)],
+            remaining: self.len,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// The pair with the given hash whose key satisfies `is_match`, with its
+    /// position in the node that holds it.
+    fn find(&self, hash: u64, mut is_match: impl FnMut(&K) -> bool) -> Option<(usize, &K, &V)> {
+        let mut node = &self.root;
+        let mut shift = 0;
+        loop {
+            match &**node {
+                Node::Branch { bitmap, entries } => {
+                    let bit = 1 << ((hash >> shift) & MASK);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    match &entries[index(*bitmap, bit)] {
+                        Slot::Leaf {
+                            hash: h,
+                            key: k,
+                            value,
+                        } => {
+                            return if *h == hash && is_match(k) {
+                                Some((0, k, value))
+                            } else {
+                                None
+                            };
+                        }
+                        Slot::Sub(child) => node = child,
+                    }
+                }
+                Node::Collision { pairs, .. } => {
+                    return pairs
+                        .iter()
+                        .enumerate()
+                        .find(|(_, (k, _))| is_match(k))
+                        .map(|(i, (k, v))| (i, k, v));
+                }
+            }
+            shift += BITS;
+        }
+    }
+
+    fn insert_mut(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&key);
+        let old = insert_into(&mut self.root, 0, hash, key, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    fn remove_mut<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hasher.hash_one(key);
+        let old = remove_from(&mut self.root, 0, hash, &mut |k: &K| k.borrow() == key);
+        if old.is_some() {
+            self.len -= 1;
+        }
+        old.map(|(_, v)| v)
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> TransientHashMap<K, V, S> {
+    pub fn len(&self) -> usize {
+        self.map.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.len == 0
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    /// Copies the nodes on the path to `key` that are shared with other maps.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        if !self.map.contains_key(key) {
+            return None;
+        }
+        let hash = self.map.hasher.hash_one(key);
+        find_mut(&mut self.map.root, 0, hash, &mut |k: &K| k.borrow() == key).map(|(_, v)| v)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert_mut(key, value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.remove_mut(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    pub fn persistent(self) -> PHashMap<K, V, S> {
+        self.map
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.map.hasher
+    }
+
+    /// The entry for `key`, hashing it once for both lookup and insertion.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        let hash = self.map.hasher.hash_one(&key);
+        match self.map.find(hash, |k| *k == key) {
+            Some((pos, _, _)) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                hash,
+                pos,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                hash,
+                key,
+            }),
+        }
+    }
+
+    /// Look up a pair by a precomputed `hash` of its key, which must agree
+    /// with `hasher()`, and a custom key equality.
+    pub fn raw_get(&self, hash: u64, is_match: impl FnMut(&K) -> bool) -> Option<(&K, &V)> {
+        self.map.find(hash, is_match).map(|(_, k, v)| (k, v))
+    }
+
+    /// The entry for the key with the given `hash` that satisfies
+    /// `is_match`. Unlike `entry`, no owned key is needed unless a vacant
+    /// entry is filled.
+    pub fn raw_entry(
+        &mut self,
+        hash: u64,
+        is_match: impl FnMut(&K) -> bool,
+    ) -> RawEntry<'_, K, V, S> {
+        match self.map.find(hash, is_match) {
+            Some((pos, _, _)) => RawEntry::Occupied(OccupiedEntry {
+                map: self,
+                hash,
+                pos,
+            }),
+            None => RawEntry::Vacant(RawVacantEntry { map: self, hash }),
+        }
+    }
+}
+
+/// A view into a single key of a `TransientHashMap`, from `entry`.
+pub enum Entry<'a, K, V, S> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+/// A view into a single key of a `TransientHashMap`, from `raw_entry`.
+pub enum RawEntry<'a, K, V, S> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(RawVacantEntry<'a, K, V, S>),
+}
+
+/// A key that is in the map. It is found again by its hash and its
+/// position in the node the hash leads to, without hashing or comparing
+/// keys.
+pub struct OccupiedEntry<'a, K, V, S> {
+    map: &'a mut TransientHashMap<K, V, S>,
+    hash: u64,
+    pos: usize,
+}
+
+/// A key that is not in the map, together with its hash.
+pub struct VacantEntry<'a, K, V, S> {
+    map: &'a mut TransientHashMap<K, V, S>,
+    hash: u64,
+    key: K,
+}
+
+/// A hash that no key in the map matches. The key is only supplied on
+/// insertion.
+pub struct RawVacantEntry<'a, K, V, S> {
+    map: &'a mut TransientHashMap<K, V, S>,
+    hash: u64,
+}
+
+impl<'a, K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> Entry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> RawEntry<'a, K, V, S> {
+    /// Calls `default` for the key and value only if the entry is vacant.
+    pub fn or_insert_with(self, default: impl FnOnce() -> (K, V)) -> (&'a K, &'a mut V) {
+        match self {
+            RawEntry::Occupied(entry) => entry.into_key_value(),
+            RawEntry::Vacant(entry) => {
+                let (key, value) = default();
+                entry.insert(key, value)
+            }
+        }
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&K, &mut V)) -> Self {
+        if let RawEntry::Occupied(entry) = &mut self {
+            let (k, v) = find_mut(&mut entry.map.map.root, 0, entry.hash, &mut nth(entry.pos))
+                .expect("entry is occupied");
+            f(k, v);
+        }
+        self
+    }
+}
+
+impl<'a, K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> OccupiedEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        self.pair().0
+    }
+
+    pub fn get(&self) -> &V {
+        self.pair().1
+    }
+
+    /// Copies the nodes on the path to the key that are shared with other
+    /// maps.
+    pub fn get_mut(&mut self) -> &mut V {
+        find_mut(&mut self.map.map.root, 0, self.hash, &mut nth(self.pos))
+            .expect("entry is occupied")
+            .1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.into_key_value().1
+    }
+
+    pub fn into_key_value(self) -> (&'a K, &'a mut V) {
+        find_mut(&mut self.map.map.root, 0, self.hash, &mut nth(self.pos))
+            .expect("entry is occupied")
+    }
+
+    /// Replace the value and return the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.map.len -= 1;
+        remove_from(&mut self.map.map.root, 0, self.hash, &mut nth(self.pos))
+            .expect("entry is occupied")
+    }
+
+    fn pair(&self) -> (&K, &V) {
+        let (_, k, v) = self
+            .map
+            .map
+            .find(self.hash, nth(self.pos))
+            .expect("entry is occupied");
+        (k, v)
+    }
+}
+
+impl<'a, K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.map.len += 1;
+        insert_new(&mut self.map.map.root, 0, self.hash, self.key, value).1
+    }
+}
+
+impl<'a, K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> RawVacantEntry<'a, K, V, S> {
+    /// `key` must have the hash the entry was looked up with.
+    pub fn insert(self, key: K, value: V) -> (&'a K, &'a mut V) {
+        debug_assert_eq!(
+            self.map.map.hasher.hash_one(&key),
+            self.hash,
+            "key hash mismatch"
+        );
+        self.map.map.len += 1;
+        insert_new(&mut self.map.map.root, 0, self.hash, key, value)
+    }
+}
+
+/// Updates happen in place, copying only the nodes that are shared with
+/// other versions of the map.
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> Map<K, V> for PHashMap<K, V, S> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_mut(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_mut(key)
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> Map<K, V>
+    for TransientHashMap<K, V, S>
+{
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+/// Collect the pairs of two tries that are not stored in shared nodes.
+/// Maps with different hashers share nothing and end up comparing all pairs.
+fn unshared_pairs<'a, K: Eq, V: PartialEq>(
+    old: &'a Rc<Node<K, V>>,
+    new: &'a Rc<Node<K, V>>,
+    only_old: &mut Vec<(&'a K, &'a V)>,
+    only_new: &mut Vec<(&'a K, &'a V)>,
+) {
+    if Rc::ptr_eq(old, new) {
+        return;
+    }
+    let (
+        Node::Branch {
+            bitmap: old_bitmap,
+            entries: old_entries,
+        },
+        Node::Branch {
+            bitmap: new_bitmap,
+            entries: new_entries,
+        },
+    ) = (&**old, &**new)
+    else {
+        all_pairs(old, only_old);
+        all_pairs(new, only_new);
+        return;
+    };
+    for n in 0..(1 << BITS) {
+        let bit = 1 << n;
+        let a = (old_bitmap & bit != 0).then(|| &old_entries[index(*old_bitmap, bit)]);
+        let b = (new_bitmap & bit != 0).then(|| &new_entries[index(*new_bitmap, bit)]);
+        match (a, b) {
+            (Some(Slot::Sub(a)), Some(Slot::Sub(b))) => unshared_pairs(a, b, only_old, only_new),
+            (
+                Some(Slot::Leaf { key, value, .. }),
+                Some(Slot::Leaf {
+                    key: k, value: v, ..
+                }),
+            ) if key == k && value == v => {}
+            (a, b) => {
+                a.into_iter().for_each(|e| entry_pairs(e, only_old));
+                b.into_iter().for_each(|e| entry_pairs(e, only_new));
+            }
+        }
+    }
+}
+
+fn all_pairs<'a, K, V>(node: &'a Node<K, V>, out: &mut Vec<(&'a K, &'a V)>) {
+    match node {
+        Node::Branch { entries, .. } => entries.iter().for_each(|e| entry_pairs(e, out)),
+        Node::Collision { pairs, .. } => out.extend(pairs.iter().map(|(k, v)| (k, v))),
+    }
+}
+
+fn entry_pairs<'a, K, V>(entry: &'a Slot<K, V>, out: &mut Vec<(&'a K, &'a V)>) {
+    match entry {
+        Slot::Leaf { key, value, .. } => out.push((key, value)),
+        Slot::Sub(node) => all_pairs(node, out),
+    }
+}
+
+fn index(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+fn insert_into<K: Clone + Eq, V: Clone>(
+    node: &mut Rc<Node<K, V>>,
+    shift: u32,
+    hash: u64,
+    key: K,
+    value: V,
+) -> Option<V> {
+    match Rc::make_mut(node) {
+        Node::Branch { bitmap, entries } => {
+            let bit = 1 << ((hash >> shift) & MASK);
+            let pos = index(*bitmap, bit);
+            if *bitmap & bit == 0 {
+                *bitmap |= bit;
+                entries.insert(pos, Slot::Leaf { hash, key, value });
+                return None;
+            }
+            match &mut entries[pos] {
+                Slot::Sub(child) => insert_into(child, shift + BITS, hash, key, value),
+                Slot::Leaf {
+                    hash: h,
+                    key: k,
+                    value: v,
+                } if *h == hash && *k == key => Some(core::mem::replace(v, value)),
+                Slot::Leaf {
+                    hash: h,
+                    key: k,
+                    value: v,
+                } => {
+                    let existing = (*h, k.clone(), v.clone());
+                    let sub = make_pair(shift + BITS, existing, (hash, key, value));
+                    entries[pos] = Slot::Sub(sub);
+                    None
+                }
+            }
+        }
+        Node::Collision { pairs, .. } => {
+            for (k, v) in pairs.iter_mut() {
+                if *k == key {
+                    return Some(core::mem::replace(v, value));
+                }
+            }
+            pairs.push((key, value));
+            None
+        }
+    }
+}
+
+fn find_mut<'a, K: Clone, V: Clone>(
+    node: &'a mut Rc<Node<K, V>>,
+    shift: u32,
+    hash: u64,
+    is_match: &mut impl FnMut(&K) -> bool,
+) -> Option<(&'a K, &'a mut V)> {
+    match Rc::make_mut(node) {
+        Node::Branch { bitmap, entries } => {
+            let bit = 1 << ((hash >> shift) & MASK);
+            if *bitmap & bit == 0 {
+                return None;
+            }
+            match &mut entries[index(*bitmap, bit)] {
+                Slot::Leaf {
+                    hash: h,
+                    key: k,
+                    value,
+                } if *h == hash && is_match(k) => Some((k, value)),
+                Slot::Leaf { .. } => None,
+                Slot::Sub(child) => find_mut(child, shift + BITS, hash, is_match),
+            }
+        }
+        Node::Collision { pairs, .. } => pairs
+            .iter_mut()
+            .find(|(k, _)| is_match(k))
+            .map(|(k, v)| (&*k, v)),
+    }
+}
+
+/// Insert a key that is known to be missing and return references to the
+/// new pair.
+fn insert_new<K: Clone, V: Clone>(
+    node: &mut Rc<Node<K, V>>,
+    shift: u32,
+    hash: u64,
+    key: K,
+    value: V,
+) -> (&K, &mut V) {
+    match Rc::make_mut(node) {
+        Node::Branch { bitmap, entries } => {
+            let bit = 1 << ((hash >> shift) & MASK);
+            let pos = index(*bitmap, bit);
+            if *bitmap & bit == 0 {
+                *bitmap |= bit;
+                entries.insert(pos, Slot::Leaf { hash, key, value });
+                let Slot::Leaf { key, value, .. } = &mut entries[pos] else {
+                    unreachable!()
+                };
+                return (key, value);
+            }
+            if let Slot::Leaf { hash: h, .. } = entries[pos] {
+                // push the existing leaf one level down, then insert next to it
+                let child = if shift + BITS >= HASH_BITS {
+                    Node::Collision {
+                        hash: h,
+                        pairs: vec![],
+                    }
+                } else {
+                    Node::empty()
+                };
+                let leaf = core::mem::replace(&mut entries[pos], Slot::Sub(Rc::new(child)));
+                let (Slot::Leaf { key, value, .. }, Slot::Sub(child)) = (leaf, &mut entries[pos])
+                else {
+                    unreachable!()
+                };
+                insert_new(child, shift + BITS, h, key, value);
+            }
+            match &mut entries[pos] {
+                Slot::Sub(child) => insert_new(child, shift + BITS, hash, key, value),
+                Slot::Leaf { .. } => unreachable!(),
+            }
+        }
+        Node::Collision { pairs, .. } => {
+            pairs.push((key, value));
+            let (k, v) = pairs.last_mut().unwrap();
+            (k, v)
+        }
+    }
+}
+
+fn make_pair<K, V>(shift: u32, a: (u64, K, V), b: (u64, K, V)) -> Rc<Node<K, V>> {
+    if shift >= HASH_BITS {
+        return Rc::new(Node::Collision {
+            hash: a.0,
+            pairs: vec![(a.1, a.2), (b.1, b.2)],
+        });
+    }
+    let ia = (a.0 >> shift) & MASK;
+    let ib = (b.0 >> shift) & MASK;
+    if ia == ib {
+        return Rc::new(Node::Branch {
+            bitmap: 1 << ia,
+            entries: vec![Slot::Sub(make_pair(shift + BITS, a, b))],
+        });
+    }
+    let (first, second) = if ia < ib { (a, b) } else { (b, a) };
+    Rc::new(Node::Branch {
+        bitmap: (1 << ia) | (1 << ib),
+        entries: vec![
+            Slot::Leaf {
+                hash: first.0,
+                key: first.1,
+                value: first.2,
+            },
+            Slot::Leaf {
+                hash: second.0,
+                key: second.1,
+                value: second.2,
+            },
+        ],
+    })
+}
+
+fn remove_from<K: Clone, V: Clone>(
+    node: &mut Rc<Node<K, V>>,
+    shift: u32,
+    hash: u64,
+    is_match: &mut impl FnMut(&K) -> bool,
+) -> Option<(K, V)> {
+    match Rc::make_mut(node) {
+        Node::Branch { bitmap, entries } => {
+            let bit = 1 << ((hash >> shift) & MASK);
+            if *bitmap & bit == 0 {
+                return None;
+            }
+            let pos = index(*bitmap, bit);
+            match &mut entries[pos] {
+                Slot::Leaf {
+                    hash: h, key: k, ..
+                } if *h == hash && is_match(k) => {
+                    *bitmap &= !bit;
+                    match entries.remove(pos) {
+                        Slot::Leaf { key, value, .. } => Some((key, value)),
+                        Slot::Sub(_) => unreachable!(),
+                    }
+                }
+                Slot::Leaf { .. } => None,
+                Slot::Sub(child) => {
+                    let old = remove_from(child, shift + BITS, hash, is_match);
+                    if let Some(leaf) = collapse(child) {
+                        entries[pos] = leaf;
+                    }
+                    old
+                }
+            }
+        }
+        Node::Collision { pairs, .. } => {
+            let pos = pairs.iter().position(|(k, _)| is_match(k))?;
+            Some(pairs.swap_remove(pos))
+        }
+    }
+}
+
+/// A predicate for `find_mut` and `remove_from` that accepts the key at
+/// position `pos` of the node a hash leads to, as reported by `find`.
+fn nth<K>(pos: usize) -> impl FnMut(&K) -> bool {
+    let mut seen = 0;
+    move |_| {
+        seen += 1;
+        seen > pos
+    }
+}
+
+/// If a node holds only a single key, return it as a leaf entry that can
+/// replace the node in its parent.
+fn collapse<K: Clone, V: Clone>(node: &mut Rc<Node<K, V>>) -> Option<Slot<K, V>> {
+    match Rc::make_mut(node) {
+        Node::Branch { entries, .. } if entries.len() == 1 => match &entries[0] {
+            Slot::Leaf { .. } => entries.pop(),
+            Slot::Sub(_) => None,
+        },
+        Node::Collision { hash, pairs } if pairs.len() == 1 => {
+            let (key, value) = pairs.pop().unwrap();
+            Some(Slot::Leaf {
+                hash: *hash,
+                key,
+                value,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> Default for PHashMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S, Q> Index<&Q> for PHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq + Borrow<Q>,
+    V: Clone,
+    S: BuildHasher + Clone,
+    Q: ?Sized + Hash + Eq,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found")
+    }
+}
+
+impl<K, V, S, Q> Index<&Q> for TransientHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq + Borrow<Q>,
+    V: Clone,
+    S: BuildHasher + Clone,
+    Q: ?Sized + Hash + Eq,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found")
+    }
+}
+
+impl<K, V, S, Q> IndexMut<&Q> for TransientHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq + Borrow<Q>,
+    V: Clone,
+    S: BuildHasher + Clone,
+    Q: ?Sized + Hash + Eq,
+{
+    fn index_mut(&mut self, key: &Q) -> &mut V {
+        self.get_mut(key).expect("key not found")
+    }
+}
+
+impl<K, V, S> PHashMap<K, V, S> {
+    /// All nodes with their depth, root first.
+    fn nodes(&self) -> impl Iterator<Item = (&Rc<Node<K, V>>, usize)> {
+        let mut stack = vec![(&self.root, 1)];
+        core::iter::from_fn(move || {
+            let (node, depth) = stack.pop()?;
+            if let Node::Branch { entries, .. } = &**node {
+                for entry in entries {
+                    if let Slot::Sub(child) = entry {
+                        stack.push((child, depth + 1));
+                    }
+                }
+            }
+            Some((node, depth))
+        })
+    }
+}
+
+/// Slots count the 32 possible children of every branch, so the fill
+/// factor shows how densely the branches are populated.
+impl<K, V, S> MemoryFootprint for PHashMap<K, V, S> {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        for (node, depth) in self.nodes() {
+            stats.nodes += 1;
+            stats.depth = stats.depth.max(depth);
+            stats.heap_bytes += rc_bytes::<Node<K, V>>();
+            match &**node {
+                Node::Branch { entries, .. } => {
+                    stats.heap_bytes += entries.capacity() * size_of::<Slot<K, V>>();
+                    stats.used_slots += entries.len();
+                    stats.total_slots += 1 << BITS;
+                }
+                Node::Collision { pairs, .. } => {
+                    stats.heap_bytes += pairs.capacity() * size_of::<(K, V)>();
+                    stats.used_slots += pairs.len();
+                    stats.total_slots += pairs.len();
+                }
+            }
+        }
+        stats
+    }
+}
+
+impl<K, V, S> SharedFootprint for PHashMap<K, V, S> {
+    fn shares_structure_with(&self, other: &Self) -> SharingStats {
+        let addresses = |map: &Self| {
+            map.nodes()
+                .map(|(node, _)| Rc::as_ptr(node) as usize)
+                .collect::<Vec<_>>()
+        };
+        compare_nodes(addresses(self).into_iter(), addresses(other).into_iter())
+    }
+}
+
+impl<K, V, S> fmt::Debug for PHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq + fmt::Debug,
+    V: Clone + fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for PHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone + PartialEq,
+    S: BuildHasher + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> FromIterator<(K, V)> for PHashMap<K, V, RandomState> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = PHashMap::new().transient();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map.persistent()
+    }
+}
+
+/// Keeps the hasher of the std map.
+#[cfg(feature = "std")]
+impl<K, V, S> From<std::collections::HashMap<K, V, S>> for PHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let mut result = PHashMap::with_hasher(map.hasher().clone()).transient();
+        for (k, v) in map {
+            result.insert(k, v);
+        }
+        result.persistent()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> From<&PHashMap<K, V, S>> for std::collections::HashMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn from(map: &PHashMap<K, V, S>) -> Self {
+        let mut result =
+            std::collections::HashMap::with_capacity_and_hasher(map.len(), map.hasher.clone());
+        result.extend(map.iter().map(|(k, v)| (k.clone(), v.clone())));
+        result
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a PHashMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+enum NodeIter<'a, K, V> {
+    Entries(core::slice::Iter<'a, Slot<K, V>>),
+    Pairs(core::slice::Iter<'a, (K, V)>),
+}
+
+impl<'a, K, V> NodeIter<'a, K, V> {
+    fn new(node: &'a Node<K, V>) -> Self {
+        match node {
+            Node::Branch { entries, .. } => NodeIter::Entries(entries.iter()),
+            Node::Collision { pairs, .. } => NodeIter::Pairs(pairs.iter()),
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    stack: Vec<NodeIter<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()? {
+                NodeIter::Entries(entries) => match entries.next() {
+                    None => {
+                        self.stack.pop();
+                    }
+                    Some(Slot::Leaf { key, value, .. }) => {
+                        self.remaining -= 1;
+                        return Some((key, value));
+                    }
+                    Some(Slot::Sub(child)) => self.stack.push(NodeIter::new(child)),
+                },
+                NodeIter::Pairs(pairs) => match pairs.next() {
+                    None => {
+                        self.stack.pop();
+                    }
+                    Some((key, value)) => {
+                        self.remaining -= 1;
+                        return Some((key, value));
+                    }
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, S: Clone> Clone for PHashSet<T, S> {
+    fn clone(&self) -> Self {
+        PHashSet {
+            map: self.map.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone + Hash + Eq> PHashSet<T, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+/// The set operations only look at the parts of the two tries that are not
+/// shared, so combining versions of the same set is cheap.
+impl<T: Clone + Hash + Eq, S: BuildHasher + Clone> PHashSet<T, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        PHashSet {
+            map: PHashMap::with_hasher(hasher),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains<Q>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(item)
+    }
+
+    pub fn insert(&self, item: T) -> Self {
+        PHashSet {
+            map: self.map.insert(item, ()),
+        }
+    }
+
+    pub fn remove<Q>(&self, item: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        PHashSet {
+            map: self.map.remove(item),
+        }
+    }
+
+    pub fn iter(&self) -> SetIter<'_, T> {
+        SetIter {
+            inner: self.map.iter(),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        if self.len() < other.len() {
+            return other.union(self);
+        }
+        let (_, only_other) = self.unshared(other);
+        let mut result = self.map.transient();
+        for item in only_other {
+            if !self.contains(item) {
+                result.insert(item.clone(), ());
+            }
+        }
+        PHashSet {
+            map: result.persistent(),
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (only_self, _) = self.unshared(other);
+        let mut result = self.map.transient();
+        for item in only_self {
+            if !other.contains(item) {
+                result.remove(item);
+            }
+        }
+        PHashSet {
+            map: result.persistent(),
+        }
+    }
+
+    /// The items of `self` that are not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let (only_self, _) = self.unshared(other);
+        let mut result = PHashMap::with_hasher(self.map.hasher.clone()).transient();
+        for item in only_self {
+            if !other.contains(item) {
+                result.insert(item.clone(), ());
+            }
+        }
+        PHashSet {
+            map: result.persistent(),
+        }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        let (only_self, _) = self.unshared(other);
+        self.len() <= other.len() && only_self.into_iter().all(|item| other.contains(item))
+    }
+
+    /// The items stored outside of the nodes both sets share. Items of one
+    /// list may still be in the other set.
+    fn unshared<'a>(&'a self, other: &'a Self) -> (Vec<&'a T>, Vec<&'a T>) {
+        let mut only_self = vec![];
+        let mut only_other = vec![];
+        unshared_pairs(
+            &self.map.root,
+            &other.map.root,
+            &mut only_self,
+            &mut only_other,
+        );
+        (
+            only_self.into_iter().map(|(k, _)| k).collect(),
+            only_other.into_iter().map(|(k, _)| k).collect(),
+        )
+    }
+}
+
+impl<T, S> SharedFootprint for PHashSet<T, S> {
+    fn shares_structure_with(&self, other: &Self) -> SharingStats {
+        self.map.shares_structure_with(&other.map)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone + Hash + Eq> Default for PHashSet<T, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S> fmt::Debug for PHashSet<T, S>
+where
+    T: Clone + Hash + Eq + fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, S> PartialEq for PHashSet<T, S>
+where
+    T: Clone + Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone + Hash + Eq> FromIterator<T> for PHashSet<T, RandomState> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PHashSet {
+            map: iter.into_iter().map(|item| (item, ())).collect(),
+        }
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a PHashSet<T, S>
+where
+    T: Clone + Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    type Item = &'a T;
+    type IntoIter = SetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct SetIter<'a, T> {
+    inner: Iter<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for SetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(item, _)| item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::hash::Hasher;
+
+    #[derive(Clone, Default)]
+    struct BadHasher;
+
+    impl BuildHasher for BadHasher {
+        type Hasher = BadHasher;
+        fn build_hasher(&self) -> BadHasher {
+            BadHasher
+        }
+    }
+
+    impl Hasher for BadHasher {
+        fn finish(&self) -> u64 {
+            42
+        }
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    #[test]
+    fn empty_map() {
+        let map = PHashMap::<i32, i32>::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let map = PHashMap::new().insert(1, "one").insert(2, "two");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some(&"one"));
+        assert_eq!(map.get(&2), Some(&"two"));
+        assert_eq!(map.get(&3), None);
+    }
+
+    #[test]
+    fn insert_replaces_value() {
+        let map = PHashMap::new().insert(1, "one").insert(1, "uno");
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&1), Some(&"uno"));
+    }
+
+    #[test]
+    fn old_versions_are_unaffected() {
+        let v1: PHashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        let v2 = v1.insert(1000, 1000).remove(&5).insert(7, 0);
+        assert_eq!(v1.len(), 100);
+        assert_eq!(v1.get(&5), Some(&5));
+        assert_eq!(v1.get(&7), Some(&7));
+        assert_eq!(v1.get(&1000), None);
+        assert_eq!(v2.len(), 100);
+        assert_eq!(v2.get(&5), None);
+        assert_eq!(v2.get(&7), Some(&0));
+    }
+
+    #[test]
+    fn many_keys() {
+        let map: PHashMap<_, _> = (0..10000).map(|i| (i, i * 2)).collect();
+        assert_eq!(map.len(), 10000);
+        for i in 0..10000 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+        assert_eq!(map.iter().count(), 10000);
+    }
+
+    #[test]
+    fn remove_all_keys() {
+        let mut map: PHashMap<_, _> = (0..1000).map(|i| (i, i)).collect();
+        for i in 0..1000 {
+            map = map.remove(&i);
+            assert_eq!(map.get(&i), None);
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn full_hash_collisions() {
+        let mut map = PHashMap::with_hasher(BadHasher);
+        for i in 0..10 {
+            map = map.insert(i, i);
+        }
+        assert_eq!(map.len(), 10);
+        for i in 0..10 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        let map = map.remove(&3);
+        assert_eq!(map.get(&3), None);
+        assert_eq!(map.len(), 9);
+    }
+
+    #[test]
+    fn borrowed_key_lookup() {
+        let map = PHashMap::new().insert("key".to_string(), 1);
+        assert_eq!(map.get("key"), Some(&1));
+        assert!(map.remove("key").is_empty());
+    }
+
+    #[test]
+    fn transient_batch_mutation() {
+        let base: PHashMap<_, _> = (0..10).map(|i| (i, i)).collect();
+        let mut t = base.transient();
+        assert_eq!(t.insert(3, 30), Some(3));
+        assert_eq!(t.insert(20, 20), None);
+        assert_eq!(t.remove(&0), Some(0));
+        assert_eq!(t.remove(&0), None);
+        let map = t.persistent();
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(base.get(&3), Some(&3));
+        assert_eq!(base.get(&0), Some(&0));
+    }
+
+    #[test]
+    fn index_operators() {
+        let base: PHashMap<_, _> = (0..100).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(base["42"], 42);
+        let mut t = base.transient();
+        t["42"] += 1000;
+        *t.get_mut("7").unwrap() = 0;
+        assert_eq!(t.get_mut("100"), None);
+        let map = t.persistent();
+        assert_eq!((map["42"], map["7"]), (1042, 0));
+        assert_eq!((base["42"], base["7"]), (42, 7));
+
+        let mut t = PHashMap::with_hasher(BadHasher).transient();
+        t.insert(1, 'a');
+        t.insert(2, 'b');
+        t[&2] = 'c';
+        assert_eq!((t[&1], t[&2]), ('a', 'c'));
+    }
+
+    #[test]
+    fn equality_ignores_insertion_order() {
+        let a: PHashMap<_, _> = (0..50).map(|i| (i, i)).collect();
+        let b: PHashMap<_, _> = (0..50).rev().map(|i| (i, i)).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, b.insert(0, 1));
+    }
+
+    #[test]
+    fn set_insert_remove_contains() {
+        let empty = PHashSet::new();
+        let a = empty.insert("x").insert("y").insert("x");
+        let b = a.remove("x");
+        assert!(empty.is_empty());
+        assert_eq!(a.len(), 2);
+        assert!(a.contains("x") && a.contains("y"));
+        assert_eq!(b.len(), 1);
+        assert!(!b.contains("x"));
+        assert_eq!(format!("{:?}", b), r#"{"y"}"#);
+    }
+
+    #[test]
+    fn set_operations_between_versions() {
+        let base: PHashSet<u32> = (0..5000).collect();
+        let more = base.insert(5000).insert(5001);
+        let fewer = base.remove(&7).remove(&4000);
+
+        let union = more.union(&fewer);
+        assert_eq!(union.len(), 5002);
+        assert!(union.contains(&7) && union.contains(&5001));
+        assert_eq!(fewer.union(&more), union);
+
+        let both = more.intersection(&fewer);
+        assert_eq!(both.len(), 4998);
+        assert!(!both.contains(&7) && !both.contains(&5000));
+        // the result reuses the nodes of the inputs
+        assert!(both.shares_structure_with(&fewer).shared > 100);
+
+        let mut diff: Vec<_> = more.difference(&fewer).iter().copied().collect();
+        diff.sort();
+        assert_eq!(diff, vec![7, 4000, 5000, 5001]);
+        assert!(fewer.is_subset(&base) && !base.is_subset(&fewer));
+        assert!(base.is_subset(&more));
+    }
+
+    #[test]
+    fn set_operations_on_unrelated_sets() {
+        let a: PHashSet<u32> = (0..100).collect();
+        let b: PHashSet<u32> = (50..150).collect();
+        assert_eq!(a.union(&b), (0..150).collect());
+        assert_eq!(a.intersection(&b), (50..100).collect());
+        assert_eq!(a.difference(&b), (0..50).collect());
+        assert_eq!(a.intersection(&PHashSet::new()), PHashSet::new());
+
+        let c = PHashSet::with_hasher(BadHasher).insert(1).insert(2);
+        let d = PHashSet::with_hasher(BadHasher).insert(2).insert(3);
+        assert_eq!(c.union(&d).len(), 3);
+        assert_eq!(c.intersection(&d).iter().collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    fn std_hash_map_conversions() {
+        let std_map: std::collections::HashMap<_, _> = (0..100).map(|i| (i, i * 2)).collect();
+        let map = PHashMap::from(std_map.clone());
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&21), Some(&42));
+        assert_eq!(std::collections::HashMap::from(&map), std_map);
+    }
+
+    #[test]
+    fn entry_counts_words() {
+        let mut counts = PHashMap::new().transient();
+        for word in "a b a c b a".split(' ') {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts.get("a"), Some(&3));
+        counts.entry("c").and_modify(|n| *n *= 10).or_default();
+        counts.entry("d").and_modify(|n| *n *= 10).or_default();
+        assert_eq!((counts["c"], counts["d"]), (10, 0));
+    }
+
+    #[test]
+    fn occupied_entries_replace_and_remove() {
+        let base: PHashMap<_, _> = (0..500).map(|i| (i, i)).collect();
+        let mut map = base.transient();
+        for i in 0..500 {
+            match map.entry(i) {
+                Entry::Occupied(mut e) if i % 2 == 0 => assert_eq!(e.insert(-i), i),
+                Entry::Occupied(e) => assert_eq!(e.remove_entry(), (i, i)),
+                Entry::Vacant(_) => panic!("{i} is in the map"),
+            }
+        }
+        assert_eq!(map.len(), 250);
+        assert!(map.iter().all(|(k, v)| k % 2 == 0 && *v == -k));
+        assert_eq!(base.len(), 500);
+        assert!(base.iter().all(|(k, v)| k == v));
+    }
+
+    #[test]
+    fn entries_with_colliding_hashes() {
+        let mut map = PHashMap::with_hasher(BadHasher).transient();
+        for i in 0..10 {
+            assert_eq!(*map.entry(i).or_insert(i * 2), i * 2);
+        }
+        let Entry::Occupied(e) = map.entry(7) else {
+            panic!("7 is in the map");
+        };
+        assert_eq!((e.key(), e.get()), (&7, &14));
+        assert_eq!(e.remove(), 14);
+        assert_eq!(map.len(), 9);
+        assert!((0..10).all(|i| map.get(&i) == (i != 7).then_some(&(i * 2))));
+    }
+
+    #[test]
+    fn raw_entry_promotes_key_on_insert() {
+        let mut map: TransientHashMap<String, usize> = PHashMap::new().transient();
+        for word in "x y x z x".split(' ') {
+            let hash = map.hasher().hash_one(word);
+            let (_, n) = map
+                .raw_entry(hash, |k| k == word)
+                .and_modify(|_, n| *n += 1)
+                .or_insert_with(|| (word.to_string(), 1));
+            assert!(*n >= 1);
+        }
+        let hash = map.hasher().hash_one("x");
+        assert_eq!(
+            map.raw_get(hash, |k| k == "x"),
+            Some((&"x".to_string(), &3))
+        );
+        assert_eq!(map.raw_get(hash, |k| k == "w"), None);
+        let RawEntry::Vacant(e) = map.raw_entry(map.hasher().hash_one("w"), |k| k == "w") else {
+            panic!("w is not in the map");
+        };
+        e.insert("w".to_string(), 0);
+        assert_eq!(map.len(), 4);
+    }
+}
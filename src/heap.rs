@@ -1,15 +1,66 @@
-#[derive(Debug, Clone)]
-pub struct Heap<T> {
-    data: Vec<T>,
+use crate::allocator::{AllocVec, Allocator, Global};
+use crate::compare::{ByKey, Compare, Natural};
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use crate::traits::PriorityQueue;
+use alloc::collections::BinaryHeap;
+use alloc::vec::{self, Vec};
+use core::fmt;
+
+/// A binary max-heap. The comparator `C` decides which item is the
+/// largest; `Reverse` turns it into a min-heap.
+#[derive(Clone)]
+pub struct Heap<T, C = Natural, A: Allocator = Global> {
+    data: AllocVec<T, A>,
+    cmp: C,
+    rebuild_factor: usize,
 }
 
+/// See `Heap::set_rebuild_factor`.
+pub const DEFAULT_REBUILD_FACTOR: usize = 2;
+
 impl<T: PartialOrd> Heap<T> {
     pub fn new() -> Self {
-        Heap { data: vec![] }
+        Self::new_in(Global)
     }
 
     pub fn from_vec(data: Vec<T>) -> Self {
-        let mut heap = Heap { data };
+        Self::from_vec_with(data, Natural)
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> Heap<T, Natural, A> {
+    /// An empty heap that stores its items in memory from `alloc`.
+    pub fn new_in(alloc: A) -> Self {
+        Self::with_comparator_in(Natural, alloc)
+    }
+}
+
+/// A heap ordered by the key that `F` extracts from each item.
+pub type KeyHeap<T, F> = Heap<T, ByKey<F>>;
+
+impl<T, K: PartialOrd, F: Fn(&T) -> K> Heap<T, ByKey<F>> {
+    /// An empty heap whose largest item is the one with the largest key;
+    /// `T` itself needs no ordering.
+    pub fn by_key(f: F) -> Self {
+        Self::with_comparator(ByKey(f))
+    }
+
+    pub fn from_vec_by_key(data: Vec<T>, f: F) -> Self {
+        Self::from_vec_with(data, ByKey(f))
+    }
+}
+
+impl<T, C: Compare<T>> Heap<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_comparator_in(cmp, Global)
+    }
+
+    pub fn from_vec_with(data: Vec<T>, cmp: C) -> Self {
+        let mut heap = Heap {
+            data: AllocVec::from_vec(data),
+            cmp,
+            rebuild_factor: DEFAULT_REBUILD_FACTOR,
+        };
         for i in 1..heap.data.len() {
             heap.trickle_up(i)
         }
@@ -17,7 +68,35 @@ impl<T: PartialOrd> Heap<T> {
     }
 
     pub fn into_vec(self) -> Vec<T> {
-        self.data
+        self.data.into_vec()
+    }
+}
+
+impl<T, C: Compare<T>, A: Allocator> Heap<T, C, A> {
+    pub fn with_comparator_in(cmp: C, alloc: A) -> Self {
+        Heap {
+            data: AllocVec::new_in(alloc),
+            cmp,
+            rebuild_factor: DEFAULT_REBUILD_FACTOR,
+        }
+    }
+
+    /// The items in heap order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The largest item.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
     }
 
     pub fn push(&mut self, item: T) {
@@ -26,6 +105,40 @@ impl<T: PartialOrd> Heap<T> {
         self.trickle_up(new_node);
     }
 
+    /// Like `push`, but hands the item back if the allocator is out of memory.
+    pub fn try_push(&mut self, item: T) -> Result<(), T> {
+        let new_node = self.data.len();
+        self.data.try_push(item)?;
+        self.trickle_up(new_node);
+        Ok(())
+    }
+
+    /// Push all items of `iter`. If the batch is large relative to the
+    /// heap, the items are appended and the whole heap is rebuilt in O(n)
+    /// instead of sifting up each item in O(log n).
+    pub fn extend_rebuild(&mut self, iter: impl IntoIterator<Item = T>) {
+        // The guard restores heap order even if `iter` panics.
+        let guard = ExtendGuard {
+            start: self.data.len(),
+            heap: self,
+        };
+        for item in iter {
+            guard.heap.data.push(item);
+        }
+    }
+
+    /// Tune when `extend_rebuild` rebuilds: it does so if `k` items added to
+    /// a heap that then holds `n` would take more than `factor * n` steps
+    /// to sift up, i.e. if `k * log2(n) >= factor * n`. Larger factors
+    /// rebuild less often; 0 always rebuilds.
+    pub fn set_rebuild_factor(&mut self, factor: usize) {
+        self.rebuild_factor = factor;
+    }
+
+    pub fn rebuild_factor(&self) -> usize {
+        self.rebuild_factor
+    }
+
     pub fn pop(&mut self) -> Option<T> {
         if self.data.is_empty() {
             None
@@ -36,12 +149,60 @@ impl<T: PartialOrd> Heap<T> {
         }
     }
 
+    /// Remove all items, in no particular order. The heap keeps its
+    /// allocation; items that are not consumed are dropped.
+    pub fn drain(&mut self) -> Drain<'_, T, A> {
+        Drain {
+            data: &mut self.data,
+        }
+    }
+
+    /// Remove the items for which `pred` returns true and return them in no
+    /// particular order.
+    pub fn drain_filter(&mut self, mut pred: impl FnMut(&T) -> bool) -> vec::IntoIter<T> {
+        // decide first, so a panicking predicate leaves the heap intact
+        let remove: Vec<bool> = self.data.iter().map(&mut pred).collect();
+        let mut removed = Vec::new();
+        // from the back, so every swapped-in item has already been kept
+        for i in (0..remove.len()).rev() {
+            if remove[i] {
+                removed.push(self.data.swap_remove(i));
+            }
+        }
+        if !removed.is_empty() {
+            self.rebuild();
+        }
+        removed.into_iter()
+    }
+
+    /// Restore the heap order after appending the items from `start` on.
+    fn restore_appended(&mut self, start: usize) {
+        let (batch, len) = (self.data.len() - start, self.data.len());
+        if batch == 0 {
+            return;
+        }
+        if batch * (len.ilog2() as usize + 1) >= self.rebuild_factor.saturating_mul(len) {
+            self.rebuild();
+        } else {
+            for i in start..len {
+                self.trickle_up(i);
+            }
+        }
+    }
+
+    /// Restore the heap order of all items bottom-up, in O(n).
+    fn rebuild(&mut self) {
+        for i in (0..self.data.len() / 2).rev() {
+            self.trickle_down(i);
+        }
+    }
+
     fn trickle_up(&mut self, node: usize) {
         if node == 0 {
             return;
         }
         let p = self.parent(node);
-        if self.data[p] < self.data[node] {
+        if self.cmp.less(&self.data[p], &self.data[node]) {
             self.data.swap(p, node);
             self.trickle_up(p);
         }
@@ -54,17 +215,13 @@ impl<T: PartialOrd> Heap<T> {
             return;
         }
 
-        let child;
-
-        if r >= self.data.len() {
-            child = l;
-        } else if self.data[l] > self.data[r] {
-            child = l;
+        let child = if r >= self.data.len() || self.cmp.greater(&self.data[l], &self.data[r]) {
+            l
         } else {
-            child = r;
-        }
+            r
+        };
 
-        if self.data[node] < self.data[child] {
+        if self.cmp.less(&self.data[node], &self.data[child]) {
             self.data.swap(child, node);
             self.trickle_down(child);
         }
@@ -79,9 +236,121 @@ impl<T: PartialOrd> Heap<T> {
     }
 }
 
+/// Restores the order of a heap when `extend_rebuild` finishes or unwinds.
+struct ExtendGuard<'a, T, C: Compare<T>, A: Allocator> {
+    heap: &'a mut Heap<T, C, A>,
+    start: usize,
+}
+
+impl<T, C: Compare<T>, A: Allocator> Drop for ExtendGuard<'_, T, C, A> {
+    fn drop(&mut self) {
+        self.heap.restore_appended(self.start);
+    }
+}
+
+/// Iterator returned by `Heap::drain`.
+pub struct Drain<'a, T, A: Allocator = Global> {
+    data: &'a mut AllocVec<T, A>,
+}
+
+impl<T, A: Allocator> Iterator for Drain<'_, T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.data.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.data.len(), Some(self.data.len()))
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> Drop for Drain<'_, T, A> {
+    fn drop(&mut self) {
+        self.data.clear();
+    }
+}
+
+/// Uses `extend_rebuild`.
+impl<T, C: Compare<T>, A: Allocator> Extend<T> for Heap<T, C, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.extend_rebuild(iter);
+    }
+}
+
+impl<T, C, A: Allocator> MemoryFootprint for Heap<T, C, A> {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<T>(self.data.len(), self.data.capacity())
+    }
+}
+
+impl<T: PartialOrd> Default for Heap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialOrd> From<Vec<T>> for Heap<T> {
+    fn from(data: Vec<T>) -> Self {
+        Heap::from_vec(data)
+    }
+}
+
+impl<T, C: Compare<T>> From<Heap<T, C>> for Vec<T> {
+    fn from(heap: Heap<T, C>) -> Self {
+        heap.into_vec()
+    }
+}
+
+/// `BinaryHeap` uses the same layout, so its items are taken over as they
+/// are.
+impl<T: Ord> From<BinaryHeap<T>> for Heap<T> {
+    fn from(heap: BinaryHeap<T>) -> Self {
+        Heap {
+            data: AllocVec::from_vec(heap.into_vec()),
+            cmp: Natural,
+            rebuild_factor: DEFAULT_REBUILD_FACTOR,
+        }
+    }
+}
+
+impl<T: Ord> From<Heap<T>> for BinaryHeap<T> {
+    fn from(heap: Heap<T>) -> Self {
+        BinaryHeap::from(heap.into_vec())
+    }
+}
+
+impl<T: fmt::Debug, C, A: Allocator> fmt::Debug for Heap<T, C, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Heap").field("data", &self.data).finish()
+    }
+}
+
+impl<T, C: Compare<T>, A: Allocator> PriorityQueue<T> for Heap<T, C, A> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push(&mut self, item: T) {
+        self.push(item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.peek()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::allocator::BumpAllocator;
+    use alloc::vec;
 
     #[test]
     fn heap_and_vec() {
@@ -124,4 +393,176 @@ mod tests {
         heap.pop();
         assert_eq!(heap.into_vec(), vec![7, 4, 6, 1, 2, 3, 5])
     }
+
+    #[test]
+    fn heap_in_fixed_buffer() {
+        let mut buffer = [core::mem::MaybeUninit::uninit(); 64];
+        let bump = BumpAllocator::new(&mut buffer);
+        let mut heap = Heap::new_in(&bump);
+        for x in [4u32, 9, 1, 7] {
+            heap.push(x);
+        }
+        while heap.try_push(0).is_ok() {}
+        assert_eq!(heap.len(), 16);
+        assert_eq!(heap.pop(), Some(9));
+        assert_eq!(heap.pop(), Some(7));
+    }
+
+    #[test]
+    fn min_heap_and_key_order() {
+        use crate::compare::{ByKey, Reverse};
+        let mut heap = Heap::from_vec_with(vec![5, 1, 4, 2], Reverse(Natural));
+        heap.push(3);
+        let sorted: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+
+        let mut heap = Heap::with_comparator(ByKey(|s: &&str| s.len()));
+        heap.push("aa");
+        heap.push("a");
+        heap.push("aaa");
+        assert_eq!(heap.pop(), Some("aaa"));
+
+        let mut heap = Heap::with_comparator(|a: &i32, b: &i32| (a % 10).cmp(&(b % 10)));
+        heap.push(19);
+        heap.push(25);
+        assert_eq!(heap.peek(), Some(&19));
+    }
+
+    #[test]
+    fn drain_keeps_allocation() {
+        let mut heap = Heap::from_vec((0..10).collect());
+        let capacity = heap.memory_stats().total_slots;
+        let drained: Vec<_> = heap.drain().take(3).collect();
+        assert_eq!(drained.len(), 3);
+        assert!(heap.is_empty());
+        assert_eq!(heap.memory_stats().total_slots, capacity);
+        heap.push(1);
+        assert_eq!(heap.pop(), Some(1));
+    }
+
+    #[test]
+    fn panicking_drain_filter_leaves_heap_intact() {
+        let mut heap = Heap::from_vec((0..20).collect());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            heap.drain_filter(|&x| {
+                if x == 7 {
+                    panic!("bad item")
+                } else {
+                    x % 2 == 0
+                }
+            })
+            .count()
+        }));
+        assert!(result.is_err());
+        assert_eq!(heap.len(), 20);
+        assert_eq!(heap.pop(), Some(19));
+    }
+
+    #[test]
+    fn drain_filter_restores_heap_order() {
+        let mut heap = Heap::from_vec((0..20).collect());
+        let mut odd: Vec<_> = heap.drain_filter(|x| x % 2 == 1).collect();
+        odd.sort();
+        assert_eq!(odd, (0..20).filter(|x| x % 2 == 1).collect::<Vec<_>>());
+        let rest: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(
+            rest,
+            (0..20).rev().filter(|x| x % 2 == 0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn ordered_by_extracted_key() {
+        #[derive(Debug, PartialEq)]
+        struct Job {
+            name: &'static str,
+            priority: u8,
+        }
+        let job = |name, priority| Job { name, priority };
+
+        let mut heap = Heap::by_key(|j: &Job| j.priority);
+        heap.push(job("b", 2));
+        heap.push(job("c", 7));
+        heap.extend([job("a", 5)]);
+        assert_eq!(heap.pop().map(|j| j.name), Some("c"));
+        assert_eq!(heap.peek(), Some(&job("a", 5)));
+
+        fn by_name(j: &Job) -> &'static str {
+            j.name
+        }
+        let heap: KeyHeap<Job, fn(&Job) -> &'static str> =
+            Heap::from_vec_by_key(vec![job("x", 1), job("z", 1), job("y", 1)], by_name);
+        assert_eq!(heap.peek().map(|j| j.name), Some("z"));
+    }
+
+    fn is_heap(heap: &Heap<u32>) -> bool {
+        let data = heap.as_slice();
+        (1..data.len()).all(|i| data[(i - 1) / 2] >= data[i])
+    }
+
+    #[test]
+    fn extend_rebuild_small_and_large_batches() {
+        let mut heap: Heap<u32> = Heap::from_vec((0..100).collect());
+        // a few items are sifted up, so earlier items stay in place
+        let before = heap.as_slice()[..50].to_vec();
+        heap.extend_rebuild([7, 3, 1]);
+        assert!(is_heap(&heap));
+        assert_eq!(&heap.as_slice()[..50], &before[..]);
+
+        heap.extend_rebuild((0..1000).map(|i| i * 7919 % 1000));
+        assert!(is_heap(&heap));
+        assert_eq!(heap.len(), 1103);
+        heap.extend(Vec::new());
+        let sorted: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert!(sorted.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(sorted[0], 999);
+    }
+
+    #[test]
+    fn extend_rebuild_keeps_heap_order_if_the_iterator_panics() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        for batch in [3, 500] {
+            let mut heap: Heap<u32> = Heap::from_vec((0..50).collect());
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                heap.extend_rebuild((0..).map(|i| {
+                    assert!(i < batch, "iterator failed");
+                    i * 7919 % 1000
+                }));
+            }));
+            assert!(result.is_err());
+            assert_eq!(heap.len(), 50 + batch as usize);
+            assert!(is_heap(&heap));
+        }
+    }
+
+    #[test]
+    fn rebuild_factor_is_tunable() {
+        let mut heap: Heap<u32> = Heap::new();
+        assert_eq!(heap.rebuild_factor(), DEFAULT_REBUILD_FACTOR);
+        heap.extend_rebuild([]);
+        heap.extend_rebuild([5, 1, 9]);
+        heap.set_rebuild_factor(0);
+        heap.extend_rebuild([2]);
+        assert!(is_heap(&heap));
+        heap.set_rebuild_factor(usize::MAX / 64);
+        heap.extend(0..500);
+        assert!(is_heap(&heap));
+        assert_eq!(heap.pop(), Some(499));
+    }
+
+    #[test]
+    fn std_conversions() {
+        let std_heap = BinaryHeap::from(vec![3, 1, 4, 1, 5, 9, 2, 6]);
+        let mut heap = Heap::from(std_heap.clone());
+        assert_eq!(heap.as_slice(), std_heap.as_slice());
+        assert_eq!(heap.pop(), Some(9));
+        assert_eq!(
+            BinaryHeap::from(heap).into_sorted_vec(),
+            vec![1, 1, 2, 3, 4, 5, 6]
+        );
+
+        let heap: Heap<_> = vec![2, 7, 1].into();
+        assert_eq!(Vec::from(heap)[0], 7);
+    }
 }
@@ -0,0 +1,358 @@
+//! A sequence stored in a skip list whose links know how many items they
+//! skip, so items are found by position rather than by key.
+//!
+//! Every link records its width: the number of level-0 steps it spans.
+//! Walking down from the top level while summing widths finds position `i`
+//! in O(log n) expected time, and inserting or removing an item only
+//! adjusts the widths of the links that pass over it.
+
+use crate::slab::Slab;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+const MAX_LEVEL: usize = 24;
+
+/// Marks the end of a level; also used as the id of the head.
+const NIL: usize = usize::MAX;
+
+struct Node<T> {
+    value: T,
+    next: Vec<usize>,
+    width: Vec<usize>,
+}
+
+pub struct IndexableSkipList<T> {
+    nodes: Slab<Node<T>>,
+    head_next: [usize; MAX_LEVEL],
+    /// Links to `NIL` span to one past the last item.
+    head_width: [usize; MAX_LEVEL],
+    seed: u64,
+}
+
+impl<T> IndexableSkipList<T> {
+    pub fn new() -> Self {
+        IndexableSkipList {
+            nodes: Slab::new(),
+            head_next: [NIL; MAX_LEVEL],
+            head_width: [1; MAX_LEVEL],
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let id = self.find(index)?;
+        Some(&self.nodes[id].value)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let id = self.find(index)?;
+        Some(&mut self.nodes[id].value)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// Insert `value` so that it ends up at `index`. Panics if
+    /// `index > len`.
+    pub fn insert_at(&mut self, index: usize, value: T) {
+        assert!(index <= self.len(), "insertion index out of bounds");
+        let (preds, positions) = self.predecessors(index);
+        let height = self.random_level() + 1;
+        let id = self.nodes.insert(Node {
+            value,
+            next: vec![NIL; height],
+            width: vec![0; height],
+        });
+        // positions count the head as 0, so the new node is at index + 1
+        let pos = index + 1;
+        for level in 0..MAX_LEVEL {
+            let pred = preds[level];
+            if level < height {
+                let next_pos = positions[level] + self.width(pred, level) + 1;
+                let next = self.next_of(pred, level);
+                let node = &mut self.nodes[id];
+                node.next[level] = next;
+                node.width[level] = next_pos - pos;
+                self.set_link(pred, level, id, pos - positions[level]);
+            } else {
+                let width = self.width(pred, level) + 1;
+                let next = self.next_of(pred, level);
+                self.set_link(pred, level, next, width);
+            }
+        }
+    }
+
+    /// Remove and return the item at `index`, if it exists.
+    pub fn remove_at(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        let (preds, _) = self.predecessors(index);
+        let victim = self.next_of(preds[0], 0);
+        for (level, &pred) in preds.iter().enumerate() {
+            if self.next_of(pred, level) == victim {
+                let node = &self.nodes[victim];
+                let (next, width) = (node.next[level], node.width[level]);
+                let width = self.width(pred, level) + width - 1;
+                self.set_link(pred, level, next, width);
+            } else {
+                let width = self.width(pred, level) - 1;
+                let next = self.next_of(pred, level);
+                self.set_link(pred, level, next, width);
+            }
+        }
+        self.nodes.remove(victim).map(|node| node.value)
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.insert_at(self.len(), value);
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.insert_at(0, value);
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.remove_at(self.len().checked_sub(1)?)
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.remove_at(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.head_next = [NIL; MAX_LEVEL];
+        self.head_width = [1; MAX_LEVEL];
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            node: self.head_next[0],
+            remaining: self.len(),
+        }
+    }
+
+    fn find(&self, index: usize) -> Option<usize> {
+        if index >= self.len() {
+            return None;
+        }
+        let (preds, _) = self.predecessors(index);
+        Some(self.next_of(preds[0], 0))
+    }
+
+    /// On every level, the last node before `index` and its position.
+    fn predecessors(&self, index: usize) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut preds = [NIL; MAX_LEVEL];
+        let mut positions = [0; MAX_LEVEL];
+        let (mut node, mut pos) = (NIL, 0);
+        for level in (0..MAX_LEVEL).rev() {
+            while self.next_of(node, level) != NIL && pos + self.width(node, level) <= index {
+                pos += self.width(node, level);
+                node = self.next_of(node, level);
+            }
+            preds[level] = node;
+            positions[level] = pos;
+        }
+        (preds, positions)
+    }
+
+    fn next_of(&self, node: usize, level: usize) -> usize {
+        if node == NIL {
+            self.head_next[level]
+        } else {
+            self.nodes[node].next[level]
+        }
+    }
+
+    fn width(&self, node: usize, level: usize) -> usize {
+        if node == NIL {
+            self.head_width[level]
+        } else {
+            self.nodes[node].width[level]
+        }
+    }
+
+    fn set_link(&mut self, node: usize, level: usize, next: usize, width: usize) {
+        if node == NIL {
+            self.head_next[level] = next;
+            self.head_width[level] = width;
+        } else {
+            let node = &mut self.nodes[node];
+            node.next[level] = next;
+            node.width[level] = width;
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        let mut x = self.seed;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.seed = x;
+        (x & ((1 << (MAX_LEVEL - 1)) - 1)).trailing_ones() as usize
+    }
+}
+
+impl<T> Default for IndexableSkipList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<usize> for IndexableSkipList<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for IndexableSkipList<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
+
+impl<T> FromIterator<T> for IndexableSkipList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for IndexableSkipList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IndexableSkipList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IndexableSkipList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    list: &'a IndexableSkipList<T>,
+    node: usize,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.node == NIL {
+            return None;
+        }
+        let node = &self.list.nodes[self.node];
+        self.node = node.next[0];
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_index() {
+        let mut list: IndexableSkipList<_> = (0..100).collect();
+        assert_eq!(list.len(), 100);
+        assert!((0..100).all(|i| list[i] == i));
+        assert_eq!(list.get(100), None);
+        list[42] = 0;
+        assert_eq!(list.get(42), Some(&0));
+        assert_eq!((list.first(), list.last()), (Some(&0), Some(&99)));
+    }
+
+    #[test]
+    fn insert_and_remove_in_the_middle() {
+        let mut list = IndexableSkipList::new();
+        list.push_back('a');
+        list.push_back('d');
+        list.insert_at(1, 'c');
+        list.insert_at(1, 'b');
+        list.push_front('_');
+        assert_eq!(list.iter().collect::<String>(), "_abcd");
+        assert_eq!(list.remove_at(2), Some('b'));
+        assert_eq!(list.remove_at(4), None);
+        assert_eq!(list.pop_front(), Some('_'));
+        assert_eq!(list.pop_back(), Some('d'));
+        assert_eq!(format!("{list:?}"), "['a', 'c']");
+    }
+
+    #[test]
+    fn matches_vec_under_random_edits() {
+        let mut list = IndexableSkipList::new();
+        let mut model = Vec::new();
+        let mut x: u32 = 1;
+        for i in 0..3000 {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345) % (1 << 31);
+            if model.is_empty() || !x.is_multiple_of(3) {
+                let at = x as usize % (model.len() + 1);
+                list.insert_at(at, i);
+                model.insert(at, i);
+            } else {
+                let at = x as usize % model.len();
+                assert_eq!(list.remove_at(at), Some(model.remove(at)));
+            }
+            let probe = x as usize % (model.len() + 1);
+            assert_eq!(list.get(probe), model.get(probe));
+        }
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), model);
+        assert_eq!(list.iter().len(), model.len());
+    }
+
+    #[test]
+    fn clear_resets_widths() {
+        let mut list: IndexableSkipList<_> = (0..50).collect();
+        list.clear();
+        assert!(list.is_empty());
+        list.extend([7, 8]);
+        list.insert_at(1, 9);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![7, 9, 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn inserting_past_the_end_panics() {
+        IndexableSkipList::new().insert_at(1, ());
+    }
+}
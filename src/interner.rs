@@ -0,0 +1,170 @@
+use crate::arena::Arena;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A cheap handle to a string stored in an `Interner`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Symbols are numbered consecutively from 0 in the order strings were
+    /// first interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Deduplicates strings and hands out a `Symbol` for each distinct string.
+///
+/// String contents are copied into an arena exactly once; the lookup table
+/// and the symbol table both refer to that copy.
+pub struct Interner {
+    // `'static` is a lie: the strings live as long as `bytes`. They are never
+    // handed out with a lifetime longer than a borrow of the interner.
+    symbols: BTreeMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+    bytes: Arena<u8>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner {
+            symbols: BTreeMap::new(),
+            strings: vec![],
+            bytes: Arena::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// The symbol for `s`, allocating a new one if `s` was not seen before.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&sym) = self.symbols.get(s) {
+            return sym;
+        }
+        let sym = Symbol(u32::try_from(self.strings.len()).expect("too many symbols"));
+        let bytes = self.bytes.alloc_extend(s.bytes());
+        // SAFETY: the bytes were copied from a valid `str`. The arena never
+        // moves or frees them before it is dropped together with `self`, and
+        // we never give out references that outlive a borrow of `self`.
+        let stored: &'static str = unsafe {
+            let bytes = core::slice::from_raw_parts(bytes.as_ptr(), bytes.len());
+            core::str::from_utf8_unchecked(bytes)
+        };
+        self.symbols.insert(stored, sym);
+        self.strings.push(stored);
+        sym
+    }
+
+    /// The symbol for `s`, if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// The string of a symbol. Panics if the symbol is from another interner.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        self.strings[sym.index()]
+    }
+
+    pub fn try_resolve(&self, sym: Symbol) -> Option<&str> {
+        self.strings.get(sym.index()).copied()
+    }
+
+    /// All symbols with their strings, in order of creation.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.strings
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| (Symbol(i as u32), s))
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.strings.iter()).finish()
+    }
+}
+
+impl<'a> Extend<&'a str> for Interner {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.intern(s);
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Interner {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut interner = Interner::new();
+        interner.extend(iter);
+        interner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_strings_share_a_symbol() {
+        let mut interner = Interner::new();
+        let a = interner.intern("foo");
+        let b = interner.intern("bar");
+        let c = interner.intern(&String::from("foo"));
+        assert_eq!(a, c);
+        assert_ne!(a, b);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn resolve_returns_original_text() {
+        let mut interner = Interner::new();
+        let syms: Vec<_> = ["x", "", "héllo", "x"]
+            .iter()
+            .map(|s| interner.intern(s))
+            .collect();
+        assert_eq!(interner.resolve(syms[0]), "x");
+        assert_eq!(interner.resolve(syms[1]), "");
+        assert_eq!(interner.resolve(syms[2]), "héllo");
+        assert_eq!(syms[3].index(), 0);
+        assert_eq!(interner.try_resolve(Symbol(7)), None);
+    }
+
+    #[test]
+    fn lookup_without_interning() {
+        let mut interner: Interner = ["a", "b"].into_iter().collect();
+        assert_eq!(interner.get("b"), Some(Symbol(1)));
+        assert_eq!(interner.get("c"), None);
+        assert_eq!(interner.len(), 2);
+        interner.intern("c");
+        assert!(interner.get("c").is_some());
+    }
+
+    #[test]
+    fn many_strings_survive_arena_growth() {
+        let mut interner = Interner::new();
+        let syms: Vec<_> = (0..1000)
+            .map(|i| interner.intern(&format!("sym{i}")))
+            .collect();
+        let moved = interner;
+        for (i, &sym) in syms.iter().enumerate() {
+            assert_eq!(moved.resolve(sym), format!("sym{i}"));
+        }
+        let order: Vec<_> = moved.iter().take(3).map(|(_, s)| s).collect();
+        assert_eq!(order, vec!["sym0", "sym1", "sym2"]);
+    }
+}
@@ -0,0 +1,362 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::{Bound, Deref, DerefMut, Index, Range};
+
+/// A map from disjoint half-open ranges to values.
+///
+/// Inserting a range overwrites whatever overlapped it, splitting ranges
+/// that are only partly covered. Adjacent ranges with equal values are
+/// merged, so the map always holds the fewest possible ranges.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IntervalMap<K, V> {
+    // start -> (end, value)
+    ranges: BTreeMap<K, (K, V)>,
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> IntervalMap<K, V> {
+    pub fn new() -> Self {
+        IntervalMap {
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Number of stored ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// The value at `point`.
+    pub fn get<Q>(&self, point: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_range_value(point).map(|(_, v)| v)
+    }
+
+    /// The value of the whole range containing `point`. When the guard is
+    /// dropped, the range is merged with neighbors that now have an equal
+    /// value, as if it had been inserted.
+    pub fn get_mut<Q>(&mut self, point: &Q) -> Option<ValueMut<'_, K, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (start, (end, _)) = self
+            .ranges
+            .range::<Q, _>((Bound::Unbounded, Bound::Included(point)))
+            .next_back()?;
+        let start = start.clone();
+        (point < (*end).borrow()).then_some(ValueMut { map: self, start })
+    }
+
+    /// The range containing `point`, with its value.
+    pub fn get_range_value<Q>(&self, point: &Q) -> Option<(Range<K>, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (start, (end, value)) = self
+            .ranges
+            .range::<Q, _>((Bound::Unbounded, Bound::Included(point)))
+            .next_back()?;
+        (point < end.borrow()).then(|| (start.clone()..end.clone(), value))
+    }
+
+    pub fn contains_point<Q>(&self, point: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get(point).is_some()
+    }
+
+    /// Map every point in `range` to `value`.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        if range.is_empty() {
+            return;
+        }
+        self.remove(range.clone());
+        let Range { mut start, mut end } = range;
+
+        let left = self.ranges.range(..&start).next_back();
+        if let Some((s, (e, v))) = left {
+            if *e == start && *v == value {
+                start = s.clone();
+                self.ranges.remove(&start);
+            }
+        }
+        if let Some((e, v)) = self.ranges.get(&end) {
+            if *v == value {
+                let e = e.clone();
+                self.ranges.remove(&end);
+                end = e;
+            }
+        }
+        self.ranges.insert(start, (end, value));
+    }
+
+    /// Unmap every point in `range`.
+    pub fn remove(&mut self, range: Range<K>) {
+        if range.is_empty() {
+            return;
+        }
+        let Range { start, end } = range;
+
+        // a range that starts before `start` may stick out on either side
+        if let Some((_, (e, v))) = self.ranges.range_mut(..&start).next_back() {
+            if *e > start {
+                if *e > end {
+                    let tail = (e.clone(), v.clone());
+                    *e = start.clone();
+                    self.ranges.insert(end, tail);
+                    return;
+                }
+                *e = start.clone();
+            }
+        }
+
+        let inside: Vec<K> = self
+            .ranges
+            .range(&start..&end)
+            .map(|(s, _)| s.clone())
+            .collect();
+        for s in inside {
+            let (e, v) = self.ranges.remove(&s).unwrap();
+            if e > end {
+                self.ranges.insert(end.clone(), (e, v));
+            }
+        }
+    }
+
+    /// All ranges in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<K>, &V)> {
+        self.ranges
+            .iter()
+            .map(|(s, (e, v))| (s.clone()..e.clone(), v))
+    }
+
+    /// Stored ranges that overlap `range`, in ascending order. They are not
+    /// clipped to `range`.
+    pub fn overlapping(&self, range: Range<K>) -> impl Iterator<Item = (Range<K>, &V)> {
+        let first = self
+            .ranges
+            .range(..&range.start)
+            .next_back()
+            .filter(|(_, (e, _))| *e > range.start)
+            .map(|(s, _)| s.clone())
+            .unwrap_or_else(|| range.start.clone());
+        let iter = if range.is_empty() {
+            None
+        } else {
+            Some(self.ranges.range(first..range.end))
+        };
+        iter.into_iter()
+            .flatten()
+            .map(|(s, (e, v))| (s.clone()..e.clone(), v))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> Default for IntervalMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, Q> Index<&Q> for IntervalMap<K, V>
+where
+    K: Ord + Clone + Borrow<Q>,
+    V: Clone + Eq,
+    Q: ?Sized + Ord,
+{
+    type Output = V;
+
+    fn index(&self, point: &Q) -> &V {
+        self.get(point).expect("point not covered")
+    }
+}
+
+/// Mutable access to the value of one range, returned by
+/// `IntervalMap::get_mut`.
+pub struct ValueMut<'a, K: Ord + Clone, V: Clone + Eq> {
+    map: &'a mut IntervalMap<K, V>,
+    start: K,
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> Deref for ValueMut<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.map.ranges[&self.start].1
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> DerefMut for ValueMut<'_, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.map.ranges.get_mut(&self.start).unwrap().1
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> Drop for ValueMut<'_, K, V> {
+    fn drop(&mut self) {
+        let (end, value) = self.map.ranges.remove(&self.start).unwrap();
+        self.map.insert(self.start.clone()..end, value);
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IntervalMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.ranges.iter().map(|(s, (e, v))| (s..e, v)))
+            .finish()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> Extend<(Range<K>, V)> for IntervalMap<K, V> {
+    fn extend<I: IntoIterator<Item = (Range<K>, V)>>(&mut self, iter: I) {
+        for (range, value) in iter {
+            self.insert(range, value);
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> FromIterator<(Range<K>, V)> for IntervalMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        let mut map = IntervalMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(map: &IntervalMap<u32, char>) -> Vec<(Range<u32>, char)> {
+        map.iter().map(|(r, &v)| (r, v)).collect()
+    }
+
+    #[test]
+    fn point_lookup() {
+        let map: IntervalMap<_, _> = [(0..10, 'a'), (20..30, 'b')].into_iter().collect();
+        assert_eq!(map.get(&0), Some(&'a'));
+        assert_eq!(map.get(&9), Some(&'a'));
+        assert_eq!(map.get(&10), None);
+        assert_eq!(map.get(&25), Some(&'b'));
+        assert_eq!(map.get_range_value(&22), Some((20..30, &'b')));
+        assert!(!map.contains_point(&30));
+    }
+
+    #[test]
+    fn insert_splits_overlapped_ranges() {
+        let mut map = IntervalMap::new();
+        map.insert(0..10, 'a');
+        map.insert(3..5, 'b');
+        assert_eq!(ranges(&map), vec![(0..3, 'a'), (3..5, 'b'), (5..10, 'a')]);
+        map.insert(4..8, 'c');
+        assert_eq!(
+            ranges(&map),
+            vec![(0..3, 'a'), (3..4, 'b'), (4..8, 'c'), (8..10, 'a')]
+        );
+        map.insert(0..20, 'd');
+        assert_eq!(ranges(&map), vec![(0..20, 'd')]);
+    }
+
+    #[test]
+    fn adjacent_equal_values_coalesce() {
+        let mut map = IntervalMap::new();
+        map.insert(0..5, 'a');
+        map.insert(10..15, 'a');
+        map.insert(5..10, 'a');
+        assert_eq!(ranges(&map), vec![(0..15, 'a')]);
+        map.insert(15..20, 'b');
+        assert_eq!(map.len(), 2);
+        map.insert(3..7, 'b');
+        map.insert(3..7, 'a');
+        assert_eq!(ranges(&map), vec![(0..15, 'a'), (15..20, 'b')]);
+    }
+
+    #[test]
+    fn remove_punches_holes() {
+        let mut map: IntervalMap<_, _> = [(0..10, 'a'), (10..20, 'b')].into_iter().collect();
+        map.remove(5..7);
+        assert_eq!(ranges(&map), vec![(0..5, 'a'), (7..10, 'a'), (10..20, 'b')]);
+        map.remove(8..15);
+        assert_eq!(ranges(&map), vec![(0..5, 'a'), (7..8, 'a'), (15..20, 'b')]);
+        map.remove(0..100);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let map: IntervalMap<_, _> = [(0..5, 'a'), (5..10, 'b'), (12..20, 'c')]
+            .into_iter()
+            .collect();
+        let hits: Vec<_> = map.overlapping(3..13).map(|(_, &v)| v).collect();
+        assert_eq!(hits, vec!['a', 'b', 'c']);
+        assert_eq!(map.overlapping(10..12).count(), 0);
+        assert_eq!(map.overlapping(4..4).count(), 0);
+        assert_eq!(map.overlapping(19..30).count(), 1);
+    }
+
+    #[test]
+    fn matches_naive_array() {
+        let mut map = IntervalMap::new();
+        let mut naive = [None; 64];
+        let mut x = 1usize;
+        for _ in 0..500 {
+            x = (x * 1103 + 12345) % 100_003;
+            let a = (x % 64) as u32;
+            let b = ((x / 64) % 65) as u32;
+            let range = a.min(b)..a.max(b);
+            if x.is_multiple_of(4) {
+                map.remove(range.clone());
+                naive[range.start as usize..range.end as usize].fill(None);
+            } else {
+                let v = (b'a' + (x % 3) as u8) as char;
+                map.insert(range.clone(), v);
+                naive[range.start as usize..range.end as usize].fill(Some(v));
+            }
+            for (i, expected) in naive.iter().enumerate() {
+                assert_eq!(map.get(&(i as u32)), expected.as_ref());
+            }
+            // coalescing leaves no two touching ranges with equal values
+            let r = ranges(&map);
+            assert!(r
+                .windows(2)
+                .all(|w| w[0].0.end < w[1].0.start || w[0].1 != w[1].1));
+        }
+    }
+
+    #[test]
+    fn get_mut_merges_equal_neighbors() {
+        let mut map: IntervalMap<_, _> = [(0..10, 'a'), (10..20, 'b')].into_iter().collect();
+        assert_eq!(map[&15], 'b');
+        *map.get_mut(&3).unwrap() = 'b';
+        assert_eq!(ranges(&map), vec![(0..20, 'b')]);
+        assert_eq!(map, [(0..20, 'b')].into_iter().collect());
+        assert!(map.get_mut(&20).is_none());
+        *map.get_mut(&19).unwrap() = 'c';
+        assert_eq!(ranges(&map), vec![(0..20, 'c')]);
+    }
+
+    #[test]
+    fn borrowed_point_lookup() {
+        let mut map = IntervalMap::new();
+        map.insert(String::from("a")..String::from("m"), 1);
+        map.insert(String::from("m")..String::from("z"), 2);
+        assert_eq!(map.get("hello"), Some(&1));
+        assert_eq!(map["world"], 2);
+        assert!(!map.contains_point("zebra"));
+    }
+}
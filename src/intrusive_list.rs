@@ -0,0 +1,455 @@
+//! A doubly linked list whose links live inside the items.
+//!
+//! An item type embeds one `Link` per list it may belong to, and an
+//! `Adapter` tells the list which link to use. The list stores no nodes of
+//! its own: it borrows the items, so pushing and unlinking never allocate,
+//! and an item can be unlinked in O(1) given only a reference to it.
+//!
+//! ```
+//! use data_structures::intrusive_adapter;
+//! use data_structures::intrusive_list::{IntrusiveList, Link};
+//!
+//! #[derive(Debug)]
+//! struct Task {
+//!     id: u32,
+//!     run_queue: Link<Task>,
+//!     all_tasks: Link<Task>,
+//! }
+//!
+//! intrusive_adapter!(RunQueue = Task { run_queue });
+//! intrusive_adapter!(AllTasks = Task { all_tasks });
+//!
+//! let tasks: Vec<_> = (0..3)
+//!     .map(|id| Task { id, run_queue: Link::new(), all_tasks: Link::new() })
+//!     .collect();
+//! let mut all = IntrusiveList::<AllTasks>::new();
+//! let mut ready = IntrusiveList::<RunQueue>::new();
+//! for task in &tasks {
+//!     all.push_back(task).unwrap();
+//! }
+//! ready.push_back(&tasks[2]).unwrap();
+//! ready.push_back(&tasks[0]).unwrap();
+//! assert_eq!(ready.iter().map(|t| t.id).collect::<Vec<_>>(), [2, 0]);
+//! assert_eq!(all.len(), 3);
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Source of list ids; 0 marks an unlinked `Link`.
+static NEXT_LIST_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// The prev/next pointers an item needs to be a member of one list.
+pub struct Link<T> {
+    prev: Cell<Option<NonNull<T>>>,
+    next: Cell<Option<NonNull<T>>>,
+    /// Id of the list the item is in, or 0.
+    owner: Cell<usize>,
+}
+
+impl<T> Link<T> {
+    pub const fn new() -> Self {
+        Link {
+            prev: Cell::new(None),
+            next: Cell::new(None),
+            owner: Cell::new(0),
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.owner.get() != 0
+    }
+
+    fn unlink(&self) {
+        self.prev.set(None);
+        self.next.set(None);
+        self.owner.set(0);
+    }
+}
+
+impl<T> Default for Link<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Link<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Link")
+            .field("linked", &self.is_linked())
+            .finish()
+    }
+}
+
+/// Selects the `Link` of an item that a list uses.
+///
+/// # Safety
+///
+/// `link` must always return the same field of `item`, and no two adapters
+/// used with the same items may return the same field. The
+/// `intrusive_adapter!` macro generates correct implementations.
+pub unsafe trait Adapter {
+    type Item;
+
+    fn link(item: &Self::Item) -> &Link<Self::Item>;
+}
+
+/// Define a unit struct that implements `Adapter` for a link field.
+///
+/// `intrusive_adapter!(pub ByAge = Task { age_link })`
+#[macro_export]
+macro_rules! intrusive_adapter {
+    ($vis:vis $name:ident = $item:ty { $field:ident }) => {
+        #[derive(Debug, Default, Copy, Clone)]
+        $vis struct $name;
+
+        // SAFETY: always the same field
+        unsafe impl $crate::intrusive_list::Adapter for $name {
+            type Item = $item;
+
+            fn link(item: &$item) -> &$crate::intrusive_list::Link<$item> {
+                &item.$field
+            }
+        }
+    };
+}
+
+/// A list of items borrowed for `'a`. Dropping the list unlinks them.
+pub struct IntrusiveList<'a, A: Adapter> {
+    head: Option<NonNull<A::Item>>,
+    tail: Option<NonNull<A::Item>>,
+    len: usize,
+    id: usize,
+    _items: PhantomData<&'a A::Item>,
+}
+
+impl<'a, A: Adapter> IntrusiveList<'a, A> {
+    pub fn new() -> Self {
+        IntrusiveList {
+            head: None,
+            tail: None,
+            len: 0,
+            id: NEXT_LIST_ID.fetch_add(1, Ordering::Relaxed),
+            _items: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&'a A::Item> {
+        // SAFETY: members are borrowed for 'a
+        self.head.map(|p| unsafe { p.as_ref() })
+    }
+
+    pub fn back(&self) -> Option<&'a A::Item> {
+        // SAFETY: members are borrowed for 'a
+        self.tail.map(|p| unsafe { p.as_ref() })
+    }
+
+    /// Whether `item` is a member of this list. O(1).
+    pub fn contains(&self, item: &A::Item) -> bool {
+        A::link(item).owner.get() == self.id
+    }
+
+    /// Fails if the item is already in a list that uses the same link.
+    pub fn push_front(&mut self, item: &'a A::Item) -> Result<(), &'a A::Item> {
+        let link = A::link(item);
+        if link.is_linked() {
+            return Err(item);
+        }
+        link.owner.set(self.id);
+        link.next.set(self.head);
+        let ptr = NonNull::from(item);
+        match self.head {
+            Some(head) => A::link(deref(head)).prev.set(Some(ptr)),
+            None => self.tail = Some(ptr),
+        }
+        self.head = Some(ptr);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Fails if the item is already in a list that uses the same link.
+    pub fn push_back(&mut self, item: &'a A::Item) -> Result<(), &'a A::Item> {
+        let link = A::link(item);
+        if link.is_linked() {
+            return Err(item);
+        }
+        link.owner.set(self.id);
+        link.prev.set(self.tail);
+        let ptr = NonNull::from(item);
+        match self.tail {
+            Some(tail) => A::link(deref(tail)).next.set(Some(ptr)),
+            None => self.head = Some(ptr),
+        }
+        self.tail = Some(ptr);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<&'a A::Item> {
+        let item = self.front()?;
+        self.unlink(item);
+        Some(item)
+    }
+
+    pub fn pop_back(&mut self) -> Option<&'a A::Item> {
+        let item = self.back()?;
+        self.unlink(item);
+        Some(item)
+    }
+
+    /// Unlink `item`. Returns `false` if it is not in this list.
+    pub fn remove(&mut self, item: &A::Item) -> bool {
+        if !self.contains(item) {
+            return false;
+        }
+        self.unlink(item);
+        true
+    }
+
+    /// Move a member to the back, e.g. to mark it as recently used.
+    /// Returns `false` if it is not in this list.
+    pub fn move_to_back(&mut self, item: &'a A::Item) -> bool {
+        if !self.remove(item) {
+            return false;
+        }
+        self.push_back(item).is_ok()
+    }
+
+    /// Unlink all items.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'a, A> {
+        Iter {
+            front: self.head,
+            back: self.tail,
+            remaining: self.len,
+            _items: PhantomData,
+        }
+    }
+
+    /// Unlink a member of this list.
+    fn unlink(&mut self, item: &A::Item) {
+        let link = A::link(item);
+        let (prev, next) = (link.prev.get(), link.next.get());
+        match prev {
+            Some(prev) => A::link(deref(prev)).next.set(next),
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => A::link(deref(next)).prev.set(prev),
+            None => self.tail = prev,
+        }
+        link.unlink();
+        self.len -= 1;
+    }
+}
+
+/// Members are borrowed for the lifetime of the list, so their pointers
+/// stay valid while the list can reach them.
+fn deref<'a, T>(ptr: NonNull<T>) -> &'a T {
+    // SAFETY: only called on pointers to members, see above
+    unsafe { ptr.as_ref() }
+}
+
+impl<A: Adapter> Default for IntrusiveList<'_, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Adapter> Drop for IntrusiveList<'_, A> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<A: Adapter> fmt::Debug for IntrusiveList<'_, A>
+where
+    A::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, A: Adapter> IntoIterator for &IntrusiveList<'a, A> {
+    type Item = &'a A::Item;
+    type IntoIter = Iter<'a, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, A: Adapter> {
+    front: Option<NonNull<A::Item>>,
+    back: Option<NonNull<A::Item>>,
+    remaining: usize,
+    _items: PhantomData<&'a A::Item>,
+}
+
+impl<'a, A: Adapter> Iterator for Iter<'a, A> {
+    type Item = &'a A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let item = deref(self.front?);
+        self.front = A::link(item).next.get();
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<A: Adapter> DoubleEndedIterator for Iter<'_, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let item = deref(self.back?);
+        self.back = A::link(item).prev.get();
+        self.remaining -= 1;
+        Some(item)
+    }
+}
+
+impl<A: Adapter> ExactSizeIterator for Iter<'_, A> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[derive(Debug)]
+    struct Item {
+        value: u32,
+        a: Link<Item>,
+        b: Link<Item>,
+    }
+
+    intrusive_adapter!(ByA = Item { a });
+    intrusive_adapter!(ByB = Item { b });
+
+    fn items(n: u32) -> Vec<Item> {
+        (0..n)
+            .map(|value| Item {
+                value,
+                a: Link::new(),
+                b: Link::new(),
+            })
+            .collect()
+    }
+
+    fn values<A: Adapter<Item = Item>>(list: &IntrusiveList<A>) -> Vec<u32> {
+        list.iter().map(|item| item.value).collect()
+    }
+
+    #[test]
+    fn push_and_pop_at_both_ends() {
+        let items = items(4);
+        let mut list = IntrusiveList::<ByA>::new();
+        list.push_back(&items[1]).unwrap();
+        list.push_front(&items[0]).unwrap();
+        list.push_back(&items[2]).unwrap();
+        assert_eq!(values(&list), [0, 1, 2]);
+        assert_eq!(
+            list.iter().rev().map(|i| i.value).collect::<Vec<_>>(),
+            [2, 1, 0]
+        );
+        assert_eq!(list.front().unwrap().value, 0);
+        assert_eq!(list.back().unwrap().value, 2);
+        assert_eq!(list.pop_front().unwrap().value, 0);
+        assert_eq!(list.pop_back().unwrap().value, 2);
+        assert_eq!(list.pop_back().unwrap().value, 1);
+        assert!(list.pop_front().is_none());
+        assert!(list.is_empty());
+        assert!(!items[1].a.is_linked());
+    }
+
+    #[test]
+    fn items_in_several_lists() {
+        let items = items(5);
+        let mut evens = IntrusiveList::<ByA>::new();
+        let mut all = IntrusiveList::<ByB>::new();
+        for item in &items {
+            all.push_back(item).unwrap();
+            if item.value % 2 == 0 {
+                evens.push_front(item).unwrap();
+            }
+        }
+        assert_eq!(values(&evens), [4, 2, 0]);
+        assert!(all.remove(&items[2]));
+        assert_eq!(values(&all), [0, 1, 3, 4]);
+        assert_eq!(values(&evens), [4, 2, 0]);
+        assert!(evens.contains(&items[2]) && !all.contains(&items[2]));
+    }
+
+    #[test]
+    fn membership_is_checked() {
+        let items = items(2);
+        let mut one = IntrusiveList::<ByA>::new();
+        let mut two = IntrusiveList::<ByA>::new();
+        one.push_back(&items[0]).unwrap();
+        assert!(two.push_back(&items[0]).is_err());
+        assert!(!two.remove(&items[0]));
+        assert!(!one.remove(&items[1]));
+        assert_eq!(one.len(), 1);
+        drop(one);
+        // dropping a list releases its items
+        two.push_back(&items[0]).unwrap();
+        assert_eq!(values(&two), [0]);
+    }
+
+    #[test]
+    fn move_to_back_for_lru_order() {
+        let items = items(4);
+        let mut lru = IntrusiveList::<ByA>::new();
+        for item in &items {
+            lru.push_back(item).unwrap();
+        }
+        assert!(lru.move_to_back(&items[1]));
+        assert!(lru.move_to_back(&items[0]));
+        assert!(lru.move_to_back(&items[0]));
+        assert_eq!(values(&lru), [2, 3, 1, 0]);
+        lru.clear();
+        assert!(lru.is_empty());
+        assert!(!lru.move_to_back(&items[0]));
+        assert!(items.iter().all(|i| !i.a.is_linked()));
+    }
+
+    #[test]
+    fn remove_from_the_middle() {
+        let items = items(5);
+        let mut list = IntrusiveList::<ByA>::new();
+        for item in &items {
+            list.push_back(item).unwrap();
+        }
+        assert!(list.remove(&items[2]));
+        assert!(list.remove(&items[0]));
+        assert!(list.remove(&items[4]));
+        assert_eq!(values(&list), [1, 3]);
+        assert_eq!(list.iter().len(), 2);
+        assert_eq!(
+            format!("{:?}", list.iter().next().unwrap().a),
+            "Link { linked: true }"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! A priority queue of keyed entries with cheap cancellation.
+//!
+//! Removing a key only forgets it in a map of live keys and leaves a
+//! tombstone in the heap, which `pop` skips later on. Once tombstones
+//! outnumber the live entries the heap is compacted, so it never grows
+//! beyond twice the live size. Workloads that cancel most of what they
+//! schedule avoid both the sift of a plain heap removal and the index upkeep
+//! of an indexed heap.
+
+use crate::compare::Compare;
+use crate::heap::Heap;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+/// Compaction is not worth it for tiny heaps.
+const MIN_COMPACT: usize = 32;
+
+struct Entry<K, P> {
+    priority: P,
+    key: K,
+    seq: u64,
+}
+
+#[derive(Default)]
+struct ByPriority;
+
+impl<K, P: PartialOrd> Compare<Entry<K, P>> for ByPriority {
+    fn compare(&self, a: &Entry<K, P>, b: &Entry<K, P>) -> Option<core::cmp::Ordering> {
+        a.priority.partial_cmp(&b.priority)
+    }
+}
+
+/// A max-priority queue of `(key, priority)` pairs with O(1) `remove`.
+/// Wrap priorities in `core::cmp::Reverse` to pop the smallest first.
+pub struct LazyDeletePriorityQueue<K, P> {
+    heap: Heap<Entry<K, P>, ByPriority>,
+    /// Sequence number of the live entry of each key.
+    live: BTreeMap<K, u64>,
+    next_seq: u64,
+}
+
+impl<K: Ord + Clone, P: PartialOrd> LazyDeletePriorityQueue<K, P> {
+    pub fn new() -> Self {
+        LazyDeletePriorityQueue {
+            heap: Heap::with_comparator(ByPriority),
+            live: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    /// Number of removed entries still stored in the heap.
+    pub fn tombstones(&self) -> usize {
+        self.heap.len() - self.live.len()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.live.contains_key(key)
+    }
+
+    /// Insert `key` with `priority`. If the key is already queued, its old
+    /// entry is removed, so this also changes priorities.
+    pub fn push(&mut self, key: K, priority: P) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.live.insert(key.clone(), seq);
+        self.heap.push(Entry { priority, key, seq });
+        self.maybe_compact();
+    }
+
+    /// Remove `key` from the queue. Returns `false` if it was not queued.
+    pub fn remove(&mut self, key: &K) -> bool {
+        if self.live.remove(key).is_none() {
+            return false;
+        }
+        self.maybe_compact();
+        true
+    }
+
+    /// The entry with the highest priority. Takes `&mut self` to discard
+    /// tombstones on top of the heap.
+    pub fn peek(&mut self) -> Option<(&K, &P)> {
+        self.skip_tombstones();
+        self.heap.peek().map(|e| (&e.key, &e.priority))
+    }
+
+    /// Remove and return the entry with the highest priority.
+    pub fn pop(&mut self) -> Option<(K, P)> {
+        self.skip_tombstones();
+        let entry = self.heap.pop()?;
+        self.live.remove(&entry.key);
+        Some((entry.key, entry.priority))
+    }
+
+    /// Drop all tombstones from the heap.
+    pub fn compact(&mut self) {
+        let live = &self.live;
+        self.heap
+            .drain_filter(|e| live.get(&e.key) != Some(&e.seq))
+            .for_each(drop);
+    }
+
+    pub fn clear(&mut self) {
+        self.heap.drain();
+        self.live.clear();
+    }
+
+    fn is_live(&self, entry: &Entry<K, P>) -> bool {
+        self.live.get(&entry.key) == Some(&entry.seq)
+    }
+
+    fn skip_tombstones(&mut self) {
+        while let Some(top) = self.heap.peek() {
+            if self.is_live(top) {
+                break;
+            }
+            self.heap.pop();
+        }
+    }
+
+    fn maybe_compact(&mut self) {
+        if self.heap.len() >= MIN_COMPACT && self.tombstones() > self.live.len() {
+            self.compact();
+        }
+    }
+}
+
+impl<K: Ord + Clone, P: PartialOrd> Default for LazyDeletePriorityQueue<K, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, P: PartialOrd> Extend<(K, P)> for LazyDeletePriorityQueue<K, P> {
+    fn extend<I: IntoIterator<Item = (K, P)>>(&mut self, iter: I) {
+        for (key, priority) in iter {
+            self.push(key, priority);
+        }
+    }
+}
+
+impl<K: Ord + Clone, P: PartialOrd> FromIterator<(K, P)> for LazyDeletePriorityQueue<K, P> {
+    fn from_iter<I: IntoIterator<Item = (K, P)>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl<K: Ord + Clone + fmt::Debug, P: PartialOrd + fmt::Debug> fmt::Debug
+    for LazyDeletePriorityQueue<K, P>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.heap
+                    .as_slice()
+                    .iter()
+                    .filter(|e| self.is_live(e))
+                    .map(|e| (&e.key, &e.priority)),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cmp::Reverse;
+
+    #[test]
+    fn pops_by_priority() {
+        let mut q: LazyDeletePriorityQueue<_, _> = [("a", 3), ("b", 7), ("c", 1), ("d", 5)]
+            .into_iter()
+            .collect();
+        assert_eq!(q.len(), 4);
+        assert_eq!(q.peek(), Some((&"b", &7)));
+        let order: Vec<_> = std::iter::from_fn(|| q.pop()).collect();
+        assert_eq!(order, vec![("b", 7), ("d", 5), ("a", 3), ("c", 1)]);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn removed_keys_are_skipped() {
+        let mut q: LazyDeletePriorityQueue<_, _> = (0..10).map(|i| (i, Reverse(i))).collect();
+        assert!(q.remove(&0));
+        assert!(q.remove(&3));
+        assert!(!q.remove(&3));
+        assert!(!q.contains_key(&0));
+        assert_eq!(q.len(), 8);
+        assert_eq!(q.tombstones(), 2);
+        assert_eq!(q.peek(), Some((&1, &Reverse(1))));
+        assert_eq!(q.tombstones(), 1);
+        let keys: Vec<_> = std::iter::from_fn(|| q.pop()).map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![1, 2, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(q.tombstones(), 0);
+    }
+
+    #[test]
+    fn pushing_again_changes_priority() {
+        let mut q = LazyDeletePriorityQueue::new();
+        q.push("x", 1);
+        q.push("y", 2);
+        q.push("x", 3);
+        assert_eq!(q.len(), 2);
+        assert_eq!(q.pop(), Some(("x", 3)));
+        assert_eq!(q.pop(), Some(("y", 2)));
+        assert_eq!(q.pop(), None);
+
+        // a key that was removed and pushed again must not be shadowed by
+        // its old tombstone
+        q.push("z", 5);
+        q.remove(&"z");
+        q.push("z", 1);
+        assert_eq!(q.pop(), Some(("z", 1)));
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn cancellations_trigger_compaction() {
+        let mut q = LazyDeletePriorityQueue::new();
+        for i in 0..1000 {
+            q.push(i, i);
+            if i % 10 != 0 {
+                q.remove(&i);
+            }
+            assert!(q.tombstones() <= q.len().max(MIN_COMPACT));
+        }
+        assert_eq!(q.len(), 100);
+        q.compact();
+        assert_eq!(q.tombstones(), 0);
+        assert_eq!(q.pop(), Some((990, 990)));
+    }
+
+    #[test]
+    fn debug_shows_live_entries() {
+        let mut q = LazyDeletePriorityQueue::new();
+        q.push(1, 'a');
+        q.push(2, 'b');
+        q.remove(&2);
+        assert_eq!(format!("{q:?}"), "{1: 'a'}");
+        q.clear();
+        assert!(q.is_empty() && q.tombstones() == 0);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::errors::DataStructureError;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Add, Mul};
+
+/// The linear function `slope * x + intercept`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Line<X> {
+    pub slope: X,
+    pub intercept: X,
+}
+
+impl<X: Copy + Add<Output = X> + Mul<Output = X>> Line<X> {
+    pub fn new(slope: X, intercept: X) -> Self {
+        Line { slope, intercept }
+    }
+
+    pub fn eval(&self, x: X) -> X {
+        self.slope * x + self.intercept
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Extremum {
+    Min,
+    Max,
+}
+
+/// A Li Chao tree: a set of lines and line segments that answers "what is
+/// the lowest (or highest) value of any of them at x?" in O(log n).
+///
+/// The x coordinates that can be queried are fixed when the tree is built,
+/// which makes it work for integer and floating point coordinates alike.
+/// Inserting a line takes O(log n), a segment O(log² n).
+#[derive(Clone)]
+pub struct LiChaoTree<X> {
+    xs: Vec<X>,
+    // one line per node of an implicit segment tree over `xs`, 1-based
+    nodes: Vec<Option<Line<X>>>,
+    extremum: Extremum,
+    len: usize,
+}
+
+impl<X> LiChaoTree<X>
+where
+    X: Copy + PartialOrd + Add<Output = X> + Mul<Output = X>,
+{
+    /// A tree answering minimum queries at the given x coordinates.
+    pub fn new_min(xs: impl IntoIterator<Item = X>) -> Self {
+        Self::new(xs, Extremum::Min)
+    }
+
+    /// A tree answering maximum queries at the given x coordinates.
+    pub fn new_max(xs: impl IntoIterator<Item = X>) -> Self {
+        Self::new(xs, Extremum::Max)
+    }
+
+    fn new(xs: impl IntoIterator<Item = X>, extremum: Extremum) -> Self {
+        let mut xs: Vec<X> = xs.into_iter().collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).expect("incomparable coordinate"));
+        xs.dedup();
+        LiChaoTree {
+            nodes: vec![None; 4 * xs.len().max(1)],
+            xs,
+            extremum,
+            len: 0,
+        }
+    }
+
+    /// Number of inserted lines and segments.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The query coordinates, in ascending order.
+    pub fn coordinates(&self) -> &[X] {
+        &self.xs
+    }
+
+    pub fn insert_line(&mut self, line: Line<X>) {
+        self.len += 1;
+        if !self.xs.is_empty() {
+            self.insert_at(1, 0, self.xs.len(), line);
+        }
+    }
+
+    /// Insert a line that only exists for `x_min <= x <= x_max`.
+    pub fn insert_segment(&mut self, line: Line<X>, x_min: X, x_max: X) {
+        self.len += 1;
+        let lo = self.xs.partition_point(|&x| x < x_min);
+        let hi = self.xs.partition_point(|&x| x <= x_max);
+        if lo < hi {
+            self.insert_range(1, 0, self.xs.len(), lo, hi, line);
+        }
+    }
+
+    /// The best value of all lines at `x`, or `None` if no line covers `x`.
+    ///
+    /// Panics if `x` is not one of the coordinates the tree was built with.
+    pub fn query(&self, x: X) -> Option<X> {
+        self.try_query(x).expect("not a query point")
+    }
+
+    /// Like `query`, but fails with `KeyNotFound` if `x` is not one of the
+    /// coordinates the tree was built with.
+    pub fn try_query(&self, x: X) -> Result<Option<X>, DataStructureError> {
+        let i = self.xs.partition_point(|&c| c < x);
+        if i == self.xs.len() || self.xs[i] != x {
+            return Err(DataStructureError::KeyNotFound);
+        }
+        let (mut node, mut lo, mut hi) = (1, 0, self.xs.len());
+        let mut best: Option<X> = None;
+        loop {
+            if let Some(line) = self.nodes[node] {
+                let y = line.eval(x);
+                if best.is_none_or(|b| self.better(y, b)) {
+                    best = Some(y);
+                }
+            }
+            if hi - lo == 1 {
+                return Ok(best);
+            }
+            let mid = (lo + hi) / 2;
+            if i < mid {
+                (node, hi) = (2 * node, mid);
+            } else {
+                (node, lo) = (2 * node + 1, mid);
+            }
+        }
+    }
+
+    fn better(&self, a: X, b: X) -> bool {
+        match self.extremum {
+            Extremum::Min => a < b,
+            Extremum::Max => a > b,
+        }
+    }
+
+    fn insert_range(
+        &mut self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        from: usize,
+        to: usize,
+        line: Line<X>,
+    ) {
+        if to <= lo || hi <= from {
+            return;
+        }
+        if from <= lo && hi <= to {
+            return self.insert_at(node, lo, hi, line);
+        }
+        let mid = (lo + hi) / 2;
+        self.insert_range(2 * node, lo, mid, from, to, line);
+        self.insert_range(2 * node + 1, mid, hi, from, to, line);
+    }
+
+    /// Insert a line into the subtree that covers `xs[lo..hi]`.
+    fn insert_at(&mut self, mut node: usize, mut lo: usize, mut hi: usize, mut line: Line<X>) {
+        loop {
+            let Some(mut kept) = self.nodes[node] else {
+                self.nodes[node] = Some(line);
+                return;
+            };
+            let mid = (lo + hi) / 2;
+            // the node keeps whichever line is better in the middle; the
+            // other one can only win on one side of it
+            if self.better(line.eval(self.xs[mid]), kept.eval(self.xs[mid])) {
+                core::mem::swap(&mut kept, &mut line);
+                self.nodes[node] = Some(kept);
+            }
+            if hi - lo == 1 {
+                return;
+            }
+            if self.better(line.eval(self.xs[lo]), kept.eval(self.xs[lo])) {
+                (node, hi) = (2 * node, mid);
+            } else if self.better(line.eval(self.xs[hi - 1]), kept.eval(self.xs[hi - 1])) {
+                (node, lo) = (2 * node + 1, mid);
+            } else {
+                return;
+            }
+        }
+    }
+}
+
+impl<X: fmt::Debug> fmt::Debug for LiChaoTree<X> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LiChaoTree")
+            .field("extremum", &self.extremum)
+            .field("lines", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tree() {
+        let tree = LiChaoTree::<i64>::new_min(0..10);
+        assert!(tree.is_empty());
+        assert_eq!(tree.query(3), None);
+    }
+
+    #[test]
+    fn lower_envelope_of_lines() {
+        let mut tree = LiChaoTree::new_min(-10..=10);
+        tree.insert_line(Line::new(1, 0));
+        tree.insert_line(Line::new(-1, 0));
+        tree.insert_line(Line::new(0, -3));
+        assert_eq!(tree.query(-10), Some(-10));
+        assert_eq!(tree.query(0), Some(-3));
+        assert_eq!(tree.query(2), Some(-3));
+        assert_eq!(tree.query(7), Some(-7));
+    }
+
+    #[test]
+    fn upper_envelope_over_floats() {
+        let xs = [0.0, 0.5, 1.0, 2.5, 4.0];
+        let mut tree = LiChaoTree::new_max(xs);
+        tree.insert_line(Line::new(2.0, 0.0));
+        tree.insert_line(Line::new(-1.0, 6.0));
+        assert_eq!(tree.query(0.5), Some(5.5));
+        assert_eq!(tree.query(2.5), Some(5.0));
+        assert_eq!(tree.query(4.0), Some(8.0));
+        assert_eq!(tree.coordinates().len(), 5);
+    }
+
+    #[test]
+    fn segments_only_cover_their_range() {
+        let mut tree = LiChaoTree::new_min(0..20);
+        tree.insert_segment(Line::new(0, 5), 3, 8);
+        tree.insert_segment(Line::new(1, -5), 6, 12);
+        assert_eq!(tree.query(2), None);
+        assert_eq!(tree.query(3), Some(5));
+        assert_eq!(tree.query(7), Some(2));
+        assert_eq!(tree.query(12), Some(7));
+        assert_eq!(tree.query(13), None);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a query point")]
+    fn unknown_coordinate() {
+        LiChaoTree::new_min([1, 2, 4]).query(3);
+    }
+
+    #[test]
+    fn unknown_coordinate_without_panic() {
+        let mut tree = LiChaoTree::new_max([1, 2, 4]);
+        tree.insert_line(Line::new(1, 0));
+        assert_eq!(tree.try_query(3), Err(DataStructureError::KeyNotFound));
+        assert_eq!(tree.try_query(4), Ok(Some(4)));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let xs: Vec<i64> = (-50..50).map(|x| x * 3).collect();
+        let mut tree = LiChaoTree::new_min(xs.clone());
+        let mut segments = vec![];
+        let mut s = 11i64;
+        for _ in 0..200 {
+            s = (s * 1103 + 12345) % 100_003;
+            let line = Line::new(s % 21 - 10, s % 997 - 500);
+            let (a, b) = (s % 301 - 150, (s / 301) % 301 - 150);
+            let (lo, hi) = (a.min(b), a.max(b));
+            tree.insert_segment(line, lo, hi);
+            segments.push((line, lo, hi));
+            let x = xs[(s % 100) as usize];
+            let expected = segments
+                .iter()
+                .filter(|(_, lo, hi)| (*lo..=*hi).contains(&x))
+                .map(|(l, _, _)| l.eval(x))
+                .min();
+            assert_eq!(tree.query(x), expected);
+        }
+    }
+}
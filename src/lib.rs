@@ -1,2 +1,179 @@
+//! Without the default `std` feature the crate is `no_std`. The `alloc`
+//! feature, which `std` enables, brings back everything that only needs a
+//! heap; structures built on std hash maps, locks or threads stay
+//! unavailable, and the HAMT-based maps need an explicit hasher. Without
+//! `alloc` only the fixed-capacity `array_*` structures, the comparators
+//! and the collection traits remain.
+//!
+//! The `arbitrary` feature adds proptest `Arbitrary` implementations for
+//! property testing code that uses these structures.
+//!
+//! The `rkyv` feature adds zero-copy archiving of the contiguous
+//! structures, so large read-only indexes can be used straight from a
+//! memory-mapped file.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod allocator;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+#[cfg(feature = "alloc")]
+pub mod arena;
+pub mod array_deque;
+pub mod array_heap;
+pub mod array_vec;
+#[cfg(feature = "alloc")]
+pub mod bimap;
+#[cfg(feature = "alloc")]
 pub mod binary_search_tree;
+#[cfg(feature = "alloc")]
+pub mod binary_trie;
+#[cfg(feature = "alloc")]
+pub mod bit_vec;
+#[cfg(feature = "std")]
+pub mod blocking_queue;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "alloc")]
+pub mod catenable_list;
+pub mod compare;
+#[cfg(feature = "std")]
+pub mod concurrent_hash_map;
+#[cfg(feature = "std")]
+pub mod concurrent_skip_list;
+#[cfg(feature = "alloc")]
+pub mod csr_graph;
+#[cfg(feature = "alloc")]
+pub mod dancing_links;
+#[cfg(feature = "alloc")]
+pub mod dynamic_connectivity;
+#[cfg(feature = "alloc")]
+pub mod elias_fano;
+pub mod errors;
+#[cfg(feature = "alloc")]
+pub mod euler_tour_tree;
+#[cfg(feature = "alloc")]
+pub mod event_log;
+#[cfg(feature = "alloc")]
+pub mod finger_tree;
+#[cfg(feature = "alloc")]
+pub mod frozen_map;
+#[cfg(feature = "alloc")]
+pub mod graph;
+#[cfg(feature = "alloc")]
+pub mod grid;
+#[cfg(feature = "std")]
+pub mod guarded_map;
+#[cfg(feature = "alloc")]
+pub mod hamt;
+#[cfg(feature = "alloc")]
 pub mod heap;
+#[cfg(feature = "alloc")]
+pub mod indexable_skip_list;
+#[cfg(feature = "alloc")]
+pub mod interner;
+#[cfg(feature = "alloc")]
+pub mod interval_map;
+#[cfg(feature = "alloc")]
+pub mod intrusive_list;
+#[cfg(feature = "alloc")]
+pub mod lazy_priority_queue;
+#[cfg(feature = "alloc")]
+pub mod li_chao_tree;
+#[cfg(feature = "alloc")]
+pub mod link_cut_tree;
+#[cfg(feature = "alloc")]
+pub mod louds;
+#[cfg(feature = "alloc")]
+pub mod matching;
+#[cfg(feature = "alloc")]
+pub mod memory;
+#[cfg(feature = "alloc")]
+pub mod merge;
+#[cfg(feature = "alloc")]
+pub mod merkle_tree;
+#[cfg(feature = "alloc")]
+pub mod min_max_stack;
+#[cfg(feature = "alloc")]
+pub mod mpsc_queue;
+#[cfg(feature = "alloc")]
+pub mod multimap;
+#[cfg(feature = "alloc")]
+pub mod multiset;
+#[cfg(feature = "alloc")]
+pub mod nav_tree;
+#[cfg(feature = "std")]
+pub mod object_pool;
+#[cfg(feature = "alloc")]
+pub mod order_maintenance;
+#[cfg(feature = "alloc")]
+pub mod packed_int_vec;
+#[cfg(feature = "alloc")]
+pub mod persistent_deque;
+#[cfg(feature = "alloc")]
+pub mod persistent_vector;
+#[cfg(feature = "alloc")]
+pub mod piece_table;
+#[cfg(feature = "alloc")]
+pub mod pretty;
+#[cfg(feature = "alloc")]
+pub mod priority_expiry_map;
+#[cfg(feature = "alloc")]
+pub mod radix_heap;
+#[cfg(feature = "alloc")]
+pub mod range_set;
+#[cfg(feature = "alloc")]
+pub mod real_time_queue;
+#[cfg(feature = "alloc")]
+pub mod shortest_path;
+#[cfg(feature = "alloc")]
+pub mod slab;
+#[cfg(feature = "alloc")]
+pub mod slot_map;
+#[cfg(feature = "alloc")]
+pub mod small_vec;
+#[cfg(feature = "alloc")]
+pub mod sorted_vec;
+#[cfg(feature = "alloc")]
+pub mod sparse_set;
+#[cfg(feature = "alloc")]
+pub mod splay_sequence;
+#[cfg(feature = "alloc")]
+pub mod spsc_ring_buffer;
+#[cfg(feature = "alloc")]
+pub mod sqrt_decomposition;
+#[cfg(feature = "alloc")]
+pub mod stream;
+#[cfg(feature = "alloc")]
+pub mod streaming_median;
+#[cfg(feature = "alloc")]
+pub mod timer_wheel;
+pub mod traits;
+#[cfg(feature = "alloc")]
+pub mod treiber_stack;
+#[cfg(feature = "alloc")]
+pub mod undo_stack;
+#[cfg(feature = "alloc")]
+pub mod union_find;
+#[cfg(feature = "alloc")]
+pub mod unrolled_list;
+#[cfg(feature = "alloc")]
+pub mod van_emde_boas;
+#[cfg(feature = "alloc")]
+pub mod veb_layout;
+#[cfg(feature = "alloc")]
+pub mod versioned_map;
+#[cfg(feature = "alloc")]
+pub mod wavelet_matrix;
+#[cfg(feature = "std")]
+pub mod work_stealing_deque;
+#[cfg(feature = "alloc")]
+pub mod xor_list;
+#[cfg(feature = "alloc")]
+pub mod y_fast_trie;
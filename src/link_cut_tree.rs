@@ -0,0 +1,359 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+const NIL: usize = usize::MAX;
+
+/// A forest of `n` nodes that supports adding and removing edges and
+/// aggregating values along tree paths, all in amortized O(log n).
+///
+/// This is the classic Sleator–Tarjan structure: each tree is split into
+/// preferred paths that are stored as splay trees. Edges are undirected, so
+/// `combine` must be associative and commutative (sum, min, max, ...).
+pub struct LinkCutTree<T, F> {
+    nodes: Vec<Node<T>>,
+    combine: F,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    children: [usize; 2],
+    // splay parent, or path parent if this is the root of its splay tree
+    parent: usize,
+    reversed: bool,
+    value: T,
+    aggregate: T,
+}
+
+impl<T: Clone, F: Fn(&T, &T) -> T> LinkCutTree<T, F> {
+    /// A forest of isolated nodes, one for each value.
+    pub fn new(values: impl IntoIterator<Item = T>, combine: F) -> Self {
+        let nodes = values
+            .into_iter()
+            .map(|value| Node {
+                children: [NIL, NIL],
+                parent: NIL,
+                reversed: false,
+                aggregate: value.clone(),
+                value,
+            })
+            .collect();
+        LinkCutTree { nodes, combine }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, x: usize) -> &T {
+        &self.nodes[x].value
+    }
+
+    pub fn set(&mut self, x: usize, value: T) {
+        self.access(x);
+        self.nodes[x].value = value;
+        self.update(x);
+    }
+
+    /// Connect `u` and `v` with an edge. Returns false, and changes nothing,
+    /// if they are already in the same tree.
+    pub fn link(&mut self, u: usize, v: usize) -> bool {
+        self.make_root(u);
+        if self.find_root(v) == u {
+            return false;
+        }
+        self.nodes[u].parent = v;
+        true
+    }
+
+    /// Remove the edge between `u` and `v`. Returns false if there is none.
+    pub fn cut(&mut self, u: usize, v: usize) -> bool {
+        if u == v {
+            return false;
+        }
+        self.make_root(u);
+        self.access(v);
+        // the path u..v is now v's splay tree; the edge exists iff u is the
+        // only other node on it
+        if self.nodes[v].children[0] != u || self.nodes[u].children != [NIL, NIL] {
+            return false;
+        }
+        self.nodes[v].children[0] = NIL;
+        self.nodes[u].parent = NIL;
+        self.update(v);
+        true
+    }
+
+    /// The root of the tree containing `x`.
+    ///
+    /// Trees are unrooted; the root is whichever node was made root last by
+    /// `link`, `cut` or `path_aggregate`, which is good enough to tell trees
+    /// apart.
+    pub fn find_root(&mut self, x: usize) -> usize {
+        self.access(x);
+        let mut r = x;
+        self.push(r);
+        while self.nodes[r].children[0] != NIL {
+            r = self.nodes[r].children[0];
+            self.push(r);
+        }
+        self.splay(r);
+        r
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        u == v || self.find_root(u) == self.find_root(v)
+    }
+
+    /// Combine the values of all nodes on the path from `u` to `v`, or
+    /// `None` if they are in different trees.
+    pub fn path_aggregate(&mut self, u: usize, v: usize) -> Option<T> {
+        if !self.connected(u, v) {
+            return None;
+        }
+        self.make_root(u);
+        self.access(v);
+        Some(self.nodes[v].aggregate.clone())
+    }
+
+    fn make_root(&mut self, x: usize) {
+        self.access(x);
+        self.nodes[x].reversed ^= true;
+    }
+
+    /// Make the path from the root to `x` preferred and splay `x` to the top
+    /// of its splay tree.
+    fn access(&mut self, x: usize) {
+        let mut last = NIL;
+        let mut y = x;
+        while y != NIL {
+            self.splay(y);
+            self.nodes[y].children[1] = last;
+            self.update(y);
+            last = y;
+            y = self.nodes[y].parent;
+        }
+        self.splay(x);
+    }
+
+    fn is_splay_root(&self, x: usize) -> bool {
+        let p = self.nodes[x].parent;
+        p == NIL || !self.nodes[p].children.contains(&x)
+    }
+
+    fn side(&self, x: usize) -> usize {
+        usize::from(self.nodes[self.nodes[x].parent].children[1] == x)
+    }
+
+    fn push(&mut self, x: usize) {
+        if self.nodes[x].reversed {
+            self.nodes[x].reversed = false;
+            self.nodes[x].children.swap(0, 1);
+            for c in self.nodes[x].children {
+                if c != NIL {
+                    self.nodes[c].reversed ^= true;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let [l, r] = self.nodes[x].children;
+        let mut agg = self.nodes[x].value.clone();
+        if l != NIL {
+            agg = (self.combine)(&self.nodes[l].aggregate, &agg);
+        }
+        if r != NIL {
+            agg = (self.combine)(&agg, &self.nodes[r].aggregate);
+        }
+        self.nodes[x].aggregate = agg;
+    }
+
+    fn rotate(&mut self, x: usize) {
+        let p = self.nodes[x].parent;
+        let g = self.nodes[p].parent;
+        let d = self.side(x);
+        if !self.is_splay_root(p) {
+            let pd = self.side(p);
+            self.nodes[g].children[pd] = x;
+        }
+        self.nodes[x].parent = g;
+        let b = self.nodes[x].children[d ^ 1];
+        self.nodes[p].children[d] = b;
+        if b != NIL {
+            self.nodes[b].parent = p;
+        }
+        self.nodes[x].children[d ^ 1] = p;
+        self.nodes[p].parent = x;
+        self.update(p);
+        self.update(x);
+    }
+
+    fn splay(&mut self, x: usize) {
+        // pending reversals must be applied top-down before rotating
+        let mut path = vec![x];
+        let mut y = x;
+        while !self.is_splay_root(y) {
+            y = self.nodes[y].parent;
+            path.push(y);
+        }
+        for &y in path.iter().rev() {
+            self.push(y);
+        }
+
+        while !self.is_splay_root(x) {
+            let p = self.nodes[x].parent;
+            if !self.is_splay_root(p) {
+                if self.side(x) == self.side(p) {
+                    self.rotate(p);
+                } else {
+                    self.rotate(x);
+                }
+            }
+            self.rotate(x);
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for LinkCutTree<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.nodes.iter().map(|n| &n.value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, VecDeque};
+
+    fn add(a: &i64, b: &i64) -> i64 {
+        a + b
+    }
+
+    // path sum in a forest given as an edge set, by breadth-first search
+    fn naive_path_sum(
+        n: usize,
+        edges: &BTreeSet<(usize, usize)>,
+        values: &[i64],
+        u: usize,
+        v: usize,
+    ) -> Option<i64> {
+        let mut prev = vec![NIL; n];
+        let mut seen = vec![false; n];
+        let mut queue = VecDeque::from([u]);
+        seen[u] = true;
+        while let Some(x) = queue.pop_front() {
+            for &(a, b) in edges {
+                let y = if a == x {
+                    b
+                } else if b == x {
+                    a
+                } else {
+                    continue;
+                };
+                if !seen[y] {
+                    seen[y] = true;
+                    prev[y] = x;
+                    queue.push_back(y);
+                }
+            }
+        }
+        if !seen[v] {
+            return None;
+        }
+        let mut sum = values[v];
+        let mut x = v;
+        while x != u {
+            x = prev[x];
+            sum += values[x];
+        }
+        Some(sum)
+    }
+
+    #[test]
+    fn isolated_nodes() {
+        let mut lct = LinkCutTree::new([1, 2, 3], add);
+        assert_eq!(lct.len(), 3);
+        assert!(!lct.connected(0, 1));
+        assert_eq!(lct.find_root(2), 2);
+        assert_eq!(lct.path_aggregate(1, 1), Some(2));
+        assert_eq!(lct.path_aggregate(0, 1), None);
+    }
+
+    #[test]
+    fn link_and_cut() {
+        let mut lct = LinkCutTree::new([1, 10, 100, 1000], add);
+        assert!(lct.link(0, 1));
+        assert!(lct.link(1, 2));
+        assert!(lct.link(3, 1));
+        assert!(!lct.link(0, 2));
+        assert_eq!(lct.path_aggregate(0, 2), Some(111));
+        assert_eq!(lct.path_aggregate(3, 0), Some(1011));
+        assert!(!lct.cut(0, 2));
+        assert!(lct.cut(2, 1));
+        assert!(!lct.cut(1, 2));
+        assert!(!lct.connected(0, 2));
+        assert!(lct.connected(0, 3));
+    }
+
+    #[test]
+    fn update_values() {
+        let mut lct = LinkCutTree::new([5, 3, 8], |a: &i32, b: &i32| *a.min(b));
+        lct.link(0, 1);
+        lct.link(1, 2);
+        assert_eq!(lct.path_aggregate(0, 2), Some(3));
+        lct.set(1, 9);
+        assert_eq!(*lct.get(1), 9);
+        assert_eq!(lct.path_aggregate(2, 0), Some(5));
+    }
+
+    #[test]
+    fn find_root_distinguishes_trees() {
+        let mut lct = LinkCutTree::new(vec![0i64; 6], add);
+        lct.link(0, 1);
+        lct.link(2, 1);
+        lct.link(3, 4);
+        let a = lct.find_root(0);
+        assert_eq!(lct.find_root(2), a);
+        assert_ne!(lct.find_root(4), a);
+        assert_eq!(lct.find_root(5), 5);
+    }
+
+    #[test]
+    fn matches_naive_forest() {
+        let n = 30;
+        let values: Vec<i64> = (0..n as i64).map(|i| i * i % 17).collect();
+        let mut lct = LinkCutTree::new(values.clone(), add);
+        let mut edges = BTreeSet::new();
+        let mut x = 7usize;
+        for _ in 0..2000 {
+            x = (x * 1103 + 12345) % 1_000_003;
+            let u = x % n;
+            let v = (x / n) % n;
+            if u == v {
+                continue;
+            }
+            let key = (u.min(v), u.max(v));
+            let connected = naive_path_sum(n, &edges, &values, u, v).is_some();
+            match x % 3 {
+                0 => {
+                    assert_eq!(lct.link(u, v), !connected);
+                    if !connected {
+                        edges.insert(key);
+                    }
+                }
+                1 => assert_eq!(lct.cut(u, v), edges.remove(&key)),
+                _ => assert_eq!(
+                    lct.path_aggregate(u, v),
+                    naive_path_sum(n, &edges, &values, u, v)
+                ),
+            }
+        }
+    }
+}
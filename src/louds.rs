@@ -0,0 +1,227 @@
+//! Level-order unary degree sequence (LOUDS): a read-only tree in about
+//! two bits per node plus its labels.
+//!
+//! Nodes are numbered in level order, the root being 0, so the children of
+//! a node have consecutive numbers. The shape is stored as a bit string
+//! `10` followed by `1...10` for every node, with one `1` per child.
+//! Navigation uses rank and select on that string.
+
+use crate::bit_vec::{BitVec, RankSelect};
+use crate::memory::{MemoryFootprint, MemoryStats};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::ops::Range;
+
+#[derive(Debug, Clone)]
+pub struct Louds<T> {
+    bits: RankSelect,
+    labels: Vec<T>,
+}
+
+impl<T> Louds<T> {
+    /// Encode the tree below `root`. `children` lists the children of a
+    /// node in order, and `label` extracts what is kept of each node.
+    pub fn from_tree<N>(root: N, label: impl Fn(&N) -> T, children: impl Fn(&N) -> Vec<N>) -> Self {
+        let mut bits = BitVec::new();
+        bits.push(true);
+        bits.push(false);
+        let mut labels = Vec::new();
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            labels.push(label(&node));
+            for child in children(&node) {
+                bits.push(true);
+                queue.push_back(child);
+            }
+            bits.push(false);
+        }
+        labels.shrink_to_fit();
+        Louds {
+            bits: RankSelect::new(bits),
+            labels,
+        }
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Always false; a tree has at least its root.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn label(&self, node: usize) -> Option<&T> {
+        self.labels.get(node)
+    }
+
+    /// Labels of all nodes in level order.
+    pub fn labels(&self) -> &[T] {
+        &self.labels
+    }
+
+    pub fn parent(&self, node: usize) -> Option<usize> {
+        if node == 0 || node >= self.len() {
+            return None;
+        }
+        Some(self.bits.rank0(self.bits.select1(node)?) - 1)
+    }
+
+    /// The range of node numbers of the children of `node`.
+    pub fn children(&self, node: usize) -> Range<usize> {
+        if node >= self.len() {
+            return 0..0;
+        }
+        let start = self.bits.select0(node).unwrap() + 1;
+        let end = self.bits.select0(node + 1).unwrap();
+        let first = self.bits.rank1(start);
+        first..first + (end - start)
+    }
+
+    pub fn degree(&self, node: usize) -> usize {
+        self.children(node).len()
+    }
+
+    pub fn child(&self, node: usize, index: usize) -> Option<usize> {
+        let children = self.children(node);
+        (index < children.len()).then(|| children.start + index)
+    }
+
+    pub fn is_leaf(&self, node: usize) -> bool {
+        self.degree(node) == 0
+    }
+
+    /// The nodes on the path from the root to `node`.
+    pub fn path_to(&self, mut node: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        if node >= self.len() {
+            return path;
+        }
+        path.push(node);
+        while let Some(parent) = self.parent(node) {
+            path.push(parent);
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl<T> MemoryFootprint for Louds<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        let shape = self.bits.memory_stats();
+        MemoryStats {
+            nodes: shape.nodes + 1,
+            heap_bytes: shape.heap_bytes + self.labels.capacity() * size_of::<T>(),
+            depth: 1,
+            used_slots: self.labels.len(),
+            total_slots: self.labels.capacity(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_search_tree::BinarySearchTree;
+    use alloc::vec;
+
+    struct Tree(char, Vec<Tree>);
+
+    fn leaf(c: char) -> Tree {
+        Tree(c, vec![])
+    }
+
+    fn encode(tree: &Tree) -> Louds<char> {
+        Louds::from_tree(tree, |t| t.0, |t| t.1.iter().collect())
+    }
+
+    #[test]
+    fn navigation() {
+        //      a
+        //    / | \
+        //   b  c  d
+        //  / \    |
+        // e   f   g
+        let tree = Tree(
+            'a',
+            vec![
+                Tree('b', vec![leaf('e'), leaf('f')]),
+                leaf('c'),
+                Tree('d', vec![leaf('g')]),
+            ],
+        );
+        let louds = encode(&tree);
+        assert_eq!(louds.len(), 7);
+        assert_eq!(louds.labels(), &['a', 'b', 'c', 'd', 'e', 'f', 'g']);
+        assert_eq!(louds.children(0), 1..4);
+        assert_eq!(louds.children(1), 4..6);
+        assert_eq!(louds.children(2), 6..6);
+        assert_eq!(louds.children(3), 6..7);
+        assert_eq!(louds.child(3, 0), Some(6));
+        assert_eq!(louds.child(3, 1), None);
+        assert_eq!(louds.parent(0), None);
+        assert_eq!(
+            (1..7).map(|n| louds.parent(n).unwrap()).collect::<Vec<_>>(),
+            vec![0, 0, 0, 1, 1, 3]
+        );
+        assert!(louds.is_leaf(5) && !louds.is_leaf(1));
+        assert_eq!(louds.path_to(6), vec![0, 3, 6]);
+        assert_eq!(louds.label(6), Some(&'g'));
+    }
+
+    #[test]
+    fn single_node() {
+        let louds = encode(&leaf('x'));
+        assert_eq!(louds.len(), 1);
+        assert!(!louds.is_empty());
+        assert_eq!(louds.degree(0), 0);
+        assert_eq!(louds.parent(0), None);
+        assert_eq!(louds.parent(1), None);
+        assert_eq!(louds.path_to(0), vec![0]);
+    }
+
+    #[test]
+    fn large_tree_matches_source() {
+        // node i has children 4i+1..=4i+4, truncated at 5000 nodes
+        let n = 5000;
+        let louds = Louds::from_tree(
+            0usize,
+            |&i| i,
+            |&i| (4 * i + 1..(4 * i + 5).min(n)).collect(),
+        );
+        assert_eq!(louds.len(), n);
+        for i in 0..n {
+            assert_eq!(louds.label(i), Some(&i));
+            let expected = (4 * i + 1).min(n)..(4 * i + 5).min(n);
+            assert_eq!(louds.children(i), expected);
+            assert_eq!(louds.parent(i), i.checked_sub(1).map(|j| j / 4));
+        }
+        let stats = louds.memory_stats();
+        // the shape takes well under a byte per node
+        assert!(stats.heap_bytes - n * size_of::<usize>() < n);
+    }
+
+    #[test]
+    fn from_binary_search_tree() {
+        let tree = [5, 3, 8, 1, 4, 9]
+            .into_iter()
+            .fold(BinarySearchTree::new(), |t, x| t.insert(x));
+        let louds = Louds::from_tree(
+            tree,
+            |t| *t.item().unwrap(),
+            |t| {
+                [t.left().unwrap(), t.right().unwrap()]
+                    .into_iter()
+                    .filter(|c| !c.is_empty())
+                    .cloned()
+                    .collect()
+            },
+        );
+        assert_eq!(louds.labels(), &[5, 3, 8, 1, 4, 9]);
+        assert_eq!(louds.children(2), 5..6);
+        assert_eq!(louds.path_to(4), vec![0, 1, 4]);
+    }
+}
@@ -0,0 +1,222 @@
+//! Maximum matching in bipartite graphs with the Hopcroft–Karp algorithm.
+//!
+//! Each phase finds, with one breadth-first search, the length of the
+//! shortest augmenting paths and then flips a maximal set of disjoint paths
+//! of that length. There are at most O(√n) phases, for O(e·√n) in total.
+
+use crate::graph::Graph;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const NONE: usize = usize::MAX;
+
+/// A maximum matching between the nodes in `left` and the other nodes of
+/// `graph`, as `(left, right)` pairs sorted by the left node.
+///
+/// Only edges leaving a left node and entering a right node are used, so an
+/// undirected graph stored with edges in both directions works as well.
+pub fn hopcroft_karp<G: Graph>(
+    graph: &G,
+    left: impl IntoIterator<Item = usize>,
+) -> Vec<(usize, usize)> {
+    let n = graph.node_count();
+    let mut is_left = vec![false; n];
+    let left: Vec<usize> = left
+        .into_iter()
+        .filter(|&u| !core::mem::replace(&mut is_left[u], true))
+        .collect();
+    let adjacency: Vec<Vec<usize>> = (0..n)
+        .map(|u| match is_left[u] {
+            true => graph
+                .neighbors(u)
+                .map(|(v, _)| v)
+                .filter(|&v| !is_left[v])
+                .collect(),
+            false => Vec::new(),
+        })
+        .collect();
+
+    let mut mate = vec![NONE; n];
+    let mut layer = vec![NONE; n];
+    let mut next_edge = vec![0; n];
+    while bfs_layers(&left, &adjacency, &mate, &mut layer) {
+        next_edge.fill(0);
+        for &u in &left {
+            if mate[u] == NONE {
+                augment(u, &adjacency, &mut mate, &mut layer, &mut next_edge);
+            }
+        }
+    }
+
+    let mut matching: Vec<_> = left
+        .into_iter()
+        .filter(|&u| mate[u] != NONE)
+        .map(|u| (u, mate[u]))
+        .collect();
+    matching.sort_unstable();
+    matching
+}
+
+/// Number the left nodes by their distance from a free left node along
+/// alternating paths. Returns whether a free right node is reachable.
+fn bfs_layers(
+    left: &[usize],
+    adjacency: &[Vec<usize>],
+    mate: &[usize],
+    layer: &mut [usize],
+) -> bool {
+    let mut queue = VecDeque::new();
+    for &u in left {
+        layer[u] = if mate[u] == NONE {
+            queue.push_back(u);
+            0
+        } else {
+            NONE
+        };
+    }
+    let mut found = false;
+    while let Some(u) = queue.pop_front() {
+        for &v in &adjacency[u] {
+            match mate[v] {
+                NONE => found = true,
+                w if layer[w] == NONE => {
+                    layer[w] = layer[u] + 1;
+                    queue.push_back(w);
+                }
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+/// Search for an augmenting path from the free left node `root` that
+/// follows the layers, and flip it if there is one.
+fn augment(
+    root: usize,
+    adjacency: &[Vec<usize>],
+    mate: &mut [usize],
+    layer: &mut [usize],
+    next_edge: &mut [usize],
+) -> bool {
+    let mut stack = vec![root];
+    while let Some(&u) = stack.last() {
+        let Some(&v) = adjacency[u].get(next_edge[u]) else {
+            // dead end: no later search in this phase needs to come here
+            layer[u] = NONE;
+            stack.pop();
+            continue;
+        };
+        next_edge[u] += 1;
+        match mate[v] {
+            NONE => {
+                // every left node on the stack takes the right node chosen
+                // below it, releasing the one it had to its parent
+                let mut v = v;
+                for &u in stack.iter().rev() {
+                    let previous = mate[u];
+                    mate[u] = v;
+                    mate[v] = u;
+                    v = previous;
+                }
+                return true;
+            }
+            w if layer[w] == layer[u] + 1 => stack.push(w),
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{AdjacencyList, AdjacencyMatrix, GraphMut};
+
+    fn graph(nodes: usize, edges: &[(usize, usize)]) -> AdjacencyList<()> {
+        let mut g = AdjacencyList::new(nodes);
+        for &(u, v) in edges {
+            g.add_edge(u, v, ());
+        }
+        g
+    }
+
+    fn is_matching(g: &impl Graph, matching: &[(usize, usize)]) -> bool {
+        let mut used = Vec::new();
+        matching.iter().all(|&(u, v)| {
+            let fresh = !used.contains(&u) && !used.contains(&v);
+            used.extend([u, v]);
+            fresh && g.has_edge(u, v)
+        })
+    }
+
+    #[test]
+    fn perfect_matching_needs_augmenting_paths() {
+        // greedily matching 0-3 and 1-4 blocks 2, which only knows 3
+        let g = graph(6, &[(0, 3), (0, 4), (1, 4), (1, 5), (2, 3)]);
+        let matching = hopcroft_karp(&g, [0, 1, 2]);
+        assert_eq!(matching, vec![(0, 4), (1, 5), (2, 3)]);
+    }
+
+    #[test]
+    fn maximum_but_not_perfect() {
+        // workers 0..3 can only do jobs 4 and 5
+        let g = graph(6, &[(0, 4), (1, 4), (1, 5), (2, 5), (3, 4)]);
+        let matching = hopcroft_karp(&g, 0..4);
+        assert_eq!(matching.len(), 2);
+        assert!(is_matching(&g, &matching));
+        assert!(hopcroft_karp(&g, []).is_empty());
+    }
+
+    #[test]
+    fn undirected_edges_and_matrix_graphs() {
+        let mut g = AdjacencyMatrix::new(4);
+        for (u, v) in [(0, 2), (1, 2), (1, 3)] {
+            g.add_edge(u, v, 1);
+            g.add_edge(v, u, 1);
+        }
+        // edges between left nodes are ignored
+        g.add_edge(0, 1, 1);
+        assert_eq!(hopcroft_karp(&g, [1, 0]), vec![(0, 2), (1, 3)]);
+        // the bipartition may be given from either side
+        assert_eq!(hopcroft_karp(&g, [2, 3]), vec![(2, 0), (3, 1)]);
+    }
+
+    #[test]
+    fn matches_brute_force_on_random_graphs() {
+        fn brute_force(adjacency: &[Vec<usize>], u: usize, used: &mut Vec<usize>) -> usize {
+            if u == adjacency.len() {
+                return 0;
+            }
+            let mut best = brute_force(adjacency, u + 1, used);
+            for &v in &adjacency[u] {
+                if !used.contains(&v) {
+                    used.push(v);
+                    best = best.max(1 + brute_force(adjacency, u + 1, used));
+                    used.pop();
+                }
+            }
+            best
+        }
+
+        let mut x: u32 = 7;
+        for _ in 0..50 {
+            let mut edges = Vec::new();
+            let mut adjacency = vec![Vec::new(); 6];
+            for (u, targets) in adjacency.iter_mut().enumerate() {
+                for v in 6..12 {
+                    x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                    if (x >> 16).is_multiple_of(4) {
+                        edges.push((u, v));
+                        targets.push(v);
+                    }
+                }
+            }
+            let g = graph(12, &edges);
+            let matching = hopcroft_karp(&g, 0..6);
+            assert!(is_matching(&g, &matching));
+            assert_eq!(matching.len(), brute_force(&adjacency, 0, &mut Vec::new()));
+        }
+    }
+}
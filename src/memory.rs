@@ -0,0 +1,181 @@
+//! Estimates of how much memory a structure uses and how it is shaped, for
+//! comparing structures empirically.
+
+use alloc::collections::BTreeSet;
+use core::mem::size_of;
+
+/// Size and shape of a structure, as reported by `MemoryFootprint`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Separately allocated nodes or buffers.
+    pub nodes: usize,
+    /// Estimated bytes allocated by the structure itself. Memory owned by
+    /// the items, like the contents of a `String`, is not included.
+    pub heap_bytes: usize,
+    /// Number of nodes on the longest path from the root; 1 for flat
+    /// structures with an allocated buffer.
+    pub depth: usize,
+    /// Item (or child) slots that are in use.
+    pub used_slots: usize,
+    /// Item (or child) slots that are allocated.
+    pub total_slots: usize,
+}
+
+impl MemoryStats {
+    /// Fraction of the allocated slots that are in use.
+    pub fn fill_factor(&self) -> f64 {
+        if self.total_slots == 0 {
+            return 1.0;
+        }
+        self.used_slots as f64 / self.total_slots as f64
+    }
+}
+
+pub trait MemoryFootprint {
+    fn memory_stats(&self) -> MemoryStats;
+}
+
+/// How many nodes of one version of a persistent structure are shared with
+/// another version. Sharing can be broken on purpose with `clone_deep`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SharingStats {
+    pub shared: usize,
+    pub unique: usize,
+}
+
+pub trait SharedFootprint {
+    /// Count the nodes of `self` that `other` also uses.
+    fn shares_structure_with(&self, other: &Self) -> SharingStats;
+}
+
+/// Estimated size of an `Rc` allocation holding a `T`.
+pub(crate) fn rc_bytes<T>() -> usize {
+    size_of::<T>() + 2 * size_of::<usize>()
+}
+
+/// Stats of a structure backed by a single buffer.
+pub(crate) fn buffer_stats<T>(len: usize, capacity: usize) -> MemoryStats {
+    let allocated = capacity > 0 && size_of::<T>() > 0;
+    MemoryStats {
+        nodes: allocated as usize,
+        heap_bytes: if allocated {
+            capacity * size_of::<T>()
+        } else {
+            0
+        },
+        depth: allocated as usize,
+        used_slots: len,
+        total_slots: capacity,
+    }
+}
+
+/// Compare two sets of node addresses.
+pub(crate) fn compare_nodes(
+    mine: impl Iterator<Item = usize>,
+    theirs: impl Iterator<Item = usize>,
+) -> SharingStats {
+    let theirs: BTreeSet<usize> = theirs.collect();
+    let mut sharing = SharingStats::default();
+    for node in mine.collect::<BTreeSet<_>>() {
+        if theirs.contains(&node) {
+            sharing.shared += 1;
+        } else {
+            sharing.unique += 1;
+        }
+    }
+    sharing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_search_tree::BinarySearchTree;
+    use crate::heap::Heap;
+    use crate::persistent_vector::PVec;
+    use crate::sorted_vec::SortedVec;
+    use crate::unrolled_list::UnrolledList;
+
+    #[test]
+    fn flat_buffers() {
+        let heap = Heap::from_vec(Vec::with_capacity(8));
+        assert_eq!(heap.memory_stats().nodes, 1);
+        let mut heap = heap;
+        heap.push(1u64);
+        heap.push(2);
+        let stats = heap.memory_stats();
+        assert_eq!((stats.heap_bytes, stats.depth), (64, 1));
+        assert_eq!(stats.fill_factor(), 0.25);
+
+        let empty = SortedVec::<u32>::new().memory_stats();
+        assert_eq!(empty, MemoryStats::default());
+    }
+
+    #[test]
+    fn linked_nodes() {
+        let list: UnrolledList<u8> = (0..100).collect();
+        let stats = list.memory_stats();
+        assert_eq!(stats.nodes, list.node_count());
+        assert_eq!(stats.used_slots, 100);
+        assert!(stats.fill_factor() > 0.5);
+
+        let tree = [4, 2, 6, 1, 3, 5, 7]
+            .into_iter()
+            .fold(BinarySearchTree::new(), |t, x| t.insert(x));
+        let stats = tree.memory_stats();
+        assert_eq!((stats.nodes, stats.depth), (7, 3));
+        assert_eq!(stats.fill_factor(), 1.0);
+    }
+
+    #[test]
+    fn versions_share_nodes() {
+        let a = [4, 2, 6, 1, 3, 5, 7]
+            .into_iter()
+            .fold(BinarySearchTree::new(), |t, x| t.insert(x));
+        // inserting 8 copies the path 4 -> 6 -> 7 and adds a node
+        let b = a.insert(8);
+        assert_eq!(
+            b.shares_structure_with(&a),
+            SharingStats {
+                shared: 4,
+                unique: 4
+            }
+        );
+        assert_eq!(
+            a.shares_structure_with(&b),
+            SharingStats {
+                shared: 4,
+                unique: 3
+            }
+        );
+        assert_eq!(a.shares_structure_with(&a.clone()).unique, 0);
+        let copy = a.clone_deep();
+        assert_eq!(copy, a);
+        assert_eq!(copy.shares_structure_with(&a).shared, 0);
+
+        let v: PVec<u32> = (0..1000).collect();
+        let w = v.update(0, 42);
+        let s = w.shares_structure_with(&v);
+        assert!(s.shared > 25 && s.unique <= 3);
+        let copy = w.clone_deep();
+        assert_eq!(copy.shares_structure_with(&w).shared, 0);
+        assert_eq!(copy.get(0), Some(&42));
+        assert_eq!(copy.len(), 1000);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hash_map_versions_share_nodes() {
+        use crate::hamt::PHashMap;
+        let m: PHashMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+        let n = m.insert(5, 0);
+        let s = n.shares_structure_with(&m);
+        // only the path to the key is copied
+        assert!(s.shared > 10 && s.unique <= n.memory_stats().depth);
+        let copy = n.clone_deep();
+        assert_eq!(copy.shares_structure_with(&n).shared, 0);
+        assert_eq!(copy, n);
+        let stats = m.memory_stats();
+        assert!(stats.heap_bytes > 1000 * 8);
+        assert!(stats.depth >= 2 && stats.fill_factor() < 1.0);
+    }
+}
@@ -0,0 +1,188 @@
+//! Merging any number of sorted iterators into one sorted stream.
+//!
+//! The current head of every source is kept in a `Heap`, so each item costs
+//! O(log k) for k sources. Equal items are yielded in the order of the
+//! sources that produced them, which makes `merge_dedup` keep the item from
+//! the first source - handy for LSM-style compaction, where the newest run
+//! comes first.
+
+use crate::compare::Reverse;
+use crate::heap::Heap;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+/// Iterator returned by `merge`.
+pub struct Merge<I: Iterator> {
+    sources: Vec<I>,
+    heads: Heap<(I::Item, usize), Reverse>,
+}
+
+/// Iterator returned by `merge_dedup`.
+pub struct MergeDedup<I: Iterator> {
+    inner: Merge<I>,
+}
+
+/// Merge sorted sources into one sorted iterator. Each source must yield
+/// its items in ascending order.
+pub fn merge<S>(sources: S) -> Merge<<S::Item as IntoIterator>::IntoIter>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator,
+    <S::Item as IntoIterator>::Item: Ord,
+{
+    let mut sources: Vec<_> = sources.into_iter().map(IntoIterator::into_iter).collect();
+    let mut heads = Heap::with_comparator(Reverse::default());
+    for (i, source) in sources.iter_mut().enumerate() {
+        if let Some(item) = source.next() {
+            heads.push((item, i));
+        }
+    }
+    Merge { sources, heads }
+}
+
+/// Like `merge`, but equal items are yielded only once. Of several equal
+/// items, the one from the earliest source is kept.
+pub fn merge_dedup<S>(sources: S) -> MergeDedup<<S::Item as IntoIterator>::IntoIter>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator,
+    <S::Item as IntoIterator>::Item: Ord,
+{
+    MergeDedup {
+        inner: merge(sources),
+    }
+}
+
+impl<I: Iterator> Merge<I>
+where
+    I::Item: Ord,
+{
+    fn pop(&mut self) -> Option<(I::Item, usize)> {
+        let (item, i) = self.heads.pop()?;
+        if let Some(next) = self.sources[i].next() {
+            self.heads.push((next, i));
+        }
+        Some((item, i))
+    }
+}
+
+impl<I: Iterator> Iterator for Merge<I>
+where
+    I::Item: Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop().map(|(item, _)| item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sources
+            .iter()
+            .fold((self.heads.len(), Some(self.heads.len())), |(lo, hi), s| {
+                let (l, h) = s.size_hint();
+                (
+                    lo.saturating_add(l),
+                    hi.zip(h).and_then(|(a, b)| a.checked_add(b)),
+                )
+            })
+    }
+}
+
+impl<I: Iterator> FusedIterator for Merge<I> where I::Item: Ord {}
+
+impl<I: Iterator> Iterator for MergeDedup<I>
+where
+    I::Item: Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (item, _) = self.inner.pop()?;
+        while self
+            .inner
+            .heads
+            .peek()
+            .is_some_and(|(head, _)| head.cmp(&item).is_eq())
+        {
+            self.inner.pop();
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.inner.size_hint();
+        (lo.min(1), hi)
+    }
+}
+
+impl<I: Iterator> FusedIterator for MergeDedup<I> where I::Item: Ord {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::SortedVec;
+    use alloc::vec;
+    use core::cmp::Ordering;
+
+    /// An item that compares by key only, to tell equal items apart.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Tagged(u32, &'static str);
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn merges_sorted_sources() {
+        let merged: Vec<_> = merge(vec![vec![1, 4, 7], vec![2, 5, 8], vec![0, 3, 6, 9]]).collect();
+        assert_eq!(merged, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_and_uneven_sources() {
+        assert_eq!(merge(Vec::<Vec<u8>>::new()).next(), None);
+        let merged: Vec<_> = merge([vec![], vec![5], vec![], vec![1, 2, 3, 4]]).collect();
+        assert_eq!(merged, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn equal_items_keep_source_order() {
+        let merged: Vec<_> = merge([
+            vec![Tagged(1, "a"), Tagged(2, "a")],
+            vec![Tagged(1, "b")],
+            vec![Tagged(1, "c")],
+        ])
+        .collect();
+        let tags: Vec<_> = merged.iter().map(|t| t.1).collect();
+        assert_eq!(tags, vec!["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn dedup_keeps_first_source() {
+        let merged: Vec<_> = merge_dedup([vec![1, 1, 3], vec![1, 2, 3], vec![3, 4]]).collect();
+        assert_eq!(merged, vec![1, 2, 3, 4]);
+
+        let newest = vec![Tagged(1, "new"), Tagged(3, "new")];
+        let oldest = vec![Tagged(1, "old"), Tagged(2, "old"), Tagged(3, "old")];
+        let compacted: Vec<_> = merge_dedup([newest, oldest]).map(|t| t.1).collect();
+        assert_eq!(compacted, vec!["new", "old", "new"]);
+    }
+
+    #[test]
+    fn merging_collection_iterators() {
+        let a: SortedVec<_> = [5, 1, 9].into_iter().collect();
+        let b: SortedVec<_> = [4, 8, 2].into_iter().collect();
+        let merged = merge([a.iter(), b.iter()]);
+        assert_eq!(merged.size_hint(), (6, Some(6)));
+        assert_eq!(merged.copied().collect::<Vec<_>>(), vec![1, 2, 4, 5, 8, 9]);
+    }
+}
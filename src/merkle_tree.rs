@@ -0,0 +1,243 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use core::hash::Hasher;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
+
+/// Hash function used to build a Merkle tree. Leaves and inner nodes are
+/// hashed by separate functions so that a leaf can never be passed off as an
+/// inner node.
+pub trait MerkleHasher {
+    type Digest: Clone + PartialEq + Debug;
+    fn hash_leaf(data: &[u8]) -> Self::Digest;
+    fn hash_nodes(left: &Self::Digest, right: &Self::Digest) -> Self::Digest;
+}
+
+/// A fast but *not* cryptographically secure hasher based on the std SipHash
+/// implementation. Plug in a cryptographic hash if tamper evidence against
+/// an adversary is required.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct SipHasher;
+
+#[cfg(feature = "std")]
+impl MerkleHasher for SipHasher {
+    type Digest = u64;
+
+    fn hash_leaf(data: &[u8]) -> u64 {
+        let mut h = DefaultHasher::new();
+        h.write_u8(0);
+        h.write(data);
+        h.finish()
+    }
+
+    fn hash_nodes(left: &u64, right: &u64) -> u64 {
+        let mut h = DefaultHasher::new();
+        h.write_u8(1);
+        h.write_u64(*left);
+        h.write_u64(*right);
+        h.finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofStep<D> {
+    pub sibling: D,
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InclusionProof<D> {
+    pub index: usize,
+    pub steps: Vec<ProofStep<D>>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H: MerkleHasher = SipHasher> {
+    // levels[0] holds the leaf hashes, the last level holds the root
+    levels: Vec<Vec<H::Digest>>,
+    _hasher: PhantomData<H>,
+}
+
+// without std there is no default hasher
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H: MerkleHasher> {
+    levels: Vec<Vec<H::Digest>>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    pub fn from_leaves<I>(leaves: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let hashes: Vec<_> = leaves
+            .into_iter()
+            .map(|data| H::hash_leaf(data.as_ref()))
+            .collect();
+        let mut levels = vec![hashes];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => H::hash_nodes(left, right),
+                    // an unpaired node is promoted unchanged
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree {
+            levels,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn root(&self) -> Option<&H::Digest> {
+        self.levels.last().unwrap().first()
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Option<&H::Digest> {
+        self.levels[0].get(index)
+    }
+
+    /// Replace the data of one leaf and recompute the hashes on its path.
+    pub fn update_leaf(&mut self, index: usize, data: &[u8]) {
+        assert!(index < self.len(), "leaf index out of bounds");
+        self.levels[0][index] = H::hash_leaf(data);
+        let mut idx = index;
+        for level in 1..self.levels.len() {
+            let parent = idx / 2;
+            let below = &self.levels[level - 1];
+            let hash = match below.get(parent * 2 + 1) {
+                Some(right) => H::hash_nodes(&below[parent * 2], right),
+                None => below[parent * 2].clone(),
+            };
+            self.levels[level][parent] = hash;
+            idx = parent;
+        }
+    }
+
+    pub fn proof(&self, index: usize) -> Option<InclusionProof<H::Digest>> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut steps = vec![];
+        let mut idx = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = idx ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                let side = if sibling < idx {
+                    Side::Left
+                } else {
+                    Side::Right
+                };
+                steps.push(ProofStep {
+                    sibling: hash.clone(),
+                    side,
+                });
+            }
+            idx /= 2;
+        }
+        Some(InclusionProof { index, steps })
+    }
+
+    pub fn verify(root: &H::Digest, data: &[u8], proof: &InclusionProof<H::Digest>) -> bool {
+        let hash = proof
+            .steps
+            .iter()
+            .fold(H::hash_leaf(data), |hash, step| match step.side {
+                Side::Left => H::hash_nodes(&step.sibling, &hash),
+                Side::Right => H::hash_nodes(&hash, &step.sibling),
+            });
+        hash == *root
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Transparent "hash" that makes the tree structure visible.
+    struct Concat;
+
+    impl MerkleHasher for Concat {
+        type Digest = String;
+        fn hash_leaf(data: &[u8]) -> String {
+            String::from_utf8(data.to_vec()).unwrap()
+        }
+        fn hash_nodes(left: &String, right: &String) -> String {
+            format!("({}{})", left, right)
+        }
+    }
+
+    #[test]
+    fn empty_tree_has_no_root() {
+        let tree = MerkleTree::<SipHasher>::from_leaves(Vec::<Vec<u8>>::new());
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), None);
+        assert!(tree.proof(0).is_none());
+    }
+
+    #[test]
+    fn tree_shape() {
+        let tree = MerkleTree::<Concat>::from_leaves(["a", "b", "c", "d", "e"]);
+        assert_eq!(tree.root().unwrap(), "(((ab)(cd))e)");
+        let tree = MerkleTree::<Concat>::from_leaves(["a"]);
+        assert_eq!(tree.root().unwrap(), "a");
+    }
+
+    #[test]
+    fn proofs_verify_for_all_leaves() {
+        let leaves: Vec<_> = (0..13u8).map(|i| vec![i; 3]).collect();
+        let tree = MerkleTree::<SipHasher>::from_leaves(&leaves);
+        let root = *tree.root().unwrap();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(i).unwrap();
+            assert!(MerkleTree::<SipHasher>::verify(&root, leaf, &proof));
+        }
+    }
+
+    #[test]
+    fn tampered_data_fails_verification() {
+        let tree = MerkleTree::<SipHasher>::from_leaves(["x", "y", "z"]);
+        let root = *tree.root().unwrap();
+        let proof = tree.proof(1).unwrap();
+        assert!(!MerkleTree::<SipHasher>::verify(&root, b"Y", &proof));
+        assert!(!MerkleTree::<SipHasher>::verify(&root, b"x", &proof));
+    }
+
+    #[test]
+    fn update_leaf_changes_root() {
+        let mut tree = MerkleTree::<Concat>::from_leaves(["a", "b", "c"]);
+        tree.update_leaf(2, b"C");
+        assert_eq!(tree.root().unwrap(), "((ab)C)");
+        tree.update_leaf(0, b"A");
+        assert_eq!(tree.root().unwrap(), "((Ab)C)");
+        let rebuilt = MerkleTree::<Concat>::from_leaves(["A", "b", "C"]);
+        assert_eq!(tree.root(), rebuilt.root());
+    }
+}
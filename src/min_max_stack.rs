@@ -0,0 +1,170 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A stack that can report its minimum and maximum item in O(1).
+///
+/// Every entry remembers the positions of the minimum and maximum of the
+/// stack up to and including itself, so popping never requires a search.
+#[derive(Clone)]
+pub struct MinMaxStack<T> {
+    items: Vec<Entry<T>>,
+}
+
+#[derive(Clone)]
+struct Entry<T> {
+    value: T,
+    min: usize,
+    max: usize,
+}
+
+impl<T: Ord> MinMaxStack<T> {
+    pub fn new() -> Self {
+        MinMaxStack { items: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn push(&mut self, value: T) {
+        let index = self.items.len();
+        let (min, max) = match self.items.last() {
+            None => (index, index),
+            Some(top) => {
+                // ties go to the older item so it stays reported after the
+                // newer one is popped
+                let min = if value < self.items[top.min].value {
+                    index
+                } else {
+                    top.min
+                };
+                let max = if value > self.items[top.max].value {
+                    index
+                } else {
+                    top.max
+                };
+                (min, max)
+            }
+        };
+        self.items.push(Entry { value, min, max });
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.items.pop().map(|e| e.value)
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.items.last().map(|e| &e.value)
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        let top = self.items.last()?;
+        Some(&self.items[top.min].value)
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        let top = self.items.last()?;
+        Some(&self.items[top.max].value)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Items from bottom to top.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.items.iter().map(|e| &e.value)
+    }
+}
+
+impl<T: Ord> Default for MinMaxStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MinMaxStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.items.iter().map(|e| &e.value))
+            .finish()
+    }
+}
+
+impl<T: Ord> Extend<T> for MinMaxStack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for MinMaxStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = MinMaxStack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_stack() {
+        let stack = MinMaxStack::<i32>::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.min(), None);
+        assert_eq!(stack.max(), None);
+        assert_eq!(stack.peek(), None);
+    }
+
+    #[test]
+    fn min_and_max_follow_pops() {
+        let mut stack: MinMaxStack<_> = [5, 3, 8, 1, 9].into_iter().collect();
+        assert_eq!((stack.min(), stack.max()), (Some(&1), Some(&9)));
+        stack.pop();
+        assert_eq!((stack.min(), stack.max()), (Some(&1), Some(&8)));
+        stack.pop();
+        assert_eq!((stack.min(), stack.max()), (Some(&3), Some(&8)));
+        stack.pop();
+        assert_eq!((stack.min(), stack.max()), (Some(&3), Some(&5)));
+        assert_eq!(stack.peek(), Some(&3));
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut stack: MinMaxStack<_> = [2, 1, 1, 3, 3].into_iter().collect();
+        stack.pop();
+        assert_eq!(stack.max(), Some(&3));
+        stack.pop();
+        stack.pop();
+        assert_eq!(stack.min(), Some(&1));
+        assert_eq!(stack.max(), Some(&2));
+    }
+
+    #[test]
+    fn matches_naive_scan() {
+        let mut stack = MinMaxStack::new();
+        let mut reference = vec![];
+        let mut x = 17u32;
+        for _ in 0..1000 {
+            x = (x * 1103 + 12345) % 1000;
+            if x.is_multiple_of(3) {
+                assert_eq!(stack.pop(), reference.pop());
+            } else {
+                stack.push(x);
+                reference.push(x);
+            }
+            assert_eq!(stack.min(), reference.iter().min());
+            assert_eq!(stack.max(), reference.iter().max());
+        }
+        assert!(stack.iter().eq(reference.iter()));
+    }
+}
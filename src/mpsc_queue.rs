@@ -0,0 +1,224 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Create an unbounded multi-producer single-consumer queue.
+///
+/// The `Producer` can be cloned and sent to any number of threads; there is
+/// exactly one `Consumer`.
+pub fn queue<T>() -> (Producer<T>, Consumer<T>) {
+    let inner = Arc::new(Inner::new());
+    (
+        Producer {
+            inner: inner.clone(),
+        },
+        Consumer { inner },
+    )
+}
+
+pub struct Producer<T> {
+    inner: Arc<Inner<T>>,
+}
+
+pub struct Consumer<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Vyukov's intrusive MPSC queue. Producers swap themselves into `head`, the
+/// consumer follows `next` pointers from `tail`, which always points to a stub
+/// node whose value has already been taken.
+struct Inner<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: UnsafeCell<*mut Node<T>>,
+}
+
+struct Node<T> {
+    value: Option<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Node<T> {
+    fn alloc(value: Option<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+impl<T> Inner<T> {
+    fn new() -> Self {
+        let stub = Node::alloc(None);
+        Inner {
+            head: AtomicPtr::new(stub),
+            tail: UnsafeCell::new(stub),
+        }
+    }
+
+    fn push(&self, value: T) {
+        let node = Node::alloc(Some(value));
+        let prev = self.head.swap(node, Ordering::AcqRel);
+        // SAFETY: `prev` is only freed by the consumer after it has seen this
+        // store, so it is still alive
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+
+    /// SAFETY: must only be called by the single consumer.
+    unsafe fn pop(&self) -> Option<T> {
+        let tail = *self.tail.get();
+        loop {
+            let next = (*tail).next.load(Ordering::Acquire);
+            if !next.is_null() {
+                *self.tail.get() = next;
+                drop(Box::from_raw(tail));
+                return (*next).value.take();
+            }
+            if self.head.load(Ordering::Acquire) == tail {
+                return None;
+            }
+            // a producer has swapped `head` but not linked its node yet
+            hint::spin_loop();
+        }
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let mut node = *self.tail.get_mut();
+        while !node.is_null() {
+            // SAFETY: no other references exist any more
+            let boxed = unsafe { Box::from_raw(node) };
+            node = boxed.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T> Producer<T> {
+    pub fn push(&self, value: T) {
+        self.inner.push(value)
+    }
+}
+
+impl<T> Clone for Producer<T> {
+    fn clone(&self) -> Self {
+        Producer {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Take the oldest item. Returns `None` if the queue is empty.
+    ///
+    /// If a producer is preempted in the middle of a push, this briefly spins
+    /// until its item becomes visible.
+    pub fn pop(&mut self) -> Option<T> {
+        // SAFETY: the consumer is unique and `pop` takes `&mut self`
+        unsafe { self.inner.pop() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        // SAFETY: reading `tail` is fine; only `pop` writes it and that needs
+        // `&mut self`
+        let tail = unsafe { *self.inner.tail.get() };
+        unsafe { (*tail).next.load(Ordering::Acquire).is_null() }
+    }
+
+    /// Number of producers that are still alive.
+    pub fn producer_count(&self) -> usize {
+        Arc::strong_count(&self.inner) - 1
+    }
+}
+
+impl<T> Iterator for Consumer<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn empty_queue() {
+        let (_tx, mut rx) = queue::<i32>();
+        assert!(rx.is_empty());
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn fifo_order() {
+        let (tx, mut rx) = queue();
+        tx.push(1);
+        tx.push(2);
+        assert_eq!(rx.pop(), Some(1));
+        tx.push(3);
+        assert!(!rx.is_empty());
+        assert_eq!(rx.collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn remaining_items_are_dropped() {
+        let item = Arc::new(());
+        {
+            let (tx, mut rx) = queue();
+            for _ in 0..5 {
+                tx.push(item.clone());
+            }
+            rx.pop();
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn producer_count() {
+        let (tx, rx) = queue::<()>();
+        let tx2 = tx.clone();
+        assert_eq!(rx.producer_count(), 2);
+        drop(tx);
+        drop(tx2);
+        assert_eq!(rx.producer_count(), 0);
+    }
+
+    #[test]
+    fn many_producers_one_consumer() {
+        let (tx, mut rx) = queue();
+        let per_thread = 10_000;
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..per_thread {
+                        tx.push((t, i));
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut last = [None; 4];
+        let mut count = 0;
+        while count < 4 * per_thread {
+            if let Some((t, i)) = rx.pop() {
+                // items from the same producer arrive in order
+                assert!(last[t].is_none_or(|prev| prev < i));
+                last[t] = Some(i);
+                count += 1;
+            }
+        }
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(rx.pop(), None);
+    }
+}
@@ -0,0 +1,256 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+macro_rules! multimap {
+    ($(#[$attr:meta])* $name:ident, $map:ident, $($bound:tt)+) => {
+        $(#[$attr])*
+        #[derive(Clone)]
+        pub struct $name<K, V> {
+            map: $map<K, Vec<V>>,
+            len: usize,
+        }
+
+        impl<K: $($bound)+, V> $name<K, V> {
+            pub fn new() -> Self {
+                $name {
+                    map: $map::new(),
+                    len: 0,
+                }
+            }
+
+            /// Total number of values.
+            pub fn len(&self) -> usize {
+                self.len
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+
+            /// Number of distinct keys.
+            pub fn key_count(&self) -> usize {
+                self.map.len()
+            }
+
+            pub fn insert(&mut self, key: K, value: V) {
+                self.map.entry(key).or_default().push(value);
+                self.len += 1;
+            }
+
+            /// All values of a key in insertion order.
+            pub fn get_all<Q>(&self, key: &Q) -> &[V]
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + $($bound)+,
+            {
+                self.map.get(key).map_or(&[], Vec::as_slice)
+            }
+
+            /// The first value inserted for a key.
+            pub fn get<Q>(&self, key: &Q) -> Option<&V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + $($bound)+,
+            {
+                self.get_all(key).first()
+            }
+
+            pub fn contains_key<Q>(&self, key: &Q) -> bool
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + $($bound)+,
+            {
+                self.map.contains_key(key)
+            }
+
+            /// Remove one occurrence of a key-value pair.
+            pub fn remove<Q>(&mut self, key: &Q, value: &V) -> bool
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + $($bound)+,
+                V: PartialEq,
+            {
+                let Some(values) = self.map.get_mut(key) else {
+                    return false;
+                };
+                let Some(pos) = values.iter().position(|v| v == value) else {
+                    return false;
+                };
+                values.remove(pos);
+                if values.is_empty() {
+                    self.map.remove(key);
+                }
+                self.len -= 1;
+                true
+            }
+
+            /// Remove a key and return all its values.
+            pub fn remove_all<Q>(&mut self, key: &Q) -> Vec<V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + $($bound)+,
+            {
+                let values = self.map.remove(key).unwrap_or_default();
+                self.len -= values.len();
+                values
+            }
+
+            pub fn clear(&mut self) {
+                self.map.clear();
+                self.len = 0;
+            }
+
+            /// Every key with all of its values.
+            pub fn iter_grouped(&self) -> impl Iterator<Item = (&K, &[V])> {
+                self.map.iter().map(|(k, vs)| (k, vs.as_slice()))
+            }
+
+            /// Every key-value pair; a key appears once per value.
+            pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+                self.map
+                    .iter()
+                    .flat_map(|(k, vs)| vs.iter().map(move |v| (k, v)))
+            }
+
+            pub fn keys(&self) -> impl Iterator<Item = &K> {
+                self.map.keys()
+            }
+        }
+
+        impl<K: $($bound)+, V: PartialEq> PartialEq for $name<K, V> {
+            fn eq(&self, other: &Self) -> bool {
+                self.map == other.map
+            }
+        }
+
+        impl<K: $($bound)+, V: Eq> Eq for $name<K, V> {}
+
+        impl<K: $($bound)+, V> Default for $name<K, V> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<K: $($bound)+ + fmt::Debug, V: fmt::Debug> fmt::Debug for $name<K, V> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map().entries(self.iter_grouped()).finish()
+            }
+        }
+
+        impl<K: $($bound)+, V> Extend<(K, V)> for $name<K, V> {
+            fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+                for (k, v) in iter {
+                    self.insert(k, v);
+                }
+            }
+        }
+
+        impl<K: $($bound)+, V> FromIterator<(K, V)> for $name<K, V> {
+            fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+                let mut map = Self::new();
+                map.extend(iter);
+                map
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+multimap!(
+    /// A map from keys to lists of values, backed by a `HashMap`.
+    HashMultiMap,
+    HashMap,
+    Hash + Eq
+);
+
+multimap!(
+    /// A map from keys to lists of values, backed by a `BTreeMap`, so keys
+    /// are iterated in order.
+    BTreeMultiMap,
+    BTreeMap,
+    Ord
+);
+
+#[cfg(feature = "std")]
+pub type MultiMap<K, V> = HashMultiMap<K, V>;
+
+impl<K: Ord, V> BTreeMultiMap<K, V> {
+    /// Key groups whose key lies within `range`.
+    pub fn range<Q, R>(&self, range: R) -> impl Iterator<Item = (&K, &[V])>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: core::ops::RangeBounds<Q>,
+    {
+        self.map.range(range).map(|(k, vs)| (k, vs.as_slice()))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_multimap() {
+        let map = MultiMap::<&str, i32>::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get_all("x"), &[] as &[i32]);
+        assert_eq!(map.get("x"), None);
+    }
+
+    #[test]
+    fn values_accumulate_per_key() {
+        let mut map = MultiMap::new();
+        map.insert("fruit", "apple");
+        map.insert("veg", "leek");
+        map.insert("fruit", "pear");
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.key_count(), 2);
+        assert_eq!(map.get_all("fruit"), &["apple", "pear"]);
+        assert_eq!(map.get("fruit"), Some(&"apple"));
+    }
+
+    #[test]
+    fn remove_single_values_and_keys() {
+        let mut map: MultiMap<_, _> = [(1, 'a'), (1, 'b'), (1, 'a'), (2, 'c')]
+            .into_iter()
+            .collect();
+        assert!(map.remove(&1, &'a'));
+        assert_eq!(map.get_all(&1), &['b', 'a']);
+        assert!(!map.remove(&1, &'z'));
+        assert!(map.remove(&2, &'c'));
+        assert!(!map.contains_key(&2));
+        assert_eq!(map.remove_all(&1), vec!['b', 'a']);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn ordered_backend_groups_in_key_order() {
+        let map: BTreeMultiMap<_, _> = [(3, "c"), (1, "a"), (2, "b"), (1, "A")]
+            .into_iter()
+            .collect();
+        let groups: Vec<_> = map.iter_grouped().collect();
+        assert_eq!(
+            groups,
+            vec![(&1, &["a", "A"][..]), (&2, &["b"][..]), (&3, &["c"][..])]
+        );
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, vec![(1, "a"), (1, "A"), (2, "b"), (3, "c")]);
+        assert_eq!(map.range(2..).count(), 2);
+    }
+
+    #[test]
+    fn borrowed_keys() {
+        let mut map = BTreeMultiMap::new();
+        map.insert(String::from("k"), 1);
+        map.insert(String::from("k"), 2);
+        assert_eq!(map.get_all("k"), &[1, 2]);
+        assert!(map.remove("k", &1));
+    }
+}
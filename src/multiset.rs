@@ -0,0 +1,283 @@
+use crate::sorted_vec::SortedVecMap;
+use alloc::vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// A bag of items that counts how often each distinct item occurs.
+///
+/// Counts are stored in a `SortedVecMap`, so iteration is in item order and
+/// the set operations are linear merges.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Multiset<T> {
+    counts: SortedVecMap<T, usize>,
+    len: usize,
+}
+
+impl<T: Ord> Multiset<T> {
+    pub fn new() -> Self {
+        Multiset {
+            counts: SortedVecMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Total number of items, counting multiplicities.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of distinct items.
+    pub fn distinct_len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Add one occurrence of `item` and return its new count.
+    pub fn insert(&mut self, item: T) -> usize {
+        self.insert_many(item, 1)
+    }
+
+    pub fn insert_many(&mut self, item: T, n: usize) -> usize {
+        if n == 0 {
+            return self.count(&item);
+        }
+        self.len += n;
+        if let Some(count) = self.counts.get_mut(&item) {
+            *count += n;
+            return *count;
+        }
+        self.counts.insert(item, n);
+        n
+    }
+
+    pub fn count<Q>(&self, item: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.get(item).copied().unwrap_or(0)
+    }
+
+    pub fn contains<Q>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.contains_key(item)
+    }
+
+    /// Remove one occurrence of `item`. Returns false if it was not present.
+    pub fn remove_one<Q>(&mut self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(count) = self.counts.get_mut(item) else {
+            return false;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(item);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Remove all occurrences of `item` and return how many there were.
+    pub fn remove_all<Q>(&mut self, item: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let count = self.counts.remove(item).unwrap_or(0);
+        self.len -= count;
+        count
+    }
+
+    /// Distinct items with their counts, in order.
+    pub fn iter_counts(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.counts.iter().map(|(item, &n)| (item, n))
+    }
+
+    /// All items in order, each repeated according to its count.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.iter_counts()
+            .flat_map(|(item, n)| core::iter::repeat_n(item, n))
+    }
+
+    /// Items with the larger of both counts.
+    pub fn union(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::max)
+    }
+
+    /// Items with the smaller of both counts.
+    pub fn intersection(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::min)
+    }
+
+    /// Counts of `other` subtracted from this multiset (but not below zero).
+    pub fn difference(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::saturating_sub)
+    }
+
+    /// Counts of both multisets added up.
+    pub fn sum(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        self.combine(other, |a, b| a + b)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.iter_counts().all(|(item, n)| other.count(item) >= n)
+    }
+
+    fn combine(&self, other: &Self, op: impl Fn(usize, usize) -> usize) -> Self
+    where
+        T: Clone,
+    {
+        let mut result = vec![];
+        let mut a = self.counts.as_slice().iter().peekable();
+        let mut b = other.counts.as_slice().iter().peekable();
+        loop {
+            let (item, n) = match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some(&(x, n)), None) => {
+                    a.next();
+                    (x, op(*n, 0))
+                }
+                (None, Some(&(y, m))) => {
+                    b.next();
+                    (y, op(0, *m))
+                }
+                (Some(&(x, n)), Some(&(y, m))) => match x.cmp(y) {
+                    Ordering::Less => {
+                        a.next();
+                        (x, op(*n, 0))
+                    }
+                    Ordering::Greater => {
+                        b.next();
+                        (y, op(0, *m))
+                    }
+                    Ordering::Equal => {
+                        a.next();
+                        b.next();
+                        (x, op(*n, *m))
+                    }
+                },
+            };
+            if n > 0 {
+                result.push((item.clone(), n));
+            }
+        }
+        Multiset {
+            len: result.iter().map(|(_, n)| n).sum(),
+            counts: SortedVecMap::from_unsorted(result),
+        }
+    }
+}
+
+impl<T: Ord> Default for Multiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + fmt::Debug> fmt::Debug for Multiset<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_counts()).finish()
+    }
+}
+
+impl<T: Ord> Extend<T> for Multiset<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for Multiset<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Multiset::new();
+        set.extend(iter);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bag(s: &str) -> Multiset<char> {
+        s.chars().collect()
+    }
+
+    fn chars(m: &Multiset<char>) -> String {
+        m.iter().collect()
+    }
+
+    #[test]
+    fn empty_multiset() {
+        let m = Multiset::<i32>::new();
+        assert!(m.is_empty());
+        assert_eq!(m.count(&1), 0);
+        assert!(!m.contains(&1));
+    }
+
+    #[test]
+    fn counting() {
+        let mut m = bag("mississippi");
+        assert_eq!(m.len(), 11);
+        assert_eq!(m.distinct_len(), 4);
+        assert_eq!(m.count(&'s'), 4);
+        assert_eq!(m.insert('m'), 2);
+        assert_eq!(m.insert_many('x', 3), 3);
+        assert_eq!(chars(&m), "iiiimmppssssxxx");
+    }
+
+    #[test]
+    fn removal() {
+        let mut m = bag("aab");
+        assert!(m.remove_one(&'a'));
+        assert_eq!(m.count(&'a'), 1);
+        assert!(m.remove_one(&'a'));
+        assert!(!m.contains(&'a'));
+        assert!(!m.remove_one(&'a'));
+        assert_eq!(m.remove_all(&'b'), 1);
+        assert_eq!(m.remove_all(&'b'), 0);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn borrowed_lookup() {
+        let m: Multiset<String> = ["a", "b", "a"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(m.count("a"), 2);
+    }
+
+    #[test]
+    fn multiset_operations() {
+        let a = bag("aaabbc");
+        let b = bag("abbbd");
+        assert_eq!(chars(&a.union(&b)), "aaabbbcd");
+        assert_eq!(chars(&a.intersection(&b)), "abb");
+        assert_eq!(chars(&a.difference(&b)), "aac");
+        assert_eq!(chars(&a.sum(&b)), "aaaabbbbbcd");
+        assert_eq!(a.sum(&b).len(), 11);
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+    }
+}
@@ -0,0 +1,527 @@
+//! A mutable binary search tree whose nodes point back to their parents.
+//!
+//! `BinarySearchTree` shares subtrees between versions, so its nodes cannot
+//! know their parent. A `NavTree` gives up persistence for `Weak` parent
+//! links: a `NodeRef` handle can walk to its parent, successor and
+//! predecessor without searching from the root, and new items can be
+//! inserted next to a handle in time proportional to the distance walked.
+//!
+//! Handles stay valid while the tree changes; removing a node only
+//! detaches it.
+
+use crate::binary_search_tree::BinarySearchTree;
+use crate::compare::{Compare, Natural};
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::{Cell, RefCell};
+use core::cmp::Ordering;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+static NEXT_TREE_ID: AtomicUsize = AtomicUsize::new(1);
+
+pub struct NavTree<T, C = Natural> {
+    root: Option<Rc<NavNode<T>>>,
+    len: usize,
+    id: usize,
+    cmp: PhantomData<C>,
+}
+
+struct NavNode<T> {
+    item: T,
+    /// Id of the tree the node is in, or 0 once it was removed.
+    tree: Cell<usize>,
+    links: RefCell<Links<T>>,
+}
+
+struct Links<T> {
+    parent: Weak<NavNode<T>>,
+    left: Option<Rc<NavNode<T>>>,
+    right: Option<Rc<NavNode<T>>>,
+}
+
+/// A handle to a node of a `NavTree`.
+pub struct NodeRef<T>(Rc<NavNode<T>>);
+
+impl<T, C: Compare<T> + Default> NavTree<T, C> {
+    pub fn new() -> Self {
+        NavTree {
+            root: None,
+            len: 0,
+            id: NEXT_TREE_ID.fetch_add(1, AtomicOrdering::Relaxed),
+            cmp: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn root(&self) -> Option<NodeRef<T>> {
+        self.root.clone().map(NodeRef)
+    }
+
+    pub fn first(&self) -> Option<NodeRef<T>> {
+        Some(self.root()?.leftmost())
+    }
+
+    pub fn last(&self) -> Option<NodeRef<T>> {
+        Some(self.root()?.rightmost())
+    }
+
+    pub fn find<Q>(&self, key: &Q) -> Option<NodeRef<T>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut node = self.root()?;
+        loop {
+            node = match C::default().compare(node.item().borrow(), key)? {
+                Ordering::Equal => return Some(node),
+                Ordering::Greater => node.left()?,
+                Ordering::Less => node.right()?,
+            };
+        }
+    }
+
+    /// Whether `node` is part of this tree.
+    pub fn contains_node(&self, node: &NodeRef<T>) -> bool {
+        node.0.tree.get() == self.id
+    }
+
+    /// Insert `item` and return its node, or `None` if an equal item is
+    /// already present. Panics if `item` cannot be compared with an item in
+    /// the tree.
+    pub fn insert(&mut self, item: T) -> Option<NodeRef<T>> {
+        let Some(mut parent) = self.root() else {
+            return Some(self.attach(item, None, true));
+        };
+        loop {
+            let child = match self.compare(&item, parent.item()) {
+                Ordering::Equal => return None,
+                Ordering::Less => parent.left(),
+                Ordering::Greater => parent.right(),
+            };
+            match child {
+                Some(child) => parent = child,
+                None => {
+                    let left = self.compare(&item, parent.item()) == Ordering::Less;
+                    return Some(self.attach(item, Some(&parent), left));
+                }
+            }
+        }
+    }
+
+    /// Insert `item` by walking from `hint` to its place in order, which
+    /// is fast if the two are close. Returns `None` if an equal item is
+    /// already present. Panics if `hint` is not in this tree.
+    pub fn insert_near(&mut self, hint: &NodeRef<T>, item: T) -> Option<NodeRef<T>> {
+        assert!(self.contains_node(hint), "node is not in this tree");
+        // find the neighbours `pred < item < succ` in order
+        let (mut pred, mut succ) = (None, None);
+        match self.compare(&item, hint.item()) {
+            Ordering::Equal => return None,
+            Ordering::Greater => {
+                let mut node = hint.clone();
+                loop {
+                    let next = node.next();
+                    pred = Some(node);
+                    match next {
+                        Some(n) => match self.compare(&item, n.item()) {
+                            Ordering::Equal => return None,
+                            Ordering::Less => {
+                                succ = Some(n);
+                                break;
+                            }
+                            Ordering::Greater => node = n,
+                        },
+                        None => break,
+                    }
+                }
+            }
+            Ordering::Less => {
+                let mut node = hint.clone();
+                loop {
+                    let prev = node.prev();
+                    succ = Some(node);
+                    match prev {
+                        Some(p) => match self.compare(&item, p.item()) {
+                            Ordering::Equal => return None,
+                            Ordering::Greater => {
+                                pred = Some(p);
+                                break;
+                            }
+                            Ordering::Less => node = p,
+                        },
+                        None => break,
+                    }
+                }
+            }
+        }
+        // if `pred` has a right subtree, `succ` is its leftmost node
+        Some(match (pred, succ) {
+            (Some(p), _) if p.right().is_none() => self.attach(item, Some(&p), false),
+            (_, Some(s)) => self.attach(item, Some(&s), true),
+            _ => unreachable!("adjacent nodes always have a free slot"),
+        })
+    }
+
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        C::default().compare(a, b).expect("incomparable items")
+    }
+}
+
+impl<T, C> NavTree<T, C> {
+    /// Remove `node` from the tree and return whether it was in it. The
+    /// handle stays usable but is detached from all other nodes.
+    pub fn remove(&mut self, node: &NodeRef<T>) -> bool {
+        if node.0.tree.get() != self.id {
+            return false;
+        }
+        let (left, right) = {
+            let links = node.0.links.borrow();
+            (links.left.clone(), links.right.clone())
+        };
+        match (left, right) {
+            (None, child) | (child, None) => self.replace(&node.0, child),
+            (Some(left), Some(right)) => {
+                let successor = NodeRef(right.clone()).leftmost().0;
+                if !Rc::ptr_eq(&successor, &right) {
+                    let successor_right = successor.links.borrow().right.clone();
+                    self.replace(&successor, successor_right);
+                    set_parent(&right, &successor);
+                    successor.links.borrow_mut().right = Some(right);
+                }
+                set_parent(&left, &successor);
+                successor.links.borrow_mut().left = Some(left);
+                self.replace(&node.0, Some(successor));
+            }
+        }
+        *node.0.links.borrow_mut() = Links {
+            parent: Weak::new(),
+            left: None,
+            right: None,
+        };
+        node.0.tree.set(0);
+        self.len -= 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        for node in self.iter().collect::<Vec<_>>() {
+            node.0.tree.set(0);
+            *node.0.links.borrow_mut() = Links {
+                parent: Weak::new(),
+                left: None,
+                right: None,
+            };
+        }
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// The nodes in order.
+    pub fn iter(&self) -> Iter<T> {
+        Iter {
+            next: self.root.clone().map(|root| NodeRef(root).leftmost()),
+        }
+    }
+
+    fn attach(&mut self, item: T, parent: Option<&NodeRef<T>>, left: bool) -> NodeRef<T> {
+        let node = Rc::new(NavNode {
+            item,
+            tree: Cell::new(self.id),
+            links: RefCell::new(Links {
+                parent: parent.map_or_else(Weak::new, |p| Rc::downgrade(&p.0)),
+                left: None,
+                right: None,
+            }),
+        });
+        match parent {
+            None => self.root = Some(node.clone()),
+            Some(p) if left => p.0.links.borrow_mut().left = Some(node.clone()),
+            Some(p) => p.0.links.borrow_mut().right = Some(node.clone()),
+        }
+        self.len += 1;
+        NodeRef(node)
+    }
+
+    /// Put `new` where `old` hangs below its parent.
+    fn replace(&mut self, old: &Rc<NavNode<T>>, new: Option<Rc<NavNode<T>>>) {
+        let parent = old.links.borrow().parent.upgrade();
+        if let Some(new) = &new {
+            new.links.borrow_mut().parent = old.links.borrow().parent.clone();
+        }
+        match parent {
+            None => self.root = new,
+            Some(parent) => {
+                let mut links = parent.links.borrow_mut();
+                if links.left.as_ref().is_some_and(|l| Rc::ptr_eq(l, old)) {
+                    links.left = new;
+                } else {
+                    links.right = new;
+                }
+            }
+        }
+    }
+}
+
+fn set_parent<T>(child: &Rc<NavNode<T>>, parent: &Rc<NavNode<T>>) {
+    child.links.borrow_mut().parent = Rc::downgrade(parent);
+}
+
+impl<T> NodeRef<T> {
+    pub fn item(&self) -> &T {
+        &self.0.item
+    }
+
+    pub fn parent(&self) -> Option<NodeRef<T>> {
+        self.0.links.borrow().parent.upgrade().map(NodeRef)
+    }
+
+    pub fn left(&self) -> Option<NodeRef<T>> {
+        self.0.links.borrow().left.clone().map(NodeRef)
+    }
+
+    pub fn right(&self) -> Option<NodeRef<T>> {
+        self.0.links.borrow().right.clone().map(NodeRef)
+    }
+
+    /// The node with the next larger item.
+    pub fn next(&self) -> Option<NodeRef<T>> {
+        if let Some(right) = self.right() {
+            return Some(right.leftmost());
+        }
+        let mut node = self.clone();
+        loop {
+            let parent = node.parent()?;
+            if parent.left().is_some_and(|l| l == node) {
+                return Some(parent);
+            }
+            node = parent;
+        }
+    }
+
+    /// The node with the next smaller item.
+    pub fn prev(&self) -> Option<NodeRef<T>> {
+        if let Some(left) = self.left() {
+            return Some(left.rightmost());
+        }
+        let mut node = self.clone();
+        loop {
+            let parent = node.parent()?;
+            if parent.right().is_some_and(|r| r == node) {
+                return Some(parent);
+            }
+            node = parent;
+        }
+    }
+
+    /// The parent, grandparent, ... up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = NodeRef<T>> {
+        core::iter::successors(self.parent(), NodeRef::parent)
+    }
+
+    /// Number of edges to the root.
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    fn leftmost(self) -> NodeRef<T> {
+        let mut node = self;
+        while let Some(left) = node.left() {
+            node = left;
+        }
+        node
+    }
+
+    fn rightmost(self) -> NodeRef<T> {
+        let mut node = self;
+        while let Some(right) = node.right() {
+            node = right;
+        }
+        node
+    }
+}
+
+impl<T> Clone for NodeRef<T> {
+    fn clone(&self) -> Self {
+        NodeRef(self.0.clone())
+    }
+}
+
+/// Handles are equal if they refer to the same node.
+impl<T> PartialEq for NodeRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for NodeRef<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for NodeRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("NodeRef").field(self.item()).finish()
+    }
+}
+
+pub struct Iter<T> {
+    next: Option<NodeRef<T>>,
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = NodeRef<T>;
+
+    fn next(&mut self) -> Option<NodeRef<T>> {
+        let node = self.next.take()?;
+        self.next = node.next();
+        Some(node)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for NavTree<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for NavTree<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter().map(|n| n.0)).finish()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for NavNode<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.item.fmt(f)
+    }
+}
+
+/// Panics if an item cannot be compared with another.
+impl<T, C: Compare<T> + Default> FromIterator<T> for NavTree<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        for item in iter {
+            tree.insert(item);
+        }
+        tree
+    }
+}
+
+/// Copies the items, keeping the shape of the tree.
+impl<T: Clone, C: Compare<T> + Default> From<&BinarySearchTree<T, C>> for NavTree<T, C> {
+    fn from(source: &BinarySearchTree<T, C>) -> Self {
+        fn copy<T: Clone, C: Compare<T> + Default>(
+            tree: &mut NavTree<T, C>,
+            source: &BinarySearchTree<T, C>,
+            parent: Option<&NodeRef<T>>,
+            left: bool,
+        ) {
+            if let Some(item) = source.item() {
+                let node = tree.attach(item.clone(), parent, left);
+                copy(tree, source.left().unwrap(), Some(&node), true);
+                copy(tree, source.right().unwrap(), Some(&node), false);
+            }
+        }
+        let mut tree = NavTree::new();
+        copy(&mut tree, source, None, false);
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn items<T: Clone, C>(tree: &NavTree<T, C>) -> Vec<T> {
+        tree.iter().map(|n| n.item().clone()).collect()
+    }
+
+    #[test]
+    fn walk_from_a_handle() {
+        let tree: NavTree<i32> = [50, 30, 70, 20, 40, 60, 80, 35].into_iter().collect();
+        let node = tree.find(&35).unwrap();
+        assert_eq!(node.parent().map(|p| *p.item()), Some(40));
+        assert_eq!(
+            node.ancestors().map(|n| *n.item()).collect::<Vec<_>>(),
+            vec![40, 30, 50]
+        );
+        assert_eq!(node.depth(), 3);
+        assert_eq!(node.next().map(|n| *n.item()), Some(40));
+        assert_eq!(node.prev().map(|n| *n.item()), Some(30));
+        assert_eq!(tree.find(&40).unwrap().next().map(|n| *n.item()), Some(50));
+        assert!(tree.last().unwrap().next().is_none());
+        assert!(tree.root().unwrap().parent().is_none());
+        assert_eq!(items(&tree), vec![20, 30, 35, 40, 50, 60, 70, 80]);
+    }
+
+    #[test]
+    fn insert_near_a_cursor() {
+        let mut tree: NavTree<i32> = (0..100).step_by(10).collect();
+        let mut cursor = tree.find(&40).unwrap();
+        for item in [41, 42, 39, 45, 95, -5] {
+            cursor = tree.insert_near(&cursor, item).unwrap();
+            assert_eq!(*cursor.item(), item);
+        }
+        assert!(tree.insert_near(&cursor, 50).is_none());
+        assert!(tree.insert(41).is_none());
+        assert_eq!(tree.len(), 16);
+        assert_eq!(
+            items(&tree),
+            vec![-5, 0, 10, 20, 30, 39, 40, 41, 42, 45, 50, 60, 70, 80, 90, 95]
+        );
+    }
+
+    #[test]
+    fn remove_keeps_other_handles_valid() {
+        let mut tree: NavTree<i32> = [50, 30, 70, 20, 40, 60, 80, 65].into_iter().collect();
+        let root = tree.find(&50).unwrap();
+        let sixty = tree.find(&60).unwrap();
+        assert!(tree.remove(&root));
+        assert!(!tree.remove(&root));
+        assert!(root.parent().is_none() && root.left().is_none());
+        // the successor 60 took the place of the root
+        assert_eq!(tree.root(), Some(sixty.clone()));
+        assert_eq!(sixty.next().map(|n| *n.item()), Some(65));
+        assert_eq!(sixty.prev().map(|n| *n.item()), Some(40));
+        for x in [20, 70, 60] {
+            assert!(tree.remove(&tree.find(&x).unwrap()));
+        }
+        assert_eq!(items(&tree), vec![30, 40, 65, 80]);
+        assert_eq!(tree.len(), 4);
+        assert!(tree
+            .iter()
+            .all(|n| n.next().is_none_or(|m| m.prev() == Some(n))));
+    }
+
+    #[test]
+    fn handles_belong_to_one_tree() {
+        let mut a: NavTree<i32> = [1, 2].into_iter().collect();
+        let mut b: NavTree<i32> = [1, 2].into_iter().collect();
+        let node = a.find(&1).unwrap();
+        assert!(a.contains_node(&node) && !b.contains_node(&node));
+        assert!(!b.remove(&node));
+        a.clear();
+        assert!(a.is_empty() && !a.contains_node(&node));
+        assert_eq!(node.next(), None);
+    }
+
+    #[test]
+    fn from_persistent_tree() {
+        let persistent: BinarySearchTree<i32> =
+            BinarySearchTree::from_sorted((1..=7).collect()).unwrap();
+        let tree = NavTree::from(&persistent);
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.root().map(|n| *n.item()), Some(4));
+        assert_eq!(tree.first().unwrap().depth(), 2);
+        assert_eq!(format!("{tree:?}"), "{1, 2, 3, 4, 5, 6, 7}");
+    }
+}
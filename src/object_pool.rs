@@ -0,0 +1,242 @@
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+type Create<T> = Box<dyn Fn() -> T + Send + Sync>;
+type Reset<T> = Box<dyn Fn(&mut T) + Send + Sync>;
+
+/// A pool of reusable objects.
+///
+/// `get` checks out an idle object (or creates a new one) wrapped in a guard
+/// that puts it back into the pool when dropped. At most `capacity` idle
+/// objects are kept; surplus objects are simply dropped.
+pub struct ObjectPool<T> {
+    idle: Mutex<Vec<T>>,
+    capacity: usize,
+    create: Create<T>,
+    reset: Option<Reset<T>>,
+}
+
+impl<T> ObjectPool<T> {
+    pub fn new(capacity: usize, create: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        ObjectPool {
+            idle: Mutex::new(Vec::with_capacity(capacity)),
+            capacity,
+            create: Box::new(create),
+            reset: None,
+        }
+    }
+
+    /// Run `reset` on every object that is returned to the pool, e.g. to
+    /// clear a buffer while keeping its allocation.
+    pub fn with_reset(mut self, reset: impl Fn(&mut T) + Send + Sync + 'static) -> Self {
+        self.reset = Some(Box::new(reset));
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of idle objects ready to be checked out.
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn get(&self) -> Pooled<'_, T> {
+        let idle = self.lock().pop();
+        let object = idle.unwrap_or_else(|| (self.create)());
+        Pooled::new(self, object)
+    }
+
+    /// Check out an idle object without creating a new one.
+    pub fn try_get(&self) -> Option<Pooled<'_, T>> {
+        let object = self.lock().pop()?;
+        Some(Pooled::new(self, object))
+    }
+
+    /// Create objects until `n` are idle (or the capacity is reached).
+    ///
+    /// The objects are created without holding the lock, so other threads
+    /// can use the pool meanwhile. Objects that are no longer needed when
+    /// they are added, because others were returned in the meantime, are
+    /// dropped.
+    pub fn prefill(&self, n: usize) {
+        let n = n.min(self.capacity);
+        let missing = n.saturating_sub(self.available());
+        let mut fresh: Vec<T> = (0..missing).map(|_| (self.create)()).collect();
+        let mut idle = self.lock();
+        let room = n.saturating_sub(idle.len()).min(fresh.len());
+        idle.extend(fresh.drain(..room));
+    }
+
+    fn give_back(&self, mut object: T) {
+        if let Some(reset) = &self.reset {
+            reset(&mut object);
+        }
+        let mut idle = self.lock();
+        if idle.len() < self.capacity {
+            idle.push(object);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<T>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> fmt::Debug for ObjectPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ObjectPool")
+            .field("capacity", &self.capacity)
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+/// An object checked out of an `ObjectPool`.
+pub struct Pooled<'a, T> {
+    pool: &'a ObjectPool<T>,
+    object: ManuallyDrop<T>,
+}
+
+impl<'a, T> Pooled<'a, T> {
+    fn new(pool: &'a ObjectPool<T>, object: T) -> Self {
+        Pooled {
+            pool,
+            object: ManuallyDrop::new(object),
+        }
+    }
+
+    /// Take the object out of the pool's management for good.
+    pub fn detach(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the object is moved out once
+        unsafe { ManuallyDrop::take(&mut this.object) }
+    }
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.object
+    }
+}
+
+impl<T> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.object
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: the object is not used after this
+        let object = unsafe { ManuallyDrop::take(&mut self.object) };
+        self.pool.give_back(object);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Pooled<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn objects_are_reused() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let counter = created.clone();
+        let pool = ObjectPool::new(4, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Vec::<u8>::with_capacity(1024)
+        });
+        for _ in 0..10 {
+            let mut buf = pool.get();
+            buf.push(1);
+        }
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn reset_hook_runs_on_return() {
+        let pool = ObjectPool::new(2, Vec::<i32>::new).with_reset(|v| v.clear());
+        {
+            let mut v = pool.get();
+            v.extend([1, 2, 3]);
+        }
+        let v = pool.get();
+        assert!(v.is_empty());
+        assert!(v.capacity() >= 3);
+    }
+
+    #[test]
+    fn surplus_objects_are_dropped() {
+        let pool = ObjectPool::new(2, String::new);
+        let objects: Vec<_> = (0..5).map(|_| pool.get()).collect();
+        assert_eq!(pool.available(), 0);
+        drop(objects);
+        assert_eq!(pool.available(), 2);
+    }
+
+    #[test]
+    fn try_get_and_prefill() {
+        let pool = ObjectPool::new(3, || 0);
+        assert!(pool.try_get().is_none());
+        pool.prefill(10);
+        assert_eq!(pool.available(), 3);
+        let x = pool.try_get().unwrap();
+        assert_eq!(*x, 0);
+        assert_eq!(pool.available(), 2);
+    }
+
+    #[test]
+    fn objects_are_created_outside_the_lock() {
+        use std::sync::OnceLock;
+        // `create` looks at the pool, which deadlocks if it holds the lock.
+        static POOL: OnceLock<ObjectPool<usize>> = OnceLock::new();
+        let pool = POOL.get_or_init(|| ObjectPool::new(4, || POOL.get().unwrap().available()));
+        pool.prefill(2);
+        assert_eq!(pool.available(), 2);
+        let a = pool.get();
+        let b = pool.get();
+        assert_eq!(*pool.get(), 0);
+        drop((a, b));
+        assert_eq!(pool.available(), 3);
+    }
+
+    #[test]
+    fn detached_objects_are_not_returned() {
+        let pool = ObjectPool::new(3, || String::from("x"));
+        let s = pool.get().detach();
+        assert_eq!(s, "x");
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let pool = ObjectPool::new(4, Vec::<usize>::new).with_reset(|v| v.clear());
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let pool = &pool;
+                s.spawn(move || {
+                    for i in 0..100 {
+                        let mut v = pool.get();
+                        assert!(v.is_empty());
+                        v.push(t * i);
+                    }
+                });
+            }
+        });
+        assert!(pool.available() <= 4);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::slot_map::{Key, SlotMap};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+pub type Handle = Key;
+
+/// A list that answers "which of these two items comes first?" in O(1).
+///
+/// Every item carries an integer label that increases along the list. A new
+/// item gets the label halfway between its neighbours; when there is no room
+/// left, the smallest enclosing label range that is sparse enough is
+/// relabelled evenly (Bender et al.), which costs amortized O(log n).
+#[derive(Debug, Clone)]
+pub struct OrderList {
+    nodes: SlotMap<Node>,
+    // hidden first item with label 0, so that every real item has a
+    // predecessor
+    base: Handle,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    label: u64,
+    prev: Option<Handle>,
+    next: Option<Handle>,
+}
+
+// a range of 2^i labels may be relabelled if it holds at most (2/T)^i items;
+// T = 3/2 is expressed as a fraction to stay in integer arithmetic
+const T_NUM: u128 = 3;
+const T_DEN: u128 = 2;
+
+impl OrderList {
+    pub fn new() -> Self {
+        let mut nodes = SlotMap::new();
+        let base = nodes.insert(Node {
+            label: 0,
+            prev: None,
+            next: None,
+        });
+        OrderList { nodes, base }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, h: Handle) -> bool {
+        h != self.base && self.nodes.contains_key(h)
+    }
+
+    pub fn first(&self) -> Option<Handle> {
+        self.nodes[self.base].next
+    }
+
+    pub fn next(&self, h: Handle) -> Option<Handle> {
+        self.node(h).next
+    }
+
+    pub fn prev(&self, h: Handle) -> Option<Handle> {
+        self.node(h).prev.filter(|&p| p != self.base)
+    }
+
+    pub fn insert_front(&mut self) -> Handle {
+        self.insert_after_node(self.base)
+    }
+
+    pub fn insert_after(&mut self, h: Handle) -> Handle {
+        self.node(h);
+        self.insert_after_node(h)
+    }
+
+    pub fn insert_before(&mut self, h: Handle) -> Handle {
+        let prev = self.node(h).prev.unwrap();
+        self.insert_after_node(prev)
+    }
+
+    pub fn delete(&mut self, h: Handle) -> bool {
+        if !self.contains(h) {
+            return false;
+        }
+        let Node { prev, next, .. } = self.nodes.remove(h).unwrap();
+        let prev = prev.unwrap();
+        self.nodes[prev].next = next;
+        if let Some(next) = next {
+            self.nodes[next].prev = Some(prev);
+        }
+        true
+    }
+
+    /// Compare the positions of two items.
+    pub fn order(&self, a: Handle, b: Handle) -> Ordering {
+        self.node(a).label.cmp(&self.node(b).label)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Handle> + '_ {
+        core::iter::successors(self.first(), move |&h| self.nodes[h].next)
+    }
+
+    fn node(&self, h: Handle) -> &Node {
+        assert!(h != self.base, "invalid handle");
+        self.nodes.get(h).expect("invalid handle")
+    }
+
+    fn insert_after_node(&mut self, h: Handle) -> Handle {
+        if self.gap_after(h) < 2 {
+            self.relabel_around(h);
+        }
+        let label = self.nodes[h].label + self.gap_after(h) / 2;
+        let next = self.nodes[h].next;
+        let new = self.nodes.insert(Node {
+            label,
+            prev: Some(h),
+            next,
+        });
+        self.nodes[h].next = Some(new);
+        if let Some(next) = next {
+            self.nodes[next].prev = Some(new);
+        }
+        new
+    }
+
+    fn gap_after(&self, h: Handle) -> u64 {
+        let node = &self.nodes[h];
+        let end = node.next.map_or(u64::MAX, |n| self.nodes[n].label);
+        end - node.label
+    }
+
+    /// Spread out the labels of the smallest sparse enough label range that
+    /// contains `h`, so that there is room for a new item after `h`.
+    fn relabel_around(&mut self, h: Handle) {
+        let label = self.nodes[h].label;
+        for bits in 1..64u32 {
+            let size = 1u64 << bits;
+            let lo = label & !(size - 1);
+            let hi = lo + (size - 1);
+            let range = self.nodes_in_range(h, lo, hi);
+            let count = range.len() as u64 + 1;
+            if count * 2 <= size && sparse_enough(count, bits) {
+                return self.spread(&range, lo, size);
+            }
+        }
+        let all: Vec<_> = core::iter::once(self.base).chain(self.iter()).collect();
+        assert!(all.len() < (1 << 62), "too many items");
+        self.spread(&all, 0, u64::MAX);
+    }
+
+    fn nodes_in_range(&self, h: Handle, lo: u64, hi: u64) -> Vec<Handle> {
+        let mut first = h;
+        while let Some(p) = self.nodes[first].prev {
+            if self.nodes[p].label < lo {
+                break;
+            }
+            first = p;
+        }
+        let mut range = vec![first];
+        let mut cur = first;
+        while let Some(n) = self.nodes[cur].next {
+            if self.nodes[n].label > hi {
+                break;
+            }
+            range.push(n);
+            cur = n;
+        }
+        range
+    }
+
+    fn spread(&mut self, range: &[Handle], lo: u64, size: u64) {
+        let step = size / (range.len() as u64 + 1);
+        for (i, &h) in range.iter().enumerate() {
+            self.nodes[h].label = lo + i as u64 * step;
+        }
+    }
+}
+
+/// Whether `count <= (2 / T)^bits`, i.e. `count * T_NUM^bits <= (2 * T_DEN)^bits`.
+fn sparse_enough(count: u64, bits: u32) -> bool {
+    let lhs = T_NUM
+        .checked_pow(bits)
+        .and_then(|t| t.checked_mul(count as u128));
+    let rhs = (2 * T_DEN).checked_pow(bits);
+    match (lhs, rhs) {
+        (Some(l), Some(r)) => l <= r,
+        (None, _) => false,
+        (Some(_), None) => true,
+    }
+}
+
+impl Default for OrderList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_list() {
+        let list = OrderList::new();
+        assert!(list.is_empty());
+        assert_eq!(list.first(), None);
+        assert_eq!(list.iter().count(), 0);
+    }
+
+    #[test]
+    fn relative_order() {
+        let mut list = OrderList::new();
+        let b = list.insert_front();
+        let a = list.insert_front();
+        let d = list.insert_after(b);
+        let c = list.insert_before(d);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![a, b, c, d]);
+        assert_eq!(list.order(a, d), Ordering::Less);
+        assert_eq!(list.order(c, b), Ordering::Greater);
+        assert_eq!(list.order(c, c), Ordering::Equal);
+        assert_eq!(list.prev(a), None);
+        assert_eq!(list.next(b), Some(c));
+    }
+
+    #[test]
+    fn delete_items() {
+        let mut list = OrderList::new();
+        let a = list.insert_front();
+        let b = list.insert_after(a);
+        let c = list.insert_after(b);
+        assert!(list.delete(b));
+        assert!(!list.delete(b));
+        assert!(!list.contains(b));
+        assert_eq!(list.next(a), Some(c));
+        assert_eq!(list.prev(c), Some(a));
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn repeated_insertion_at_one_spot_relabels() {
+        let mut list = OrderList::new();
+        let first = list.insert_front();
+        let last = list.insert_after(first);
+        // always inserting right after `first` halves the same gap
+        let mut inserted = vec![];
+        for _ in 0..1000 {
+            inserted.push(list.insert_after(first));
+        }
+        let order: Vec<_> = list.iter().collect();
+        assert_eq!(order.len(), 1002);
+        assert_eq!(order[0], first);
+        assert_eq!(order[1001], last);
+        inserted.reverse();
+        assert_eq!(&order[1..1001], &inserted[..]);
+        for w in order.windows(2) {
+            assert_eq!(list.order(w[0], w[1]), Ordering::Less);
+        }
+    }
+
+    #[test]
+    fn matches_vec_model() {
+        let mut list = OrderList::new();
+        let mut model: Vec<Handle> = vec![list.insert_front()];
+        let mut x = 5usize;
+        for _ in 0..3000 {
+            x = (x * 1103 + 12345) % 100_003;
+            let i = x % model.len();
+            if x.is_multiple_of(5) && model.len() > 1 {
+                list.delete(model.remove(i));
+            } else if x.is_multiple_of(2) {
+                let h = list.insert_after(model[i]);
+                model.insert(i + 1, h);
+            } else {
+                let h = list.insert_before(model[i]);
+                model.insert(i, h);
+            }
+        }
+        assert_eq!(list.iter().collect::<Vec<_>>(), model);
+        let a = model[model.len() / 3];
+        let b = model[2 * model.len() / 3];
+        assert_eq!(list.order(a, b), Ordering::Less);
+    }
+}
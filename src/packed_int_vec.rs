@@ -0,0 +1,283 @@
+//! A vector of unsigned integers that all fit in the same number of bits,
+//! stored back to back in `u64` words.
+//!
+//! With a width of `w` bits, `n` values take `n * w / 64` words; values may
+//! straddle two words. A million 12-bit values need 1.5 MB instead of the
+//! 8 MB of a `Vec<u64>`.
+
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use alloc::vec::Vec;
+use core::fmt;
+
+const WORD_BITS: usize = 64;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PackedIntVec {
+    words: Vec<u64>,
+    len: usize,
+    width: u32,
+}
+
+impl PackedIntVec {
+    /// An empty vector of `width`-bit values. Panics unless `1 <= width <= 64`.
+    pub fn new(width: u32) -> Self {
+        Self::with_capacity(width, 0)
+    }
+
+    pub fn with_capacity(width: u32, capacity: usize) -> Self {
+        assert!((1..=64).contains(&width), "width must be between 1 and 64");
+        PackedIntVec {
+            words: Vec::with_capacity((capacity * width as usize).div_ceil(WORD_BITS)),
+            len: 0,
+            width,
+        }
+    }
+
+    /// `len` copies of `value`.
+    pub fn from_elem(width: u32, len: usize, value: u64) -> Self {
+        let mut v = Self::with_capacity(width, len);
+        for _ in 0..len {
+            v.push(value);
+        }
+        v
+    }
+
+    /// The smallest width that can hold `value`.
+    pub fn width_for(value: u64) -> u32 {
+        (u64::BITS - value.leading_zeros()).max(1)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The largest value that fits.
+    pub fn max_value(&self) -> u64 {
+        u64::MAX >> (u64::BITS - self.width)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        let (word, offset) = self.position(index);
+        let mut value = self.words[word] >> offset;
+        if offset + self.width as usize > WORD_BITS {
+            value |= self.words[word + 1] << (WORD_BITS - offset);
+        }
+        Some(value & self.max_value())
+    }
+
+    /// Panics if `index` is out of bounds or `value` does not fit.
+    pub fn set(&mut self, index: usize, value: u64) {
+        assert!(index < self.len, "index out of bounds");
+        assert!(value <= self.max_value(), "value does not fit in the width");
+        let mask = self.max_value();
+        let (word, offset) = self.position(index);
+        self.words[word] &= !(mask << offset);
+        self.words[word] |= value << offset;
+        if offset + self.width as usize > WORD_BITS {
+            let shift = WORD_BITS - offset;
+            self.words[word + 1] &= !(mask >> shift);
+            self.words[word + 1] |= value >> shift;
+        }
+    }
+
+    /// Panics if `value` does not fit.
+    pub fn push(&mut self, value: u64) {
+        self.len += 1;
+        let needed = (self.len * self.width as usize).div_ceil(WORD_BITS);
+        if self.words.len() < needed {
+            self.words.push(0);
+        }
+        self.set(self.len - 1, value);
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        let value = self.get(self.len.checked_sub(1)?)?;
+        self.set(self.len - 1, 0);
+        self.len -= 1;
+        self.words
+            .truncate((self.len * self.width as usize).div_ceil(WORD_BITS));
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            vec: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+
+    /// The packed words; unused high bits of the last word are zero.
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+
+    fn position(&self, index: usize) -> (usize, usize) {
+        let bit = index * self.width as usize;
+        (bit / WORD_BITS, bit % WORD_BITS)
+    }
+}
+
+impl MemoryFootprint for PackedIntVec {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<u64>(self.words.len(), self.words.capacity())
+    }
+}
+
+impl fmt::Debug for PackedIntVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Uses the smallest width that holds all values.
+impl FromIterator<u64> for PackedIntVec {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let values: Vec<u64> = iter.into_iter().collect();
+        let max = values.iter().copied().max().unwrap_or(0);
+        let mut v = Self::with_capacity(Self::width_for(max), values.len());
+        v.extend(values);
+        v
+    }
+}
+
+/// Panics if a value does not fit.
+impl Extend<u64> for PackedIntVec {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a PackedIntVec {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a> {
+    vec: &'a PackedIntVec,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.vec.get(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.back - self.front;
+        (n, Some(n))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u64> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.vec.get(self.back)
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn push_get_set() {
+        let mut v = PackedIntVec::new(5);
+        for i in 0..40 {
+            v.push(i % 32);
+        }
+        assert_eq!(v.len(), 40);
+        assert_eq!(v.as_words().len(), 4);
+        assert!((0..40).all(|i| v.get(i) == Some(i as u64 % 32)));
+        assert_eq!(v.get(40), None);
+        v.set(12, 31);
+        v.set(13, 0);
+        assert_eq!(
+            (v.get(11), v.get(12), v.get(13), v.get(14)),
+            (Some(11), Some(31), Some(0), Some(14))
+        );
+    }
+
+    #[test]
+    fn values_straddling_words() {
+        for width in [3, 7, 13, 33, 63, 64] {
+            let mut v = PackedIntVec::new(width);
+            let max = v.max_value();
+            let values: Vec<u64> = (0..200u64)
+                .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) & max)
+                .collect();
+            v.extend(values.iter().copied());
+            assert_eq!(v.iter().collect::<Vec<_>>(), values, "width {width}");
+            v.set(99, max);
+            assert_eq!(v.get(98), Some(values[98]));
+            assert_eq!(v.get(99), Some(max));
+            assert_eq!(v.get(100), Some(values[100]));
+        }
+    }
+
+    #[test]
+    fn pop_keeps_words_clean() {
+        let mut v = PackedIntVec::from_elem(10, 13, 1023);
+        let mut w = PackedIntVec::from_elem(10, 12, 1023);
+        assert_eq!(v.pop(), Some(1023));
+        assert_eq!(v, w);
+        while v.pop().is_some() {}
+        assert!(v.is_empty() && v.as_words().is_empty());
+        w.clear();
+        assert_eq!(v, w);
+    }
+
+    #[test]
+    fn collect_picks_smallest_width() {
+        let v: PackedIntVec = [3, 0, 17, 9].into_iter().collect();
+        assert_eq!(v.width(), 5);
+        assert_eq!(v.iter().rev().collect::<Vec<_>>(), vec![9, 17, 0, 3]);
+        assert_eq!(format!("{v:?}"), "[3, 0, 17, 9]");
+        assert_eq!(PackedIntVec::width_for(0), 1);
+        assert_eq!(PackedIntVec::width_for(u64::MAX), 64);
+
+        let big = PackedIntVec::from_elem(12, 1_000_000, 4095);
+        assert!(big.memory_stats().heap_bytes <= 1_500_008);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn oversized_values_are_rejected() {
+        PackedIntVec::new(4).push(16);
+    }
+}
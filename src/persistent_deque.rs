@@ -0,0 +1,269 @@
+use crate::stream::{Stream, StreamNode};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Neither side may grow larger than `BALANCE` times the other side (plus one).
+const BALANCE: usize = 3;
+
+/// Okasaki's banker's deque. All operations run in amortized O(1) time, even
+/// when old versions are reused, because rebalancing is done lazily.
+pub struct PDeque<T> {
+    front_len: usize,
+    front: Stream<T>,
+    rear_len: usize,
+    rear: Stream<T>,
+}
+
+impl<T> Clone for PDeque<T> {
+    fn clone(&self) -> Self {
+        PDeque {
+            front_len: self.front_len,
+            front: self.front.clone(),
+            rear_len: self.rear_len,
+            rear: self.rear.clone(),
+        }
+    }
+}
+
+impl<T: Clone> PDeque<T> {
+    pub fn new() -> Self {
+        PDeque {
+            front_len: 0,
+            front: Stream::empty(),
+            rear_len: 0,
+            rear: Stream::empty(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.front_len + self.rear_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        // the front stream can only be empty if the rear holds at most one item
+        self.front.head().or_else(|| self.rear.head())
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.rear.head().or_else(|| self.front.head())
+    }
+
+    pub fn push_front(&self, item: T) -> Self {
+        Self::check(
+            self.front_len + 1,
+            Stream::cons(item, self.front.clone()),
+            self.rear_len,
+            self.rear.clone(),
+        )
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        Self::check(
+            self.front_len,
+            self.front.clone(),
+            self.rear_len + 1,
+            Stream::cons(item, self.rear.clone()),
+        )
+    }
+
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        match self.front.force() {
+            StreamNode::Nil => {
+                let item = self.rear.head()?.clone();
+                Some((item, PDeque::new()))
+            }
+            StreamNode::Cons(item, rest) => {
+                let deque = Self::check(
+                    self.front_len - 1,
+                    rest.clone(),
+                    self.rear_len,
+                    self.rear.clone(),
+                );
+                Some((item.clone(), deque))
+            }
+        }
+    }
+
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        match self.rear.force() {
+            StreamNode::Nil => {
+                let item = self.front.head()?.clone();
+                Some((PDeque::new(), item))
+            }
+            StreamNode::Cons(item, rest) => {
+                let deque = Self::check(
+                    self.front_len,
+                    self.front.clone(),
+                    self.rear_len - 1,
+                    rest.clone(),
+                );
+                Some((deque, item.clone()))
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let rear: Vec<&T> = self.rear.iter().collect();
+        self.front.iter().chain(rear.into_iter().rev())
+    }
+
+    fn check(front_len: usize, front: Stream<T>, rear_len: usize, rear: Stream<T>) -> Self {
+        let total = front_len + rear_len;
+        if front_len > BALANCE * rear_len + 1 {
+            let new_front_len = total / 2;
+            let new_rear_len = total - new_front_len;
+            PDeque {
+                front_len: new_front_len,
+                front: front.take(new_front_len),
+                rear_len: new_rear_len,
+                rear: rear.append(&front.drop(new_front_len).reverse()),
+            }
+        } else if rear_len > BALANCE * front_len + 1 {
+            let new_rear_len = total / 2;
+            let new_front_len = total - new_rear_len;
+            PDeque {
+                front_len: new_front_len,
+                front: front.append(&rear.drop(new_rear_len).reverse()),
+                rear_len: new_rear_len,
+                rear: rear.take(new_rear_len),
+            }
+        } else {
+            PDeque {
+                front_len,
+                front,
+                rear_len,
+                rear,
+            }
+        }
+    }
+}
+
+impl<T: Clone> Default for PDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for PDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for PDeque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PDeque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(PDeque::new(), |deque, item| deque.push_back(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_deque() {
+        let d = PDeque::<i32>::new();
+        assert!(d.is_empty());
+        assert_eq!(d.front(), None);
+        assert_eq!(d.back(), None);
+        assert!(d.pop_front().is_none());
+        assert!(d.pop_back().is_none());
+    }
+
+    #[test]
+    fn fifo_order() {
+        let mut d: PDeque<_> = (0..100).collect();
+        for i in 0..100 {
+            let (item, rest) = d.pop_front().unwrap();
+            assert_eq!(item, i);
+            d = rest;
+        }
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn lifo_order_from_both_ends() {
+        let mut d = PDeque::new();
+        for i in 0..50 {
+            d = d.push_front(i);
+        }
+        for i in 0..50 {
+            let (item, rest) = d.pop_front().unwrap();
+            assert_eq!(item, 49 - i);
+            d = rest;
+        }
+
+        for i in 0..50 {
+            d = d.push_back(i);
+        }
+        for i in 0..50 {
+            let (rest, item) = d.pop_back().unwrap();
+            assert_eq!(item, 49 - i);
+            d = rest;
+        }
+    }
+
+    #[test]
+    fn single_element_from_either_side() {
+        let d = PDeque::new().push_back(1);
+        assert_eq!(d.front(), Some(&1));
+        assert_eq!(d.back(), Some(&1));
+        assert_eq!(d.pop_back().unwrap().1, 1);
+        let d = PDeque::new().push_front(2);
+        assert_eq!(d.pop_back().unwrap().1, 2);
+    }
+
+    #[test]
+    fn old_versions_remain_valid() {
+        let d1: PDeque<_> = (0..10).collect();
+        let (_, d2) = d1.pop_front().unwrap();
+        let d3 = d2.push_back(10);
+        assert!(d1.iter().copied().eq(0..10));
+        assert!(d2.iter().copied().eq(1..10));
+        assert!(d3.iter().copied().eq(1..11));
+        let (_, d4) = d1.pop_front().unwrap();
+        assert_eq!(d2, d4);
+    }
+
+    #[test]
+    fn mixed_operations() {
+        let mut d = PDeque::new();
+        let mut expected = std::collections::VecDeque::new();
+        for i in 0..1000 {
+            match i % 5 {
+                0 | 1 => {
+                    d = d.push_back(i);
+                    expected.push_back(i);
+                }
+                2 => {
+                    d = d.push_front(i);
+                    expected.push_front(i);
+                }
+                3 => {
+                    let (item, rest) = d.pop_front().unwrap();
+                    assert_eq!(Some(item), expected.pop_front());
+                    d = rest;
+                }
+                _ => {
+                    let (rest, item) = d.pop_back().unwrap();
+                    assert_eq!(Some(item), expected.pop_back());
+                    d = rest;
+                }
+            }
+            assert_eq!(d.len(), expected.len());
+            assert_eq!(d.front(), expected.front());
+            assert_eq!(d.back(), expected.back());
+        }
+    }
+}
@@ -0,0 +1,738 @@
+use crate::memory::{
+    compare_nodes, rc_bytes, MemoryFootprint, MemoryStats, SharedFootprint, SharingStats,
+};
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::size_of;
+use core::ops::Range;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+
+/// Maximum number of extra nodes a level may hold over the optimal packing
+/// before concatenation repacks it.
+const EXTRA_NODES: usize = 2;
+
+pub struct PVec<T> {
+    len: usize,
+    height: usize,
+    root: Node<T>,
+    tail: Rc<Vec<T>>,
+}
+
+enum Node<T> {
+    Leaf(Rc<Vec<T>>),
+    Branch(Rc<Branch<T>>),
+}
+
+#[derive(Clone)]
+struct Branch<T> {
+    children: Vec<Node<T>>,
+    // cumulative sizes; only present if the node is relaxed
+    sizes: Option<Vec<usize>>,
+}
+
+impl<T> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Leaf(items) => Node::Leaf(items.clone()),
+            Node::Branch(branch) => Node::Branch(branch.clone()),
+        }
+    }
+}
+
+impl<T> Clone for PVec<T> {
+    fn clone(&self) -> Self {
+        PVec {
+            len: self.len,
+            height: self.height,
+            root: self.root.clone(),
+            tail: self.tail.clone(),
+        }
+    }
+}
+
+impl<T: Clone> PVec<T> {
+    pub fn new() -> Self {
+        PVec {
+            len: 0,
+            height: 0,
+            root: Node::Leaf(Rc::new(vec![])),
+            tail: Rc::new(vec![]),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A copy of the vector that shares no nodes with `self`.
+    pub fn clone_deep(&self) -> Self {
+        PVec {
+            len: self.len,
+            height: self.height,
+            root: self.root.clone_deep(),
+            tail: Rc::new((*self.tail).clone()),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let tree_len = self.tree_len();
+        if index >= tree_len {
+            return self.tail.get(index - tree_len);
+        }
+        let (leaf, offset) = self.root.leaf_at(self.height, index);
+        Some(&leaf[index - offset])
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn update(&self, index: usize, item: T) -> Self {
+        assert!(index < self.len, "index out of bounds");
+        let mut result = self.clone();
+        let tree_len = self.tree_len();
+        if index >= tree_len {
+            Rc::make_mut(&mut result.tail)[index - tree_len] = item;
+        } else {
+            result.root = self.root.update(self.height, index, item);
+        }
+        result
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        let mut result = self.clone();
+        result.push_mut(item);
+        result
+    }
+
+    pub fn pop_back(&self) -> Option<(Self, T)> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut result = self.clone();
+        if result.tail.is_empty() {
+            result.pull_tail();
+        }
+        let item = Rc::make_mut(&mut result.tail).pop().unwrap();
+        result.len -= 1;
+        Some((result, item))
+    }
+
+    pub fn concat(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+
+        let mut left = self.clone();
+        left.flush_tail();
+        if other.tree_len() == 0 {
+            left.tail = other.tail.clone();
+            left.len += other.len;
+            return left;
+        }
+
+        let (root, height) = concat_trees(left.root, left.height, other.root.clone(), other.height);
+        PVec {
+            len: self.len + other.len,
+            height,
+            root,
+            tail: other.tail.clone(),
+        }
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        assert!(range.start <= range.end, "invalid range");
+        assert!(range.end <= self.len, "slice range out of bounds");
+        if range.is_empty() {
+            return PVec::new();
+        }
+
+        let mut vec = self.clone();
+        vec.flush_tail();
+        let mut root = vec.root;
+        let mut height = vec.height;
+        if range.end < vec.len {
+            root = root.take(height, range.end);
+            (root, height) = trim_root(root, height);
+        }
+        if range.start > 0 {
+            root = root.drop(height, range.start);
+            (root, height) = trim_root(root, height);
+        }
+        PVec {
+            len: range.end - range.start,
+            height,
+            root,
+            tail: Rc::new(vec![]),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            vec: self,
+            index: 0,
+            leaf: &[],
+        }
+    }
+
+    fn tree_len(&self) -> usize {
+        self.len - self.tail.len()
+    }
+
+    fn push_mut(&mut self, item: T) {
+        if self.tail.len() == WIDTH {
+            self.flush_tail();
+        }
+        Rc::make_mut(&mut self.tail).push(item);
+        self.len += 1;
+    }
+
+    fn flush_tail(&mut self) {
+        if self.tail.is_empty() {
+            return;
+        }
+        let tree_len = self.tree_len();
+        let leaf = Node::Leaf(core::mem::replace(&mut self.tail, Rc::new(vec![])));
+        if tree_len == 0 {
+            self.root = leaf;
+            self.height = 0;
+            return;
+        }
+        match self.root.push_leaf(self.height, leaf) {
+            Ok(root) => self.root = root,
+            Err(overflow) => {
+                let children = vec![self.root.clone(), overflow];
+                self.height += 1;
+                self.root = Node::branch(children, self.height);
+            }
+        }
+    }
+
+    fn pull_tail(&mut self) {
+        let tree_len = self.tree_len();
+        let (leaf, offset) = self.root.leaf_at(self.height, tree_len - 1);
+        self.tail = Rc::new(leaf.to_vec());
+        if offset == 0 {
+            self.root = Node::Leaf(Rc::new(vec![]));
+            self.height = 0;
+        } else {
+            let root = self.root.take(self.height, offset);
+            (self.root, self.height) = trim_root(root, self.height);
+        }
+    }
+}
+
+impl<T: Clone> Node<T> {
+    fn clone_deep(&self) -> Self {
+        match self {
+            Node::Leaf(items) => Node::Leaf(Rc::new((**items).clone())),
+            Node::Branch(branch) => Node::Branch(Rc::new(Branch {
+                children: branch.children.iter().map(Node::clone_deep).collect(),
+                sizes: branch.sizes.clone(),
+            })),
+        }
+    }
+
+    fn branch(children: Vec<Node<T>>, height: usize) -> Self {
+        let full = 1 << (BITS * height);
+        let n = children.len();
+        let regular = children[..n - 1]
+            .iter()
+            .all(|child| child.len(height - 1) == full);
+        let sizes = if regular {
+            None
+        } else {
+            let mut total = 0;
+            Some(
+                children
+                    .iter()
+                    .map(|child| {
+                        total += child.len(height - 1);
+                        total
+                    })
+                    .collect(),
+            )
+        };
+        Node::Branch(Rc::new(Branch { children, sizes }))
+    }
+
+    fn len(&self, height: usize) -> usize {
+        match self {
+            Node::Leaf(items) => items.len(),
+            Node::Branch(branch) => match &branch.sizes {
+                Some(sizes) => *sizes.last().unwrap(),
+                None => {
+                    let n = branch.children.len();
+                    ((n - 1) << (BITS * height)) + branch.children[n - 1].len(height - 1)
+                }
+            },
+        }
+    }
+
+    fn children(&self) -> &[Node<T>] {
+        match self {
+            Node::Leaf(_) => unreachable!(),
+            Node::Branch(branch) => &branch.children,
+        }
+    }
+
+    fn items(&self) -> &[T] {
+        match self {
+            Node::Leaf(items) => items,
+            Node::Branch(_) => unreachable!(),
+        }
+    }
+
+    /// Find the child containing `index` and return its position together
+    /// with the number of elements stored in the preceding children.
+    fn locate(&self, height: usize, index: usize) -> (usize, usize) {
+        match self {
+            Node::Leaf(_) => unreachable!(),
+            Node::Branch(branch) => match &branch.sizes {
+                None => {
+                    let idx = index >> (BITS * height);
+                    (idx, idx << (BITS * height))
+                }
+                Some(sizes) => {
+                    let idx = sizes.partition_point(|&s| s <= index);
+                    (idx, if idx == 0 { 0 } else { sizes[idx - 1] })
+                }
+            },
+        }
+    }
+
+    fn leaf_at(&self, height: usize, index: usize) -> (&[T], usize) {
+        match self {
+            Node::Leaf(items) => (items, 0),
+            Node::Branch(branch) => {
+                let (idx, offset) = self.locate(height, index);
+                let (leaf, sub_offset) = branch.children[idx].leaf_at(height - 1, index - offset);
+                (leaf, offset + sub_offset)
+            }
+        }
+    }
+
+    fn update(&self, height: usize, index: usize, item: T) -> Self {
+        match self {
+            Node::Leaf(items) => {
+                let mut items = items.clone();
+                Rc::make_mut(&mut items)[index] = item;
+                Node::Leaf(items)
+            }
+            Node::Branch(branch) => {
+                let (idx, offset) = self.locate(height, index);
+                let mut branch = branch.clone();
+                let b = Rc::make_mut(&mut branch);
+                b.children[idx] = b.children[idx].update(height - 1, index - offset, item);
+                Node::Branch(branch)
+            }
+        }
+    }
+
+    /// Append a leaf at the right edge of the tree. If the node is full, the
+    /// leaf is returned wrapped into a new node of the same height.
+    fn push_leaf(&self, height: usize, leaf: Node<T>) -> Result<Self, Self> {
+        if height == 0 {
+            return Err(leaf);
+        }
+        let mut children = self.children().to_vec();
+        let last = children.pop().unwrap();
+        match last.push_leaf(height - 1, leaf) {
+            Ok(new_last) => {
+                children.push(new_last);
+                Ok(Node::branch(children, height))
+            }
+            Err(overflow) if children.len() + 1 < WIDTH => {
+                children.push(last);
+                children.push(overflow);
+                Ok(Node::branch(children, height))
+            }
+            Err(overflow) => Err(Node::branch(vec![overflow], height)),
+        }
+    }
+
+    fn take(&self, height: usize, n: usize) -> Self {
+        match self {
+            Node::Leaf(items) => Node::Leaf(Rc::new(items[..n].to_vec())),
+            Node::Branch(branch) => {
+                let (idx, offset) = self.locate(height, n - 1);
+                let mut children = branch.children[..idx].to_vec();
+                children.push(branch.children[idx].take(height - 1, n - offset));
+                Node::branch(children, height)
+            }
+        }
+    }
+
+    fn drop(&self, height: usize, n: usize) -> Self {
+        match self {
+            Node::Leaf(items) => Node::Leaf(Rc::new(items[n..].to_vec())),
+            Node::Branch(branch) => {
+                let (idx, offset) = self.locate(height, n);
+                let mut children = vec![branch.children[idx].drop(height - 1, n - offset)];
+                children.extend_from_slice(&branch.children[idx + 1..]);
+                Node::branch(children, height)
+            }
+        }
+    }
+}
+
+fn trim_root<T: Clone>(mut root: Node<T>, mut height: usize) -> (Node<T>, usize) {
+    while height > 0 && root.children().len() == 1 {
+        root = root.children()[0].clone();
+        height -= 1;
+    }
+    (root, height)
+}
+
+fn concat_trees<T: Clone>(
+    mut left: Node<T>,
+    mut lh: usize,
+    mut right: Node<T>,
+    mut rh: usize,
+) -> (Node<T>, usize) {
+    while lh < rh {
+        lh += 1;
+        left = Node::branch(vec![left], lh);
+    }
+    while rh < lh {
+        rh += 1;
+        right = Node::branch(vec![right], rh);
+    }
+    let mut nodes = concat_same(&left, &right, lh);
+    if nodes.len() == 1 {
+        (nodes.pop().unwrap(), lh)
+    } else {
+        (Node::branch(nodes, lh + 1), lh + 1)
+    }
+}
+
+/// Concatenate two nodes of equal height, returning one or two nodes of
+/// that height.
+fn concat_same<T: Clone>(left: &Node<T>, right: &Node<T>, height: usize) -> Vec<Node<T>> {
+    if height == 0 {
+        let items = left.items().iter().chain(right.items()).cloned();
+        return pack_leaves(items);
+    }
+
+    let lc = left.children();
+    let rc = right.children();
+    let middle = concat_same(&lc[lc.len() - 1], &rc[0], height - 1);
+
+    let mut children = lc[..lc.len() - 1].to_vec();
+    children.extend(middle);
+    children.extend_from_slice(&rc[1..]);
+    let children = rebalance(children, height - 1);
+
+    children
+        .chunks(WIDTH)
+        .map(|chunk| Node::branch(chunk.to_vec(), height))
+        .collect()
+}
+
+fn rebalance<T: Clone>(nodes: Vec<Node<T>>, height: usize) -> Vec<Node<T>> {
+    if height == 0 {
+        let total: usize = nodes.iter().map(|node| node.items().len()).sum();
+        if nodes.len() <= total.div_ceil(WIDTH) + EXTRA_NODES {
+            return nodes;
+        }
+        pack_leaves(nodes.iter().flat_map(|node| node.items()).cloned())
+    } else {
+        let total: usize = nodes.iter().map(|node| node.children().len()).sum();
+        if nodes.len() <= total.div_ceil(WIDTH) + EXTRA_NODES {
+            return nodes;
+        }
+        let grandchildren: Vec<_> = nodes
+            .iter()
+            .flat_map(|node| node.children())
+            .cloned()
+            .collect();
+        grandchildren
+            .chunks(WIDTH)
+            .map(|chunk| Node::branch(chunk.to_vec(), height))
+            .collect()
+    }
+}
+
+fn pack_leaves<T>(items: impl Iterator<Item = T>) -> Vec<Node<T>> {
+    let mut leaves = vec![];
+    let mut current = Vec::with_capacity(WIDTH);
+    for item in items {
+        if current.len() == WIDTH {
+            leaves.push(Node::Leaf(Rc::new(current)));
+            current = Vec::with_capacity(WIDTH);
+        }
+        current.push(item);
+    }
+    if !current.is_empty() {
+        leaves.push(Node::Leaf(Rc::new(current)));
+    }
+    leaves
+}
+
+impl<T> PVec<T> {
+    /// All nodes with their depth, root first, followed by the tail.
+    fn nodes(&self) -> impl Iterator<Item = (&Node<T>, usize)> {
+        let mut stack = vec![(&self.root, 1)];
+        core::iter::from_fn(move || {
+            let (node, depth) = stack.pop()?;
+            if let Node::Branch(branch) = node {
+                stack.extend(branch.children.iter().map(|child| (child, depth + 1)));
+            }
+            Some((node, depth))
+        })
+    }
+
+    fn node_addresses(&self) -> impl Iterator<Item = usize> + '_ {
+        let tail = Rc::as_ptr(&self.tail) as usize;
+        self.nodes()
+            .map(|(node, _)| match node {
+                Node::Leaf(items) => Rc::as_ptr(items) as usize,
+                Node::Branch(branch) => Rc::as_ptr(branch) as usize,
+            })
+            .chain(core::iter::once(tail))
+    }
+}
+
+impl<T> MemoryFootprint for PVec<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        let leaf = |stats: &mut MemoryStats, items: &Vec<T>| {
+            stats.nodes += 1;
+            stats.heap_bytes += rc_bytes::<Vec<T>>() + items.capacity() * size_of::<T>();
+            stats.used_slots += items.len();
+            stats.total_slots += WIDTH;
+        };
+        let mut stats = MemoryStats::default();
+        for (node, depth) in self.nodes() {
+            stats.depth = stats.depth.max(depth);
+            match node {
+                Node::Leaf(items) => leaf(&mut stats, items),
+                Node::Branch(branch) => {
+                    stats.nodes += 1;
+                    stats.heap_bytes += rc_bytes::<Branch<T>>()
+                        + branch.children.capacity() * size_of::<Node<T>>()
+                        + branch
+                            .sizes
+                            .as_ref()
+                            .map_or(0, |s| s.capacity() * size_of::<usize>());
+                    stats.used_slots += branch.children.len();
+                    stats.total_slots += WIDTH;
+                }
+            }
+        }
+        leaf(&mut stats, &self.tail);
+        stats
+    }
+}
+
+impl<T> SharedFootprint for PVec<T> {
+    fn shares_structure_with(&self, other: &Self) -> SharingStats {
+        compare_nodes(self.node_addresses(), other.node_addresses())
+    }
+}
+
+impl<T: Clone> Default for PVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for PVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for PVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = PVec::new();
+        for item in iter {
+            vec.push_mut(item);
+        }
+        vec
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a PVec<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    vec: &'a PVec<T>,
+    index: usize,
+    leaf: &'a [T],
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.leaf.is_empty() {
+            if self.index >= self.vec.len {
+                return None;
+            }
+            let tree_len = self.vec.tree_len();
+            self.leaf = if self.index >= tree_len {
+                &self.vec.tail[self.index - tree_len..]
+            } else {
+                let (leaf, offset) = self.vec.root.leaf_at(self.vec.height, self.index);
+                &leaf[self.index - offset..]
+            };
+        }
+        let (item, rest) = self.leaf.split_first()?;
+        self.leaf = rest;
+        self.index += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(n: usize) -> PVec<usize> {
+        (0..n).collect()
+    }
+
+    #[test]
+    fn empty_vector() {
+        let v = PVec::<i32>::new();
+        assert!(v.is_empty());
+        assert_eq!(v.get(0), None);
+        assert_eq!(v.iter().count(), 0);
+        assert!(v.pop_back().is_none());
+    }
+
+    #[test]
+    fn push_and_get_many() {
+        let v = range(5000);
+        assert_eq!(v.len(), 5000);
+        for i in 0..5000 {
+            assert_eq!(v.get(i), Some(&i));
+        }
+        assert_eq!(v.get(5000), None);
+    }
+
+    #[test]
+    fn push_keeps_old_version() {
+        let v1 = range(100);
+        let v2 = v1.push_back(100);
+        assert_eq!(v1.len(), 100);
+        assert_eq!(v2.len(), 101);
+        assert_eq!(v2.last(), Some(&100));
+    }
+
+    #[test]
+    fn update_keeps_old_version() {
+        let v1 = range(1000);
+        let v2 = v1.update(500, 0).update(999, 0);
+        assert_eq!(v1.get(500), Some(&500));
+        assert_eq!(v2.get(500), Some(&0));
+        assert_eq!(v2.get(999), Some(&0));
+    }
+
+    #[test]
+    fn pop_back_everything() {
+        let mut v = range(100);
+        for i in (0..100).rev() {
+            let (rest, item) = v.pop_back().unwrap();
+            assert_eq!(item, i);
+            v = rest;
+        }
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn concat_small_vectors() {
+        let a: PVec<_> = (0..3).collect();
+        let b: PVec<_> = (3..7).collect();
+        let c = a.concat(&b);
+        assert_eq!(
+            c.iter().copied().collect::<Vec<_>>(),
+            (0..7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn concat_large_vectors() {
+        for &(n, m) in &[(1, 2000), (33, 1057), (1025, 1025), (40000, 17), (70, 0)] {
+            let a: PVec<_> = (0..n).collect();
+            let b: PVec<_> = (n..n + m).collect();
+            let c = a.concat(&b);
+            assert_eq!(c.len(), n + m);
+            assert!(c.iter().copied().eq(0..n + m));
+            for i in 0..n + m {
+                assert_eq!(c.get(i), Some(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_concatenation() {
+        let mut v = PVec::new();
+        let mut expected = vec![];
+        for i in 0..200 {
+            let chunk: PVec<_> = (0..i % 37).map(|x| x + i).collect();
+            expected.extend(chunk.iter().copied());
+            v = v.concat(&chunk);
+        }
+        assert_eq!(v.iter().copied().collect::<Vec<_>>(), expected);
+        let v = v.push_back(9999);
+        assert_eq!(v.last(), Some(&9999));
+    }
+
+    #[test]
+    fn slice_vector() {
+        let v = range(3000);
+        let s = v.slice(100..2500);
+        assert_eq!(s.len(), 2400);
+        assert!(s.iter().copied().eq(100..2500));
+        assert!(v.slice(0..0).is_empty());
+        assert!(v.slice(2990..3000).iter().copied().eq(2990..3000));
+    }
+
+    #[test]
+    fn push_and_pop_after_slice() {
+        let v = range(1000).slice(10..500);
+        let v = v.push_back(7);
+        assert_eq!(v.len(), 491);
+        assert_eq!(v.last(), Some(&7));
+        let (v, _) = v.pop_back().unwrap();
+        let (v, item) = v.pop_back().unwrap();
+        assert_eq!(item, 499);
+        assert_eq!(v.len(), 489);
+    }
+}
@@ -0,0 +1,422 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Buffer {
+    Original,
+    Added,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    buffer: Buffer,
+    start: usize,
+    len: usize,
+}
+
+impl Piece {
+    /// The pieces before and after the first `at` bytes.
+    fn split(self, at: usize) -> (Piece, Piece) {
+        let left = Piece { len: at, ..self };
+        let right = Piece {
+            start: self.start + at,
+            len: self.len - at,
+            ..self
+        };
+        (left, right)
+    }
+}
+
+/// An edit, recorded as the pieces it replaced at `index` and the pieces
+/// that replaced them. Undoing it swaps them back.
+#[derive(Debug, Clone)]
+struct Edit {
+    index: usize,
+    removed: Vec<Piece>,
+    inserted: Vec<Piece>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PieceTable {
+    original: String,
+    added: String,
+    pieces: Vec<Piece>,
+    len: usize,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl PieceTable {
+    pub fn new() -> Self {
+        Self::from_text(String::new())
+    }
+
+    pub fn from_text(original: impl Into<String>) -> Self {
+        let original = original.into();
+        let len = original.len();
+        let pieces = if len == 0 {
+            vec![]
+        } else {
+            vec![Piece {
+                buffer: Buffer::Original,
+                start: 0,
+                len,
+            }]
+        };
+        PieceTable {
+            original,
+            added: String::new(),
+            pieces,
+            len,
+            undo_stack: vec![],
+            redo_stack: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, pos: usize, text: &str) {
+        assert!(pos <= self.len, "insert position out of bounds");
+        assert!(
+            self.is_char_boundary(pos),
+            "insert position not on a char boundary"
+        );
+        if text.is_empty() {
+            return;
+        }
+
+        let new_piece = Piece {
+            buffer: Buffer::Added,
+            start: self.added.len(),
+            len: text.len(),
+        };
+        self.added.push_str(text);
+
+        let (index, offset) = self.locate(pos);
+        let edit = if pos > offset {
+            let piece = self.pieces[index];
+            let (left, right) = piece.split(pos - offset);
+            Edit {
+                index,
+                removed: vec![piece],
+                inserted: vec![left, new_piece, right],
+            }
+        } else {
+            match index.checked_sub(1).map(|i| self.pieces[i]) {
+                Some(prev)
+                    if prev.buffer == Buffer::Added && prev.start + prev.len == new_piece.start =>
+                {
+                    Edit {
+                        index: index - 1,
+                        removed: vec![prev],
+                        inserted: vec![Piece {
+                            len: prev.len + new_piece.len,
+                            ..prev
+                        }],
+                    }
+                }
+                _ => Edit {
+                    index,
+                    removed: vec![],
+                    inserted: vec![new_piece],
+                },
+            }
+        };
+        self.apply(edit);
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.insert(self.len, text)
+    }
+
+    pub fn delete(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end, "invalid range");
+        assert!(range.end <= self.len, "delete range out of bounds");
+        assert!(
+            self.is_char_boundary(range.start) && self.is_char_boundary(range.end),
+            "delete range not on char boundaries"
+        );
+        if range.is_empty() {
+            return;
+        }
+
+        let (first, first_offset) = self.locate(range.start);
+        let (last, last_offset) = self.locate(range.end - 1);
+        let mut inserted = vec![];
+        if range.start > first_offset {
+            inserted.push(self.pieces[first].split(range.start - first_offset).0);
+        }
+        let tail = self.pieces[last];
+        if range.end < last_offset + tail.len {
+            inserted.push(tail.split(range.end - last_offset).1);
+        }
+        self.apply(Edit {
+            index: first,
+            removed: self.pieces[first..=last].to_vec(),
+            inserted,
+        });
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> String {
+        assert!(range.start <= range.end, "invalid range");
+        assert!(range.end <= self.len, "slice range out of bounds");
+        let mut out = String::with_capacity(range.end - range.start);
+        let mut offset = 0;
+        for chunk in self.chunks() {
+            let chunk_range = offset..offset + chunk.len();
+            offset = chunk_range.end;
+            if chunk_range.end <= range.start {
+                continue;
+            }
+            if chunk_range.start >= range.end {
+                break;
+            }
+            let lo = range.start.max(chunk_range.start) - chunk_range.start;
+            let hi = range.end.min(chunk_range.end) - chunk_range.start;
+            out.push_str(&chunk[lo..hi]);
+        }
+        out
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            None => false,
+            Some(edit) => {
+                self.replace(edit.index, &edit.inserted, &edit.removed);
+                self.redo_stack.push(edit);
+                true
+            }
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            None => false,
+            Some(edit) => {
+                self.replace(edit.index, &edit.removed, &edit.inserted);
+                self.undo_stack.push(edit);
+                true
+            }
+        }
+    }
+
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks {
+            table: self,
+            pieces: self.pieces.iter(),
+        }
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chunks().flat_map(str::chars)
+    }
+
+    fn piece_text(&self, piece: &Piece) -> &str {
+        let buffer = match piece.buffer {
+            Buffer::Original => &self.original,
+            Buffer::Added => &self.added,
+        };
+        &buffer[piece.start..piece.start + piece.len]
+    }
+
+    fn is_char_boundary(&self, pos: usize) -> bool {
+        let (index, offset) = self.locate(pos);
+        match self.pieces.get(index) {
+            Some(piece) => self.piece_text(piece).is_char_boundary(pos - offset),
+            None => pos == offset,
+        }
+    }
+
+    /// The index of the piece containing `pos` and the position where that
+    /// piece starts, or the number of pieces and the length past the end.
+    fn locate(&self, pos: usize) -> (usize, usize) {
+        let mut offset = 0;
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if pos < offset + piece.len {
+                return (i, offset);
+            }
+            offset += piece.len;
+        }
+        (self.pieces.len(), offset)
+    }
+
+    /// Perform a new edit, which clears the redo history.
+    fn apply(&mut self, edit: Edit) {
+        self.replace(edit.index, &edit.removed, &edit.inserted);
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    /// Replace the pieces `old` at `index` with `new`.
+    fn replace(&mut self, index: usize, old: &[Piece], new: &[Piece]) {
+        debug_assert_eq!(&self.pieces[index..index + old.len()], old);
+        self.pieces
+            .splice(index..index + old.len(), new.iter().copied());
+        self.len = self.len + new.iter().map(|p| p.len).sum::<usize>()
+            - old.iter().map(|p| p.len).sum::<usize>();
+    }
+}
+
+impl Default for PieceTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for PieceTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Chunks<'a> {
+    table: &'a PieceTable,
+    pieces: core::slice::Iter<'a, Piece>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let piece = self.pieces.next()?;
+        Some(self.table.piece_text(piece))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_table() {
+        let pt = PieceTable::new();
+        assert!(pt.is_empty());
+        assert_eq!(pt.to_string(), "");
+        assert_eq!(pt.chunks().count(), 0);
+    }
+
+    #[test]
+    fn original_text_is_preserved() {
+        let pt = PieceTable::from_text("hello world");
+        assert_eq!(pt.len(), 11);
+        assert_eq!(pt.to_string(), "hello world");
+    }
+
+    #[test]
+    fn insert_in_the_middle() {
+        let mut pt = PieceTable::from_text("hello world");
+        pt.insert(5, ",");
+        assert_eq!(pt.to_string(), "hello, world");
+        assert_eq!(
+            pt.chunks().collect::<Vec<_>>(),
+            vec!["hello", ",", " world"]
+        );
+    }
+
+    #[test]
+    fn consecutive_appends_share_a_piece() {
+        let mut pt = PieceTable::new();
+        pt.push_str("abc");
+        pt.push_str("def");
+        pt.push_str("ghi");
+        assert_eq!(pt.to_string(), "abcdefghi");
+        assert_eq!(pt.chunks().count(), 1);
+    }
+
+    #[test]
+    fn delete_across_pieces() {
+        let mut pt = PieceTable::from_text("hello world");
+        pt.insert(5, " brave new");
+        pt.delete(3..13);
+        assert_eq!(pt.to_string(), "helew world");
+        assert_eq!(pt.len(), 11);
+    }
+
+    #[test]
+    fn slice_across_pieces() {
+        let mut pt = PieceTable::from_text("hello world");
+        pt.insert(6, "big ");
+        assert_eq!(pt.slice(4..11), "o big w");
+        assert_eq!(pt.slice(0..0), "");
+        assert_eq!(pt.slice(0..pt.len()), "hello big world");
+    }
+
+    #[test]
+    fn undo_and_redo_edits() {
+        let mut pt = PieceTable::from_text("abc");
+        pt.insert(3, "def");
+        pt.delete(0..1);
+        assert_eq!(pt.to_string(), "bcdef");
+        assert!(pt.undo());
+        assert_eq!(pt.to_string(), "abcdef");
+        assert!(pt.undo());
+        assert_eq!(pt.to_string(), "abc");
+        assert!(!pt.undo());
+        assert!(pt.redo());
+        assert_eq!(pt.to_string(), "abcdef");
+        assert_eq!(pt.len(), 6);
+    }
+
+    #[test]
+    fn new_edit_clears_redo_history() {
+        let mut pt = PieceTable::from_text("abc");
+        pt.push_str("d");
+        pt.undo();
+        pt.push_str("e");
+        assert!(!pt.redo());
+        assert_eq!(pt.to_string(), "abce");
+    }
+
+    #[test]
+    fn chars_iterate_logical_text() {
+        let mut pt = PieceTable::from_text("äöü");
+        pt.insert(2, "x");
+        assert_eq!(pt.chars().collect::<String>(), "äxöü");
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_inside_multibyte_char_panics() {
+        let mut pt = PieceTable::from_text("ä");
+        pt.insert(1, "x");
+    }
+
+    #[test]
+    fn undo_and_redo_replay_every_edit() {
+        let mut pt = PieceTable::from_text("the quick brown fox");
+        let mut versions = vec![pt.to_string()];
+        pt.insert(4, "very ");
+        versions.push(pt.to_string());
+        pt.delete(0..4);
+        versions.push(pt.to_string());
+        pt.insert(pt.len(), " jumps");
+        versions.push(pt.to_string());
+        pt.delete(5..20);
+        versions.push(pt.to_string());
+        pt.insert(7, "!");
+        versions.push(pt.to_string());
+        for version in versions.iter().rev().skip(1) {
+            assert!(pt.undo());
+            assert_eq!(&pt.to_string(), version);
+            assert_eq!(pt.len(), version.len());
+        }
+        assert_eq!(pt.chunks().count(), 1);
+        for version in &versions[1..] {
+            assert!(pt.redo());
+            assert_eq!(&pt.to_string(), version);
+        }
+        assert!(!pt.redo());
+    }
+}
@@ -0,0 +1,166 @@
+//! Indented ASCII renderings of trees for debugging in a terminal.
+//!
+//! ```text
+//! 5
+//! +-- 3
+//! |   +-- 1
+//! |   `-- 4
+//! `-- 8
+//!     +-- -
+//!     `-- 9
+//! ```
+//!
+//! A `-` marks a missing child whose sibling exists, so left and right can
+//! be told apart.
+
+use crate::allocator::Allocator;
+use crate::binary_search_tree::BinarySearchTree;
+use crate::compare::Compare;
+use crate::heap::Heap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Write};
+
+/// Render the tree below `root`, one node per line.
+///
+/// `children` returns the child slots of a node; `None` is drawn as a
+/// missing child. Nodes whose slots are all `None` are drawn as leaves.
+pub fn render<N>(
+    root: &N,
+    label: impl Fn(&N) -> String,
+    children: impl Fn(&N) -> Vec<Option<N>>,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&label(root));
+    out.push('\n');
+    render_children(&mut out, root, "", &label, &children);
+    out
+}
+
+fn render_children<N>(
+    out: &mut String,
+    node: &N,
+    prefix: &str,
+    label: &impl Fn(&N) -> String,
+    children: &impl Fn(&N) -> Vec<Option<N>>,
+) {
+    let slots = children(node);
+    if slots.iter().all(Option::is_none) {
+        return;
+    }
+    let count = slots.len();
+    for (i, slot) in slots.into_iter().enumerate() {
+        let last = i + 1 == count;
+        let (branch, indent) = if last {
+            ("`-- ", "    ")
+        } else {
+            ("+-- ", "|   ")
+        };
+        match slot {
+            None => {
+                let _ = writeln!(out, "{prefix}{branch}-");
+            }
+            Some(child) => {
+                let _ = writeln!(out, "{prefix}{branch}{}", label(&child));
+                render_children(out, &child, &format!("{prefix}{indent}"), label, children);
+            }
+        }
+    }
+}
+
+impl<T: Clone + Debug, C: Compare<T> + Clone> BinarySearchTree<T, C> {
+    /// An ASCII drawing of the tree, with the left child above the right.
+    pub fn display_tree(&self) -> String {
+        if self.is_empty() {
+            return String::from("(empty)\n");
+        }
+        render(
+            self,
+            |tree| format!("{:?}", tree.item().unwrap()),
+            |tree| {
+                [tree.left().unwrap(), tree.right().unwrap()]
+                    .into_iter()
+                    .map(|child| (!child.is_empty()).then(|| child.clone()))
+                    .collect()
+            },
+        )
+    }
+}
+
+impl<T: Debug, C: Compare<T>, A: Allocator> Heap<T, C, A> {
+    /// An ASCII drawing of the heap as the implicit binary tree it stores.
+    pub fn display_tree(&self) -> String {
+        let items = self.as_slice();
+        if items.is_empty() {
+            return String::from("(empty)\n");
+        }
+        render(
+            &0,
+            |&i| format!("{:?}", items[i]),
+            |&i| {
+                [2 * i + 1, 2 * i + 2]
+                    .into_iter()
+                    .map(|child| (child < items.len()).then_some(child))
+                    .collect()
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().collect()
+    }
+
+    #[test]
+    fn binary_search_tree() {
+        let tree = [5, 3, 8, 1, 4, 9]
+            .into_iter()
+            .fold(BinarySearchTree::new(), |t, x| t.insert(x));
+        assert_eq!(
+            lines(&tree.display_tree()),
+            vec![
+                "5",
+                "+-- 3",
+                "|   +-- 1",
+                "|   `-- 4",
+                "`-- 8",
+                "    +-- -",
+                "    `-- 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn heap_as_implicit_tree() {
+        let heap = Heap::from_vec(vec![1, 2, 3, 4]);
+        assert_eq!(
+            lines(&heap.display_tree()),
+            vec!["4", "+-- 3", "|   +-- 1", "|   `-- -", "`-- 2"]
+        );
+    }
+
+    #[test]
+    fn empty_structures() {
+        assert_eq!(BinarySearchTree::<u8>::new().display_tree(), "(empty)\n");
+        assert_eq!(Heap::<u8>::new().display_tree(), "(empty)\n");
+    }
+
+    #[test]
+    fn generic_trees() {
+        let text = render(
+            &3u32,
+            |n| format!("n{n}"),
+            |&n| (0..n).map(|c| (c > 0).then_some(c)).collect(),
+        );
+        assert_eq!(
+            lines(&text),
+            vec!["n3", "+-- -", "+-- n1", "`-- n2", "    +-- -", "    `-- n1"]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! A map whose entries also have a priority and an expiry time.
+//!
+//! The entries live in a `Slab`. A `BTreeMap` finds them by key, and two
+//! ordered sets of `(priority, slot)` and `(expiry, slot)` find them by
+//! priority and by expiry. Every operation updates all three indexes
+//! together, in O(log n).
+
+use crate::slab::Slab;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+
+struct Record<K, V, P, T> {
+    key: K,
+    value: V,
+    priority: P,
+    expires: T,
+}
+
+/// A map from `K` to `V` in which every entry has a priority `P` and an
+/// expiry time `T`. Entries with equal priorities pop in no particular
+/// order.
+pub struct PriorityExpiryMap<K, V, P, T> {
+    records: Slab<Record<K, V, P, T>>,
+    index: BTreeMap<K, usize>,
+    by_priority: BTreeSet<(P, usize)>,
+    by_expiry: BTreeSet<(T, usize)>,
+}
+
+impl<K, V, P, T> PriorityExpiryMap<K, V, P, T>
+where
+    K: Ord + Clone,
+    P: Ord + Clone,
+    T: Ord + Clone,
+{
+    pub fn new() -> Self {
+        PriorityExpiryMap {
+            records: Slab::new(),
+            index: BTreeMap::new(),
+            by_priority: BTreeSet::new(),
+            by_expiry: BTreeSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.record(key).map(|r| &r.value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let slot = *self.index.get(key)?;
+        self.records.get_mut(slot).map(|r| &mut r.value)
+    }
+
+    pub fn priority(&self, key: &K) -> Option<&P> {
+        self.record(key).map(|r| &r.priority)
+    }
+
+    pub fn expiry(&self, key: &K) -> Option<&T> {
+        self.record(key).map(|r| &r.expires)
+    }
+
+    /// Insert an entry, replacing the value, priority and expiry time of an
+    /// existing entry for `key`. Returns the old value.
+    pub fn insert(&mut self, key: K, value: V, priority: P, expires: T) -> Option<V> {
+        if let Some(&slot) = self.index.get(&key) {
+            self.set_priority(&key, priority);
+            self.set_expiry(&key, expires);
+            return Some(core::mem::replace(&mut self.records[slot].value, value));
+        }
+        let slot = self.records.insert(Record {
+            key: key.clone(),
+            value,
+            priority: priority.clone(),
+            expires: expires.clone(),
+        });
+        self.index.insert(key, slot);
+        self.by_priority.insert((priority, slot));
+        self.by_expiry.insert((expires, slot));
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = *self.index.get(key)?;
+        Some(self.remove_slot(slot).1)
+    }
+
+    /// Change the priority of an entry. Returns `false` if `key` is missing.
+    pub fn set_priority(&mut self, key: &K, priority: P) -> bool {
+        let Some(&slot) = self.index.get(key) else {
+            return false;
+        };
+        let record = &mut self.records[slot];
+        self.by_priority.remove(&(record.priority.clone(), slot));
+        self.by_priority.insert((priority.clone(), slot));
+        record.priority = priority;
+        true
+    }
+
+    /// Change the expiry time of an entry. Returns `false` if `key` is
+    /// missing.
+    pub fn set_expiry(&mut self, key: &K, expires: T) -> bool {
+        let Some(&slot) = self.index.get(key) else {
+            return false;
+        };
+        let record = &mut self.records[slot];
+        self.by_expiry.remove(&(record.expires.clone(), slot));
+        self.by_expiry.insert((expires.clone(), slot));
+        record.expires = expires;
+        true
+    }
+
+    /// The entry with the highest priority.
+    pub fn peek_highest_priority(&self) -> Option<(&K, &V, &P)> {
+        let &(_, slot) = self.by_priority.last()?;
+        let r = &self.records[slot];
+        Some((&r.key, &r.value, &r.priority))
+    }
+
+    pub fn pop_highest_priority(&mut self) -> Option<(K, V)> {
+        let &(_, slot) = self.by_priority.last()?;
+        Some(self.remove_slot(slot))
+    }
+
+    /// The earliest expiry time of any entry.
+    pub fn next_expiry(&self) -> Option<&T> {
+        self.by_expiry.first().map(|(t, _)| t)
+    }
+
+    /// Remove the entries that expire before `t`, earliest first. Entries
+    /// the iterator does not reach stay in the map.
+    pub fn expire_older_than(&mut self, t: T) -> Expired<'_, K, V, P, T> {
+        Expired { map: self, t }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.records.iter().map(|(_, r)| (&r.key, &r.value))
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.index.clear();
+        self.by_priority.clear();
+        self.by_expiry.clear();
+    }
+
+    fn record(&self, key: &K) -> Option<&Record<K, V, P, T>> {
+        self.records.get(*self.index.get(key)?)
+    }
+
+    fn remove_slot(&mut self, slot: usize) -> (K, V) {
+        let r = self
+            .records
+            .remove(slot)
+            .expect("indexes refer to live slots");
+        self.index.remove(&r.key);
+        self.by_priority.remove(&(r.priority, slot));
+        self.by_expiry.remove(&(r.expires, slot));
+        (r.key, r.value)
+    }
+}
+
+impl<K, V, P, T> Default for PriorityExpiryMap<K, V, P, T>
+where
+    K: Ord + Clone,
+    P: Ord + Clone,
+    T: Ord + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, P: fmt::Debug, T: fmt::Debug> fmt::Debug
+    for PriorityExpiryMap<K, V, P, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.records
+                    .iter()
+                    .map(|(_, r)| (&r.key, &r.value, &r.priority, &r.expires)),
+            )
+            .finish()
+    }
+}
+
+/// Iterator returned by `PriorityExpiryMap::expire_older_than`.
+pub struct Expired<'a, K, V, P, T> {
+    map: &'a mut PriorityExpiryMap<K, V, P, T>,
+    t: T,
+}
+
+impl<K, V, P, T> Iterator for Expired<'_, K, V, P, T>
+where
+    K: Ord + Clone,
+    P: Ord + Clone,
+    T: Ord + Clone,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let (expires, slot) = self.map.by_expiry.first()?;
+        if *expires >= self.t {
+            return None;
+        }
+        let slot = *slot;
+        Some(self.map.remove_slot(slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_by_priority() {
+        let mut map = PriorityExpiryMap::new();
+        map.insert("a", 1, 5, 100);
+        map.insert("b", 2, 9, 100);
+        map.insert("c", 3, 1, 100);
+        assert_eq!(map.peek_highest_priority(), Some((&"b", &2, &9)));
+        assert!(map.set_priority(&"c", 10));
+        assert_eq!(map.pop_highest_priority(), Some(("c", 3)));
+        assert_eq!(map.pop_highest_priority(), Some(("b", 2)));
+        assert_eq!(map.pop_highest_priority(), Some(("a", 1)));
+        assert_eq!(map.pop_highest_priority(), None);
+        assert!(!map.set_priority(&"a", 0));
+    }
+
+    #[test]
+    fn expires_in_time_order() {
+        let mut map = PriorityExpiryMap::new();
+        for (i, t) in [30, 10, 20, 40].into_iter().enumerate() {
+            map.insert(i, (), 0, t);
+        }
+        map.set_expiry(&3, 5);
+        assert_eq!(map.next_expiry(), Some(&5));
+        let expired: Vec<_> = map.expire_older_than(21).map(|(k, _)| k).collect();
+        assert_eq!(expired, vec![3, 1, 2]);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.expire_older_than(30).count(), 0);
+        assert_eq!(map.expiry(&0), Some(&30));
+    }
+
+    #[test]
+    fn unconsumed_expired_entries_stay() {
+        let mut map = PriorityExpiryMap::new();
+        for i in 0..5 {
+            map.insert(i, i, i, i);
+        }
+        assert_eq!(map.expire_older_than(10).next(), Some((0, 0)));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.peek_highest_priority(), Some((&4, &4, &4)));
+    }
+
+    #[test]
+    fn insert_replaces_all_three_parts() {
+        let mut map = PriorityExpiryMap::new();
+        assert_eq!(map.insert("k", 1, 1, 1), None);
+        map.insert("other", 0, 5, 5);
+        assert_eq!(map.insert("k", 2, 9, 9), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.next_expiry(), Some(&5));
+        assert_eq!(map.peek_highest_priority(), Some((&"k", &2, &9)));
+        *map.get_mut(&"k").unwrap() += 1;
+        assert_eq!(map.remove(&"k"), Some(3));
+        assert_eq!(map.pop_highest_priority(), Some(("other", 0)));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn indexes_stay_in_sync() {
+        let mut map = PriorityExpiryMap::new();
+        let mut model: BTreeMap<u32, (u32, u32)> = BTreeMap::new();
+        let mut x: u32 = 7;
+        for _ in 0..2000 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            let key = x % 50;
+            match x % 7 {
+                0 => assert_eq!(map.remove(&key).is_some(), model.remove(&key).is_some()),
+                1 => {
+                    let popped = map.pop_highest_priority();
+                    let best = model.values().map(|&(p, _)| p).max();
+                    assert_eq!(popped.map(|(k, _)| model.remove(&k).unwrap().0), best);
+                }
+                2 => {
+                    let t = x % 1000;
+                    for (k, _) in map.expire_older_than(t) {
+                        assert!(model.remove(&k).unwrap().1 < t);
+                    }
+                    assert!(model.values().all(|&(_, e)| e >= t));
+                }
+                _ => {
+                    map.insert(key, (), x % 100, x % 1000);
+                    model.insert(key, (x % 100, x % 1000));
+                }
+            }
+            assert_eq!(map.len(), model.len());
+        }
+    }
+}
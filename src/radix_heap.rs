@@ -0,0 +1,177 @@
+//! A min-priority queue for monotone `u64` keys.
+//!
+//! A radix heap only accepts keys that are not smaller than the last key
+//! it popped, which holds for Dijkstra's algorithm with non-negative
+//! weights. Items are kept in 65 buckets by the highest bit in which their
+//! key differs from the last popped key. Popping empties the first
+//! non-empty bucket into lower ones; every item moves to a lower bucket at
+//! most 64 times, so operations take amortized O(log C) for keys up to `C`
+//! with cheap integer work instead of comparisons.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+const BUCKETS: usize = 65;
+
+#[derive(Clone)]
+pub struct RadixHeap<V> {
+    buckets: [Vec<(u64, V)>; BUCKETS],
+    /// The last popped key; all keys in the heap are at least this.
+    last: u64,
+    len: usize,
+}
+
+impl<V> RadixHeap<V> {
+    pub fn new() -> Self {
+        RadixHeap {
+            buckets: core::array::from_fn(|_| Vec::new()),
+            last: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The smallest key that may still be pushed.
+    pub fn last_key(&self) -> u64 {
+        self.last
+    }
+
+    /// Panics if `key` is smaller than the last popped key.
+    pub fn push(&mut self, key: u64, value: V) {
+        assert!(
+            key >= self.last,
+            "keys must not decrease below the last pop"
+        );
+        self.buckets[bucket(key, self.last)].push((key, value));
+        self.len += 1;
+    }
+
+    /// Remove an item with the smallest key.
+    pub fn pop(&mut self) -> Option<(u64, V)> {
+        if self.buckets[0].is_empty() {
+            let i = self.buckets.iter().position(|b| !b.is_empty())?;
+            let mut items = core::mem::take(&mut self.buckets[i]);
+            self.last = items.iter().map(|&(key, _)| key).min().unwrap();
+            // every item lands in a bucket below `i`
+            for (key, value) in items.drain(..) {
+                self.buckets[bucket(key, self.last)].push((key, value));
+            }
+            self.buckets[i] = items;
+        }
+        self.len -= 1;
+        self.buckets[0].pop()
+    }
+
+    /// The smallest key, in O(n) if it is not the last popped key.
+    pub fn peek_key(&self) -> Option<u64> {
+        if !self.buckets[0].is_empty() {
+            return Some(self.last);
+        }
+        let bucket = self.buckets.iter().find(|b| !b.is_empty())?;
+        bucket.iter().map(|&(key, _)| key).min()
+    }
+
+    /// Remove all items and accept any key again.
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.clear();
+        }
+        self.last = 0;
+        self.len = 0;
+    }
+}
+
+/// Bucket 0 holds keys equal to `last`; bucket `i` the keys whose highest
+/// bit that differs from `last` is bit `i - 1`.
+fn bucket(key: u64, last: u64) -> usize {
+    (u64::BITS - (key ^ last).leading_zeros()) as usize
+}
+
+impl<V> Default for RadixHeap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for RadixHeap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RadixHeap")
+            .field("last", &self.last)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Reverse;
+    use crate::heap::Heap;
+
+    #[test]
+    fn pops_in_key_order() {
+        let mut heap = RadixHeap::new();
+        for (key, value) in [(5, 'a'), (1, 'b'), (9, 'c'), (1, 'd'), (300, 'e')] {
+            heap.push(key, value);
+        }
+        assert_eq!(heap.peek_key(), Some(1));
+        let mut keys = Vec::new();
+        while let Some((key, _)) = heap.pop() {
+            keys.push(key);
+        }
+        assert_eq!(keys, [1, 1, 5, 9, 300]);
+        assert!(heap.is_empty());
+        assert_eq!(heap.last_key(), 300);
+    }
+
+    #[test]
+    fn monotone_interleaving_matches_binary_heap() {
+        let mut radix = RadixHeap::new();
+        let mut heap: Heap<u64, Reverse> = Heap::with_comparator(Reverse::default());
+        let mut x: u64 = 11;
+        for round in 0..5000 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            if round % 3 != 2 {
+                let key = radix.last_key() + x % 1000;
+                radix.push(key, round);
+                heap.push(key);
+            } else {
+                assert_eq!(radix.pop().map(|(k, _)| k), heap.pop());
+            }
+        }
+        assert_eq!(radix.len(), heap.len());
+    }
+
+    #[test]
+    fn extreme_keys() {
+        let mut heap = RadixHeap::new();
+        heap.push(u64::MAX, ());
+        heap.push(0, ());
+        heap.push(u64::MAX - 1, ());
+        assert_eq!(heap.pop(), Some((0, ())));
+        assert_eq!(heap.pop(), Some((u64::MAX - 1, ())));
+        assert_eq!(heap.pop(), Some((u64::MAX, ())));
+        assert_eq!(heap.pop(), None);
+        heap.clear();
+        heap.push(3, ());
+        assert_eq!(heap.peek_key(), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "must not decrease")]
+    fn decreasing_keys_are_rejected() {
+        let mut heap = RadixHeap::new();
+        heap.push(10, ());
+        heap.pop();
+        heap.push(9, ());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::interval_map::IntervalMap;
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::Range;
+
+/// A set of points stored as disjoint, non-touching half-open ranges.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RangeSet<K> {
+    map: IntervalMap<K, ()>,
+}
+
+impl<K: Ord + Clone> RangeSet<K> {
+    pub fn new() -> Self {
+        RangeSet {
+            map: IntervalMap::new(),
+        }
+    }
+
+    /// Number of disjoint ranges.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn insert(&mut self, range: Range<K>) {
+        self.map.insert(range, ());
+    }
+
+    pub fn remove(&mut self, range: Range<K>) {
+        self.map.remove(range);
+    }
+
+    pub fn contains<Q>(&self, point: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.map.contains_point(point)
+    }
+
+    /// The stored range that contains `point`.
+    pub fn range_containing<Q>(&self, point: &Q) -> Option<Range<K>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.map.get_range_value(point).map(|(r, _)| r)
+    }
+
+    /// Whether every point of `range` is in the set.
+    pub fn contains_range(&self, range: &Range<K>) -> bool {
+        range.is_empty()
+            || self
+                .range_containing(&range.start)
+                .is_some_and(|r| r.end >= range.end)
+    }
+
+    /// Whether any point of `range` is in the set.
+    pub fn overlaps(&self, range: &Range<K>) -> bool {
+        self.map.overlapping(range.clone()).next().is_some()
+    }
+
+    /// All ranges in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Range<K>> + '_ {
+        self.map.iter().map(|(r, _)| r)
+    }
+
+    /// The maximal ranges within `within` that are not in the set, in
+    /// ascending order.
+    pub fn gaps(&self, within: Range<K>) -> impl Iterator<Item = Range<K>> + '_ {
+        let mut cursor = Some(within.start.clone());
+        let end = within.end.clone();
+        let mut covered = self.map.overlapping(within).map(|(r, _)| r);
+        core::iter::from_fn(move || loop {
+            let from = cursor.take()?;
+            if from >= end {
+                return None;
+            }
+            match covered.next() {
+                None => return Some(from..end.clone()),
+                Some(r) => {
+                    cursor = Some(r.end.clone());
+                    if from < r.start {
+                        return Some(from..r.start);
+                    }
+                    // `from` lies inside `r`; continue behind it
+                }
+            }
+        })
+    }
+
+    /// The points of `within` that are not in the set.
+    pub fn complement(&self, within: Range<K>) -> Self {
+        self.gaps(within).collect()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.extend(other.iter());
+        result
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = RangeSet::new();
+        let mut a = self.iter().peekable();
+        let mut b = other.iter().peekable();
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            let start = x.start.clone().max(y.start.clone());
+            let end = x.end.clone().min(y.end.clone());
+            if start < end {
+                result.insert(start..end);
+            }
+            // drop whichever range ends first; it cannot overlap anything else
+            if x.end < y.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        result
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for r in other.iter() {
+            result.remove(r);
+        }
+        result
+    }
+}
+
+impl<K: Ord + Clone> Default for RangeSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone + fmt::Debug> fmt::Debug for RangeSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord + Clone> Extend<Range<K>> for RangeSet<K> {
+    fn extend<I: IntoIterator<Item = Range<K>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+impl<K: Ord + Clone> FromIterator<Range<K>> for RangeSet<K> {
+    fn from_iter<I: IntoIterator<Item = Range<K>>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[Range<u32>]) -> RangeSet<u32> {
+        ranges.iter().cloned().collect()
+    }
+
+    fn ranges(set: &RangeSet<u32>) -> Vec<Range<u32>> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn insert_merges_touching_ranges() {
+        let s = set(&[0..5, 10..15, 5..7, 14..20]);
+        assert_eq!(ranges(&s), vec![0..7, 10..20]);
+        assert!(s.contains(&6));
+        assert!(!s.contains(&7));
+        assert_eq!(s.range_containing(&12), Some(10..20));
+    }
+
+    #[test]
+    fn remove_splits_ranges() {
+        let mut s: RangeSet<u32> = std::iter::once(0..10).collect();
+        s.remove(3..5);
+        assert_eq!(ranges(&s), vec![0..3, 5..10]);
+        assert!(s.contains_range(&(5..10)));
+        assert!(!s.contains_range(&(2..6)));
+        assert!(s.overlaps(&(2..6)));
+        assert!(!s.overlaps(&(3..5)));
+    }
+
+    #[test]
+    fn gaps_and_complement() {
+        let s = set(&[2..4, 6..8, 12..20]);
+        assert_eq!(s.gaps(0..15).collect::<Vec<_>>(), vec![0..2, 4..6, 8..12]);
+        assert_eq!(s.gaps(3..7).collect::<Vec<_>>(), vec![4..6]);
+        assert_eq!(s.gaps(13..18).count(), 0);
+        assert_eq!(
+            ranges(&s.complement(0..25)),
+            vec![0..2, 4..6, 8..12, 20..25]
+        );
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 40..50]);
+        assert_eq!(ranges(&a.union(&b)), vec![0..30, 40..50]);
+        assert_eq!(ranges(&a.intersection(&b)), vec![5..10, 20..25]);
+        assert_eq!(ranges(&a.difference(&b)), vec![0..5, 25..30]);
+        assert!(a.intersection(&RangeSet::new()).is_empty());
+    }
+
+    #[test]
+    fn free_space_tracking() {
+        let mut free: RangeSet<u32> = std::iter::once(0..1024).collect();
+        let mut allocated = vec![];
+        for size in [100, 200, 50] {
+            let block = free.iter().find(|r| r.end - r.start >= size).unwrap();
+            let range = block.start..block.start + size;
+            free.remove(range.clone());
+            allocated.push(range);
+        }
+        assert_eq!(ranges(&free), vec![350..1024]);
+        free.insert(allocated[1].clone());
+        assert_eq!(ranges(&free), vec![100..300, 350..1024]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::stream::{Stream, StreamNode};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Okasaki's real-time queue. Every operation runs in worst-case O(1) time,
+/// even when old versions are reused.
+///
+/// The rear list is moved to the front by a lazy rotation once it becomes
+/// longer than the front. Instead of waiting for a reader to force the whole
+/// rotation, every operation forces one more step of it through `schedule`,
+/// which always points at the first unevaluated cell of `front`.
+pub struct RealTimeQueue<T> {
+    len: usize,
+    front: Stream<T>,
+    rear: Stream<T>,
+    schedule: Stream<T>,
+}
+
+impl<T> Clone for RealTimeQueue<T> {
+    fn clone(&self) -> Self {
+        RealTimeQueue {
+            len: self.len,
+            front: self.front.clone(),
+            rear: self.rear.clone(),
+            schedule: self.schedule.clone(),
+        }
+    }
+}
+
+impl<T: Clone> RealTimeQueue<T> {
+    pub fn new() -> Self {
+        RealTimeQueue {
+            len: 0,
+            front: Stream::empty(),
+            rear: Stream::empty(),
+            schedule: Stream::empty(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.front.head()
+    }
+
+    pub fn push_back(&self, item: T) -> Self {
+        Self::exec(
+            self.len + 1,
+            self.front.clone(),
+            Stream::cons(item, self.rear.clone()),
+            &self.schedule,
+        )
+    }
+
+    pub fn pop_front(&self) -> Option<(T, Self)> {
+        match self.front.force() {
+            StreamNode::Nil => None,
+            StreamNode::Cons(item, rest) => {
+                let queue = Self::exec(
+                    self.len - 1,
+                    rest.clone(),
+                    self.rear.clone(),
+                    &self.schedule,
+                );
+                Some((item.clone(), queue))
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let rear: Vec<&T> = self.rear.iter().collect();
+        self.front.iter().chain(rear.into_iter().rev())
+    }
+
+    /// Advance the schedule by one step, or start a new rotation when the
+    /// previous one is complete (which is exactly when the rear has become
+    /// one longer than the front).
+    fn exec(len: usize, front: Stream<T>, rear: Stream<T>, schedule: &Stream<T>) -> Self {
+        match schedule.force() {
+            StreamNode::Cons(_, rest) => RealTimeQueue {
+                len,
+                front,
+                rear,
+                schedule: rest.clone(),
+            },
+            StreamNode::Nil => {
+                let front = front.rotate(&rear, &Stream::empty());
+                RealTimeQueue {
+                    len,
+                    schedule: front.clone(),
+                    front,
+                    rear: Stream::empty(),
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone> Default for RealTimeQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for RealTimeQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for RealTimeQueue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for RealTimeQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(RealTimeQueue::new(), |queue, item| queue.push_back(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_queue() {
+        let q = RealTimeQueue::<i32>::new();
+        assert!(q.is_empty());
+        assert_eq!(q.front(), None);
+        assert!(q.pop_front().is_none());
+    }
+
+    #[test]
+    fn fifo_order() {
+        let mut q: RealTimeQueue<_> = (0..100).collect();
+        assert_eq!(q.len(), 100);
+        for i in 0..100 {
+            assert_eq!(q.front(), Some(&i));
+            let (item, rest) = q.pop_front().unwrap();
+            assert_eq!(item, i);
+            q = rest;
+        }
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn interleaved_pushes_and_pops() {
+        let mut q = RealTimeQueue::new();
+        let mut expected = std::collections::VecDeque::new();
+        for i in 0..500 {
+            q = q.push_back(i);
+            expected.push_back(i);
+            if i % 3 == 2 {
+                let (item, rest) = q.pop_front().unwrap();
+                assert_eq!(Some(item), expected.pop_front());
+                q = rest;
+            }
+        }
+        assert!(q.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn old_versions_are_unaffected() {
+        let q1: RealTimeQueue<_> = (0..5).collect();
+        let q2 = q1.push_back(5);
+        let (_, q3) = q1.pop_front().unwrap();
+        assert!(q1.iter().copied().eq(0..5));
+        assert!(q2.iter().copied().eq(0..6));
+        assert!(q3.iter().copied().eq(1..5));
+        assert_ne!(q1, q3);
+    }
+
+    #[test]
+    fn long_queue() {
+        // rotations are forced step by step, so even long queues never
+        // build deep chains of suspensions
+        let mut q: RealTimeQueue<_> = (0..100_000).collect();
+        for i in 0..100_000 {
+            let (item, rest) = q.pop_front().unwrap();
+            assert_eq!(item, i);
+            q = rest;
+        }
+    }
+}
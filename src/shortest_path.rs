@@ -0,0 +1,358 @@
+//! Dijkstra's algorithm on graphs given as a neighbor function.
+//!
+//! Nodes are numbered `0..n`, and `edges(u)` lists `(v, weight)` pairs.
+//! A `ShortestPathTree` keeps its buffers between searches and only resets
+//! the nodes the last search touched, so repeated queries on a large graph
+//! cost only as much as the part of the graph they explore.
+//!
+//! The priority queue is a type parameter. The default binary heap works
+//! for any weight type; for `u64` weights a `RadixHeap` is usually faster.
+
+use crate::compare::Reverse;
+use crate::heap::Heap;
+use crate::radix_heap::RadixHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Add;
+
+const NONE: usize = usize::MAX;
+
+/// Result of a search: the distance to every settled node and the edge
+/// through which it was reached.
+#[derive(Clone)]
+pub struct ShortestPathTree<W, Q = Heap<(W, usize), Reverse>> {
+    dist: Vec<Option<W>>,
+    parent: Vec<usize>,
+    settled: Vec<bool>,
+    touched: Vec<usize>,
+    queue: Q,
+    target: Option<usize>,
+}
+
+/// A min-priority queue of tentative `(distance, node)` pairs.
+pub trait DistanceQueue<W> {
+    fn push(&mut self, dist: W, node: usize);
+
+    /// Remove a pair with the smallest distance.
+    fn pop(&mut self) -> Option<(W, usize)>;
+
+    fn clear(&mut self);
+}
+
+impl<W: Ord> DistanceQueue<W> for Heap<(W, usize), Reverse> {
+    fn push(&mut self, dist: W, node: usize) {
+        Heap::push(self, (dist, node));
+    }
+
+    fn pop(&mut self) -> Option<(W, usize)> {
+        Heap::pop(self)
+    }
+
+    fn clear(&mut self) {
+        self.drain();
+    }
+}
+
+/// Dijkstra only pushes distances that are at least the last popped one,
+/// which is all a radix heap needs.
+impl DistanceQueue<u64> for RadixHeap<usize> {
+    fn push(&mut self, dist: u64, node: usize) {
+        RadixHeap::push(self, dist, node);
+    }
+
+    fn pop(&mut self) -> Option<(u64, usize)> {
+        RadixHeap::pop(self)
+    }
+
+    fn clear(&mut self) {
+        RadixHeap::clear(self);
+    }
+}
+
+/// Distances from `source` to all reachable nodes.
+pub fn dijkstra<W, E>(
+    nodes: usize,
+    source: usize,
+    edges: impl FnMut(usize) -> E,
+) -> ShortestPathTree<W>
+where
+    W: Copy + Ord + Add<Output = W> + Default,
+    E: IntoIterator<Item = (usize, W)>,
+{
+    dijkstra_multi(nodes, [source], edges)
+}
+
+/// Distances to all reachable nodes from the nearest of `sources`.
+pub fn dijkstra_multi<W, E>(
+    nodes: usize,
+    sources: impl IntoIterator<Item = usize>,
+    edges: impl FnMut(usize) -> E,
+) -> ShortestPathTree<W>
+where
+    W: Copy + Ord + Add<Output = W> + Default,
+    E: IntoIterator<Item = (usize, W)>,
+{
+    let mut tree = ShortestPathTree::new(nodes);
+    tree.search(sources, edges);
+    tree
+}
+
+/// Like `dijkstra`, with integer weights and a radix heap as the queue.
+pub fn dijkstra_radix<E>(
+    nodes: usize,
+    source: usize,
+    edges: impl FnMut(usize) -> E,
+) -> ShortestPathTree<u64, RadixHeap<usize>>
+where
+    E: IntoIterator<Item = (usize, u64)>,
+{
+    let mut tree = ShortestPathTree::with_queue(nodes, RadixHeap::new());
+    tree.search([source], edges);
+    tree
+}
+
+impl<W: Copy + Ord + Add<Output = W> + Default> ShortestPathTree<W> {
+    /// Buffers for searches on graphs with up to `nodes` nodes.
+    pub fn new(nodes: usize) -> Self {
+        Self::with_queue(nodes, Heap::with_comparator(Reverse::default()))
+    }
+}
+
+impl<W, Q> ShortestPathTree<W, Q>
+where
+    W: Copy + Ord + Add<Output = W> + Default,
+    Q: DistanceQueue<W>,
+{
+    /// Like `new`, but with `queue` as the priority queue.
+    pub fn with_queue(nodes: usize, mut queue: Q) -> Self {
+        queue.clear();
+        ShortestPathTree {
+            dist: vec![None; nodes],
+            parent: vec![NONE; nodes],
+            settled: vec![false; nodes],
+            touched: Vec::new(),
+            queue,
+            target: None,
+        }
+    }
+
+    /// Search from all `sources` at once, replacing the previous result.
+    pub fn search<E>(
+        &mut self,
+        sources: impl IntoIterator<Item = usize>,
+        edges: impl FnMut(usize) -> E,
+    ) where
+        E: IntoIterator<Item = (usize, W)>,
+    {
+        self.search_until(sources, |_| false, edges);
+    }
+
+    /// Like `search`, but stop as soon as a node for which `is_target`
+    /// returns true is settled, and return that node. Only the nodes
+    /// settled up to then have final distances.
+    pub fn search_until<E>(
+        &mut self,
+        sources: impl IntoIterator<Item = usize>,
+        mut is_target: impl FnMut(usize) -> bool,
+        mut edges: impl FnMut(usize) -> E,
+    ) -> Option<usize>
+    where
+        E: IntoIterator<Item = (usize, W)>,
+    {
+        self.reset();
+        for source in sources {
+            self.relax(source, W::default(), NONE);
+        }
+        while let Some((d, u)) = self.queue.pop() {
+            if self.settled[u] || self.dist[u] != Some(d) {
+                continue;
+            }
+            self.settled[u] = true;
+            if is_target(u) {
+                self.target = Some(u);
+                return Some(u);
+            }
+            for (v, w) in edges(u) {
+                if !self.settled[v] {
+                    self.relax(v, d + w, u);
+                }
+            }
+        }
+        None
+    }
+
+    /// The target at which the last `search_until` stopped.
+    pub fn reached_target(&self) -> Option<usize> {
+        self.target
+    }
+
+    /// Length of the shortest path to `node`, if it was settled.
+    pub fn distance(&self, node: usize) -> Option<W> {
+        if *self.settled.get(node)? {
+            self.dist[node]
+        } else {
+            None
+        }
+    }
+
+    /// The node before `node` on its shortest path; `None` for sources and
+    /// unsettled nodes.
+    pub fn predecessor(&self, node: usize) -> Option<usize> {
+        self.distance(node)?;
+        Some(self.parent[node]).filter(|&p| p != NONE)
+    }
+
+    /// The shortest path to `node`, starting at the source it was reached
+    /// from.
+    pub fn path_to(&self, node: usize) -> Option<Vec<usize>> {
+        self.distance(node)?;
+        let mut path = vec![node];
+        let mut current = node;
+        while let Some(p) = self.predecessor(current) {
+            path.push(p);
+            current = p;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The source whose search tree contains `node`.
+    pub fn source_of(&self, node: usize) -> Option<usize> {
+        self.path_to(node).map(|path| path[0])
+    }
+
+    fn relax(&mut self, node: usize, dist: W, parent: usize) {
+        if self.dist[node].is_some_and(|d| d <= dist) {
+            return;
+        }
+        if self.dist[node].is_none() {
+            self.touched.push(node);
+        }
+        self.dist[node] = Some(dist);
+        self.parent[node] = parent;
+        self.queue.push(dist, node);
+    }
+
+    fn reset(&mut self) {
+        for node in self.touched.drain(..) {
+            self.dist[node] = None;
+            self.parent[node] = NONE;
+            self.settled[node] = false;
+        }
+        self.queue.clear();
+        self.target = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adjacency lists of a small weighted directed graph:
+    ///
+    /// ```text
+    /// 0 -4-> 1 -1-> 3
+    /// 0 -1-> 2 -2-> 1
+    /// 2 -7-> 3 -3-> 4
+    /// 5 (isolated)
+    /// ```
+    fn graph() -> Vec<Vec<(usize, u32)>> {
+        vec![
+            vec![(1, 4), (2, 1)],
+            vec![(3, 1)],
+            vec![(1, 2), (3, 7)],
+            vec![(4, 3)],
+            vec![],
+            vec![],
+        ]
+    }
+
+    #[test]
+    fn single_source() {
+        let g = graph();
+        let tree = dijkstra(g.len(), 0, |u| g[u].iter().copied());
+        let dists: Vec<_> = (0..6).map(|n| tree.distance(n)).collect();
+        assert_eq!(
+            dists,
+            vec![Some(0), Some(3), Some(1), Some(4), Some(7), None]
+        );
+        assert_eq!(tree.path_to(4), Some(vec![0, 2, 1, 3, 4]));
+        assert_eq!(tree.predecessor(0), None);
+        assert_eq!(tree.path_to(5), None);
+    }
+
+    #[test]
+    fn multiple_sources() {
+        let g = graph();
+        let tree = dijkstra_multi(g.len(), [0, 3], |u| g[u].iter().copied());
+        assert_eq!(tree.distance(4), Some(3));
+        assert_eq!(tree.source_of(4), Some(3));
+        assert_eq!(tree.source_of(1), Some(0));
+        assert_eq!(tree.path_to(3), Some(vec![3]));
+    }
+
+    #[test]
+    fn stops_at_first_target() {
+        let g = graph();
+        let mut tree = ShortestPathTree::new(g.len());
+        let targets = [3, 4];
+        let hit = tree.search_until([0], |n| targets.contains(&n), |u| g[u].iter().copied());
+        assert_eq!(hit, Some(3));
+        assert_eq!(tree.reached_target(), Some(3));
+        assert_eq!(tree.distance(3), Some(4));
+        assert_eq!(tree.distance(4), None);
+        assert_eq!(
+            tree.search_until([4], |n| n == 0, |u| g[u].iter().copied()),
+            None
+        );
+    }
+
+    #[test]
+    fn buffers_are_reused_between_searches() {
+        // a path 0 - 1 - ... - 999 with unit weights in both directions
+        let n = 1000;
+        let edges = |u: usize| {
+            [u.checked_sub(1), Some(u + 1).filter(|&v| v < n)]
+                .into_iter()
+                .flatten()
+                .map(|v| (v, 1u64))
+        };
+        let mut tree = ShortestPathTree::new(n);
+        tree.search([0], edges);
+        assert_eq!(tree.distance(999), Some(999));
+        tree.search_until([500], |v| v == 498, edges);
+        assert_eq!(tree.distance(0), None);
+        assert_eq!(tree.distance(498), Some(2));
+        assert!(tree.touched.len() < 10);
+        tree.search([999, 0], edges);
+        assert_eq!(tree.distance(500), Some(499));
+        assert_eq!(tree.source_of(400), Some(0));
+    }
+
+    #[test]
+    fn radix_heap_backend_agrees() {
+        let n = 300;
+        let mut x: u64 = 7;
+        let g: Vec<Vec<(usize, u64)>> = (0..n)
+            .map(|_| {
+                (0..4)
+                    .map(|_| {
+                        x ^= x << 13;
+                        x ^= x >> 7;
+                        x ^= x << 17;
+                        ((x % n as u64) as usize, x % 1000)
+                    })
+                    .collect()
+            })
+            .collect();
+        let binary = dijkstra(n, 0, |u| g[u].iter().copied());
+        let radix = dijkstra_radix(n, 0, |u| g[u].iter().copied());
+        for node in 0..n {
+            assert_eq!(binary.distance(node), radix.distance(node));
+        }
+        let mut reused = ShortestPathTree::with_queue(n, RadixHeap::new());
+        reused.search([5], |u| g[u].iter().copied());
+        reused.search([0], |u| g[u].iter().copied());
+        assert!((0..n).all(|v| reused.distance(v) == binary.distance(v)));
+    }
+}
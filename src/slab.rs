@@ -0,0 +1,220 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+/// Storage for values addressed by stable `usize` keys.
+///
+/// Removed slots are kept on an intrusive free list and reused by later
+/// inserts, so keys stay small and dense.
+#[derive(Clone)]
+pub struct Slab<T> {
+    entries: Vec<Entry<T>>,
+    // head of the free list; `entries.len()` if there are no vacant slots
+    next_free: usize,
+    len: usize,
+}
+
+#[derive(Clone)]
+enum Entry<T> {
+    Vacant(usize),
+    Occupied(T),
+}
+
+impl<T> Slab<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        Slab {
+            entries: Vec::with_capacity(n),
+            next_free: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of slots, occupied or vacant, that fit without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The key that the next `insert` will return.
+    pub fn vacant_key(&self) -> usize {
+        self.next_free
+    }
+
+    pub fn insert(&mut self, value: T) -> usize {
+        let key = self.next_free;
+        if key == self.entries.len() {
+            self.entries.push(Entry::Occupied(value));
+            self.next_free += 1;
+        } else {
+            match core::mem::replace(&mut self.entries[key], Entry::Occupied(value)) {
+                Entry::Vacant(next) => self.next_free = next,
+                Entry::Occupied(_) => unreachable!("free list points to an occupied slot"),
+            }
+        }
+        self.len += 1;
+        key
+    }
+
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        if !self.contains(key) {
+            return None;
+        }
+        let entry = core::mem::replace(&mut self.entries[key], Entry::Vacant(self.next_free));
+        self.next_free = key;
+        self.len -= 1;
+        match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => unreachable!(),
+        }
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: usize) -> Option<&T> {
+        match self.entries.get(key)? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        match self.entries.get_mut(key)? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next_free = 0;
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                Entry::Vacant(_) => None,
+            })
+    }
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<usize> for Slab<T> {
+    type Output = T;
+
+    fn index(&self, key: usize) -> &T {
+        self.get(key).expect("invalid slab key")
+    }
+}
+
+impl<T> IndexMut<usize> for Slab<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        self.get_mut(key).expect("invalid slab key")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Slab<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> FromIterator<T> for Slab<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut slab = Slab::new();
+        for value in iter {
+            slab.insert(value);
+        }
+        slab
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_slab() {
+        let slab = Slab::<i32>::new();
+        assert!(slab.is_empty());
+        assert_eq!(slab.get(0), None);
+        assert_eq!(slab.vacant_key(), 0);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut slab = Slab::new();
+        let a = slab.insert("a");
+        let b = slab.insert("b");
+        assert_ne!(a, b);
+        assert_eq!(slab[a], "a");
+        assert_eq!(slab.remove(a), Some("a"));
+        assert_eq!(slab.remove(a), None);
+        assert_eq!(slab.get(a), None);
+        assert_eq!(slab[b], "b");
+        assert_eq!(slab.len(), 1);
+    }
+
+    #[test]
+    fn keys_are_reused_last_in_first_out() {
+        let mut slab: Slab<_> = (0..5).collect();
+        slab.remove(1);
+        slab.remove(3);
+        assert_eq!(slab.vacant_key(), 3);
+        assert_eq!(slab.insert(30), 3);
+        assert_eq!(slab.insert(10), 1);
+        assert_eq!(slab.insert(5), 5);
+        assert_eq!(slab.len(), 6);
+    }
+
+    #[test]
+    fn iteration_skips_vacant_slots() {
+        let mut slab: Slab<_> = (0..6).collect();
+        slab.remove(0);
+        slab.remove(4);
+        for (_, v) in slab.iter_mut() {
+            *v *= 10;
+        }
+        let items: Vec<_> = slab.iter().map(|(k, &v)| (k, v)).collect();
+        assert_eq!(items, vec![(1, 10), (2, 20), (3, 30), (5, 50)]);
+    }
+
+    #[test]
+    fn clear_resets_keys() {
+        let mut slab: Slab<_> = (0..3).collect();
+        slab.clear();
+        assert!(slab.is_empty());
+        assert_eq!(slab.insert(7), 0);
+    }
+}
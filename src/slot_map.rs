@@ -0,0 +1,278 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+/// Handle to a value in a `SlotMap`.
+///
+/// A key remembers the generation of its slot, so it stops working once the
+/// value is removed, even if the slot is reused for another value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    index: u32,
+    generation: u32,
+}
+
+impl Key {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[derive(Clone)]
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    next_free: u32,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct Slot<T> {
+    generation: u32,
+    entry: Entry<T>,
+}
+
+#[derive(Clone)]
+enum Entry<T> {
+    Vacant(u32),
+    Occupied(T),
+}
+
+impl<T> SlotMap<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        SlotMap {
+            slots: Vec::with_capacity(n),
+            next_free: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, value: T) -> Key {
+        self.insert_with_key(|_| value)
+    }
+
+    /// Insert a value that needs to know its own key.
+    pub fn insert_with_key(&mut self, f: impl FnOnce(Key) -> T) -> Key {
+        let index = self.next_free;
+        let key = if index as usize == self.slots.len() {
+            assert!(index < u32::MAX, "slot map is full");
+            let key = Key {
+                index,
+                generation: 0,
+            };
+            self.slots.push(Slot {
+                generation: 0,
+                entry: Entry::Occupied(f(key)),
+            });
+            self.next_free += 1;
+            key
+        } else {
+            let slot = &mut self.slots[index as usize];
+            let key = Key {
+                index,
+                generation: slot.generation,
+            };
+            match core::mem::replace(&mut slot.entry, Entry::Occupied(f(key))) {
+                Entry::Vacant(next) => self.next_free = next,
+                Entry::Occupied(_) => unreachable!("free list points to an occupied slot"),
+            }
+            key
+        };
+        self.len += 1;
+        key
+    }
+
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if !self.contains_key(key) {
+            return None;
+        }
+        let slot = &mut self.slots[key.index()];
+        // wrapping could in theory revive a stale key, but only after 2^32
+        // reuses of the same slot
+        slot.generation = slot.generation.wrapping_add(1);
+        let entry = core::mem::replace(&mut slot.entry, Entry::Vacant(self.next_free));
+        self.next_free = key.index;
+        self.len -= 1;
+        match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => unreachable!(),
+        }
+    }
+
+    pub fn contains_key(&self, key: Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: Key) -> Option<&T> {
+        match self.slots.get(key.index())? {
+            Slot {
+                generation,
+                entry: Entry::Occupied(value),
+            } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        match self.slots.get_mut(key.index())? {
+            Slot {
+                generation,
+                entry: Entry::Occupied(value),
+            } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Remove all values. Keys handed out before stay invalid.
+    pub fn clear(&mut self) {
+        let keys: Vec<_> = self.keys().collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| match &slot.entry {
+                Entry::Occupied(value) => Some((
+                    Key {
+                        index: i as u32,
+                        generation: slot.generation,
+                    },
+                    value,
+                )),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Key, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, slot)| match &mut slot.entry {
+                Entry::Occupied(value) => Some((
+                    Key {
+                        index: i as u32,
+                        generation: slot.generation,
+                    },
+                    value,
+                )),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Key> for SlotMap<T> {
+    type Output = T;
+
+    fn index(&self, key: Key) -> &T {
+        self.get(key).expect("invalid slot map key")
+    }
+}
+
+impl<T> IndexMut<Key> for SlotMap<T> {
+    fn index_mut(&mut self, key: Key) -> &mut T {
+        self.get_mut(key).expect("invalid slot map key")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SlotMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = SlotMap::new();
+        let a = map.insert("a");
+        let b = map.insert("b");
+        assert_eq!(map[a], "a");
+        assert_eq!(map.get(b), Some(&"b"));
+        assert_eq!(map.remove(a), Some("a"));
+        assert_eq!(map.remove(a), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn stale_keys_are_detected() {
+        let mut map = SlotMap::new();
+        let old = map.insert(1);
+        map.remove(old);
+        let new = map.insert(2);
+        assert_eq!(old.index(), new.index());
+        assert_ne!(old, new);
+        assert_eq!(map.get(old), None);
+        assert_eq!(map.get_mut(old), None);
+        assert_eq!(map.remove(old), None);
+        assert_eq!(map[new], 2);
+    }
+
+    #[test]
+    fn insert_with_key_sees_own_key() {
+        let mut map = SlotMap::new();
+        let k = map.insert_with_key(|k| k);
+        assert_eq!(map[k], k);
+    }
+
+    #[test]
+    fn clear_invalidates_all_keys() {
+        let mut map = SlotMap::new();
+        let keys: Vec<_> = (0..5).map(|i| map.insert(i)).collect();
+        map.clear();
+        assert!(map.is_empty());
+        let fresh = map.insert(10);
+        assert!(keys.iter().all(|&k| map.get(k).is_none()));
+        assert_eq!(map[fresh], 10);
+    }
+
+    #[test]
+    fn iteration() {
+        let mut map = SlotMap::new();
+        let keys: Vec<_> = (0..5).map(|i| map.insert(i)).collect();
+        map.remove(keys[2]);
+        for (_, v) in map.iter_mut() {
+            *v += 100;
+        }
+        assert_eq!(
+            map.values().copied().collect::<Vec<_>>(),
+            vec![100, 101, 103, 104]
+        );
+        assert_eq!(map.keys().count(), 4);
+    }
+}
@@ -0,0 +1,336 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// A vector that stores up to `N` items inline and moves them to the heap
+/// when it grows beyond that.
+pub struct SmallVec<T, const N: usize> {
+    data: Data<T, N>,
+}
+
+enum Data<T, const N: usize> {
+    Inline {
+        len: usize,
+        items: [MaybeUninit<T>; N],
+    },
+    Heap(Vec<T>),
+}
+
+impl<T, const N: usize> SmallVec<T, N> {
+    pub fn new() -> Self {
+        SmallVec {
+            data: Data::Inline {
+                len: 0,
+                items: [const { MaybeUninit::uninit() }; N],
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.data {
+            Data::Inline { len, .. } => *len,
+            Data::Heap(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        match &self.data {
+            Data::Inline { .. } => N,
+            Data::Heap(v) => v.capacity(),
+        }
+    }
+
+    /// Whether the items have been moved to the heap.
+    pub fn spilled(&self) -> bool {
+        matches!(self.data, Data::Heap(_))
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match &self.data {
+            // SAFETY: the first `len` items are initialized
+            Data::Inline { len, items } => unsafe {
+                core::slice::from_raw_parts(items.as_ptr() as *const T, *len)
+            },
+            Data::Heap(v) => v,
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.data {
+            // SAFETY: the first `len` items are initialized
+            Data::Inline { len, items } => unsafe {
+                core::slice::from_raw_parts_mut(items.as_mut_ptr() as *mut T, *len)
+            },
+            Data::Heap(v) => v,
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        match &mut self.data {
+            Data::Inline { len, items } if *len < N => {
+                items[*len].write(value);
+                *len += 1;
+            }
+            _ => self.heap().push(value),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.data {
+            Data::Inline { len: 0, .. } => None,
+            Data::Inline { len, items } => {
+                *len -= 1;
+                // SAFETY: the item was initialized and is now outside `len`
+                Some(unsafe { items[*len].assume_init_read() })
+            }
+            Data::Heap(v) => v.pop(),
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len(), "insertion index out of bounds");
+        match &mut self.data {
+            Data::Inline { len, items } if *len < N => {
+                // SAFETY: shifts the initialized items `index..len` up by one
+                // slot, which exists because `len < N`
+                unsafe {
+                    let p = items.as_mut_ptr().add(index);
+                    ptr::copy(p, p.add(1), *len - index);
+                    (*p).write(value);
+                }
+                *len += 1;
+            }
+            _ => self.heap().insert(index, value),
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len(), "removal index out of bounds");
+        match &mut self.data {
+            Data::Inline { len, items } => {
+                // SAFETY: moves the item out and closes the gap
+                unsafe {
+                    let p = items.as_mut_ptr().add(index);
+                    let value = (*p).assume_init_read();
+                    ptr::copy(p.add(1), p, *len - index - 1);
+                    *len -= 1;
+                    value
+                }
+            }
+            Data::Heap(v) => v.remove(index),
+        }
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        while self.len() > new_len {
+            self.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        let mut this = ManuallyDrop::new(self);
+        match &mut this.data {
+            Data::Heap(v) => core::mem::take(v),
+            Data::Inline { len, items } => {
+                let n = core::mem::take(len);
+                // SAFETY: the items are moved out and `len` is reset, so they
+                // are not dropped again
+                (0..n)
+                    .map(|i| unsafe { items[i].assume_init_read() })
+                    .collect()
+            }
+        }
+    }
+
+    /// Move the items to the heap (if they are not there yet).
+    fn heap(&mut self) -> &mut Vec<T> {
+        if let Data::Inline { len, items } = &mut self.data {
+            let mut v = Vec::with_capacity((N * 2).max(4));
+            for item in &items[..core::mem::take(len)] {
+                // SAFETY: the item is initialized and `len` has been reset
+                v.push(unsafe { item.assume_init_read() });
+            }
+            self.data = Data::Heap(v);
+        }
+        match &mut self.data {
+            Data::Heap(v) => v,
+            Data::Inline { .. } => unreachable!(),
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for SmallVec<T, N> {
+    fn drop(&mut self) {
+        if let Data::Inline { .. } = self.data {
+            // SAFETY: drops exactly the initialized items
+            unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for SmallVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for SmallVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for SmallVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for SmallVec<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for SmallVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<SmallVec<T, M>> for SmallVec<T, N> {
+    fn eq(&self, other: &SmallVec<T, M>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for SmallVec<T, N> {}
+
+impl<T, const N: usize> Extend<T> for SmallVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for SmallVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = SmallVec::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a SmallVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for SmallVec<T, N> {
+    type Item = T;
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn stays_inline_up_to_n() {
+        let mut v = SmallVec::<i32, 4>::new();
+        assert!(v.is_empty());
+        v.extend([1, 2, 3, 4]);
+        assert!(!v.spilled());
+        assert_eq!(v.capacity(), 4);
+        v.push(5);
+        assert!(v.spilled());
+        assert_eq!(&*v, &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let mut v = SmallVec::<String, 2>::new();
+        v.push("a".into());
+        v.push("b".into());
+        assert_eq!(v.pop().as_deref(), Some("b"));
+        assert_eq!(v.pop().as_deref(), Some("a"));
+        assert_eq!(v.pop(), None);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut v: SmallVec<i32, 5> = [1, 3].into_iter().collect();
+        v.insert(1, 2);
+        v.insert(0, 0);
+        v.insert(4, 4);
+        assert_eq!(&*v, &[0, 1, 2, 3, 4]);
+        assert_eq!(v.remove(2), 2);
+        assert_eq!(v.remove(0), 0);
+        assert_eq!(&*v, &[1, 3, 4]);
+        v.insert(1, 10);
+        v.insert(1, 20);
+        v.insert(1, 30);
+        assert!(v.spilled());
+        assert_eq!(v.remove(1), 30);
+        assert_eq!(&*v, &[1, 20, 10, 3, 4]);
+    }
+
+    #[test]
+    fn slice_methods_via_deref() {
+        let mut v: SmallVec<i32, 8> = [3, 1, 2].into_iter().collect();
+        v.sort();
+        assert_eq!(v.first(), Some(&1));
+        assert!(v.contains(&3));
+        v[0] = 7;
+        assert_eq!(v.iter().sum::<i32>(), 12);
+    }
+
+    #[test]
+    fn items_are_dropped_exactly_once() {
+        let item = Rc::new(());
+        {
+            let mut v = SmallVec::<_, 3>::new();
+            v.push(item.clone());
+            v.push(item.clone());
+            assert_eq!(Rc::strong_count(&item), 3);
+        }
+        assert_eq!(Rc::strong_count(&item), 1);
+        {
+            let v: SmallVec<_, 3> = (0..10).map(|_| item.clone()).collect();
+            let w = v.clone();
+            assert_eq!(Rc::strong_count(&item), 21);
+            drop(w.into_vec());
+        }
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn into_iterator_and_equality() {
+        let a: SmallVec<i32, 2> = (0..3).collect();
+        let b: SmallVec<i32, 8> = (0..3).collect();
+        assert_eq!(a, b);
+        assert_eq!(b.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,584 @@
+use crate::errors::DataStructureError;
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use crate::traits::{Map, Set};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::ops::{Bound, Index, IndexMut, RangeBounds};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortedVec<T> {
+    data: Vec<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortedVecMap<K, V> {
+    data: Vec<(K, V)>,
+}
+
+/// Return the index range of the elements whose key lies within `range`.
+pub(crate) fn range_indices<E, K, Q, R>(
+    data: &[E],
+    key: impl Fn(&E) -> &K,
+    range: R,
+) -> (usize, usize)
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Ord,
+    R: RangeBounds<Q>,
+{
+    let start = match range.start_bound() {
+        Bound::Unbounded => 0,
+        Bound::Included(lo) => data.partition_point(|x| key(x).borrow() < lo),
+        Bound::Excluded(lo) => data.partition_point(|x| key(x).borrow() <= lo),
+    };
+    let end = match range.end_bound() {
+        Bound::Unbounded => data.len(),
+        Bound::Included(hi) => data.partition_point(|x| key(x).borrow() <= hi),
+        Bound::Excluded(hi) => data.partition_point(|x| key(x).borrow() < hi),
+    };
+    (start, end.max(start))
+}
+
+impl<T: Ord> SortedVec<T> {
+    pub fn new() -> Self {
+        SortedVec { data: vec![] }
+    }
+
+    pub fn from_unsorted(mut data: Vec<T>) -> Self {
+        data.sort();
+        data.dedup();
+        SortedVec { data }
+    }
+
+    /// Take over items that are already sorted and unique, in O(n). Fails
+    /// with `InvalidStructure` otherwise.
+    pub fn from_sorted(data: Vec<T>) -> Result<Self, DataStructureError> {
+        if data.is_sorted_by(|a, b| a < b) {
+            Ok(SortedVec { data })
+        } else {
+            Err(DataStructureError::InvalidStructure)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn insert(&mut self, item: T) -> bool {
+        match self.data.binary_search(&item) {
+            Ok(_) => false,
+            Err(idx) => {
+                self.data.insert(idx, item);
+                true
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, item: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(item).ok().map(|idx| &self.data[idx])
+    }
+
+    pub fn contains<Q>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(item).is_ok()
+    }
+
+    pub fn remove<Q>(&mut self, item: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let idx = self.position(item).ok()?;
+        Some(self.data.remove(idx))
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.data.last()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> &[T]
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = range_indices(&self.data, |x| x, range);
+        &self.data[start..end]
+    }
+
+    /// Merge all items of `other` into this set in linear time.
+    pub fn merge(&mut self, other: SortedVec<T>) {
+        let left = core::mem::take(&mut self.data);
+        let mut result = Vec::with_capacity(left.len() + other.data.len());
+        let mut a = left.into_iter().peekable();
+        let mut b = other.data.into_iter().peekable();
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (Some(x), Some(y)) => match x.cmp(y) {
+                    Ordering::Less => a.next(),
+                    Ordering::Greater => b.next(),
+                    Ordering::Equal => {
+                        b.next();
+                        a.next()
+                    }
+                },
+            };
+            result.extend(next);
+        }
+        self.data = result;
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    fn position<Q>(&self, item: &Q) -> Result<usize, usize>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.data.binary_search_by(|x| x.borrow().cmp(item))
+    }
+}
+
+impl<T: Ord> Default for SortedVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_unsorted(iter.into_iter().collect())
+    }
+}
+
+impl<T: Ord> Set<T> for SortedVec<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    fn insert(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        self.remove(item).is_some()
+    }
+}
+
+impl<T> MemoryFootprint for SortedVec<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<T>(self.data.len(), self.data.capacity())
+    }
+}
+
+impl<T> IntoIterator for SortedVec<T> {
+    type Item = T;
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SortedVec<T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl<K: Ord, V> SortedVecMap<K, V> {
+    pub fn new() -> Self {
+        SortedVecMap { data: vec![] }
+    }
+
+    /// Build a map from unsorted pairs. If a key occurs multiple times, the
+    /// last value wins.
+    pub fn from_unsorted(mut data: Vec<(K, V)>) -> Self {
+        data.reverse();
+        data.sort_by(|a, b| a.0.cmp(&b.0));
+        data.dedup_by(|a, b| a.0 == b.0);
+        SortedVecMap { data }
+    }
+
+    /// Take over entries whose keys are already sorted and unique, in O(n).
+    /// Fails with `InvalidStructure` otherwise.
+    pub fn from_sorted(data: Vec<(K, V)>) -> Result<Self, DataStructureError> {
+        if data.is_sorted_by(|a, b| a.0 < b.0) {
+            Ok(SortedVecMap { data })
+        } else {
+            Err(DataStructureError::InvalidStructure)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Ok(idx) => Some(core::mem::replace(&mut self.data[idx].1, value)),
+            Err(idx) => {
+                self.data.insert(idx, (key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(key).ok().map(|idx| &self.data[idx].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(key).ok().map(|idx| &mut self.data[idx].1)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(key).is_ok()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let idx = self.position(key).ok()?;
+        Some(self.data.remove(idx).1)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> &[(K, V)]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = range_indices(&self.data, |(k, _)| k, range);
+        &self.data[start..end]
+    }
+
+    /// Merge all entries of `other` into this map in linear time. Values from
+    /// `other` replace existing values with the same key.
+    pub fn merge(&mut self, other: SortedVecMap<K, V>) {
+        let left = core::mem::take(&mut self.data);
+        let mut result = Vec::with_capacity(left.len() + other.data.len());
+        let mut a = left.into_iter().peekable();
+        let mut b = other.data.into_iter().peekable();
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (Some(x), Some(y)) => match x.0.cmp(&y.0) {
+                    Ordering::Less => a.next(),
+                    Ordering::Greater => b.next(),
+                    Ordering::Equal => {
+                        a.next();
+                        b.next()
+                    }
+                },
+            };
+            result.extend(next);
+        }
+        self.data = result;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.data.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.data.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.data.iter().map(|(_, v)| v)
+    }
+
+    pub fn as_slice(&self) -> &[(K, V)] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.data
+    }
+
+    fn position<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.data.binary_search_by(|(k, _)| k.borrow().cmp(key))
+    }
+}
+
+impl<K: Ord, V> Default for SortedVecMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The set already yields its items in order, so nothing is sorted.
+impl<T: Ord> From<BTreeSet<T>> for SortedVec<T> {
+    fn from(set: BTreeSet<T>) -> Self {
+        SortedVec {
+            data: set.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Ord> From<SortedVec<T>> for BTreeSet<T> {
+    fn from(set: SortedVec<T>) -> Self {
+        set.data.into_iter().collect()
+    }
+}
+
+/// The map already yields its entries in order, so nothing is sorted.
+impl<K: Ord, V> From<BTreeMap<K, V>> for SortedVecMap<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        SortedVecMap {
+            data: map.into_iter().collect(),
+        }
+    }
+}
+
+impl<K: Ord, V> From<SortedVecMap<K, V>> for BTreeMap<K, V> {
+    fn from(map: SortedVecMap<K, V>) -> Self {
+        map.data.into_iter().collect()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SortedVecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_unsorted(iter.into_iter().collect())
+    }
+}
+
+impl<K, V, Q> Index<&Q> for SortedVecMap<K, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found")
+    }
+}
+
+impl<K, V, Q> IndexMut<&Q> for SortedVecMap<K, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    fn index_mut(&mut self, key: &Q) -> &mut V {
+        self.get_mut(key).expect("key not found")
+    }
+}
+
+impl<K: Ord, V> Map<K, V> for SortedVecMap<K, V> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+impl<K, V> MemoryFootprint for SortedVecMap<K, V> {
+    fn memory_stats(&self) -> MemoryStats {
+        buffer_stats::<(K, V)>(self.data.len(), self.data.capacity())
+    }
+}
+
+impl<K, V> IntoIterator for SortedVecMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = alloc::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_unsorted_sorts_and_dedups() {
+        let set = SortedVec::from_unsorted(vec![5, 3, 1, 3, 4, 1]);
+        assert_eq!(set.as_slice(), &[1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn insert_keeps_order_and_uniqueness() {
+        let mut set = SortedVec::new();
+        assert!(set.insert(3));
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(2));
+        assert_eq!(set.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn lookup_and_remove() {
+        let mut set: SortedVec<_> = ["b", "a", "c"].iter().map(|s| s.to_string()).collect();
+        assert!(set.contains("a"));
+        assert_eq!(set.get("c"), Some(&"c".to_string()));
+        assert_eq!(set.remove("a"), Some("a".to_string()));
+        assert!(!set.contains("a"));
+        assert_eq!(set.remove("a"), None);
+    }
+
+    #[test]
+    fn range_slicing() {
+        let set: SortedVec<_> = (0..10).collect();
+        assert_eq!(set.range(3..6), &[3, 4, 5]);
+        assert_eq!(set.range(..=2), &[0, 1, 2]);
+        assert_eq!(set.range(8..), &[8, 9]);
+        assert_eq!(set.range((Bound::Excluded(7), Bound::Unbounded)), &[8, 9]);
+        assert!(set.range(20..30).is_empty());
+    }
+
+    #[test]
+    fn merge_sets() {
+        let mut a = SortedVec::from_unsorted(vec![1, 3, 5, 7]);
+        let b = SortedVec::from_unsorted(vec![2, 3, 6, 9]);
+        a.merge(b);
+        assert_eq!(a.as_slice(), &[1, 2, 3, 5, 6, 7, 9]);
+    }
+
+    #[test]
+    fn map_from_unsorted_keeps_last_value() {
+        let map = SortedVecMap::from_unsorted(vec![(2, "b"), (1, "a"), (2, "B")]);
+        assert_eq!(map.as_slice(), &[(1, "a"), (2, "B")]);
+    }
+
+    #[test]
+    fn map_insert_get_remove() {
+        let mut map = SortedVecMap::new();
+        assert_eq!(map.insert(2, "two"), None);
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(2, "zwei"), Some("two"));
+        assert_eq!(map.get(&2), Some(&"zwei"));
+        *map.get_mut(&1).unwrap() = "eins";
+        assert_eq!(map.remove(&1), Some("eins"));
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn map_range_and_merge() {
+        let mut a: SortedVecMap<_, _> = (0..5).map(|i| (i * 2, 'a')).collect();
+        let b: SortedVecMap<_, _> = (0..5).map(|i| (i * 3, 'b')).collect();
+        a.merge(b);
+        assert_eq!(
+            a.keys().copied().collect::<Vec<_>>(),
+            vec![0, 2, 3, 4, 6, 8, 9, 12]
+        );
+        assert_eq!(a.get(&6), Some(&'b'));
+        assert_eq!(a.range(3..7), &[(3, 'b'), (4, 'a'), (6, 'b')]);
+    }
+
+    #[test]
+    fn map_index_operators() {
+        let mut map: SortedVecMap<String, u32> = [("a".to_string(), 1), ("b".to_string(), 2)]
+            .into_iter()
+            .collect();
+        map["a"] += 10;
+        assert_eq!(map["a"], 11);
+        assert_eq!(map[&"b".to_string()], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn map_index_missing_key() {
+        let map: SortedVecMap<u32, u32> = SortedVecMap::new();
+        let _ = map[&1];
+    }
+
+    #[test]
+    fn btree_conversions() {
+        let set: SortedVec<_> = BTreeSet::from([3, 1, 2]).into();
+        assert_eq!(set.as_slice(), &[1, 2, 3]);
+        assert_eq!(BTreeSet::from(set), BTreeSet::from([1, 2, 3]));
+
+        let map: SortedVecMap<_, _> = BTreeMap::from([(2, 'b'), (1, 'a')]).into();
+        assert_eq!(map.as_slice(), &[(1, 'a'), (2, 'b')]);
+        assert_eq!(BTreeMap::from(map)[&2], 'b');
+    }
+
+    #[test]
+    fn bulk_load_sorted_items() {
+        let set = SortedVec::from_sorted((0..100).collect()).unwrap();
+        assert!(set.contains(&50));
+        assert!(SortedVec::from_sorted(vec![1, 1]).is_err());
+        let map = SortedVecMap::from_sorted(vec![(1, 'a'), (2, 'b')]).unwrap();
+        assert_eq!(map[&2], 'b');
+        assert_eq!(
+            SortedVecMap::from_sorted(vec![(2, 'b'), (1, 'a')]),
+            Err(DataStructureError::InvalidStructure)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A set of small integers with O(1) insert, remove and membership tests and
+/// iteration over a packed array of the members.
+///
+/// `sparse` maps a value to its position in `dense`. Entries of `sparse` are
+/// never cleared; a value is a member only if the two arrays point at each
+/// other, which also makes `clear` O(1).
+#[derive(Clone, Default)]
+pub struct SparseSet {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+}
+
+impl SparseSet {
+    pub fn new() -> Self {
+        SparseSet {
+            dense: vec![],
+            sparse: vec![],
+        }
+    }
+
+    /// Create a set that can hold values below `universe` without growing.
+    pub fn with_universe(universe: usize) -> Self {
+        SparseSet {
+            dense: vec![],
+            sparse: vec![0; universe],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        match self.sparse.get(value) {
+            Some(&i) => self.dense.get(i) == Some(&value),
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, value: usize) -> bool {
+        if self.contains(value) {
+            return false;
+        }
+        if value >= self.sparse.len() {
+            self.sparse.resize(value + 1, 0);
+        }
+        self.sparse[value] = self.dense.len();
+        self.dense.push(value);
+        true
+    }
+
+    /// Remove a value by moving the last member into its slot.
+    pub fn remove(&mut self, value: usize) -> bool {
+        if !self.contains(value) {
+            return false;
+        }
+        let i = self.sparse[value];
+        let last = *self.dense.last().unwrap();
+        self.dense.swap_remove(i);
+        self.sparse[last] = i;
+        true
+    }
+
+    /// Position of a value in the packed array.
+    pub fn index_of(&self, value: usize) -> Option<usize> {
+        if self.contains(value) {
+            Some(self.sparse[value])
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    /// The members in insertion order, as disturbed by removals.
+    pub fn as_slice(&self) -> &[usize] {
+        &self.dense
+    }
+
+    pub fn iter(&self) -> core::iter::Copied<core::slice::Iter<'_, usize>> {
+        self.dense.iter().copied()
+    }
+}
+
+impl fmt::Debug for SparseSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for SparseSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|x| other.contains(x))
+    }
+}
+
+impl Eq for SparseSet {}
+
+impl FromIterator<usize> for SparseSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = SparseSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<usize> for SparseSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a SparseSet {
+    type Item = usize;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'a, usize>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_set() {
+        let set = SparseSet::new();
+        assert!(set.is_empty());
+        assert!(!set.contains(0));
+        assert!(!set.contains(1_000_000));
+    }
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut set = SparseSet::with_universe(16);
+        assert!(set.insert(3));
+        assert!(set.insert(100));
+        assert!(!set.insert(3));
+        assert!(set.contains(3));
+        assert!(set.contains(100));
+        assert!(!set.contains(4));
+        assert_eq!(set.len(), 2);
+        assert!(set.remove(3));
+        assert!(!set.remove(3));
+        assert!(!set.contains(3));
+        assert_eq!(set.as_slice(), &[100]);
+    }
+
+    #[test]
+    fn removal_keeps_members_packed() {
+        let mut set: SparseSet = [5, 1, 9, 7].into_iter().collect();
+        set.remove(1);
+        assert_eq!(set.as_slice(), &[5, 7, 9]);
+        assert_eq!(set.index_of(7), Some(1));
+        assert_eq!(set.index_of(1), None);
+    }
+
+    #[test]
+    fn clear_forgets_members_with_stale_sparse_entries() {
+        let mut set: SparseSet = (0..10).collect();
+        set.clear();
+        assert!(set.is_empty());
+        assert!((0..10).all(|x| !set.contains(x)));
+        set.insert(7);
+        assert!(!set.contains(0));
+        assert!(set.contains(7));
+    }
+
+    #[test]
+    fn equality_ignores_order() {
+        let a: SparseSet = [1, 2, 3].into_iter().collect();
+        let b: SparseSet = [3, 1, 2].into_iter().collect();
+        assert_eq!(a, b);
+        assert_eq!(format!("{:?}", a), "{1, 2, 3}");
+    }
+}
@@ -0,0 +1,509 @@
+//! A sequence stored in a splay tree keyed by position.
+//!
+//! Any range of the sequence can be split off into its own subtree in
+//! amortized O(log n), by splaying its boundaries to the top. Every node
+//! stores the size, sum and minimum of its subtree, so range aggregates are
+//! read off that subtree's root. Reversal is a lazy flag on the root of the
+//! subtree, pushed down when a splay passes through. Rotating and moving a
+//! range are just splits and joins.
+//!
+//! Splaying is top-down, in a single pass without parent links, so even a
+//! degenerate tree is handled without deep recursion.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Add, Bound, RangeBounds};
+
+const NIL: usize = usize::MAX;
+
+#[derive(Clone)]
+pub struct SplaySequence<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    root: usize,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    value: T,
+    sum: T,
+    min: T,
+    children: [usize; 2],
+    size: usize,
+    /// The children of this node still have to be swapped, and so do those
+    /// of every node below it.
+    reversed: bool,
+}
+
+impl<T: Copy + PartialOrd + Add<Output = T> + Default> SplaySequence<T> {
+    pub fn new() -> Self {
+        SplaySequence {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NIL,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NIL;
+    }
+
+    /// The item at `index`, which is splayed to the root.
+    pub fn get(&mut self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.root = self.splay(self.root, index);
+        Some(&self.nodes[self.root].value)
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        assert!(index < self.len(), "index out of bounds");
+        self.root = self.splay(self.root, index);
+        self.nodes[self.root].value = value;
+        self.update(self.root);
+    }
+
+    pub fn push(&mut self, value: T) {
+        let node = self.alloc(value);
+        self.root = self.merge(self.root, node);
+    }
+
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len(), "insertion index out of bounds");
+        let (left, right) = self.split(self.root, index);
+        let node = self.alloc(value);
+        let left = self.merge(left, node);
+        self.root = self.merge(left, right);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        let root = self.splay(self.root, index);
+        let [left, right] = self.nodes[root].children;
+        self.free.push(root);
+        self.root = self.merge(left, right);
+        Some(self.nodes[root].value)
+    }
+
+    /// Sum of the items in `range`; the default value if it is empty.
+    pub fn range_sum(&mut self, range: impl RangeBounds<usize>) -> T {
+        let (left, middle, right) = self.isolate(range);
+        let sum = if middle == NIL {
+            T::default()
+        } else {
+            self.nodes[middle].sum
+        };
+        self.join(left, middle, right);
+        sum
+    }
+
+    /// Smallest item in `range`.
+    pub fn range_min(&mut self, range: impl RangeBounds<usize>) -> Option<T> {
+        let (left, middle, right) = self.isolate(range);
+        let min = (middle != NIL).then(|| self.nodes[middle].min);
+        self.join(left, middle, right);
+        min
+    }
+
+    /// Reverse the order of the items in `range`.
+    pub fn reverse(&mut self, range: impl RangeBounds<usize>) {
+        let (left, middle, right) = self.isolate(range);
+        if middle != NIL {
+            self.nodes[middle].reversed ^= true;
+        }
+        self.join(left, middle, right);
+    }
+
+    /// Rotate `range` so that its item at offset `mid` comes first, like
+    /// `slice::rotate_left`. Panics if `mid` exceeds the length of `range`.
+    pub fn rotate_left(&mut self, range: impl RangeBounds<usize>, mid: usize) {
+        let (left, middle, right) = self.isolate(range);
+        assert!(mid <= self.size(middle), "rotation out of bounds");
+        let (front, back) = self.split(middle, mid);
+        let middle = self.merge(back, front);
+        self.join(left, middle, right);
+    }
+
+    /// Rotate `range` so that its last `k` items come first, like
+    /// `slice::rotate_right`. Panics if `k` exceeds the length of `range`.
+    pub fn rotate_right(&mut self, range: impl RangeBounds<usize>, k: usize) {
+        let (start, end) = self.bounds(range);
+        assert!(k <= end - start, "rotation out of bounds");
+        self.rotate_left(start..end, end - start - k);
+    }
+
+    /// Cut out the items in `range` and insert them at position `to` of the
+    /// remaining sequence. Panics if `to` is past its end.
+    pub fn move_range(&mut self, range: impl RangeBounds<usize>, to: usize) {
+        let (left, middle, right) = self.isolate(range);
+        let rest = self.merge(left, right);
+        assert!(to <= self.size(rest), "destination out of bounds");
+        let (left, right) = self.split(rest, to);
+        self.join(left, middle, right);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        // in-order traversal, tracking whether the current subtree is
+        // reversed by pending flags above it
+        let mut items = Vec::with_capacity(self.len());
+        let mut stack = Vec::new();
+        let (mut x, mut reversed) = (self.root, false);
+        loop {
+            while x != NIL {
+                reversed ^= self.nodes[x].reversed;
+                stack.push((x, reversed));
+                x = self.nodes[x].children[usize::from(reversed)];
+            }
+            let Some((y, r)) = stack.pop() else { break };
+            items.push(&self.nodes[y].value);
+            x = self.nodes[y].children[usize::from(!r)];
+            reversed = r;
+        }
+        items.into_iter()
+    }
+
+    /// Split the tree into the items before, in and after `range`.
+    fn isolate(&mut self, range: impl RangeBounds<usize>) -> (usize, usize, usize) {
+        let (start, end) = self.bounds(range);
+        let (left, rest) = self.split(self.root, start);
+        let (middle, right) = self.split(rest, end - start);
+        (left, middle, right)
+    }
+
+    fn join(&mut self, left: usize, middle: usize, right: usize) {
+        let left = self.merge(left, middle);
+        self.root = self.merge(left, right);
+    }
+
+    fn bounds(&self, range: impl RangeBounds<usize>) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(start <= end && end <= self.len(), "range out of bounds");
+        (start, end)
+    }
+
+    fn alloc(&mut self, value: T) -> usize {
+        let node = Node {
+            value,
+            sum: value,
+            min: value,
+            children: [NIL, NIL],
+            size: 1,
+            reversed: false,
+        };
+        match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn size(&self, x: usize) -> usize {
+        if x == NIL {
+            0
+        } else {
+            self.nodes[x].size
+        }
+    }
+
+    fn push_down(&mut self, x: usize) {
+        if self.nodes[x].reversed {
+            self.nodes[x].reversed = false;
+            self.nodes[x].children.swap(0, 1);
+            for c in self.nodes[x].children {
+                if c != NIL {
+                    self.nodes[c].reversed ^= true;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let [left, right] = self.nodes[x].children;
+        let node = &self.nodes[x];
+        let (mut size, mut sum, mut min) = (1, node.value, node.value);
+        if left != NIL {
+            let l = &self.nodes[left];
+            size += l.size;
+            sum = l.sum + sum;
+            if l.min < min {
+                min = l.min;
+            }
+        }
+        if right != NIL {
+            let r = &self.nodes[right];
+            size += r.size;
+            sum = sum + r.sum;
+            if r.min < min {
+                min = r.min;
+            }
+        }
+        let node = &mut self.nodes[x];
+        node.size = size;
+        node.sum = sum;
+        node.min = min;
+    }
+
+    /// Make the item at `index` of the non-empty subtree `t` its root.
+    ///
+    /// Top-down splaying: nodes passed on the way down are hung into a left
+    /// tree (everything before the target) or a right tree (everything
+    /// after it), rotating first on zig-zig steps. At the end the two trees
+    /// become the children of the target.
+    fn splay(&mut self, mut t: usize, mut index: usize) -> usize {
+        // the nodes of each side tree, with the newest on the inner spine
+        let mut left_spine = Vec::new();
+        let mut right_spine = Vec::new();
+        loop {
+            self.push_down(t);
+            let left_size = self.size(self.nodes[t].children[0]);
+            if index < left_size {
+                let c = self.nodes[t].children[0];
+                self.push_down(c);
+                if index < self.size(self.nodes[c].children[0]) {
+                    // zig-zig: rotate right, then hang `c` to the right tree
+                    self.nodes[t].children[0] = self.nodes[c].children[1];
+                    self.update(t);
+                    self.nodes[c].children[1] = t;
+                    right_spine.push(c);
+                    t = self.nodes[c].children[0];
+                } else {
+                    right_spine.push(t);
+                    t = c;
+                }
+            } else if index > left_size {
+                index -= left_size + 1;
+                let c = self.nodes[t].children[1];
+                self.push_down(c);
+                let c_left = self.size(self.nodes[c].children[0]);
+                if index > c_left {
+                    // zig-zig: rotate left, then hang `c` to the left tree
+                    index -= c_left + 1;
+                    self.nodes[t].children[1] = self.nodes[c].children[0];
+                    self.update(t);
+                    self.nodes[c].children[0] = t;
+                    left_spine.push(c);
+                    t = self.nodes[c].children[1];
+                } else {
+                    left_spine.push(t);
+                    t = c;
+                }
+            } else {
+                break;
+            }
+        }
+        // the inner spine of each side tree gets the target's children
+        let [mut left, mut right] = self.nodes[t].children;
+        for &x in left_spine.iter().rev() {
+            self.nodes[x].children[1] = left;
+            self.update(x);
+            left = x;
+        }
+        for &x in right_spine.iter().rev() {
+            self.nodes[x].children[0] = right;
+            self.update(x);
+            right = x;
+        }
+        self.nodes[t].children = [left, right];
+        self.update(t);
+        t
+    }
+
+    /// Split the subtree `t` after its first `k` items.
+    fn split(&mut self, t: usize, k: usize) -> (usize, usize) {
+        if k == 0 {
+            return (NIL, t);
+        }
+        if k == self.size(t) {
+            return (t, NIL);
+        }
+        let t = self.splay(t, k);
+        let left = self.nodes[t].children[0];
+        self.nodes[t].children[0] = NIL;
+        self.update(t);
+        (left, t)
+    }
+
+    fn merge(&mut self, left: usize, right: usize) -> usize {
+        if left == NIL {
+            return right;
+        }
+        let left = self.splay(left, self.size(left) - 1);
+        self.nodes[left].children[1] = right;
+        self.update(left);
+        left
+    }
+}
+
+impl<T: Copy + PartialOrd + Add<Output = T> + Default> Default for SplaySequence<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy + PartialOrd + Add<Output = T> + Default> Extend<T> for SplaySequence<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Copy + PartialOrd + Add<Output = T> + Default> FromIterator<T> for SplaySequence<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut seq = Self::new();
+        seq.extend(iter);
+        seq
+    }
+}
+
+impl<T: Copy + PartialOrd + Add<Output = T> + Default + fmt::Debug> fmt::Debug
+    for SplaySequence<T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn items(seq: &SplaySequence<i64>) -> Vec<i64> {
+        seq.iter().copied().collect()
+    }
+
+    #[test]
+    fn edits_and_lookups() {
+        let mut seq: SplaySequence<i64> = (0..5).collect();
+        seq.insert(2, 10);
+        seq.insert(6, 11);
+        assert_eq!(items(&seq), vec![0, 1, 10, 2, 3, 4, 11]);
+        assert_eq!(seq.remove(0), Some(0));
+        assert_eq!(seq.remove(6), None);
+        seq.set(1, -1);
+        assert_eq!(seq.get(1), Some(&-1));
+        assert_eq!(seq.get(6), None);
+        assert_eq!(format!("{seq:?}"), "[1, -1, 2, 3, 4, 11]");
+    }
+
+    #[test]
+    fn range_aggregates() {
+        let mut seq: SplaySequence<i64> = [5, 3, 8, 1, 9, 2].into_iter().collect();
+        assert_eq!(seq.range_sum(..), 28);
+        assert_eq!(seq.range_sum(1..4), 12);
+        assert_eq!(seq.range_min(..3), Some(3));
+        assert_eq!(seq.range_min(4..=5), Some(2));
+        assert_eq!(seq.range_min(2..2), None);
+        assert_eq!(seq.range_sum(2..2), 0);
+    }
+
+    #[test]
+    fn reverse_and_rotate() {
+        let mut seq: SplaySequence<i64> = (0..10).collect();
+        seq.reverse(2..7);
+        assert_eq!(items(&seq), vec![0, 1, 6, 5, 4, 3, 2, 7, 8, 9]);
+        seq.reverse(..);
+        assert_eq!(items(&seq), vec![9, 8, 7, 2, 3, 4, 5, 6, 1, 0]);
+        seq.rotate_left(3..8, 2);
+        assert_eq!(items(&seq), vec![9, 8, 7, 4, 5, 6, 2, 3, 1, 0]);
+        seq.rotate_right(.., 3);
+        assert_eq!(items(&seq), vec![3, 1, 0, 9, 8, 7, 4, 5, 6, 2]);
+        seq.move_range(0..3, 4);
+        assert_eq!(items(&seq), vec![9, 8, 7, 4, 3, 1, 0, 5, 6, 2]);
+        assert_eq!(seq.range_min(3..6), Some(1));
+    }
+
+    #[test]
+    fn long_sequences_do_not_recurse() {
+        let mut seq: SplaySequence<i64> = (0..200_000).collect();
+        assert_eq!(seq.get(0), Some(&0));
+        seq.reverse(..);
+        assert_eq!(seq.get(0), Some(&199_999));
+        assert_eq!(seq.range_sum(..), 199_999 * 100_000);
+    }
+
+    #[test]
+    fn matches_vec_under_random_operations() {
+        let mut seq = SplaySequence::new();
+        let mut model: Vec<i64> = Vec::new();
+        let mut x: u64 = 9;
+        let mut next = |m: usize| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as usize % m.max(1)
+        };
+        for i in 0..3000 {
+            let n = model.len();
+            let (mut a, mut b) = (next(n + 1), next(n + 1));
+            if a > b {
+                core::mem::swap(&mut a, &mut b);
+            }
+            match next(7) {
+                0 | 1 => {
+                    let at = next(n + 1);
+                    let value = next(1000) as i64 - 500;
+                    seq.insert(at, value);
+                    model.insert(at, value);
+                }
+                2 if n > 0 => {
+                    let at = next(n);
+                    assert_eq!(seq.remove(at), Some(model.remove(at)));
+                }
+                3 => {
+                    seq.reverse(a..b);
+                    model[a..b].reverse();
+                }
+                4 => {
+                    let mid = next(b - a + 1);
+                    seq.rotate_left(a..b, mid);
+                    model[a..b].rotate_left(mid);
+                }
+                5 => {
+                    let to = next(n - (b - a) + 1);
+                    seq.move_range(a..b, to);
+                    let moved: Vec<_> = model.drain(a..b).collect();
+                    model.splice(to..to, moved);
+                }
+                _ => {
+                    assert_eq!(seq.range_sum(a..b), model[a..b].iter().sum::<i64>());
+                    assert_eq!(seq.range_min(a..b), model[a..b].iter().min().copied());
+                }
+            }
+            if i % 100 == 0 {
+                assert_eq!(items(&seq), model);
+            }
+        }
+        assert_eq!(items(&seq), model);
+    }
+}
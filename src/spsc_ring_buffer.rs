@@ -0,0 +1,395 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::{self, Vec};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Create a bounded single-producer single-consumer ring buffer.
+///
+/// Both `push` and `pop` are wait-free. Each side keeps a cached copy of the
+/// other side's index, so the shared indices are only touched when the cache
+/// says the buffer is full or empty.
+///
+/// The capacity is rounded up to the next power of two.
+pub fn ring_buffer<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    ring_buffer_from(capacity, 0)
+}
+
+/// Like `ring_buffer`, with both indices starting at `start`.
+fn ring_buffer_from<T>(capacity: usize, start: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "capacity must be positive");
+    let inner = Arc::new(Inner {
+        slots: (0..capacity.next_power_of_two())
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: CachePadded(AtomicUsize::new(start)),
+        tail: CachePadded(AtomicUsize::new(start)),
+    });
+    (
+        Producer {
+            inner: inner.clone(),
+            head: start,
+            tail: start,
+        },
+        Consumer {
+            inner,
+            head: start,
+            tail: start,
+        },
+    )
+}
+
+/// Keeps the two indices on separate cache lines to avoid false sharing.
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+struct Inner<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // Both indices count up forever (wrapping). The capacity is a power of
+    // two, so masking maps them to slots consistently across the wrap.
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+        self.slots[index & (self.capacity() - 1)].get()
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let mut head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        while head != tail {
+            // SAFETY: slots between head and tail are initialized
+            unsafe { (*self.slot(head)).assume_init_drop() };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+pub struct Producer<T> {
+    inner: Arc<Inner<T>>,
+    head: usize,
+    tail: usize,
+}
+
+pub struct Consumer<T> {
+    inner: Arc<Inner<T>>,
+    head: usize,
+    tail: usize,
+}
+
+impl<T> Producer<T> {
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Number of free slots. The consumer may free more at any time.
+    pub fn free_len(&mut self) -> usize {
+        self.head = self.inner.head.load(Ordering::Acquire);
+        self.capacity() - self.tail.wrapping_sub(self.head)
+    }
+
+    pub fn is_full(&mut self) -> bool {
+        self.free_len() == 0
+    }
+
+    /// Append an item, or give it back if the buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.tail.wrapping_sub(self.head) == self.capacity() && self.free_len() == 0 {
+            return Err(value);
+        }
+        // SAFETY: the slot is free and only the producer writes free slots
+        unsafe { self.inner.slot(self.tail).write(MaybeUninit::new(value)) };
+        self.tail = self.tail.wrapping_add(1);
+        self.inner.tail.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    /// Append as many items from the iterator as fit, publishing them all at
+    /// once. Returns the number of items written.
+    pub fn push_iter(&mut self, items: impl IntoIterator<Item = T>) -> usize {
+        let free = self.free_len();
+        let mut written = 0;
+        for value in items.into_iter().take(free) {
+            let index = self.tail.wrapping_add(written);
+            // SAFETY: see `push`
+            unsafe { self.inner.slot(index).write(MaybeUninit::new(value)) };
+            written += 1;
+        }
+        self.tail = self.tail.wrapping_add(written);
+        self.inner.tail.store(self.tail, Ordering::Release);
+        written
+    }
+
+    /// Copy as many items from the slice as fit. Returns the number written.
+    pub fn push_slice(&mut self, items: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        self.push_iter(items.iter().copied())
+    }
+}
+
+impl<T> Consumer<T> {
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Number of readable items. The producer may add more at any time.
+    pub fn len(&mut self) -> usize {
+        self.tail = self.inner.tail.load(Ordering::Acquire);
+        self.tail.wrapping_sub(self.head)
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.head == self.tail && self.is_empty() {
+            return None;
+        }
+        // SAFETY: the slot was published by the producer and only the
+        // consumer reads it
+        let value = unsafe { self.inner.slot(self.head).read().assume_init() };
+        self.head = self.head.wrapping_add(1);
+        self.inner.head.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    pub fn peek(&mut self) -> Option<&T> {
+        if self.head == self.tail && self.is_empty() {
+            return None;
+        }
+        // SAFETY: as in `pop`, but the slot stays occupied
+        Some(unsafe { (*self.inner.slot(self.head)).assume_init_ref() })
+    }
+
+    /// Move up to `out.len()` items into `out`, releasing their slots at
+    /// once. Returns the number of items read.
+    pub fn pop_slice(&mut self, out: &mut [T]) -> usize
+    where
+        T: Copy,
+    {
+        let n = out.len().min(self.len());
+        for (i, dst) in out[..n].iter_mut().enumerate() {
+            let index = self.head.wrapping_add(i);
+            // SAFETY: see `pop`
+            *dst = unsafe { self.inner.slot(index).read().assume_init() };
+        }
+        self.head = self.head.wrapping_add(n);
+        self.inner.head.store(self.head, Ordering::Release);
+        n
+    }
+
+    /// Remove the currently readable items for which `pred` returns true.
+    /// The remaining items keep their order and stay readable.
+    pub fn drain_filter(&mut self, mut pred: impl FnMut(&mut T) -> bool) -> vec::IntoIter<T> {
+        let n = self.len();
+        // decide first, so a panicking predicate leaves the buffer intact
+        let remove: Vec<bool> = (0..n)
+            .map(|i| {
+                // SAFETY: see `peek`; only the consumer touches these slots
+                pred(unsafe { (*self.inner.slot(self.head.wrapping_add(i))).assume_init_mut() })
+            })
+            .collect();
+        let mut removed = Vec::new();
+        // move the kept items towards the tail, starting at the back
+        let mut kept = self.head.wrapping_add(n);
+        for (i, remove) in remove.into_iter().enumerate().rev() {
+            let index = self.head.wrapping_add(i);
+            // SAFETY: every slot from `head` to `kept` is read exactly once
+            // and `kept` only ever points at a slot that was already read
+            unsafe {
+                let value = self.inner.slot(index).read();
+                if remove {
+                    removed.push(value.assume_init());
+                } else {
+                    kept = kept.wrapping_sub(1);
+                    self.inner.slot(kept).write(value);
+                }
+            }
+        }
+        self.head = kept;
+        self.inner.head.store(self.head, Ordering::Release);
+        removed.reverse();
+        removed.into_iter()
+    }
+
+    /// Remove all currently readable items. Items that are not consumed are
+    /// dropped together with the iterator.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        let remaining = self.len();
+        Drain {
+            consumer: self,
+            remaining,
+        }
+    }
+}
+
+/// Iterator returned by `Consumer::drain`.
+pub struct Drain<'a, T> {
+    consumer: &'a mut Consumer<T>,
+    remaining: usize,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.consumer.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn empty_buffer() {
+        let (mut tx, mut rx) = ring_buffer::<i32>(4);
+        assert!(rx.is_empty());
+        assert_eq!(rx.pop(), None);
+        assert_eq!(rx.peek(), None);
+        assert_eq!(tx.free_len(), 4);
+    }
+
+    #[test]
+    fn push_until_full() {
+        let (mut tx, mut rx) = ring_buffer(3);
+        assert_eq!(tx.capacity(), 4);
+        assert_eq!(tx.push_slice(&[1, 2, 3]), 3);
+        assert_eq!(tx.push(4), Ok(()));
+        assert!(tx.is_full());
+        assert_eq!(tx.push(5), Err(5));
+        assert_eq!(rx.peek(), Some(&1));
+        assert_eq!(rx.pop(), Some(1));
+        assert_eq!(tx.push(5), Ok(()));
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn indices_wrap_around_usize() {
+        let (mut tx, mut rx) = ring_buffer_from(3, usize::MAX - 5);
+        for round in 0..4 {
+            assert_eq!(tx.push_slice(&[round, round + 1, round + 2]), 3);
+            assert_eq!(rx.len(), 3);
+            assert_eq!(rx.pop(), Some(round));
+            assert_eq!(rx.drain().collect::<Vec<_>>(), vec![round + 1, round + 2]);
+        }
+        assert_eq!(tx.push_iter(0..10), 4);
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn unconsumed_drain_items_are_removed() {
+        let (mut tx, mut rx) = ring_buffer(4);
+        tx.push_slice(&[1, 2, 3]);
+        assert_eq!(rx.drain().next(), Some(1));
+        assert!(rx.is_empty());
+        assert_eq!(tx.free_len(), 4);
+    }
+
+    #[test]
+    fn batch_read_and_write() {
+        let (mut tx, mut rx) = ring_buffer(8);
+        assert_eq!(tx.push_slice(&[1, 2, 3]), 3);
+        let mut out = [0; 2];
+        assert_eq!(rx.pop_slice(&mut out), 2);
+        assert_eq!(out, [1, 2]);
+        // wraps around the end of the buffer
+        assert_eq!(tx.push_slice(&[4, 5, 6, 7, 8, 9, 10, 11]), 7);
+        let mut out = [0; 10];
+        assert_eq!(rx.pop_slice(&mut out), 8);
+        assert_eq!(out[..8], [3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn drain_filter_keeps_order() {
+        let (mut tx, mut rx) = ring_buffer(8);
+        tx.push_slice(&[0; 6]);
+        rx.drain().for_each(drop);
+        // the readable items wrap around the end of the buffer
+        tx.push_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let even: Vec<_> = rx.drain_filter(|x| *x % 2 == 0).collect();
+        assert_eq!(even, vec![2, 4, 6, 8]);
+        assert_eq!(tx.push_slice(&[9, 10, 11, 12, 13]), 4);
+        assert_eq!(
+            rx.drain().collect::<Vec<_>>(),
+            vec![1, 3, 5, 7, 9, 10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn remaining_items_are_dropped() {
+        let item = Arc::new(());
+        {
+            let (mut tx, mut rx) = ring_buffer(8);
+            tx.push_iter((0..5).map(|_| item.clone()));
+            rx.pop();
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn transfer_between_threads() {
+        let (mut tx, mut rx) = ring_buffer(16);
+        let n = 100_000;
+        let producer = thread::spawn(move || {
+            let mut i = 0;
+            while i < n {
+                match tx.push(i) {
+                    Ok(()) => i += 1,
+                    Err(_) => thread::yield_now(),
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < n {
+            match rx.pop() {
+                Some(x) => {
+                    assert_eq!(x, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+    }
+}
@@ -0,0 +1,376 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, Range, RangeBounds};
+
+/// A summary of one block of items, such as its sum, its maximum, or a
+/// sorted copy of the block.
+pub trait Summary<T> {
+    fn summarize(items: &[T]) -> Self;
+}
+
+/// A range update that can be applied lazily to whole blocks.
+pub trait Update<T, S>: Clone {
+    fn apply(&self, item: &mut T);
+
+    /// Apply the update to a block of `len` items by changing only its
+    /// summary. Return false if that is not possible; the block is then
+    /// updated item by item and summarized again.
+    fn apply_to_summary(&self, summary: &mut S, len: usize) -> bool;
+
+    /// Combine two updates into one that has the effect of applying `self`
+    /// first and `later` second.
+    fn then(self, later: &Self) -> Self;
+}
+
+/// Placeholder update type for decompositions that only use point updates.
+#[derive(Clone)]
+pub enum NoUpdate {}
+
+impl<T, S> Update<T, S> for NoUpdate {
+    fn apply(&self, _: &mut T) {
+        match *self {}
+    }
+
+    fn apply_to_summary(&self, _: &mut S, _: usize) -> bool {
+        match *self {}
+    }
+
+    fn then(self, _: &Self) -> Self {
+        match self {}
+    }
+}
+
+/// A sequence split into blocks of about √n items, each with a summary.
+///
+/// Range operations touch O(√n) block summaries plus the items of at most
+/// two partially covered blocks. Unlike a segment tree, summaries need not
+/// form a monoid: they only have to be computable from a block's items.
+pub struct SqrtDecomposition<T, S, U = NoUpdate> {
+    items: Vec<T>,
+    summaries: Vec<S>,
+    // updates that are reflected in the summary but not yet in the items
+    pending: Vec<Option<U>>,
+    block_size: usize,
+}
+
+impl<T, S: Summary<T>, U: Update<T, S>> SqrtDecomposition<T, S, U> {
+    pub fn new(items: Vec<T>) -> Self {
+        let n = items.len();
+        let block_size = n.isqrt() + usize::from(n.isqrt().pow(2) < n);
+        Self::with_block_size(items, block_size)
+    }
+
+    pub fn with_block_size(items: Vec<T>, block_size: usize) -> Self {
+        let block_size = block_size.max(1);
+        let summaries: Vec<S> = items.chunks(block_size).map(S::summarize).collect();
+        SqrtDecomposition {
+            pending: summaries.iter().map(|_| None).collect(),
+            summaries,
+            items,
+            block_size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn get(&mut self, index: usize) -> Option<&T> {
+        if index >= self.items.len() {
+            return None;
+        }
+        self.flush_block(index / self.block_size);
+        Some(&self.items[index])
+    }
+
+    pub fn set(&mut self, index: usize, value: T) {
+        self.update(index, |item| *item = value);
+    }
+
+    /// Modify one item and summarize its block again.
+    pub fn update(&mut self, index: usize, f: impl FnOnce(&mut T)) {
+        assert!(index < self.items.len(), "index out of range");
+        let block = index / self.block_size;
+        self.flush_block(block);
+        f(&mut self.items[index]);
+        self.resummarize(block);
+    }
+
+    /// Apply `update` to every item in `range`.
+    pub fn update_range(&mut self, range: impl RangeBounds<usize>, update: U) {
+        let (start, end) = self.bounds(range);
+        for piece in self.pieces(start, end) {
+            if let Piece::Whole(block) = piece {
+                let len = self.block_range(block).len();
+                if update.apply_to_summary(&mut self.summaries[block], len) {
+                    self.pending[block] = Some(match self.pending[block].take() {
+                        None => update.clone(),
+                        Some(earlier) => earlier.then(&update),
+                    });
+                    continue;
+                }
+            }
+            let (block, range) = self.piece_range(piece);
+            self.flush_block(block);
+            self.items[range]
+                .iter_mut()
+                .for_each(|item| update.apply(item));
+            self.resummarize(block);
+        }
+    }
+
+    /// Fold over `range`, using the summaries of fully covered blocks and the
+    /// individual items elsewhere. Blocks and items are visited in order.
+    pub fn query<R>(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        init: R,
+        mut on_block: impl FnMut(R, &S) -> R,
+        mut on_item: impl FnMut(R, &T) -> R,
+    ) -> R {
+        let (start, end) = self.bounds(range);
+        let mut acc = init;
+        for piece in self.pieces(start, end) {
+            acc = match piece {
+                Piece::Whole(block) => on_block(acc, &self.summaries[block]),
+                Piece::Partial(block, range) => {
+                    self.flush_block(block);
+                    self.items[range].iter().fold(acc, &mut on_item)
+                }
+            };
+        }
+        acc
+    }
+
+    /// The summaries of all blocks, in order.
+    pub fn summaries(&self) -> &[S] {
+        &self.summaries
+    }
+
+    /// Apply all pending updates and return the items.
+    pub fn as_slice(&mut self) -> &[T] {
+        for block in 0..self.summaries.len() {
+            self.flush_block(block);
+        }
+        &self.items
+    }
+
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.as_slice();
+        self.items
+    }
+
+    fn bounds(&self, range: impl RangeBounds<usize>) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.items.len(),
+        };
+        assert!(
+            start <= end && end <= self.items.len(),
+            "range out of bounds"
+        );
+        (start, end)
+    }
+
+    fn block_range(&self, block: usize) -> Range<usize> {
+        let start = block * self.block_size;
+        start..(start + self.block_size).min(self.items.len())
+    }
+
+    /// Split `start..end` into whole blocks and partial block ranges, from
+    /// left to right.
+    fn pieces(&self, start: usize, end: usize) -> impl Iterator<Item = Piece> + use<T, S, U> {
+        let (size, len) = (self.block_size, self.items.len());
+        let mut i = start;
+        core::iter::from_fn(move || {
+            if i >= end {
+                return None;
+            }
+            let block = i / size;
+            let block_end = ((block + 1) * size).min(len);
+            let piece = if i == block * size && block_end <= end {
+                Piece::Whole(block)
+            } else {
+                Piece::Partial(block, i..block_end.min(end))
+            };
+            i = block_end.min(end);
+            Some(piece)
+        })
+    }
+
+    fn piece_range(&self, piece: Piece) -> (usize, Range<usize>) {
+        match piece {
+            Piece::Whole(block) => (block, self.block_range(block)),
+            Piece::Partial(block, range) => (block, range),
+        }
+    }
+
+    fn flush_block(&mut self, block: usize) {
+        if let Some(update) = self.pending[block].take() {
+            let range = self.block_range(block);
+            self.items[range]
+                .iter_mut()
+                .for_each(|item| update.apply(item));
+        }
+    }
+
+    fn resummarize(&mut self, block: usize) {
+        let range = self.block_range(block);
+        self.summaries[block] = S::summarize(&self.items[range]);
+    }
+}
+
+enum Piece {
+    Whole(usize),
+    Partial(usize, Range<usize>),
+}
+
+impl<T: fmt::Debug, S, U> fmt::Debug for SqrtDecomposition<T, S, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SqrtDecomposition")
+            .field("len", &self.items.len())
+            .field("block_size", &self.block_size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Sum(i64);
+
+    impl Summary<i64> for Sum {
+        fn summarize(items: &[i64]) -> Self {
+            Sum(items.iter().sum())
+        }
+    }
+
+    #[derive(Clone)]
+    struct Add(i64);
+
+    impl Update<i64, Sum> for Add {
+        fn apply(&self, item: &mut i64) {
+            *item += self.0;
+        }
+
+        fn apply_to_summary(&self, summary: &mut Sum, len: usize) -> bool {
+            summary.0 += self.0 * len as i64;
+            true
+        }
+
+        fn then(self, later: &Self) -> Self {
+            Add(self.0 + later.0)
+        }
+    }
+
+    fn sum(d: &mut SqrtDecomposition<i64, Sum, Add>, range: std::ops::Range<usize>) -> i64 {
+        d.query(range, 0, |acc, s| acc + s.0, |acc, x| acc + x)
+    }
+
+    // a block summary that is not a monoid: the sorted block, for counting
+    // items below a threshold
+    struct Sorted(Vec<u32>);
+
+    impl Summary<u32> for Sorted {
+        fn summarize(items: &[u32]) -> Self {
+            let mut v = items.to_vec();
+            v.sort();
+            Sorted(v)
+        }
+    }
+
+    #[test]
+    fn blocks_cover_the_sequence() {
+        let d = SqrtDecomposition::<i64, Sum>::new((1..=10).collect());
+        assert_eq!(d.block_size(), 4);
+        assert_eq!(d.summaries(), &[Sum(10), Sum(26), Sum(19)]);
+        assert_eq!(d.len(), 10);
+    }
+
+    #[test]
+    fn point_updates_and_range_queries() {
+        let mut d = SqrtDecomposition::<i64, Sum, Add>::new((0..20).collect());
+        assert_eq!(sum(&mut d, 0..20), 190);
+        assert_eq!(sum(&mut d, 3..17), (3..17).sum::<i64>());
+        d.set(5, 100);
+        d.update(6, |x| *x *= 2);
+        assert_eq!(d.get(6), Some(&12));
+        assert_eq!(sum(&mut d, 5..7), 112);
+        assert_eq!(sum(&mut d, 4..4), 0);
+        assert_eq!(d.get(20), None);
+    }
+
+    #[test]
+    fn lazy_range_updates() {
+        let mut d = SqrtDecomposition::<i64, Sum, Add>::with_block_size(vec![0; 10], 3);
+        d.update_range(1..8, Add(2));
+        d.update_range(..5, Add(1));
+        d.update_range(6..=6, Add(10));
+        assert_eq!(sum(&mut d, 0..10), 2 * 7 + 5 + 10);
+        assert_eq!(d.get(3), Some(&3));
+        assert_eq!(d.into_vec(), vec![1, 3, 3, 3, 3, 2, 12, 2, 0, 0]);
+    }
+
+    #[test]
+    fn non_monoid_summary() {
+        let items: Vec<u32> = (0..50).map(|i| (i * 37) % 50).collect();
+        let mut d = SqrtDecomposition::<u32, Sorted>::new(items.clone());
+        let below = |d: &mut SqrtDecomposition<u32, Sorted>, r: std::ops::Range<usize>, k| {
+            d.query(
+                r,
+                0,
+                |n, s| n + s.0.partition_point(|&x| x < k),
+                |n, &x| n + usize::from(x < k),
+            )
+        };
+        for (r, k) in [(0..50, 25), (3..41, 10), (7..9, 40)] {
+            let expected = items[r.clone()].iter().filter(|&&x| x < k).count();
+            assert_eq!(below(&mut d, r, k), expected);
+        }
+        d.set(0, 1000);
+        assert_eq!(below(&mut d, 0..50, 1000), 49);
+    }
+
+    #[test]
+    fn matches_naive_vec() {
+        let mut naive: Vec<i64> = (0..37).map(|i| i * 7 % 11).collect();
+        let mut d = SqrtDecomposition::<i64, Sum, Add>::new(naive.clone());
+        let mut x = 3usize;
+        for _ in 0..500 {
+            x = (x * 1103 + 12345) % 100_003;
+            let a = x % 37;
+            let b = (x / 37) % 38;
+            let (lo, hi) = (a.min(b), a.max(b));
+            match x % 3 {
+                0 => {
+                    d.update_range(lo..hi, Add(x as i64 % 5 - 2));
+                    naive[lo..hi]
+                        .iter_mut()
+                        .for_each(|v| *v += x as i64 % 5 - 2);
+                }
+                1 => {
+                    d.set(a, b as i64);
+                    naive[a] = b as i64;
+                }
+                _ => assert_eq!(sum(&mut d, lo..hi), naive[lo..hi].iter().sum::<i64>()),
+            }
+        }
+        assert_eq!(d.as_slice(), &naive[..]);
+    }
+}
@@ -0,0 +1,281 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{OnceCell, RefCell};
+use core::fmt;
+
+/// A persistent lazy list. Suspended computations are evaluated at most once
+/// and their result is shared by all versions that refer to them.
+pub struct Stream<T> {
+    cell: Rc<LazyCell<T>>,
+}
+
+struct LazyCell<T> {
+    value: OnceCell<StreamNode<T>>,
+    suspension: RefCell<Option<Suspension<T>>>,
+}
+
+pub enum StreamNode<T> {
+    Nil,
+    Cons(T, Stream<T>),
+}
+
+enum Suspension<T> {
+    Append(Stream<T>, Stream<T>),
+    Reverse(Stream<T>),
+    Take(usize, Stream<T>),
+    Drop(usize, Stream<T>),
+    Rotate(Stream<T>, Stream<T>, Stream<T>),
+}
+
+impl<T> Clone for Stream<T> {
+    fn clone(&self) -> Self {
+        Stream {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for StreamNode<T> {
+    fn clone(&self) -> Self {
+        match self {
+            StreamNode::Nil => StreamNode::Nil,
+            StreamNode::Cons(x, rest) => StreamNode::Cons(x.clone(), rest.clone()),
+        }
+    }
+}
+
+impl<T: Clone> Stream<T> {
+    pub fn empty() -> Self {
+        Self::evaluated(StreamNode::Nil)
+    }
+
+    pub fn cons(item: T, rest: Self) -> Self {
+        Self::evaluated(StreamNode::Cons(item, rest))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self.force(), StreamNode::Nil)
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        match self.force() {
+            StreamNode::Nil => None,
+            StreamNode::Cons(x, _) => Some(x),
+        }
+    }
+
+    pub fn tail(&self) -> Option<&Self> {
+        match self.force() {
+            StreamNode::Nil => None,
+            StreamNode::Cons(_, rest) => Some(rest),
+        }
+    }
+
+    /// Lazily append `other`; the work is done incrementally as the result
+    /// is traversed.
+    pub fn append(&self, other: &Self) -> Self {
+        Self::suspended(Suspension::Append(self.clone(), other.clone()))
+    }
+
+    /// Lazily reverse the stream; the first access does all the work.
+    pub fn reverse(&self) -> Self {
+        Self::suspended(Suspension::Reverse(self.clone()))
+    }
+
+    pub fn take(&self, n: usize) -> Self {
+        Self::suspended(Suspension::Take(n, self.clone()))
+    }
+
+    pub fn drop(&self, n: usize) -> Self {
+        Self::suspended(Suspension::Drop(n, self.clone()))
+    }
+
+    /// Lazily compute `self ++ reverse(rear) ++ acc`, one element per step.
+    /// `rear` must be exactly one element longer than `self` and already
+    /// evaluated, so that each step does O(1) work.
+    pub fn rotate(&self, rear: &Self, acc: &Self) -> Self {
+        Self::suspended(Suspension::Rotate(self.clone(), rear.clone(), acc.clone()))
+    }
+
+    pub fn force(&self) -> &StreamNode<T> {
+        self.cell.value.get_or_init(|| {
+            let suspension = self.cell.suspension.borrow_mut().take();
+            suspension.expect("stream is being evaluated").evaluate()
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { stream: self }
+    }
+
+    fn evaluated(node: StreamNode<T>) -> Self {
+        Stream {
+            cell: Rc::new(LazyCell {
+                value: OnceCell::from(node),
+                suspension: RefCell::new(None),
+            }),
+        }
+    }
+
+    fn suspended(suspension: Suspension<T>) -> Self {
+        Stream {
+            cell: Rc::new(LazyCell {
+                value: OnceCell::new(),
+                suspension: RefCell::new(Some(suspension)),
+            }),
+        }
+    }
+}
+
+impl<T: Clone> Suspension<T> {
+    fn evaluate(self) -> StreamNode<T> {
+        match self {
+            Suspension::Append(front, back) => match front.force() {
+                StreamNode::Nil => back.force().clone(),
+                StreamNode::Cons(x, rest) => StreamNode::Cons(x.clone(), rest.append(&back)),
+            },
+            Suspension::Reverse(stream) => {
+                let mut result = Stream::empty();
+                for x in stream.iter() {
+                    result = Stream::cons(x.clone(), result);
+                }
+                result.force().clone()
+            }
+            Suspension::Take(0, _) => StreamNode::Nil,
+            Suspension::Take(n, stream) => match stream.force() {
+                StreamNode::Nil => StreamNode::Nil,
+                StreamNode::Cons(x, rest) => StreamNode::Cons(x.clone(), rest.take(n - 1)),
+            },
+            Suspension::Drop(n, mut stream) => {
+                for _ in 0..n {
+                    match stream.tail() {
+                        None => break,
+                        Some(rest) => stream = rest.clone(),
+                    }
+                }
+                stream.force().clone()
+            }
+            Suspension::Rotate(front, rear, acc) => {
+                let (Some(y), Some(rear)) = (rear.head(), rear.tail()) else {
+                    panic!("rotation requires a longer rear stream")
+                };
+                let acc = Stream::cons(y.clone(), acc);
+                match front.force() {
+                    StreamNode::Nil => acc.force().clone(),
+                    StreamNode::Cons(x, rest) => {
+                        StreamNode::Cons(x.clone(), rest.rotate(rear, &acc))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for LazyCell<T> {
+    // unlink the list iteratively so that long streams do not overflow the stack
+    fn drop(&mut self) {
+        let mut next = match self.value.take() {
+            Some(StreamNode::Cons(_, rest)) => Some(rest),
+            _ => None,
+        };
+        while let Some(stream) = next {
+            next = match Rc::try_unwrap(stream.cell) {
+                Ok(mut cell) => match cell.value.take() {
+                    Some(StreamNode::Cons(_, rest)) => Some(rest),
+                    _ => None,
+                },
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl<T: Clone> Default for Stream<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for Stream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Stream<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        items
+            .into_iter()
+            .rev()
+            .fold(Stream::empty(), |rest, x| Stream::cons(x, rest))
+    }
+}
+
+pub struct Iter<'a, T> {
+    stream: &'a Stream<T>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stream.force() {
+            StreamNode::Nil => None,
+            StreamNode::Cons(x, rest) => {
+                self.stream = rest;
+                Some(x)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_stream() {
+        let s = Stream::<i32>::empty();
+        assert!(s.is_empty());
+        assert_eq!(s.head(), None);
+        assert!(s.tail().is_none());
+    }
+
+    #[test]
+    fn collect_and_iterate() {
+        let s: Stream<_> = (1..=5).collect();
+        assert_eq!(s.head(), Some(&1));
+        assert!(s.iter().copied().eq(1..=5));
+    }
+
+    #[test]
+    fn lazy_operations() {
+        let a: Stream<_> = (0..5).collect();
+        let b: Stream<_> = (5..10).collect();
+        assert!(a.append(&b).iter().copied().eq(0..10));
+        assert!(a.reverse().iter().copied().eq((0..5).rev()));
+        assert!(a.take(3).iter().copied().eq(0..3));
+        assert!(a.drop(3).iter().copied().eq(3..5));
+        assert!(a.take(10).iter().copied().eq(0..5));
+        assert!(a.drop(10).is_empty());
+        let r: Stream<_> = (5..11).rev().collect();
+        assert!(a.rotate(&r, &Stream::empty()).iter().copied().eq(0..11));
+    }
+
+    #[test]
+    fn suspensions_are_shared_between_copies() {
+        let s: Stream<_> = (0..3).collect();
+        let r = s.reverse();
+        let r2 = r.clone();
+        assert_eq!(r.head(), Some(&2));
+        assert!(Rc::ptr_eq(&r.cell, &r2.cell));
+        assert!(r2.cell.value.get().is_some());
+    }
+
+    #[test]
+    fn drop_long_stream() {
+        let s: Stream<_> = (0..1_000_000).collect();
+        assert_eq!(s.iter().count(), 1_000_000);
+    }
+}
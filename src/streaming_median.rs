@@ -0,0 +1,229 @@
+//! Running quantiles of a stream, kept in two heaps.
+//!
+//! A max-heap holds the smallest items up to the tracked rank and a
+//! min-heap holds the rest, so the quantile is always the top of the first
+//! heap. Inserting moves at most one item between the heaps, for O(log n)
+//! per insert and O(1) per query.
+
+use crate::compare::Reverse;
+use crate::heap::Heap;
+use core::fmt;
+
+/// Tracks the `q`-quantile of all items inserted so far: the item at
+/// index `floor(q * (n - 1))` in sorted order.
+#[derive(Clone)]
+pub struct StreamingQuantile<T> {
+    lower: Heap<T>,
+    upper: Heap<T, Reverse>,
+    q: f64,
+}
+
+impl<T: PartialOrd> StreamingQuantile<T> {
+    /// Panics unless `0 <= q <= 1`.
+    pub fn new(q: f64) -> Self {
+        assert!((0.0..=1.0).contains(&q), "quantile must be between 0 and 1");
+        StreamingQuantile {
+            lower: Heap::new(),
+            upper: Heap::with_comparator(Reverse::default()),
+            q,
+        }
+    }
+
+    pub fn q(&self) -> f64 {
+        self.q
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+
+    pub fn insert(&mut self, item: T) {
+        match self.lower.peek() {
+            Some(top) if item > *top => self.upper.push(item),
+            _ => self.lower.push(item),
+        }
+        let target = self.lower_len(self.len());
+        if self.lower.len() > target {
+            self.upper.push(self.lower.pop().unwrap());
+        } else if self.lower.len() < target {
+            self.lower.push(self.upper.pop().unwrap());
+        }
+    }
+
+    /// The current quantile; `None` if nothing was inserted.
+    pub fn quantile(&self) -> Option<&T> {
+        self.lower.peek()
+    }
+
+    /// The smallest item above the quantile.
+    pub fn next_above(&self) -> Option<&T> {
+        self.upper.peek()
+    }
+
+    pub fn clear(&mut self) {
+        self.lower.drain();
+        self.upper.drain();
+    }
+
+    /// Number of items in the lower heap for `n` items in total.
+    fn lower_len(&self, n: usize) -> usize {
+        match n {
+            0 => 0,
+            n => (self.q * (n - 1) as f64) as usize + 1,
+        }
+    }
+}
+
+impl<T: PartialOrd> Extend<T> for StreamingQuantile<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: PartialOrd + fmt::Debug> fmt::Debug for StreamingQuantile<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamingQuantile")
+            .field("q", &self.q)
+            .field("quantile", &self.quantile())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Tracks the median of all items inserted so far.
+#[derive(Clone)]
+pub struct StreamingMedian<T> {
+    inner: StreamingQuantile<T>,
+}
+
+impl<T: PartialOrd> StreamingMedian<T> {
+    pub fn new() -> Self {
+        StreamingMedian {
+            inner: StreamingQuantile::new(0.5),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn insert(&mut self, item: T) {
+        self.inner.insert(item);
+    }
+
+    /// The median; for an even number of items the lower of the two
+    /// middle ones.
+    pub fn median(&self) -> Option<&T> {
+        self.inner.quantile()
+    }
+
+    /// Both middle items; they are the same item if the count is odd.
+    pub fn middle(&self) -> Option<(&T, &T)> {
+        let low = self.inner.quantile()?;
+        if self.len() % 2 == 1 {
+            Some((low, low))
+        } else {
+            Some((low, self.inner.next_above()?))
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl<T: PartialOrd> Default for StreamingMedian<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialOrd> Extend<T> for StreamingMedian<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.inner.extend(iter);
+    }
+}
+
+impl<T: PartialOrd> FromIterator<T> for StreamingMedian<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut median = Self::new();
+        median.extend(iter);
+        median
+    }
+}
+
+impl<T: PartialOrd + fmt::Debug> fmt::Debug for StreamingMedian<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamingMedian")
+            .field("median", &self.median())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn median_of_a_stream() {
+        let mut m = StreamingMedian::new();
+        assert_eq!(m.median(), None);
+        m.insert(5);
+        assert_eq!(m.middle(), Some((&5, &5)));
+        m.insert(1);
+        assert_eq!(m.median(), Some(&1));
+        assert_eq!(m.middle(), Some((&1, &5)));
+        m.extend([9, 3, 7]);
+        assert_eq!(m.median(), Some(&5));
+        assert_eq!(m.len(), 5);
+        m.clear();
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn matches_sorting_for_every_prefix() {
+        for q in [0.0, 0.1, 0.5, 0.9, 0.99, 1.0] {
+            let mut tracker = StreamingQuantile::new(q);
+            let mut seen = Vec::new();
+            for i in 0..500u32 {
+                let x = i.wrapping_mul(2_654_435_761) % 1000;
+                tracker.insert(x);
+                seen.push(x);
+                seen.sort();
+                let index = (q * (seen.len() - 1) as f64) as usize;
+                assert_eq!(tracker.quantile(), Some(&seen[index]), "q = {q}");
+            }
+        }
+    }
+
+    #[test]
+    fn floats_and_duplicates() {
+        let m: StreamingMedian<f64> = [2.5, 2.5, 2.5, 1.0, 7.0, 2.5].into_iter().collect();
+        assert_eq!(m.middle(), Some((&2.5, &2.5)));
+        let p90 = {
+            let mut p = StreamingQuantile::new(0.9);
+            p.extend((1..=11).map(f64::from));
+            p
+        };
+        assert_eq!(p90.quantile(), Some(&10.0));
+        assert_eq!(p90.next_above(), Some(&11.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "between 0 and 1")]
+    fn quantile_out_of_range_panics() {
+        StreamingQuantile::<u8>::new(1.5);
+    }
+}
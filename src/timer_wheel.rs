@@ -0,0 +1,402 @@
+//! A hierarchical timer wheel for scheduling many timeouts.
+//!
+//! Level `l` of the wheel has 64 slots that each span `64^l` ticks. A timer
+//! is filed at the lowest level whose slot can tell its deadline apart from
+//! the current tick, and moves down a level whenever the wheel reaches the
+//! start of its slot. Scheduling and cancelling are O(1). Polling skips
+//! ticks where nothing happens, so its cost depends on the timers that
+//! expire or move down rather than on the number of elapsed ticks.
+
+use crate::slot_map::{Key, SlotMap};
+use core::fmt;
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+
+/// Where a `TimerWheel` reads the current tick from.
+///
+/// Any `FnMut() -> u64` is a tick source, which makes it easy to drive a
+/// wheel from a simulated clock in tests.
+pub trait TickSource {
+    fn now(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> TickSource for F {
+    fn now(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// Ticks of fixed length since the source was created.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct InstantTicks {
+    start: std::time::Instant,
+    tick: std::time::Duration,
+}
+
+#[cfg(feature = "std")]
+impl InstantTicks {
+    pub fn new(tick: std::time::Duration) -> Self {
+        assert!(!tick.is_zero(), "ticks must have a positive length");
+        InstantTicks {
+            start: std::time::Instant::now(),
+            tick,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl TickSource for InstantTicks {
+    fn now(&mut self) -> u64 {
+        (self.start.elapsed().as_nanos() / self.tick.as_nanos()) as u64
+    }
+}
+
+/// Handle to a scheduled timer. It stops working once the timer has
+/// expired or was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(Key);
+
+pub struct TimerWheel<T, S> {
+    timers: SlotMap<Timer<T>>,
+    // first timer of every slot's doubly linked list
+    slots: [[Option<Key>; SLOTS]; LEVELS],
+    // bit `i` of `occupied[l]` is set if slot `i` of level `l` is not empty
+    occupied: [u64; LEVELS],
+    now: u64,
+    source: S,
+}
+
+struct Timer<T> {
+    value: T,
+    deadline: u64,
+    level: usize,
+    slot: usize,
+    prev: Option<Key>,
+    next: Option<Key>,
+}
+
+impl<T, S: TickSource> TimerWheel<T, S> {
+    pub fn new(mut source: S) -> Self {
+        TimerWheel {
+            timers: SlotMap::new(),
+            slots: [[None; SLOTS]; LEVELS],
+            occupied: [0; LEVELS],
+            now: source.now(),
+            source,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// The tick up to which timers have been expired.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Schedule `value` to expire `delay` ticks after the wheel's current
+    /// tick.
+    pub fn schedule(&mut self, delay: u64, value: T) -> TimerId {
+        self.schedule_at(self.now.saturating_add(delay), value)
+    }
+
+    /// Schedule `value` to expire at tick `deadline`. Deadlines that have
+    /// already passed expire at the next `poll`.
+    pub fn schedule_at(&mut self, deadline: u64, value: T) -> TimerId {
+        let key = self.timers.insert(Timer {
+            value,
+            deadline,
+            level: 0,
+            slot: 0,
+            prev: None,
+            next: None,
+        });
+        self.link(key);
+        TimerId(key)
+    }
+
+    /// Remove a timer before it expires.
+    pub fn cancel(&mut self, id: TimerId) -> Option<T> {
+        if !self.timers.contains_key(id.0) {
+            return None;
+        }
+        self.unlink(id.0);
+        self.timers.remove(id.0).map(|timer| timer.value)
+    }
+
+    pub fn deadline(&self, id: TimerId) -> Option<u64> {
+        self.timers.get(id.0).map(|timer| timer.deadline)
+    }
+
+    /// Advance to the tick reported by the tick source and return the
+    /// timers that expire on the way, in order of their deadlines.
+    ///
+    /// The wheel advances while the iterator is consumed; timers that are
+    /// not taken from it stay due for the next `poll`.
+    pub fn poll(&mut self) -> Expired<'_, T, S> {
+        let until = self.source.now().max(self.now);
+        Expired { wheel: self, until }
+    }
+
+    fn link(&mut self, key: Key) {
+        let deadline = self.timers[key].deadline.max(self.now);
+        let diff = deadline ^ self.now;
+        let level = if diff == 0 {
+            0
+        } else {
+            ((63 - diff.leading_zeros()) / SLOT_BITS) as usize
+        };
+        // deadlines beyond the top level wait in its slots and are filed
+        // again whenever the wheel passes them
+        let level = level.min(LEVELS - 1);
+        let slot = (deadline >> (level as u32 * SLOT_BITS)) as usize % SLOTS;
+        let head = self.slots[level][slot].replace(key);
+        self.occupied[level] |= 1 << slot;
+        if let Some(head) = head {
+            self.timers[head].prev = Some(key);
+        }
+        let timer = &mut self.timers[key];
+        timer.level = level;
+        timer.slot = slot;
+        timer.prev = None;
+        timer.next = head;
+    }
+
+    fn unlink(&mut self, key: Key) {
+        let Timer {
+            level,
+            slot,
+            prev,
+            next,
+            ..
+        } = self.timers[key];
+        match prev {
+            Some(p) => self.timers[p].next = next,
+            None => {
+                self.slots[level][slot] = next;
+                if next.is_none() {
+                    self.occupied[level] &= !(1 << slot);
+                }
+            }
+        }
+        if let Some(n) = next {
+            self.timers[n].prev = prev;
+        }
+    }
+
+    /// The first tick after `now` at which a timer expires or moves down.
+    fn next_event(&self) -> Option<u64> {
+        let mut next: Option<u64> = None;
+        for (level, &occupied) in self.occupied.iter().enumerate() {
+            let shift = level as u32 * SLOT_BITS;
+            let span = shift + SLOT_BITS;
+            let digit = (self.now >> shift) as u32 % SLOTS as u32;
+            let later = occupied & (!0u64).checked_shl(digit + 1).unwrap_or(0);
+            let rotation = self.now >> span << span;
+            let tick = if later != 0 {
+                Some(rotation | (later.trailing_zeros() as u64) << shift)
+            } else if level == LEVELS - 1 && occupied != 0 {
+                // far deadlines wait for the next rotation of the top level
+                rotation
+                    .checked_add(1 << span)
+                    .map(|r| r | (occupied.trailing_zeros() as u64) << shift)
+            } else {
+                None
+            };
+            next = match (next, tick) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        next
+    }
+
+    /// Move the timers of the slots that start at the current tick down.
+    fn cascade(&mut self) {
+        for level in (1..LEVELS).rev() {
+            let shift = level as u32 * SLOT_BITS;
+            if self.now & ((1 << shift) - 1) != 0 {
+                continue;
+            }
+            let slot = (self.now >> shift) as usize % SLOTS;
+            let mut next = self.slots[level][slot].take();
+            self.occupied[level] &= !(1 << slot);
+            while let Some(key) = next {
+                next = self.timers[key].next;
+                self.link(key);
+            }
+        }
+    }
+}
+
+impl<T, S> fmt::Debug for TimerWheel<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TimerWheel")
+            .field("now", &self.now)
+            .field("len", &self.timers.len())
+            .finish()
+    }
+}
+
+/// Iterator returned by `TimerWheel::poll`.
+pub struct Expired<'a, T, S: TickSource> {
+    wheel: &'a mut TimerWheel<T, S>,
+    until: u64,
+}
+
+impl<T, S: TickSource> Iterator for Expired<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let wheel = &mut *self.wheel;
+        loop {
+            if let Some(key) = wheel.slots[0][wheel.now as usize % SLOTS] {
+                wheel.unlink(key);
+                return wheel.timers.remove(key).map(|timer| timer.value);
+            }
+            if wheel.now >= self.until {
+                return None;
+            }
+            match wheel.next_event() {
+                Some(tick) if tick <= self.until => {
+                    wheel.now = tick;
+                    wheel.cascade();
+                }
+                _ => wheel.now = self.until,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    fn manual_clock() -> (Rc<Cell<u64>>, impl FnMut() -> u64) {
+        let clock = Rc::new(Cell::new(0));
+        let source = clock.clone();
+        (clock, move || source.get())
+    }
+
+    #[test]
+    fn expires_in_deadline_order() {
+        let (clock, source) = manual_clock();
+        let mut wheel = TimerWheel::new(source);
+        for delay in [300, 5, 70, 5, 4096, 1] {
+            wheel.schedule(delay, delay);
+        }
+        clock.set(100);
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec![1, 5, 5, 70]);
+        assert_eq!(wheel.now(), 100);
+        clock.set(10_000);
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec![300, 4096]);
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn tick_by_tick() {
+        let (clock, source) = manual_clock();
+        let mut wheel = TimerWheel::new(source);
+        let deadlines = [63, 64, 65, 127, 128, 4095, 4096, 4097];
+        for d in deadlines {
+            wheel.schedule_at(d, d);
+        }
+        let mut expired = Vec::new();
+        for tick in 0..5000 {
+            clock.set(tick);
+            for d in wheel.poll() {
+                assert_eq!(d, tick);
+                expired.push(d);
+            }
+        }
+        assert_eq!(expired, deadlines);
+    }
+
+    #[test]
+    fn cancel_timers() {
+        let (clock, source) = manual_clock();
+        let mut wheel = TimerWheel::new(source);
+        let a = wheel.schedule(10, 'a');
+        let b = wheel.schedule(10, 'b');
+        let c = wheel.schedule(1000, 'c');
+        assert_eq!(wheel.deadline(c), Some(1000));
+        assert_eq!(wheel.cancel(b), Some('b'));
+        assert_eq!(wheel.cancel(b), None);
+        assert_eq!(wheel.cancel(c), Some('c'));
+        clock.set(2000);
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec!['a']);
+        assert_eq!(wheel.cancel(a), None);
+    }
+
+    #[test]
+    fn past_and_far_deadlines() {
+        let (clock, source) = manual_clock();
+        clock.set(500);
+        let mut wheel = TimerWheel::new(source);
+        wheel.schedule_at(3, "late");
+        let far = 1 << 40;
+        wheel.schedule_at(far, "far");
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec!["late"]);
+        clock.set(far - 1);
+        assert_eq!(wheel.poll().next(), None);
+        clock.set(far);
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec!["far"]);
+    }
+
+    #[test]
+    fn matches_naive_schedule() {
+        let (clock, source) = manual_clock();
+        let mut wheel = TimerWheel::new(source);
+        let mut pending = Vec::new();
+        let mut x = 7u64;
+        for step in 0..3000u64 {
+            x = (x * 1103515245 + 12345) % (1 << 31);
+            let now = wheel.now();
+            match x % 4 {
+                0 if !pending.is_empty() => {
+                    let (_, id) = pending.swap_remove(x as usize % pending.len());
+                    assert!(wheel.cancel(id).is_some());
+                }
+                1 => clock.set(now + x % 300),
+                _ => {
+                    let deadline = now + x % 100_000;
+                    pending.push((deadline, wheel.schedule_at(deadline, (deadline, step))));
+                }
+            }
+            let now = clock.get();
+            let expired: Vec<_> = wheel.poll().map(|(deadline, _)| deadline).collect();
+            let mut expected: Vec<_> = pending
+                .iter()
+                .map(|&(d, _)| d)
+                .filter(|&d| d <= now)
+                .collect();
+            expected.sort();
+            pending.retain(|&(d, _)| d > now);
+            assert_eq!(expired, expected);
+        }
+    }
+
+    #[test]
+    fn unconsumed_timers_stay_due() {
+        let (clock, source) = manual_clock();
+        let mut wheel = TimerWheel::new(source);
+        for d in 1..=3 {
+            wheel.schedule(d, d);
+        }
+        clock.set(10);
+        assert_eq!(wheel.poll().next(), Some(1));
+        assert_eq!(wheel.len(), 2);
+        assert_eq!(wheel.poll().collect::<Vec<_>>(), vec![2, 3]);
+    }
+}
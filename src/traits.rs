@@ -0,0 +1,307 @@
+//! Interfaces shared by structures of the same kind, so that code can be
+//! written once and run on any of them.
+//!
+//! The crate's own structures implement these traits next to their
+//! definition; the implementations for the standard collections live here.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A collection of unique items.
+pub trait Set<T> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains(&self, item: &T) -> bool;
+
+    /// Returns `false` if the item was already present.
+    fn insert(&mut self, item: T) -> bool;
+
+    /// Returns `false` if the item was not present.
+    fn remove(&mut self, item: &T) -> bool;
+}
+
+/// A collection of key-value pairs with unique keys.
+pub trait Map<K, V> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &K) -> Option<&V>;
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the value previously stored under `key`.
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+}
+
+/// A collection that hands out its items by priority, greatest first.
+pub trait PriorityQueue<T> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&mut self, item: T);
+
+    fn pop(&mut self) -> Option<T>;
+
+    fn peek(&self) -> Option<&T>;
+}
+
+/// An indexable sequence that grows and shrinks at the back.
+pub trait Seq<T> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, index: usize) -> Option<&T>;
+
+    fn push_back(&mut self, item: T);
+
+    fn pop_back(&mut self) -> Option<T>;
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Ord> Set<T> for BTreeSet<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    fn insert(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        self.remove(item)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Eq + core::hash::Hash, S: core::hash::BuildHasher> Set<T>
+    for std::collections::HashSet<T, S>
+{
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    fn insert(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        self.remove(item)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Ord, V> Map<K, V> for BTreeMap<K, V> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + core::hash::Hash, V, S: core::hash::BuildHasher> Map<K, V>
+    for std::collections::HashMap<K, V, S>
+{
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Ord> PriorityQueue<T> for BinaryHeap<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push(&mut self, item: T) {
+        self.push(item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.peek()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Seq<T> for Vec<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    fn push_back(&mut self, item: T) {
+        self.push(item)
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Seq<T> for VecDeque<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+
+    fn push_back(&mut self, item: T) {
+        self.push_back(item)
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::binary_search_tree::{BinarySearchTree, TreeMap};
+    #[cfg(feature = "std")]
+    use crate::hamt::PHashMap;
+    use crate::heap::Heap;
+    use crate::sorted_vec::{SortedVec, SortedVecMap};
+    use crate::unrolled_list::UnrolledList;
+
+    fn drain_sorted<Q: PriorityQueue<u32>>(mut queue: Q, items: &[u32]) -> Vec<u32> {
+        for &x in items {
+            queue.push(x);
+        }
+        assert_eq!(queue.len(), items.len());
+        let top = queue.peek().copied();
+        let out: Vec<_> = core::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(top, out.first().copied());
+        assert!(queue.is_empty());
+        out
+    }
+
+    fn exercise_set<S: Set<u32>>(mut set: S) {
+        assert!(set.insert(3));
+        assert!(set.insert(1));
+        assert!(!set.insert(3));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&1));
+        assert!(set.remove(&1));
+        assert!(!set.remove(&1));
+        assert!(!set.contains(&1));
+        assert_eq!(set.len(), 1);
+    }
+
+    fn exercise_map<M: Map<u32, char>>(mut map: M) {
+        assert_eq!(map.insert(1, 'a'), None);
+        assert_eq!(map.insert(2, 'b'), None);
+        assert_eq!(map.insert(1, 'c'), Some('a'));
+        assert_eq!(map.get(&1), Some(&'c'));
+        assert!(map.contains_key(&2));
+        assert_eq!(map.remove(&2), Some('b'));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    fn exercise_seq<S: Seq<u32>>(mut seq: S) {
+        for x in 0..50 {
+            seq.push_back(x);
+        }
+        assert_eq!(seq.get(20), Some(&20));
+        assert_eq!(seq.get(50), None);
+        assert_eq!(seq.pop_back(), Some(49));
+        assert_eq!(seq.len(), 49);
+    }
+
+    #[test]
+    fn priority_queues_agree() {
+        let items = [5, 1, 9, 3, 7, 3];
+        let expected = vec![9, 7, 5, 3, 3, 1];
+        assert_eq!(drain_sorted(Heap::new(), &items), expected);
+        assert_eq!(drain_sorted(BinaryHeap::new(), &items), expected);
+    }
+
+    #[test]
+    fn sets_agree() {
+        exercise_set(BTreeSet::new());
+        #[cfg(feature = "std")]
+        exercise_set(std::collections::HashSet::new());
+        exercise_set(SortedVec::new());
+        exercise_set(BinarySearchTree::new());
+    }
+
+    #[test]
+    fn maps_agree() {
+        exercise_map(BTreeMap::new());
+        #[cfg(feature = "std")]
+        exercise_map(std::collections::HashMap::new());
+        exercise_map(SortedVecMap::new());
+        exercise_map(TreeMap::new());
+        #[cfg(feature = "std")]
+        exercise_map(PHashMap::new());
+        #[cfg(feature = "std")]
+        exercise_map(PHashMap::new().transient());
+    }
+
+    #[test]
+    fn sequences_agree() {
+        exercise_seq(Vec::new());
+        exercise_seq(VecDeque::new());
+        exercise_seq(UnrolledList::new());
+    }
+}
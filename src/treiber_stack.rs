@@ -0,0 +1,235 @@
+use alloc::boxed::Box;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// A lock-free stack (Treiber stack).
+///
+/// Popped nodes cannot be freed right away, because concurrent `pop` calls may
+/// still read them. Instead they are put on a pending list which is freed as
+/// soon as a thread observes that it is the only one inside `pop`.
+pub struct ConcurrentStack<T> {
+    head: AtomicPtr<Node<T>>,
+    threads_in_pop: AtomicUsize,
+    to_be_deleted: AtomicPtr<Node<T>>,
+}
+
+struct Node<T> {
+    item: ManuallyDrop<T>,
+    // atomic because concurrent poppers may read it while it is relinked
+    next: AtomicPtr<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for ConcurrentStack<T> {}
+unsafe impl<T: Send> Sync for ConcurrentStack<T> {}
+
+impl<T> ConcurrentStack<T> {
+    pub fn new() -> Self {
+        ConcurrentStack {
+            head: AtomicPtr::new(ptr::null_mut()),
+            threads_in_pop: AtomicUsize::new(0),
+            to_be_deleted: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
+    pub fn push(&self, item: T) {
+        let node = Box::into_raw(Box::new(Node {
+            item: ManuallyDrop::new(item),
+            next: AtomicPtr::new(self.head.load(Ordering::Relaxed)),
+        }));
+        loop {
+            // SAFETY: the node is not yet shared with other threads
+            let next = unsafe { (*node).next.load(Ordering::Relaxed) };
+            match self
+                .head
+                .compare_exchange_weak(next, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(actual) => unsafe { (*node).next.store(actual, Ordering::Relaxed) },
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.threads_in_pop.fetch_add(1, Ordering::SeqCst);
+        let mut old_head = self.head.load(Ordering::Acquire);
+        while !old_head.is_null() {
+            // SAFETY: nodes are not freed while any thread is inside `pop`
+            let next = unsafe { (*old_head).next.load(Ordering::Relaxed) };
+            match self.head.compare_exchange_weak(
+                old_head,
+                next,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => old_head = actual,
+            }
+        }
+
+        let item = if old_head.is_null() {
+            None
+        } else {
+            // SAFETY: the successful exchange gave us exclusive ownership of
+            // the item; other threads may only still read `next`.
+            Some(unsafe { ptr::read(&*(*old_head).item) })
+        };
+        self.try_reclaim(old_head);
+        item
+    }
+
+    fn try_reclaim(&self, old_head: *mut Node<T>) {
+        if self.threads_in_pop.load(Ordering::SeqCst) == 1 {
+            let pending = self.to_be_deleted.swap(ptr::null_mut(), Ordering::SeqCst);
+            if self.threads_in_pop.fetch_sub(1, Ordering::SeqCst) == 1 {
+                // SAFETY: no other thread is in `pop`, so nobody can have seen
+                // the pending nodes
+                unsafe { delete_nodes(pending) };
+            } else if !pending.is_null() {
+                self.chain_pending_nodes(pending);
+            }
+            if !old_head.is_null() {
+                // SAFETY: we were the only thread in `pop` when we unlinked it
+                unsafe { drop(Box::from_raw(old_head)) };
+            }
+        } else {
+            if !old_head.is_null() {
+                self.chain_pending_list(old_head, old_head);
+            }
+            self.threads_in_pop.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn chain_pending_nodes(&self, first: *mut Node<T>) {
+        let mut last = first;
+        // SAFETY: the pending list is owned exclusively by this thread
+        unsafe {
+            while !(*last).next.load(Ordering::Relaxed).is_null() {
+                last = (*last).next.load(Ordering::Relaxed);
+            }
+        }
+        self.chain_pending_list(first, last);
+    }
+
+    fn chain_pending_list(&self, first: *mut Node<T>, last: *mut Node<T>) {
+        let mut current = self.to_be_deleted.load(Ordering::SeqCst);
+        loop {
+            unsafe { (*last).next.store(current, Ordering::Relaxed) };
+            match self.to_be_deleted.compare_exchange_weak(
+                current,
+                first,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// Free a list of nodes whose items have already been moved out.
+unsafe fn delete_nodes<T>(mut node: *mut Node<T>) {
+    while !node.is_null() {
+        let next = (*node).next.load(Ordering::Relaxed);
+        drop(Box::from_raw(node));
+        node = next;
+    }
+}
+
+impl<T> Default for ConcurrentStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ConcurrentStack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        // SAFETY: we have exclusive access
+        unsafe { delete_nodes(*self.to_be_deleted.get_mut()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn empty_stack() {
+        let stack = ConcurrentStack::<i32>::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn lifo_order() {
+        let stack = ConcurrentStack::new();
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+        stack.push(4);
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn remaining_items_are_dropped() {
+        let item = Arc::new(());
+        {
+            let stack = ConcurrentStack::new();
+            for _ in 0..10 {
+                stack.push(item.clone());
+            }
+            stack.pop();
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn concurrent_push_and_pop() {
+        let stack = ConcurrentStack::new();
+        let per_thread = 10_000;
+        let popped: Vec<Vec<usize>> = thread::scope(|s| {
+            for t in 0..4 {
+                let stack = &stack;
+                s.spawn(move || {
+                    for i in 0..per_thread {
+                        stack.push(t * per_thread + i);
+                    }
+                });
+            }
+            let poppers: Vec<_> = (0..4)
+                .map(|_| {
+                    let stack = &stack;
+                    s.spawn(move || {
+                        let mut got = vec![];
+                        for _ in 0..per_thread / 2 {
+                            if let Some(x) = stack.pop() {
+                                got.push(x);
+                            }
+                        }
+                        got
+                    })
+                })
+                .collect();
+            poppers.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut all: Vec<usize> = popped.into_iter().flatten().collect();
+        while let Some(x) = stack.pop() {
+            all.push(x);
+        }
+        all.sort();
+        assert_eq!(all, (0..4 * per_thread).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,239 @@
+//! Undo and redo over whole snapshots of a state.
+//!
+//! Every step stores the complete previous state rather than a diff. That
+//! is cheap when the state is one of the crate's persistent structures
+//! (`PVec`, `PHashMap`, ...), because consecutive versions share all but
+//! the changed nodes.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+
+pub struct UndoStack<T> {
+    current: T,
+    /// Earlier states, the oldest first.
+    undo: VecDeque<T>,
+    /// States that were undone, the most recently undone last.
+    redo: Vec<T>,
+    limit: usize,
+    /// Nesting depth of open groups.
+    groups: usize,
+    /// Whether the open group has already recorded its undo step.
+    group_recorded: bool,
+}
+
+impl<T> UndoStack<T> {
+    /// Start at `initial`, remembering up to `limit` earlier states.
+    pub fn new(initial: T, limit: usize) -> Self {
+        UndoStack {
+            current: initial,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+            groups: 0,
+            group_recorded: false,
+        }
+    }
+
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of steps that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of steps that can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Make `state` the current state. The previous one becomes undoable,
+    /// and everything that could be redone is forgotten. Within a group,
+    /// only the first push records an undo step.
+    pub fn push_state(&mut self, state: T) {
+        self.redo.clear();
+        let previous = mem::replace(&mut self.current, state);
+        if self.groups > 0 {
+            if self.group_recorded {
+                return;
+            }
+            self.group_recorded = true;
+        }
+        if self.limit == 0 {
+            return;
+        }
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(previous);
+    }
+
+    /// Return to the previous state, if there is one.
+    pub fn undo(&mut self) -> Option<&T> {
+        let previous = self.undo.pop_back()?;
+        self.close_groups();
+        self.redo.push(mem::replace(&mut self.current, previous));
+        Some(&self.current)
+    }
+
+    /// Return to the most recently undone state, if there is one.
+    pub fn redo(&mut self) -> Option<&T> {
+        let next = self.redo.pop()?;
+        self.close_groups();
+        self.undo.push_back(mem::replace(&mut self.current, next));
+        Some(&self.current)
+    }
+
+    /// Start a checkpoint group: all states pushed until the matching
+    /// `end_group` are undone in a single step. Groups may be nested; only
+    /// the outermost one counts.
+    pub fn begin_group(&mut self) {
+        if self.groups == 0 {
+            self.group_recorded = false;
+        }
+        self.groups += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.groups = self.groups.saturating_sub(1);
+    }
+
+    /// Run `f` inside a checkpoint group.
+    pub fn group<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.begin_group();
+        let result = f(self);
+        self.end_group();
+        result
+    }
+
+    /// Forget all undo and redo steps, keeping the current state.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn into_current(self) -> T {
+        self.current
+    }
+
+    /// Undoing or redoing inside a group ends it; later pushes start a new
+    /// step.
+    fn close_groups(&mut self) {
+        self.groups = 0;
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for UndoStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UndoStack")
+            .field("current", &self.current)
+            .field("undo", &self.undo.len())
+            .field("redo", &self.redo.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::SharedFootprint;
+    use crate::persistent_vector::PVec;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut stack = UndoStack::new(0, 10);
+        stack.push_state(1);
+        stack.push_state(2);
+        assert_eq!(stack.undo(), Some(&1));
+        assert_eq!(stack.undo(), Some(&0));
+        assert_eq!(stack.undo(), None);
+        assert_eq!(stack.redo(), Some(&1));
+        assert_eq!(*stack.current(), 1);
+        assert_eq!((stack.undo_len(), stack.redo_len()), (1, 1));
+        // a new state discards the redo history
+        stack.push_state(5);
+        assert!(!stack.can_redo());
+        assert_eq!(stack.undo(), Some(&1));
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut stack = UndoStack::new(0, 3);
+        for i in 1..=10 {
+            stack.push_state(i);
+        }
+        assert_eq!(stack.undo_len(), 3);
+        while stack.undo().is_some() {}
+        assert_eq!(*stack.current(), 7);
+
+        let mut none = UndoStack::new('a', 0);
+        none.push_state('b');
+        assert!(!none.can_undo());
+        assert_eq!(none.into_current(), 'b');
+    }
+
+    #[test]
+    fn groups_undo_as_one_step() {
+        let mut stack = UndoStack::new(String::new(), 10);
+        stack.push_state("a".into());
+        stack.group(|s| {
+            s.push_state("ab".into());
+            s.begin_group();
+            s.push_state("abc".into());
+            s.end_group();
+            s.push_state("abcd".into());
+        });
+        stack.push_state("abcde".into());
+        assert_eq!(stack.undo_len(), 3);
+        assert_eq!(stack.undo().unwrap(), "abcd");
+        assert_eq!(stack.undo().unwrap(), "a");
+        assert_eq!(stack.redo().unwrap(), "abcd");
+    }
+
+    #[test]
+    fn undo_closes_open_group() {
+        let mut stack = UndoStack::new(0, 10);
+        stack.begin_group();
+        stack.push_state(1);
+        stack.push_state(2);
+        assert_eq!(stack.undo(), Some(&0));
+        stack.push_state(3);
+        stack.push_state(4);
+        assert_eq!(stack.undo_len(), 2);
+        stack.end_group();
+    }
+
+    #[test]
+    fn persistent_states_share_structure() {
+        let doc: PVec<u32> = (0..10_000).collect();
+        let mut stack = UndoStack::new(doc, 100);
+        for i in 0..50 {
+            let next = stack.current().update(i * 100, 0);
+            stack.push_state(next);
+        }
+        let latest = stack.current().clone();
+        let first = {
+            while stack.undo().is_some() {}
+            stack.current().clone()
+        };
+        assert_eq!(first.get(0), Some(&0));
+        assert_eq!(latest.get(100), Some(&0));
+        assert_eq!(first.get(100), Some(&100));
+        assert!(latest.shares_structure_with(&first).shared > 100);
+    }
+}
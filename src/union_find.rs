@@ -0,0 +1,302 @@
+//! Disjoint sets that can go back to earlier states.
+//!
+//! Both variants use union by size and no path compression, so `find` is
+//! O(log n) and a union changes exactly one parent pointer. That pointer is
+//! all that has to be undone or copied:
+//!
+//! * `RollbackUnionFind` logs every union and undoes them in reverse order
+//!   back to a `Snapshot`, which is what backtracking algorithms need.
+//! * `PUnionFind` is fully persistent: `union` returns a new version that
+//!   shares all but O(log n) nodes with the old one.
+
+use crate::persistent_vector::PVec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub struct RollbackUnionFind {
+    parent: Vec<usize>,
+    /// Number of elements in the set; only meaningful for roots.
+    size: Vec<usize>,
+    sets: usize,
+    /// The root that was attached below another root, per union.
+    history: Vec<usize>,
+}
+
+/// A point that `RollbackUnionFind::rollback` can return to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Snapshot(usize);
+
+impl RollbackUnionFind {
+    /// `n` singleton sets `{0}, {1}, ..., {n - 1}`.
+    pub fn new(n: usize) -> Self {
+        RollbackUnionFind {
+            parent: (0..n).collect(),
+            size: alloc::vec![1; n],
+            sets: n,
+            history: Vec::new(),
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    /// The representative of the set containing `x`.
+    pub fn find(&self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            x = self.parent[x];
+        }
+        x
+    }
+
+    pub fn same_set(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn set_size(&self, x: usize) -> usize {
+        self.size[self.find(x)]
+    }
+
+    /// Merge the sets of `a` and `b`. Returns `false` if they already were
+    /// the same set; nothing is logged then.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            core::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.sets -= 1;
+        self.history.push(b);
+        true
+    }
+
+    /// The current state, to be restored with `rollback`.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.history.len())
+    }
+
+    /// Undo all unions made since `snapshot` was taken. Panics if fewer
+    /// unions are logged now than when the snapshot was taken.
+    pub fn rollback(&mut self, snapshot: Snapshot) {
+        assert!(
+            snapshot.0 <= self.history.len(),
+            "snapshot is newer than the current state"
+        );
+        while self.history.len() > snapshot.0 {
+            self.undo();
+        }
+    }
+
+    /// Undo the most recent union, if there is one.
+    pub fn undo(&mut self) -> bool {
+        let Some(b) = self.history.pop() else {
+            return false;
+        };
+        let a = self.parent[b];
+        self.parent[b] = b;
+        self.size[a] -= self.size[b];
+        self.sets += 1;
+        true
+    }
+
+    /// Forget the history; earlier snapshots can no longer be restored.
+    pub fn commit(&mut self) {
+        self.history.clear();
+    }
+}
+
+impl fmt::Debug for RollbackUnionFind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RollbackUnionFind")
+            .field("len", &self.len())
+            .field("sets", &self.sets)
+            .field("history", &self.history.len())
+            .finish()
+    }
+}
+
+/// A persistent union-find; cloning and keeping old versions is cheap.
+#[derive(Clone)]
+pub struct PUnionFind {
+    parent: PVec<usize>,
+    size: PVec<usize>,
+    sets: usize,
+}
+
+impl PUnionFind {
+    /// `n` singleton sets `{0}, {1}, ..., {n - 1}`.
+    pub fn new(n: usize) -> Self {
+        PUnionFind {
+            parent: (0..n).collect(),
+            size: (0..n).map(|_| 1).collect(),
+            sets: n,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    /// The representative of the set containing `x`. Panics if `x` is out
+    /// of bounds.
+    pub fn find(&self, mut x: usize) -> usize {
+        loop {
+            let parent = *self.parent.get(x).expect("index out of bounds");
+            if parent == x {
+                return x;
+            }
+            x = parent;
+        }
+    }
+
+    pub fn same_set(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn set_size(&self, x: usize) -> usize {
+        self.root_size(self.find(x))
+    }
+
+    /// A version in which the sets of `a` and `b` are merged; `self` is
+    /// left unchanged.
+    pub fn union(&self, a: usize, b: usize) -> Self {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return self.clone();
+        }
+        if self.root_size(a) < self.root_size(b) {
+            core::mem::swap(&mut a, &mut b);
+        }
+        PUnionFind {
+            parent: self.parent.update(b, a),
+            size: self.size.update(a, self.root_size(a) + self.root_size(b)),
+            sets: self.sets - 1,
+        }
+    }
+
+    fn root_size(&self, root: usize) -> usize {
+        self.size.get(root).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Debug for PUnionFind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PUnionFind")
+            .field("len", &self.len())
+            .field("sets", &self.sets)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::SharedFootprint;
+
+    #[test]
+    fn union_and_find() {
+        let mut uf = RollbackUnionFind::new(6);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(1, 3));
+        assert!(!uf.union(0, 2));
+        assert!(uf.same_set(0, 3));
+        assert!(!uf.same_set(0, 4));
+        assert_eq!((uf.set_size(2), uf.set_size(5)), (4, 1));
+        assert_eq!(uf.set_count(), 3);
+    }
+
+    #[test]
+    fn rollback_to_snapshots() {
+        let mut uf = RollbackUnionFind::new(5);
+        uf.union(0, 1);
+        let outer = uf.snapshot();
+        uf.union(1, 2);
+        let inner = uf.snapshot();
+        uf.union(3, 4);
+        uf.union(2, 4);
+        assert_eq!(uf.set_count(), 1);
+
+        uf.rollback(inner);
+        assert!(uf.same_set(0, 2) && !uf.same_set(2, 3));
+        assert_eq!(uf.set_size(0), 3);
+        uf.rollback(outer);
+        assert!(uf.same_set(0, 1) && !uf.same_set(1, 2));
+        assert_eq!(uf.set_count(), 4);
+        assert!(uf.undo());
+        assert!(!uf.undo());
+        assert_eq!(uf.set_count(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "newer than the current state")]
+    fn rollback_to_a_discarded_snapshot_panics() {
+        let mut uf = RollbackUnionFind::new(3);
+        let start = uf.snapshot();
+        uf.union(0, 1);
+        let later = uf.snapshot();
+        uf.rollback(start);
+        uf.rollback(later);
+    }
+
+    #[test]
+    fn backtracking_search_for_a_spanning_forest() {
+        // count the spanning trees of a 4-cycle by trying every subset of
+        // edges depth-first, undoing each choice on the way back
+        fn count(uf: &mut RollbackUnionFind, edges: &[(usize, usize)]) -> usize {
+            if uf.set_count() == 1 {
+                return 1;
+            }
+            let Some((&(a, b), rest)) = edges.split_first() else {
+                return 0;
+            };
+            let mut total = count(uf, rest);
+            let snapshot = uf.snapshot();
+            if uf.union(a, b) {
+                total += count(uf, rest);
+            }
+            uf.rollback(snapshot);
+            total
+        }
+        let mut uf = RollbackUnionFind::new(4);
+        assert_eq!(count(&mut uf, &[(0, 1), (1, 2), (2, 3), (3, 0)]), 4);
+        assert_eq!(uf.set_count(), 4);
+    }
+
+    #[test]
+    fn persistent_versions_are_independent() {
+        let empty = PUnionFind::new(1000);
+        let a = empty.union(1, 2).union(2, 3);
+        let b = a.union(3, 999);
+        assert!(!empty.same_set(1, 2));
+        assert!(a.same_set(1, 3) && !a.same_set(1, 999));
+        assert!(b.same_set(1, 999));
+        assert_eq!((a.set_count(), b.set_count()), (998, 997));
+        assert_eq!(b.set_size(2), 4);
+        assert!(b.parent.shares_structure_with(&a.parent).shared > 20);
+    }
+}
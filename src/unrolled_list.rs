@@ -0,0 +1,572 @@
+use crate::errors::DataStructureError;
+use crate::memory::{MemoryFootprint, MemoryStats};
+use crate::slab::Slab;
+use crate::traits::Seq;
+use alloc::vec::Vec;
+use core::fmt;
+
+const DEFAULT_NODE_CAPACITY: usize = 16;
+
+/// A doubly linked list that stores a small array of items in every node.
+///
+/// Full nodes are split on insertion and underfull nodes are merged with
+/// their successor on removal, so indexing walks roughly `2n / capacity`
+/// nodes while insertion in the middle only shifts the items of one node.
+#[derive(Clone)]
+pub struct UnrolledList<T> {
+    nodes: Slab<Node<T>>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+    node_capacity: usize,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    items: Vec<T>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl<T> UnrolledList<T> {
+    pub fn new() -> Self {
+        Self::with_node_capacity(DEFAULT_NODE_CAPACITY)
+    }
+
+    pub fn with_node_capacity(node_capacity: usize) -> Self {
+        Self::try_with_node_capacity(node_capacity).expect("nodes must hold at least two items")
+    }
+
+    /// Fails with `InvalidStructure` if `node_capacity` is less than two.
+    pub fn try_with_node_capacity(node_capacity: usize) -> Result<Self, DataStructureError> {
+        if node_capacity < 2 {
+            return Err(DataStructureError::InvalidStructure);
+        }
+        Ok(UnrolledList {
+            nodes: Slab::new(),
+            head: None,
+            tail: None,
+            len: 0,
+            node_capacity,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.nodes[self.head?].items.first()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.nodes[self.tail?].items.last()
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        let node = match self.tail {
+            Some(tail) if self.nodes[tail].items.len() < self.node_capacity => tail,
+            tail => self.new_node_after(tail),
+        };
+        self.nodes[node].items.push(value);
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let node = match self.head {
+            Some(head) if self.nodes[head].items.len() < self.node_capacity => head,
+            _ => self.new_node_before_head(),
+        };
+        self.nodes[node].items.insert(0, value);
+        self.len += 1;
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        let value = self.nodes[tail].items.pop();
+        self.len -= 1;
+        if self.nodes[tail].items.is_empty() {
+            self.unlink(tail);
+        }
+        value
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head?;
+        let value = self.nodes[head].items.remove(0);
+        self.len -= 1;
+        if self.nodes[head].items.is_empty() {
+            self.unlink(head);
+        }
+        Some(value)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let (node, offset) = self.locate(index)?;
+        self.nodes[node].items.get(offset)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let (node, offset) = self.locate(index)?;
+        self.nodes[node].items.get_mut(offset)
+    }
+
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len, "insertion index out of bounds");
+        if index == self.len {
+            return self.push_back(value);
+        }
+        let (mut node, mut offset) = self.locate(index).unwrap();
+        if self.nodes[node].items.len() == self.node_capacity {
+            // split the full node in half
+            let half = self.node_capacity / 2;
+            let upper = self.nodes[node].items.split_off(half);
+            let new = self.new_node_after(Some(node));
+            self.nodes[new].items = upper;
+            if offset >= half {
+                node = new;
+                offset -= half;
+            }
+        }
+        self.nodes[node].items.insert(offset, value);
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let (node, offset) = self.locate(index)?;
+        let value = self.nodes[node].items.remove(offset);
+        self.len -= 1;
+        self.rebalance(node);
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.head = None;
+        self.tail = None;
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            node: self.head,
+            items: [].iter(),
+            remaining: self.len,
+        }
+    }
+
+    /// Remove all items from the front. Items that are not consumed are
+    /// dropped; the node storage is kept for reuse.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain { list: self }
+    }
+
+    /// Remove the items for which `pred` returns true, front to back. Items
+    /// that match are removed even if the iterator is not consumed.
+    pub fn drain_filter<F: FnMut(&mut T) -> bool>(&mut self, pred: F) -> DrainFilter<'_, T, F> {
+        DrainFilter {
+            node: self.head,
+            offset: 0,
+            list: self,
+            pred,
+        }
+    }
+
+    fn locate(&self, mut index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = self.head?;
+        while index >= self.nodes[node].items.len() {
+            index -= self.nodes[node].items.len();
+            node = self.nodes[node].next?;
+        }
+        Some((node, index))
+    }
+
+    /// Restore the half-full invariant after a removal from `node` by
+    /// merging with or borrowing from the next node.
+    fn rebalance(&mut self, node: usize) {
+        let min = self.node_capacity / 2;
+        if self.nodes[node].items.is_empty() {
+            return self.unlink(node);
+        }
+        if self.nodes[node].items.len() >= min {
+            return;
+        }
+        let Some(next) = self.nodes[node].next else {
+            return;
+        };
+        let next_len = self.nodes[next].items.len();
+        if self.nodes[node].items.len() + next_len <= self.node_capacity {
+            let moved = core::mem::take(&mut self.nodes[next].items);
+            self.nodes[node].items.extend(moved);
+            self.unlink(next);
+        } else {
+            let take = min - self.nodes[node].items.len();
+            let moved: Vec<T> = self.nodes[next].items.drain(..take).collect();
+            self.nodes[node].items.extend(moved);
+        }
+    }
+
+    fn new_node_after(&mut self, prev: Option<usize>) -> usize {
+        let next = match prev {
+            Some(p) => self.nodes[p].next,
+            None => self.head,
+        };
+        let node = self.nodes.insert(Node {
+            items: Vec::with_capacity(self.node_capacity),
+            prev,
+            next,
+        });
+        match prev {
+            Some(p) => self.nodes[p].next = Some(node),
+            None => self.head = Some(node),
+        }
+        match next {
+            Some(n) => self.nodes[n].prev = Some(node),
+            None => self.tail = Some(node),
+        }
+        node
+    }
+
+    fn new_node_before_head(&mut self) -> usize {
+        let node = self.nodes.insert(Node {
+            items: Vec::with_capacity(self.node_capacity),
+            prev: None,
+            next: self.head,
+        });
+        match self.head {
+            Some(h) => self.nodes[h].prev = Some(node),
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        node
+    }
+
+    fn unlink(&mut self, node: usize) {
+        let Node { prev, next, .. } = self.nodes.remove(node).unwrap();
+        match prev {
+            Some(p) => self.nodes[p].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => self.nodes[n].prev = prev,
+            None => self.tail = prev,
+        }
+    }
+}
+
+/// Iterator returned by `UnrolledList::drain`.
+pub struct Drain<'a, T> {
+    list: &'a mut UnrolledList<T>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        self.list.clear();
+    }
+}
+
+/// Iterator returned by `UnrolledList::drain_filter`.
+pub struct DrainFilter<'a, T, F: FnMut(&mut T) -> bool> {
+    list: &'a mut UnrolledList<T>,
+    node: Option<usize>,
+    offset: usize,
+    pred: F,
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Iterator for DrainFilter<'_, T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let node = self.node?;
+            let items = &mut self.list.nodes[node].items;
+            if self.offset == items.len() {
+                self.node = self.list.nodes[node].next;
+                self.offset = 0;
+            } else if (self.pred)(&mut items[self.offset]) {
+                let value = items.remove(self.offset);
+                self.list.len -= 1;
+                if items.is_empty() {
+                    self.node = self.list.nodes[node].next;
+                    self.offset = 0;
+                }
+                // merging only appends to `node`, so the position stays valid
+                self.list.rebalance(node);
+                return Some(value);
+            } else {
+                self.offset += 1;
+            }
+        }
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Drop for DrainFilter<'_, T, F> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<T> Default for UnrolledList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The nodes form a single path, so `depth` equals the number of nodes.
+impl<T> MemoryFootprint for UnrolledList<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            nodes: self.nodes.len(),
+            heap_bytes: self.nodes.capacity() * core::mem::size_of::<Node<T>>(),
+            depth: self.nodes.len(),
+            used_slots: self.len,
+            total_slots: 0,
+        };
+        for (_, node) in self.nodes.iter() {
+            stats.heap_bytes += node.items.capacity() * core::mem::size_of::<T>();
+            stats.total_slots += node.items.capacity();
+        }
+        stats
+    }
+}
+
+impl<T> Seq<T> for UnrolledList<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.get(index)
+    }
+
+    fn push_back(&mut self, item: T) {
+        self.push_back(item)
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for UnrolledList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for UnrolledList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> Extend<T> for UnrolledList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for UnrolledList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = UnrolledList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+pub struct Iter<'a, T> {
+    list: &'a UnrolledList<T>,
+    node: Option<usize>,
+    items: core::slice::Iter<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(item) = self.items.next() {
+                self.remaining -= 1;
+                return Some(item);
+            }
+            let node = &self.list.nodes[self.node?];
+            self.items = node.items.iter();
+            self.node = node.next;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn empty_list() {
+        let mut list = UnrolledList::<i32>::new();
+        assert!(list.is_empty());
+        assert_eq!(list.front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.get(0), None);
+    }
+
+    #[test]
+    fn node_capacity_must_be_at_least_two() {
+        assert!(UnrolledList::<u8>::try_with_node_capacity(2).is_ok());
+        assert_eq!(
+            UnrolledList::<u8>::try_with_node_capacity(1).err(),
+            Some(DataStructureError::InvalidStructure)
+        );
+    }
+
+    #[test]
+    fn push_and_pop_both_ends() {
+        let mut list = UnrolledList::with_node_capacity(4);
+        for i in 0..10 {
+            list.push_back(i);
+            list.push_front(-i - 1);
+        }
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.front(), Some(&-10));
+        assert_eq!(list.back(), Some(&9));
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            (-10..10).collect::<Vec<_>>()
+        );
+        assert_eq!(list.pop_front(), Some(-10));
+        assert_eq!(list.pop_back(), Some(9));
+    }
+
+    #[test]
+    fn indexed_access() {
+        let mut list: UnrolledList<_> = (0..100).collect();
+        assert_eq!(list.get(57), Some(&57));
+        *list.get_mut(57).unwrap() = 0;
+        assert_eq!(list.get(57), Some(&0));
+        assert_eq!(list.get(100), None);
+    }
+
+    #[test]
+    fn middle_insertion_splits_nodes() {
+        let mut list = UnrolledList::with_node_capacity(4);
+        list.extend(0..4);
+        assert_eq!(list.node_count(), 1);
+        list.insert(1, 10);
+        assert_eq!(list.node_count(), 2);
+        list.insert(4, 20);
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            vec![0, 10, 1, 2, 20, 3]
+        );
+    }
+
+    #[test]
+    fn removal_merges_nodes() {
+        let mut list = UnrolledList::with_node_capacity(4);
+        list.extend(0..12);
+        assert_eq!(list.node_count(), 3);
+        for _ in 0..6 {
+            list.remove(1);
+        }
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            vec![0, 7, 8, 9, 10, 11]
+        );
+        assert!(list.node_count() <= 3);
+        while list.remove(0).is_some() {}
+        assert_eq!(list.node_count(), 0);
+    }
+
+    #[test]
+    fn matches_vecdeque() {
+        let mut list = UnrolledList::with_node_capacity(5);
+        let mut reference = VecDeque::new();
+        let mut x = 3usize;
+        for i in 0..2000 {
+            x = (x * 1103 + 12345) % 10007;
+            match x % 6 {
+                0 => {
+                    let idx = x % (reference.len() + 1);
+                    list.insert(idx, i);
+                    reference.insert(idx, i);
+                }
+                1 if !reference.is_empty() => {
+                    let idx = x % reference.len();
+                    assert_eq!(list.remove(idx), reference.remove(idx));
+                }
+                2 => assert_eq!(list.pop_front(), reference.pop_front()),
+                3 => {
+                    list.push_front(i);
+                    reference.push_front(i);
+                }
+                _ => {
+                    list.push_back(i);
+                    reference.push_back(i);
+                }
+            }
+            assert_eq!(list.len(), reference.len());
+        }
+        assert!(list.iter().eq(reference.iter()));
+    }
+
+    #[test]
+    fn drain_reuses_node_storage() {
+        let mut list: UnrolledList<_> = (0..50).collect();
+        let slots = list.memory_stats().total_slots;
+        assert_eq!(
+            list.drain().take(5).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert!(list.is_empty());
+        list.extend(0..50);
+        assert_eq!(list.memory_stats().total_slots, slots);
+    }
+
+    #[test]
+    fn drain_filter_across_nodes() {
+        let mut list = UnrolledList::with_node_capacity(4);
+        list.extend(0..40);
+        let removed: Vec<_> = list.drain_filter(|x| *x % 3 != 0).take(4).collect();
+        assert_eq!(removed, vec![1, 2, 4, 5]);
+        assert!(list.iter().copied().eq((0..40).step_by(3)));
+        assert_eq!(list.len(), 14);
+        list.push_back(40);
+        assert_eq!(list.get(14), Some(&40));
+    }
+}
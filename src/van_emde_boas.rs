@@ -0,0 +1,367 @@
+use crate::errors::DataStructureError;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+
+/// A van Emde Boas tree over the universe `0..2^bits`.
+///
+/// Clusters are allocated lazily and kept in a `BTreeMap` by their high bits,
+/// so memory use is proportional to the number of stored keys (times the
+/// O(log log U) depth) rather than the universe.
+#[derive(Debug, Clone)]
+pub struct VebTree {
+    bits: u32,
+    min: Option<u64>,
+    max: Option<u64>,
+    summary: Option<Box<VebTree>>,
+    clusters: BTreeMap<u64, VebTree>,
+    len: usize,
+}
+
+impl VebTree {
+    pub fn new(bits: u32) -> Self {
+        Self::try_new(bits).expect("universe must have 1 to 64 bits")
+    }
+
+    /// Fails with `InvalidStructure` unless `bits` is in `1..=64`.
+    pub fn try_new(bits: u32) -> Result<Self, DataStructureError> {
+        if !(1..=64).contains(&bits) {
+            return Err(DataStructureError::InvalidStructure);
+        }
+        Ok(VebTree {
+            bits,
+            min: None,
+            max: None,
+            summary: None,
+            clusters: BTreeMap::new(),
+            len: 0,
+        })
+    }
+
+    pub fn universe_bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.is_none()
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        if !self.in_universe(x) {
+            return false;
+        }
+        if self.min == Some(x) || self.max == Some(x) {
+            return true;
+        }
+        if self.bits == 1 {
+            return false;
+        }
+        let (hi, lo) = self.split(x);
+        match self.cluster(hi) {
+            Some(cluster) => cluster.contains(lo),
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, x: u64) -> bool {
+        self.try_insert(x).expect("key outside of universe")
+    }
+
+    /// Like `insert`, but fails with `CapacityExceeded` if `x` does not fit
+    /// into the universe.
+    pub fn try_insert(&mut self, x: u64) -> Result<bool, DataStructureError> {
+        if !self.in_universe(x) {
+            return Err(DataStructureError::CapacityExceeded);
+        }
+        if self.contains(x) {
+            return Ok(false);
+        }
+        self.insert_new(x);
+        self.len += 1;
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, x: u64) -> bool {
+        if !self.contains(x) {
+            return false;
+        }
+        self.remove_present(x);
+        self.len -= 1;
+        true
+    }
+
+    /// Smallest key strictly greater than `x`.
+    pub fn successor(&self, x: u64) -> Option<u64> {
+        match self.min {
+            None => return None,
+            Some(min) if x < min => return Some(min),
+            _ => {}
+        }
+        if self.bits == 1 {
+            return match self.max {
+                Some(1) if x == 0 => Some(1),
+                _ => None,
+            };
+        }
+        let (hi, lo) = self.split(x);
+        if let Some(cluster) = self.cluster(hi) {
+            if cluster.max.is_some_and(|max| lo < max) {
+                return Some(self.join(hi, cluster.successor(lo).unwrap()));
+            }
+        }
+        let next_hi = self.summary.as_ref()?.successor(hi)?;
+        let cluster = self.cluster(next_hi).unwrap();
+        Some(self.join(next_hi, cluster.min.unwrap()))
+    }
+
+    /// Largest key strictly smaller than `x`.
+    pub fn predecessor(&self, x: u64) -> Option<u64> {
+        match self.max {
+            None => return None,
+            Some(max) if x > max => return Some(max),
+            _ => {}
+        }
+        if self.bits == 1 {
+            return match self.min {
+                Some(0) if x == 1 => Some(0),
+                _ => None,
+            };
+        }
+        let (hi, lo) = self.split(x);
+        if let Some(cluster) = self.cluster(hi) {
+            if cluster.min.is_some_and(|min| lo > min) {
+                return Some(self.join(hi, cluster.predecessor(lo).unwrap()));
+            }
+        }
+        let prev_hi = self.summary.as_ref().and_then(|s| s.predecessor(hi));
+        match prev_hi {
+            Some(prev_hi) => {
+                let cluster = self.cluster(prev_hi).unwrap();
+                Some(self.join(prev_hi, cluster.max.unwrap()))
+            }
+            // the minimum is not stored in any cluster
+            None => self.min.filter(|&min| min < x),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        core::iter::successors(self.min, move |&x| self.successor(x))
+    }
+
+    fn in_universe(&self, x: u64) -> bool {
+        self.bits == 64 || x >> self.bits == 0
+    }
+
+    fn low_bits(&self) -> u32 {
+        self.bits / 2
+    }
+
+    fn high_bits(&self) -> u32 {
+        self.bits - self.low_bits()
+    }
+
+    fn split(&self, x: u64) -> (u64, u64) {
+        let lb = self.low_bits();
+        (x >> lb, x & ((1 << lb) - 1))
+    }
+
+    fn join(&self, hi: u64, lo: u64) -> u64 {
+        (hi << self.low_bits()) | lo
+    }
+
+    fn cluster(&self, hi: u64) -> Option<&VebTree> {
+        self.clusters.get(&hi)
+    }
+
+    fn insert_new(&mut self, mut x: u64) {
+        let Some(min) = self.min else {
+            self.min = Some(x);
+            self.max = Some(x);
+            return;
+        };
+        if x < min {
+            self.min = Some(x);
+            x = min;
+        }
+        if self.bits > 1 {
+            let (hi, lo) = self.split(x);
+            let low_bits = self.low_bits();
+            let high_bits = self.high_bits();
+            let cluster = self
+                .clusters
+                .entry(hi)
+                .or_insert_with(|| VebTree::new(low_bits));
+            if cluster.is_empty() {
+                self.summary
+                    .get_or_insert_with(|| Box::new(VebTree::new(high_bits)))
+                    .insert_new(hi);
+            }
+            cluster.insert_new(lo);
+        }
+        if self.max.is_some_and(|max| x > max) {
+            self.max = Some(x);
+        }
+    }
+
+    fn remove_present(&mut self, mut x: u64) {
+        if self.min == self.max {
+            self.min = None;
+            self.max = None;
+            return;
+        }
+        if self.bits == 1 {
+            let other = 1 - x;
+            self.min = Some(other);
+            self.max = Some(other);
+            return;
+        }
+
+        if Some(x) == self.min {
+            // pull the smallest clustered key up to become the new minimum
+            let first_hi = self.summary.as_ref().unwrap().min.unwrap();
+            let lo = self.cluster(first_hi).unwrap().min.unwrap();
+            x = self.join(first_hi, lo);
+            self.min = Some(x);
+        }
+
+        let (hi, lo) = self.split(x);
+        let cluster = self.clusters.get_mut(&hi).unwrap();
+        cluster.remove_present(lo);
+        if cluster.is_empty() {
+            self.clusters.remove(&hi);
+            let summary = self.summary.as_mut().unwrap();
+            summary.remove_present(hi);
+            if summary.is_empty() {
+                self.summary = None;
+            }
+        }
+
+        if Some(x) == self.max {
+            self.max = match self.summary.as_ref().and_then(|s| s.max) {
+                None => self.min,
+                Some(last_hi) => {
+                    Some(self.join(last_hi, self.cluster(last_hi).unwrap().max.unwrap()))
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn empty_tree() {
+        let t = VebTree::new(16);
+        assert!(t.is_empty());
+        assert_eq!(t.min(), None);
+        assert_eq!(t.successor(0), None);
+        assert_eq!(t.predecessor(100), None);
+        assert!(!t.contains(0));
+    }
+
+    #[test]
+    fn insert_and_member() {
+        let mut t = VebTree::new(8);
+        assert!(t.insert(3));
+        assert!(t.insert(200));
+        assert!(!t.insert(3));
+        assert!(t.contains(3));
+        assert!(t.contains(200));
+        assert!(!t.contains(4));
+        assert!(!t.contains(1000));
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.min(), Some(3));
+        assert_eq!(t.max(), Some(200));
+    }
+
+    #[test]
+    fn successor_and_predecessor() {
+        let mut t = VebTree::new(10);
+        for x in [2, 3, 4, 5, 7, 14, 15, 900] {
+            t.insert(x);
+        }
+        assert_eq!(t.successor(0), Some(2));
+        assert_eq!(t.successor(5), Some(7));
+        assert_eq!(t.successor(15), Some(900));
+        assert_eq!(t.successor(900), None);
+        assert_eq!(t.predecessor(2), None);
+        assert_eq!(t.predecessor(3), Some(2));
+        assert_eq!(t.predecessor(14), Some(7));
+        assert_eq!(t.predecessor(1023), Some(900));
+    }
+
+    #[test]
+    fn remove_keys() {
+        let mut t = VebTree::new(6);
+        for x in [1, 9, 33, 63] {
+            t.insert(x);
+        }
+        assert!(t.remove(1));
+        assert!(!t.remove(1));
+        assert_eq!(t.min(), Some(9));
+        assert!(t.remove(63));
+        assert_eq!(t.max(), Some(33));
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![9, 33]);
+        assert!(t.remove(9));
+        assert!(t.remove(33));
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn full_64_bit_universe() {
+        let mut t = VebTree::new(64);
+        t.insert(u64::MAX);
+        t.insert(0);
+        t.insert(1 << 40);
+        assert_eq!(t.successor(1), Some(1 << 40));
+        assert_eq!(t.predecessor(u64::MAX), Some(1 << 40));
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![0, 1 << 40, u64::MAX]);
+    }
+
+    #[test]
+    fn invalid_universe_and_keys() {
+        assert_eq!(
+            VebTree::try_new(0).err(),
+            Some(DataStructureError::InvalidStructure)
+        );
+        let mut t = VebTree::try_new(4).unwrap();
+        assert_eq!(t.try_insert(15), Ok(true));
+        assert_eq!(t.try_insert(15), Ok(false));
+        assert_eq!(t.try_insert(16), Err(DataStructureError::CapacityExceeded));
+        assert_eq!(t.len(), 1);
+    }
+
+    #[test]
+    fn matches_btreeset() {
+        let mut t = VebTree::new(12);
+        let mut reference = BTreeSet::new();
+        let mut x = 7u64;
+        for i in 0..3000 {
+            x = (x * 1103 + 12345) % 4096;
+            if i % 3 == 0 {
+                assert_eq!(t.remove(x), reference.remove(&x));
+            } else {
+                assert_eq!(t.insert(x), reference.insert(x));
+            }
+            let q = (x * 7) % 4096;
+            assert_eq!(t.successor(q), reference.range(q + 1..).next().copied());
+            assert_eq!(t.predecessor(q), reference.range(..q).next_back().copied());
+        }
+        assert_eq!(t.len(), reference.len());
+        assert!(t.iter().eq(reference.iter().copied()));
+    }
+}
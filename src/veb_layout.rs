@@ -0,0 +1,279 @@
+//! A static search tree stored in the van Emde Boas layout.
+//!
+//! Binary search over a sorted array touches a new cache line (and soon a
+//! new page) on almost every step once the array is large. This structure
+//! stores the same items as a complete binary search tree, laid out
+//! recursively: the top half of the levels is stored first, followed by
+//! every bottom subtree in turn, each of them laid out the same way. Any
+//! root-to-leaf path then crosses only O(log_B n) blocks of size `B`,
+//! whatever the cache line or page size is.
+//!
+//! The tree is padded to `2^h - 1` slots with copies of the largest item.
+//! Positions are computed during the descent from small per-level tables,
+//! so no index array is needed.
+
+use crate::errors::DataStructureError;
+use crate::memory::{buffer_stats, MemoryFootprint, MemoryStats};
+use crate::sorted_vec::SortedVec;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+
+#[derive(Clone)]
+pub struct VebSearchTree<T> {
+    /// The tree nodes in van Emde Boas order.
+    slots: Vec<T>,
+    len: usize,
+    height: usize,
+    levels: Vec<Level>,
+}
+
+/// Where the subtrees rooted at one depth are stored relative to the
+/// enclosing top tree in which they are bottom trees.
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    /// Size of that top tree; it is stored first.
+    top_size: usize,
+    /// Size of each bottom tree.
+    bottom_size: usize,
+    /// Depth of the root of the top tree.
+    top_depth: usize,
+}
+
+impl<T: Ord + Clone> VebSearchTree<T> {
+    /// Build from items that are sorted, possibly with repetitions. Fails
+    /// with `InvalidStructure` otherwise.
+    pub fn from_sorted(items: Vec<T>) -> Result<Self, DataStructureError> {
+        if !items.is_sorted() {
+            return Err(DataStructureError::InvalidStructure);
+        }
+        let len = items.len();
+        let height = (usize::BITS - len.leading_zeros()) as usize;
+        let mut levels = vec![Level::default(); height];
+        split(&mut levels, 0, height);
+
+        let mut tree = VebSearchTree {
+            slots: Vec::new(),
+            len,
+            height,
+            levels,
+        };
+        let size = (1usize << height) - 1;
+        let Some(last) = items.last() else {
+            return Ok(tree);
+        };
+        let mut slots = vec![last.clone(); size];
+        let mut path = vec![0; height];
+        for node in 1..=size {
+            let depth = node.ilog2() as usize;
+            let rank = ((2 * (node - (1 << depth)) + 1) << (height - depth - 1)) - 1;
+            if rank < len {
+                slots[tree.position(node, &mut path)] = items[rank].clone();
+            }
+        }
+        tree.slots = slots;
+        Ok(tree)
+    }
+}
+
+impl<T> VebSearchTree<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The item at `index` in sorted order, in O(log n).
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let x = index + 1;
+        let up = x.trailing_zeros() as usize;
+        let depth = self.height - 1 - up;
+        let node = (x >> (up + 1)) + (1 << depth);
+        let mut path = [0; usize::BITS as usize];
+        Some(&self.slots[self.position(node, &mut path)])
+    }
+
+    /// Index of the first item `>= key`, or `len` if there is none.
+    pub fn lower_bound<Q>(&self, key: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.partition_point(|item| item.borrow() < key)
+    }
+
+    /// Index of the first item `> key`, or `len` if there is none.
+    pub fn upper_bound<Q>(&self, key: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.partition_point(|item| item.borrow() <= key)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get(self.lower_bound(key))
+            .is_some_and(|item| item.borrow() == key)
+    }
+
+    /// Index of the first item for which `pred` is false, assuming it is
+    /// true for a prefix of the items and false for the rest.
+    pub fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let mut result = self.len;
+        // position of the node on each depth of the current path
+        let mut path = [0usize; usize::BITS as usize];
+        let mut node = 1;
+        for depth in 0..self.height {
+            if depth > 0 {
+                path[depth] = self.child_position(node, depth, &path);
+            }
+            if pred(&self.slots[path[depth]]) {
+                node = 2 * node + 1;
+            } else {
+                let x = (2 * (node - (1 << depth)) + 1) << (self.height - depth - 1);
+                result = result.min(x - 1);
+                node *= 2;
+            }
+        }
+        result
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len).map(|i| self.get(i).unwrap())
+    }
+
+    /// Storage position of the node with breadth-first number `node`
+    /// (the root is 1), filling `path` with the positions of its ancestors.
+    fn position(&self, node: usize, path: &mut [usize]) -> usize {
+        let depth = node.ilog2() as usize;
+        path[0] = 0;
+        for d in 1..=depth {
+            path[d] = self.child_position(node >> (depth - d), d, path);
+        }
+        path[depth]
+    }
+
+    /// Position of `node` at `depth`, given the positions of its ancestors.
+    fn child_position(&self, node: usize, depth: usize, path: &[usize]) -> usize {
+        let level = self.levels[depth];
+        path[level.top_depth] + level.top_size + (node & level.top_size) * level.bottom_size
+    }
+}
+
+/// Record how a subtree of `height` levels whose root is at `depth` is
+/// split into a top tree and bottom trees, recursively.
+fn split(levels: &mut [Level], depth: usize, height: usize) {
+    if height <= 1 {
+        return;
+    }
+    let top = height / 2;
+    let bottom = height - top;
+    levels[depth + top] = Level {
+        top_size: (1 << top) - 1,
+        bottom_size: (1 << bottom) - 1,
+        top_depth: depth,
+    };
+    split(levels, depth, top);
+    split(levels, depth + top, bottom);
+}
+
+impl<T> MemoryFootprint for VebSearchTree<T> {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut stats = buffer_stats::<T>(self.slots.len(), self.slots.capacity());
+        stats.used_slots = self.len;
+        stats.depth = self.height;
+        stats
+    }
+}
+
+impl<T: Ord + Clone> From<SortedVec<T>> for VebSearchTree<T> {
+    fn from(items: SortedVec<T>) -> Self {
+        Self::from_sorted(items.into_vec()).expect("SortedVec is sorted")
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for VebSearchTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut items: Vec<T> = iter.into_iter().collect();
+        items.sort();
+        Self::from_sorted(items).expect("items were sorted")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for VebSearchTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_of_a_small_tree() {
+        // 15 nodes: top tree {8, 4, 12}, then the bottom trees in order
+        let tree = VebSearchTree::from_sorted((1..=15).collect()).unwrap();
+        assert_eq!(
+            tree.slots,
+            vec![8, 4, 12, 2, 1, 3, 6, 5, 7, 10, 9, 11, 14, 13, 15]
+        );
+        assert!((0..15).all(|i| tree.get(i) == Some(&(i + 1))));
+    }
+
+    #[test]
+    fn bounds_match_binary_search() {
+        for n in [0, 1, 2, 3, 7, 8, 100, 1000, 1023, 1025] {
+            let items: Vec<u32> = (0..n).map(|i| i / 3 * 2).collect();
+            let tree = VebSearchTree::from_sorted(items.clone()).unwrap();
+            assert_eq!(tree.len(), items.len());
+            for key in 0..=(n / 3 * 2 + 1) {
+                assert_eq!(
+                    tree.lower_bound(&key),
+                    items.partition_point(|&x| x < key),
+                    "n = {n}, key = {key}"
+                );
+                assert_eq!(tree.upper_bound(&key), items.partition_point(|&x| x <= key));
+            }
+            assert!(tree.iter().eq(items.iter()));
+        }
+    }
+
+    #[test]
+    fn lookup_by_borrowed_key() {
+        let tree: VebSearchTree<String> = ["pear", "apple", "fig", "kiwi"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert!(tree.contains("fig"));
+        assert!(!tree.contains("grape"));
+        assert_eq!(tree.lower_bound("grape"), 2);
+        assert_eq!(tree.get(tree.lower_bound("grape")).unwrap(), "kiwi");
+        assert_eq!(format!("{tree:?}"), r#"["apple", "fig", "kiwi", "pear"]"#);
+    }
+
+    #[test]
+    fn built_from_sorted_vec() {
+        let set = SortedVec::from_unsorted(vec![5, 3, 9, 1, 7]);
+        let tree = VebSearchTree::from(set);
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            vec![1, 3, 5, 7, 9]
+        );
+        assert_eq!(tree.memory_stats().total_slots, 7);
+        assert_eq!(
+            VebSearchTree::from_sorted(vec![2, 1]).unwrap_err(),
+            DataStructureError::InvalidStructure
+        );
+    }
+}
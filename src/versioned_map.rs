@@ -0,0 +1,391 @@
+use crate::hamt::PHashMap;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+use core::ops::Index;
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+/// A map that remembers its history.
+///
+/// Every modification produces a new persistent version, so undo, redo and
+/// named snapshots only store cheap handles that share structure with each
+/// other. Old versions are freed once nothing refers to them anymore.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct VersionedMap<K, V, S = RandomState> {
+    current: PHashMap<K, V, S>,
+    undo: Vec<PHashMap<K, V, S>>,
+    redo: Vec<PHashMap<K, V, S>>,
+    tags: BTreeMap<String, PHashMap<K, V, S>>,
+    history_limit: Option<usize>,
+}
+
+// without std there is no default hasher
+#[cfg(not(feature = "std"))]
+#[derive(Clone)]
+pub struct VersionedMap<K, V, S> {
+    current: PHashMap<K, V, S>,
+    undo: Vec<PHashMap<K, V, S>>,
+    redo: Vec<PHashMap<K, V, S>>,
+    tags: BTreeMap<String, PHashMap<K, V, S>>,
+    history_limit: Option<usize>,
+}
+
+/// One difference between two versions of a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<K, V> {
+    Added(K, V),
+    Removed(K, V),
+    /// The key maps to a different value: old, then new.
+    Changed(K, V, V),
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> VersionedMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Clone + Hash + Eq, V: Clone, S: BuildHasher + Clone> VersionedMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        VersionedMap {
+            current: PHashMap::with_hasher(hasher),
+            undo: vec![],
+            redo: vec![],
+            tags: BTreeMap::new(),
+            history_limit: None,
+        }
+    }
+
+    /// Keep at most `limit` undo steps; older ones are dropped.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = Some(limit);
+        self.prune_history(limit);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.current.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.current.contains_key(key)
+    }
+
+    /// The current version.
+    pub fn current(&self) -> &PHashMap<K, V, S> {
+        &self.current
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.current.get(&key).cloned();
+        let next = self.current.insert(key, value);
+        self.commit(next);
+        old
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let old = self.current.get(key).cloned()?;
+        let next = self.current.remove(key);
+        self.commit(next);
+        Some(old)
+    }
+
+    /// Apply several modifications as a single undo step.
+    pub fn batch<R>(&mut self, f: impl FnOnce(&mut PHashMap<K, V, S>) -> R) -> R {
+        let mut next = self.current.clone();
+        let result = f(&mut next);
+        self.commit(next);
+        result
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Go back to the previous version. Returns false if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(prev) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(mem::replace(&mut self.current, prev));
+        true
+    }
+
+    /// Reapply the most recently undone change. Returns false if there is
+    /// none.
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(mem::replace(&mut self.current, next));
+        true
+    }
+
+    /// Remember the current version under `name`, replacing any version
+    /// previously tagged with that name.
+    pub fn tag(&mut self, name: impl Into<String>) {
+        self.tags.insert(name.into(), self.current.clone());
+    }
+
+    pub fn untag(&mut self, name: &str) -> bool {
+        self.tags.remove(name).is_some()
+    }
+
+    pub fn version(&self, name: &str) -> Option<&PHashMap<K, V, S>> {
+        self.tags.get(name)
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.keys().map(String::as_str)
+    }
+
+    /// Make a tagged version current. This is an ordinary change, so it can
+    /// be undone.
+    pub fn checkout(&mut self, name: &str) -> bool {
+        let Some(version) = self.tags.get(name).cloned() else {
+            return false;
+        };
+        self.commit(version);
+        true
+    }
+
+    /// Drop all but the `keep` most recent undo steps and everything that
+    /// could be redone. Versions that are no longer reachable from the
+    /// history or a tag are freed.
+    pub fn prune_history(&mut self, keep: usize) {
+        let excess = self.undo.len().saturating_sub(keep);
+        self.undo.drain(..excess);
+        self.redo.clear();
+    }
+
+    fn commit(&mut self, next: PHashMap<K, V, S>) {
+        self.undo.push(mem::replace(&mut self.current, next));
+        self.redo.clear();
+        if let Some(limit) = self.history_limit {
+            if self.undo.len() > limit {
+                self.undo.remove(0);
+            }
+        }
+    }
+}
+
+impl<K, V, S> VersionedMap<K, V, S>
+where
+    K: Clone + Hash + Eq,
+    V: Clone + PartialEq,
+    S: BuildHasher + Clone,
+{
+    /// Changes that turn the version tagged `from` into the one tagged `to`.
+    pub fn diff_tags(&self, from: &str, to: &str) -> Option<Vec<Change<K, V>>> {
+        Some(diff(self.tags.get(from)?, self.tags.get(to)?))
+    }
+
+    /// Changes since the version tagged `name`.
+    pub fn changes_since(&self, name: &str) -> Option<Vec<Change<K, V>>> {
+        Some(diff(self.tags.get(name)?, &self.current))
+    }
+}
+
+/// Changes that turn `from` into `to`.
+pub fn diff<K, V, S>(from: &PHashMap<K, V, S>, to: &PHashMap<K, V, S>) -> Vec<Change<K, V>>
+where
+    K: Clone + Hash + Eq,
+    V: Clone + PartialEq,
+    S: BuildHasher + Clone,
+{
+    let mut changes = vec![];
+    for (k, old) in from.iter() {
+        match to.get(k) {
+            None => changes.push(Change::Removed(k.clone(), old.clone())),
+            Some(new) if new != old => {
+                changes.push(Change::Changed(k.clone(), old.clone(), new.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    for (k, new) in to.iter() {
+        if !from.contains_key(k) {
+            changes.push(Change::Added(k.clone(), new.clone()));
+        }
+    }
+    changes
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> Default for VersionedMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S, Q> Index<&Q> for VersionedMap<K, V, S>
+where
+    K: Clone + Hash + Eq + Borrow<Q>,
+    V: Clone,
+    S: BuildHasher + Clone,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found")
+    }
+}
+
+impl<K, V, S> fmt::Debug for VersionedMap<K, V, S>
+where
+    K: Clone + Hash + Eq + fmt::Debug,
+    V: Clone + fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.current.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Clone + Hash + Eq, V: Clone> FromIterator<(K, V)> for VersionedMap<K, V, RandomState> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        VersionedMap {
+            current: iter.into_iter().collect(),
+            ..Self::new()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut map = VersionedMap::new();
+        assert!(!map.undo());
+        map.insert("a", 1);
+        map.insert("b", 2);
+        assert_eq!(map.insert("a", 3), Some(1));
+        assert!(map.undo());
+        assert_eq!(map.get("a"), Some(&1));
+        assert!(map.undo());
+        assert!(!map.contains_key("b"));
+        assert!(map.redo());
+        assert!(map.redo());
+        assert!(!map.redo());
+        assert_eq!(map.get("a"), Some(&3));
+    }
+
+    #[test]
+    fn new_change_discards_redo() {
+        let mut map = VersionedMap::new();
+        map.insert(1, 'x');
+        map.undo();
+        assert!(map.can_redo());
+        map.insert(2, 'y');
+        assert!(!map.can_redo());
+        assert_eq!(map.remove(&2), Some('y'));
+        assert_eq!(map.remove(&2), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn batch_is_one_step() {
+        let mut map = VersionedMap::new();
+        map.batch(|m| {
+            *m = m.insert(1, 1).insert(2, 2).insert(3, 3);
+        });
+        assert_eq!(map.len(), 3);
+        map.undo();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn tags_and_checkout() {
+        let mut map: VersionedMap<_, _> = [("x", 1)].into_iter().collect();
+        map.tag("v1");
+        map.insert("x", 2);
+        map.insert("y", 5);
+        map.tag("v2");
+        assert_eq!(map.version("v1").unwrap().get("x"), Some(&1));
+        assert!(map.checkout("v1"));
+        assert_eq!(map.get("x"), Some(&1));
+        assert!(!map.contains_key("y"));
+        map.undo();
+        assert_eq!(map.get("y"), Some(&5));
+        assert!(!map.checkout("nope"));
+        assert!(map.untag("v1"));
+        assert_eq!(map.tags().collect::<Vec<_>>(), vec!["v2"]);
+    }
+
+    #[test]
+    fn diff_between_versions() {
+        let mut map: VersionedMap<_, _> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+        map.tag("old");
+        map.remove(&1);
+        map.insert(2, "B");
+        map.insert(4, "d");
+        map.tag("new");
+        let mut changes = map.diff_tags("old", "new").unwrap();
+        changes.sort_by_key(|c| match c {
+            Change::Added(k, _) | Change::Removed(k, _) | Change::Changed(k, _, _) => *k,
+        });
+        assert_eq!(
+            changes,
+            vec![
+                Change::Removed(1, "a"),
+                Change::Changed(2, "b", "B"),
+                Change::Added(4, "d"),
+            ]
+        );
+        assert_eq!(map.changes_since("new"), Some(vec![]));
+        assert_eq!(map.diff_tags("old", "missing"), None);
+    }
+
+    #[test]
+    fn history_limit_and_pruning() {
+        let mut map = VersionedMap::new().with_history_limit(2);
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+        assert!(map.undo());
+        assert!(map.undo());
+        assert!(!map.undo());
+        assert_eq!(map.len(), 3);
+        map.redo();
+        map.prune_history(0);
+        assert!(!map.can_undo());
+        assert!(!map.can_redo());
+        assert_eq!(map.len(), 4);
+    }
+}
@@ -0,0 +1,369 @@
+//! A wavelet matrix: a static sequence of integers that answers rank,
+//! range counting and range quantile queries in O(log σ), where σ is the
+//! largest value.
+//!
+//! Each level holds one bit of every value as a `RankSelect`, from the most
+//! significant bit down. Between levels the values are stably
+//! partitioned so that those with a zero bit come first. A query follows a
+//! position range down the levels with one pair of rank calls per level.
+//! Apart from the rank indexes this takes one bit per value and level.
+
+use crate::bit_vec::{BitVec, RankSelect};
+use crate::memory::{MemoryFootprint, MemoryStats};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct WaveletMatrix {
+    /// One level per bit, the most significant first.
+    levels: Vec<RankSelect>,
+    /// Number of zeros in each level, where the ones of the next level
+    /// start.
+    zeros: Vec<usize>,
+    len: usize,
+}
+
+impl WaveletMatrix {
+    pub fn new(values: &[u64]) -> Self {
+        let max = values.iter().copied().max().unwrap_or(0);
+        let bits = u64::BITS - max.leading_zeros();
+        let mut current = values.to_vec();
+        let mut levels = Vec::with_capacity(bits as usize);
+        let mut zeros = Vec::with_capacity(bits as usize);
+        for b in (0..bits).rev() {
+            let mut level = BitVec::new();
+            let (mut low, mut high) = (Vec::new(), Vec::new());
+            for &v in &current {
+                let bit = v >> b & 1 == 1;
+                level.push(bit);
+                if bit {
+                    high.push(v);
+                } else {
+                    low.push(v);
+                }
+            }
+            zeros.push(low.len());
+            low.append(&mut high);
+            current = low;
+            levels.push(RankSelect::new(level));
+        }
+        WaveletMatrix {
+            levels,
+            zeros,
+            len: values.len(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of bits per value, enough for the largest one.
+    pub fn bits(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    pub fn get(&self, mut index: usize) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        let mut value = 0;
+        for (level, &zeros) in self.levels.iter().zip(&self.zeros) {
+            let bit = level.get(index).unwrap();
+            value = value << 1 | bit as u64;
+            index = if bit {
+                zeros + level.rank1(index)
+            } else {
+                level.rank0(index)
+            };
+        }
+        Some(value)
+    }
+
+    /// Number of occurrences of `value` before position `pos`. Panics if
+    /// `pos > len`.
+    pub fn rank(&self, value: u64, pos: usize) -> usize {
+        self.range_count(..pos, value..=value)
+    }
+
+    /// Number of values in the position `range` that lie in `values`.
+    pub fn range_count(
+        &self,
+        range: impl RangeBounds<usize>,
+        values: impl RangeBounds<u64>,
+    ) -> usize {
+        let (l, r) = self.bounds(range);
+        let lo = match values.start_bound() {
+            Bound::Included(&v) => v as u128,
+            Bound::Excluded(&v) => v as u128 + 1,
+            Bound::Unbounded => 0,
+        };
+        let hi = match values.end_bound() {
+            Bound::Included(&v) => v as u128 + 1,
+            Bound::Excluded(&v) => v as u128,
+            Bound::Unbounded => u64::MAX as u128 + 1,
+        };
+        if lo >= hi {
+            return 0;
+        }
+        self.count_less(l, r, hi) - self.count_less(l, r, lo)
+    }
+
+    /// The `k`-th smallest value (counting from 0) in the position `range`.
+    pub fn kth_smallest(&self, range: impl RangeBounds<usize>, mut k: usize) -> Option<u64> {
+        let (mut l, mut r) = self.bounds(range);
+        if k >= r - l {
+            return None;
+        }
+        let mut value = 0;
+        for (level, &zeros) in self.levels.iter().zip(&self.zeros) {
+            let (l0, r0) = (level.rank0(l), level.rank0(r));
+            if k < r0 - l0 {
+                value <<= 1;
+                (l, r) = (l0, r0);
+            } else {
+                k -= r0 - l0;
+                value = value << 1 | 1;
+                (l, r) = (zeros + l - l0, zeros + r - r0);
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u64> + '_ {
+        (0..self.len).map(|i| self.get(i).unwrap())
+    }
+
+    /// Number of values in positions `l..r` that are less than `x`.
+    fn count_less(&self, mut l: usize, mut r: usize, x: u128) -> usize {
+        if x >> self.bits() != 0 {
+            return r - l;
+        }
+        let mut count = 0;
+        for (b, (level, &zeros)) in self.levels.iter().zip(&self.zeros).enumerate() {
+            let (l0, r0) = (level.rank0(l), level.rank0(r));
+            if x >> (self.levels.len() - 1 - b) & 1 == 1 {
+                count += r0 - l0;
+                (l, r) = (zeros + l - l0, zeros + r - r0);
+            } else {
+                (l, r) = (l0, r0);
+            }
+        }
+        count
+    }
+
+    fn bounds(&self, range: impl RangeBounds<usize>) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "range out of bounds");
+        (start, end)
+    }
+}
+
+impl FromIterator<u64> for WaveletMatrix {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        WaveletMatrix::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl MemoryFootprint for WaveletMatrix {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            nodes: 0,
+            heap_bytes: self.zeros.capacity() * size_of::<usize>(),
+            depth: self.levels.len(),
+            used_slots: self.len,
+            total_slots: self.len,
+        };
+        for level in &self.levels {
+            let s = level.memory_stats();
+            stats.nodes += s.nodes;
+            stats.heap_bytes += s.heap_bytes;
+        }
+        stats
+    }
+}
+
+impl fmt::Debug for WaveletMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Static points in the plane that answer how many lie in a rectangle.
+///
+/// The points are sorted by x and their y coordinates go into a wavelet
+/// matrix, so an x range becomes a position range found by binary search.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PointCounter {
+    xs: Vec<u64>,
+    ys: WaveletMatrix,
+}
+
+impl PointCounter {
+    pub fn new(points: &[(u64, u64)]) -> Self {
+        let mut points = points.to_vec();
+        points.sort_unstable();
+        let ys: Vec<u64> = points.iter().map(|&(_, y)| y).collect();
+        PointCounter {
+            xs: points.into_iter().map(|(x, _)| x).collect(),
+            ys: WaveletMatrix::new(&ys),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    /// Number of points with x in `xs` and y in `ys`.
+    pub fn count(&self, xs: impl RangeBounds<u64>, ys: impl RangeBounds<u64>) -> usize {
+        let start = match xs.start_bound() {
+            Bound::Included(&x) => self.xs.partition_point(|&p| p < x),
+            Bound::Excluded(&x) => self.xs.partition_point(|&p| p <= x),
+            Bound::Unbounded => 0,
+        };
+        let end = match xs.end_bound() {
+            Bound::Included(&x) => self.xs.partition_point(|&p| p <= x),
+            Bound::Excluded(&x) => self.xs.partition_point(|&p| p < x),
+            Bound::Unbounded => self.xs.len(),
+        };
+        if start >= end {
+            return 0;
+        }
+        self.ys.range_count(start..end, ys)
+    }
+}
+
+impl FromIterator<(u64, u64)> for PointCounter {
+    fn from_iter<I: IntoIterator<Item = (u64, u64)>>(iter: I) -> Self {
+        PointCounter::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl fmt::Debug for PointCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.xs.iter().zip(self.ys.iter()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_values(n: usize, max: u64, mut seed: u64) -> Vec<u64> {
+        (0..n)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 33) % (max + 1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn access_and_rank() {
+        let values = random_values(500, 40, 1);
+        let wm = WaveletMatrix::new(&values);
+        assert_eq!(wm.len(), 500);
+        assert_eq!(wm.bits(), 6);
+        assert!(wm.iter().eq(values.iter().copied()));
+        for v in 0..42 {
+            for pos in [0, 1, 77, 250, 500] {
+                let expected = values[..pos].iter().filter(|&&x| x == v).count();
+                assert_eq!(wm.rank(v, pos), expected);
+            }
+        }
+        assert_eq!(wm.get(500), None);
+    }
+
+    #[test]
+    fn range_count_matches_naive() {
+        let values = random_values(300, 100, 2);
+        let wm: WaveletMatrix = values.iter().copied().collect();
+        for (l, r) in [(0, 300), (10, 11), (50, 200), (299, 300), (7, 7)] {
+            for (lo, hi) in [(0, 101), (20, 40), (99, 100), (60, 30), (0, 0)] {
+                let expected = values[l..r].iter().filter(|&&v| lo <= v && v < hi).count();
+                assert_eq!(wm.range_count(l..r, lo..hi), expected);
+            }
+        }
+        assert_eq!(wm.range_count(.., ..), 300);
+        assert_eq!(wm.range_count(.., 101..), 0);
+        assert_eq!(
+            wm.range_count(..=9, ..=50),
+            values[..10].iter().filter(|&&v| v <= 50).count()
+        );
+    }
+
+    #[test]
+    fn kth_smallest_matches_sorting() {
+        let values = random_values(200, 1000, 3);
+        let wm = WaveletMatrix::new(&values);
+        for (l, r) in [(0, 200), (13, 57), (100, 101)] {
+            let mut sorted = values[l..r].to_vec();
+            sorted.sort();
+            for (k, &v) in sorted.iter().enumerate() {
+                assert_eq!(wm.kth_smallest(l..r, k), Some(v));
+            }
+            assert_eq!(wm.kth_smallest(l..r, r - l), None);
+        }
+    }
+
+    #[test]
+    fn full_width_and_empty() {
+        let values = [u64::MAX, 0, u64::MAX - 1, 1 << 63];
+        let wm = WaveletMatrix::new(&values);
+        assert_eq!(wm.bits(), 64);
+        assert!(wm.iter().eq(values));
+        assert_eq!(wm.range_count(.., u64::MAX..), 1);
+        assert_eq!(wm.range_count(.., 1 << 63..), 3);
+        assert_eq!(wm.kth_smallest(.., 3), Some(u64::MAX));
+
+        let zeros = WaveletMatrix::new(&[0, 0, 0]);
+        assert_eq!(zeros.bits(), 0);
+        assert_eq!(zeros.range_count(1.., 0..1), 2);
+        assert_eq!(zeros.kth_smallest(.., 2), Some(0));
+        assert!(WaveletMatrix::new(&[]).is_empty());
+    }
+
+    #[test]
+    fn counts_points_in_rectangles() {
+        let xs = random_values(400, 50, 4);
+        let ys = random_values(400, 50, 5);
+        let points: Vec<_> = xs.into_iter().zip(ys).collect();
+        let counter: PointCounter = points.iter().copied().collect();
+        for (x0, x1, y0, y1) in [
+            (0, 50, 0, 50),
+            (10, 20, 5, 45),
+            (25, 25, 0, 50),
+            (30, 10, 0, 50),
+        ] {
+            let expected = points
+                .iter()
+                .filter(|&&(x, y)| x0 <= x && x <= x1 && y0 <= y && y <= y1)
+                .count();
+            assert_eq!(counter.count(x0..=x1, y0..=y1), expected);
+        }
+        assert_eq!(counter.count(.., ..), 400);
+        assert_eq!(counter.count(51.., ..), 0);
+    }
+}
@@ -0,0 +1,322 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicIsize, AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+
+const MIN_CAPACITY: usize = 16;
+
+/// Owner side of a Chase–Lev work-stealing deque.
+///
+/// The owner pushes and pops at the bottom end (LIFO), while any number of
+/// `Stealer`s take items from the top end (FIFO).
+pub struct Worker<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Thief side of a work-stealing deque; cheap to clone and share.
+pub struct Stealer<T> {
+    inner: Arc<Inner<T>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Steal<T> {
+    Empty,
+    Success(T),
+    /// Lost a race with another thread; the deque may still contain items.
+    Retry,
+}
+
+struct Inner<T> {
+    top: AtomicIsize,
+    bottom: AtomicIsize,
+    buffer: AtomicPtr<Buffer<T>>,
+    // Buffers replaced by a grow may still be read by stealers, so they are
+    // only freed together with the deque.
+    retired: Mutex<Vec<*mut Buffer<T>>>,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+struct Buffer<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+impl<T> Buffer<T> {
+    fn alloc(capacity: usize) -> *mut Self {
+        let slots = (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect();
+        Box::into_raw(Box::new(Buffer { slots }))
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, index: isize) -> *mut MaybeUninit<T> {
+        self.slots[index as usize & (self.capacity() - 1)].get()
+    }
+
+    /// A bitwise copy of the slot. It only becomes an owned `T` once the
+    /// caller has won the item.
+    unsafe fn read(&self, index: isize) -> MaybeUninit<T> {
+        ptr::read(self.slot(index))
+    }
+
+    unsafe fn write(&self, index: isize, value: T) {
+        ptr::write(self.slot(index), MaybeUninit::new(value));
+    }
+}
+
+impl<T> Worker<T> {
+    pub fn new() -> Self {
+        Worker {
+            inner: Arc::new(Inner {
+                top: AtomicIsize::new(0),
+                bottom: AtomicIsize::new(0),
+                buffer: AtomicPtr::new(Buffer::alloc(MIN_CAPACITY)),
+                retired: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    pub fn stealer(&self) -> Stealer<T> {
+        Stealer {
+            inner: self.inner.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        let b = self.inner.bottom.load(Ordering::Relaxed);
+        let t = self.inner.top.load(Ordering::Relaxed);
+        (b - t).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        let inner = &*self.inner;
+        let b = inner.bottom.load(Ordering::Relaxed);
+        let t = inner.top.load(Ordering::Acquire);
+        let mut buffer = inner.buffer.load(Ordering::Relaxed);
+        // SAFETY: only the owner replaces the buffer, and old buffers are
+        // never freed while the deque is alive
+        unsafe {
+            if (b - t) as usize >= (*buffer).capacity() {
+                buffer = self.grow(buffer, t, b);
+            }
+            (*buffer).write(b, value);
+        }
+        fence(Ordering::Release);
+        inner.bottom.store(b + 1, Ordering::Relaxed);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let inner = &*self.inner;
+        let b = inner.bottom.load(Ordering::Relaxed) - 1;
+        let buffer = inner.buffer.load(Ordering::Relaxed);
+        inner.bottom.store(b, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        let t = inner.top.load(Ordering::Relaxed);
+
+        if t > b {
+            inner.bottom.store(b + 1, Ordering::Relaxed);
+            return None;
+        }
+        // SAFETY: the slot at `b` holds an item that no stealer can take
+        // unless it wins the race for the last item below
+        let value = unsafe { (*buffer).read(b) };
+        if t < b {
+            // SAFETY: no stealer can reach `b` while `t < b`
+            return Some(unsafe { value.assume_init() });
+        }
+        // last item: race against the stealers
+        let won = inner
+            .top
+            .compare_exchange(t, t + 1, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok();
+        inner.bottom.store(b + 1, Ordering::Relaxed);
+        // SAFETY: winning the exchange on `top` makes the item ours
+        won.then(|| unsafe { value.assume_init() })
+    }
+
+    unsafe fn grow(&self, old: *mut Buffer<T>, t: isize, b: isize) -> *mut Buffer<T> {
+        let new = Buffer::alloc((*old).capacity() * 2);
+        for i in t..b {
+            ptr::copy_nonoverlapping((*old).slot(i), (*new).slot(i), 1);
+        }
+        self.inner.buffer.store(new, Ordering::Release);
+        self.inner
+            .retired
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(old);
+        new
+    }
+}
+
+impl<T> Default for Worker<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Stealer<T> {
+    pub fn steal(&self) -> Steal<T> {
+        let inner = &*self.inner;
+        let t = inner.top.load(Ordering::Acquire);
+        fence(Ordering::SeqCst);
+        let b = inner.bottom.load(Ordering::Acquire);
+        if t >= b {
+            return Steal::Empty;
+        }
+        let buffer = inner.buffer.load(Ordering::Acquire);
+        // SAFETY: the buffer stays allocated while the deque is alive; the
+        // copy may be stale, so it is only used if we win the exchange
+        let value = unsafe { (*buffer).read(t) };
+        match inner
+            .top
+            .compare_exchange(t, t + 1, Ordering::SeqCst, Ordering::Relaxed)
+        {
+            // SAFETY: winning the exchange on `top` makes the item ours
+            Ok(_) => Steal::Success(unsafe { value.assume_init() }),
+            Err(_) => Steal::Retry,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let t = self.inner.top.load(Ordering::Acquire);
+        let b = self.inner.bottom.load(Ordering::Acquire);
+        t >= b
+    }
+}
+
+impl<T> Clone for Stealer<T> {
+    fn clone(&self) -> Self {
+        Stealer {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let t = *self.top.get_mut();
+        let b = *self.bottom.get_mut();
+        let buffer = *self.buffer.get_mut();
+        // SAFETY: no other handles exist any more
+        unsafe {
+            for i in t..b {
+                drop((*buffer).read(i).assume_init());
+            }
+            drop(Box::from_raw(buffer));
+            for old in self
+                .retired
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .drain(..)
+            {
+                drop(Box::from_raw(old));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    #[test]
+    fn empty_deque() {
+        let mut w = Worker::<i32>::new();
+        let s = w.stealer();
+        assert!(w.is_empty());
+        assert_eq!(w.pop(), None);
+        assert_eq!(s.steal(), Steal::Empty);
+    }
+
+    #[test]
+    fn owner_is_lifo_and_thieves_are_fifo() {
+        let mut w = Worker::new();
+        let s = w.stealer();
+        for i in 0..5 {
+            w.push(i);
+        }
+        assert_eq!(w.pop(), Some(4));
+        assert_eq!(s.steal(), Steal::Success(0));
+        assert_eq!(s.steal(), Steal::Success(1));
+        assert_eq!(w.len(), 2);
+        assert_eq!(w.pop(), Some(3));
+        assert_eq!(w.pop(), Some(2));
+        assert_eq!(w.pop(), None);
+    }
+
+    #[test]
+    fn grows_beyond_initial_capacity() {
+        let mut w = Worker::new();
+        let s = w.stealer();
+        for i in 0..1000 {
+            w.push(i);
+        }
+        assert_eq!(s.steal(), Steal::Success(0));
+        assert_eq!(w.pop(), Some(999));
+        assert_eq!(w.len(), 998);
+    }
+
+    #[test]
+    fn remaining_items_are_dropped() {
+        let item = Arc::new(());
+        {
+            let mut w = Worker::new();
+            for _ in 0..100 {
+                w.push(item.clone());
+            }
+            w.pop();
+            let _s = w.stealer();
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn every_item_is_taken_exactly_once() {
+        let n = 50_000;
+        let mut w = Worker::new();
+        let taken = AtomicUsize::new(0);
+        let sum = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..3 {
+                let stealer = w.stealer();
+                let (taken, sum) = (&taken, &sum);
+                s.spawn(move || {
+                    while taken.load(Ordering::SeqCst) < n {
+                        if let Steal::Success(x) = stealer.steal() {
+                            sum.fetch_add(x, Ordering::SeqCst);
+                            taken.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                });
+            }
+            for i in 0..n {
+                w.push(i);
+                if i % 3 == 0 {
+                    if let Some(x) = w.pop() {
+                        sum.fetch_add(x, Ordering::SeqCst);
+                        taken.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            }
+            while let Some(x) = w.pop() {
+                sum.fetch_add(x, Ordering::SeqCst);
+                taken.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        assert_eq!(taken.into_inner(), n);
+        assert_eq!(sum.into_inner(), n * (n - 1) / 2);
+    }
+}
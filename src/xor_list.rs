@@ -0,0 +1,624 @@
+use alloc::boxed::Box;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr;
+
+/// A doubly linked list that stores a single link per node: the XOR of the
+/// addresses of its two neighbours.
+///
+/// Walking the list in either direction needs the address of the node one
+/// came from, which is why traversal goes through cursors and iterators that
+/// remember two adjacent nodes.
+pub struct XorList<T> {
+    head: *mut Node<T>,
+    tail: *mut Node<T>,
+    len: usize,
+    _owns: PhantomData<Box<Node<T>>>,
+}
+
+struct Node<T> {
+    value: T,
+    // address of prev XOR address of next; a missing neighbour counts as 0
+    link: usize,
+}
+
+unsafe impl<T: Send> Send for XorList<T> {}
+unsafe impl<T: Sync> Sync for XorList<T> {}
+
+// The addresses are exposed so that pointers recovered from a link keep a
+// valid provenance.
+fn addr<T>(p: *mut Node<T>) -> usize {
+    p.expose_provenance()
+}
+
+fn from_addr<T>(a: usize) -> *mut Node<T> {
+    ptr::with_exposed_provenance_mut(a)
+}
+
+/// The neighbour of `node` that is not `other`.
+///
+/// SAFETY: `node` must point to a live node of the list.
+unsafe fn other_neighbour<T>(node: *mut Node<T>, other: *mut Node<T>) -> *mut Node<T> {
+    from_addr((*node).link ^ addr(other))
+}
+
+/// Replace the neighbour `old` of `node` by `new`.
+///
+/// SAFETY: `node` must be null or point to a live node of the list.
+unsafe fn relink<T>(node: *mut Node<T>, old: *mut Node<T>, new: *mut Node<T>) {
+    if !node.is_null() {
+        (*node).link ^= addr(old) ^ addr(new);
+    }
+}
+
+impl<T> XorList<T> {
+    pub fn new() -> Self {
+        XorList {
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            len: 0,
+            _owns: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        // SAFETY: head is null or a live node owned by the list
+        unsafe { self.head.as_ref().map(|n| &n.value) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: tail is null or a live node owned by the list
+        unsafe { self.tail.as_ref().map(|n| &n.value) }
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        // SAFETY: inserting between the null "ghost" position and the head
+        unsafe { self.insert_between(ptr::null_mut(), self.head, value) };
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        // SAFETY: inserting between the tail and the ghost position
+        unsafe { self.insert_between(self.tail, ptr::null_mut(), value) };
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.head.is_null() {
+            return None;
+        }
+        // SAFETY: the head is live and its predecessor is the ghost position
+        unsafe { Some(self.unlink(ptr::null_mut(), self.head)) }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.tail.is_null() {
+            return None;
+        }
+        // SAFETY: the tail is live; it is unlinked with its predecessor
+        unsafe {
+            let prev = other_neighbour(self.tail, ptr::null_mut());
+            Some(self.unlink(prev, self.tail))
+        }
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    /// Remove all items from the front. Items that are not consumed are
+    /// dropped.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain { list: self }
+    }
+
+    /// Remove the items for which `pred` returns true, front to back. Items
+    /// that match are removed even if the iterator is not consumed.
+    pub fn drain_filter<F: FnMut(&mut T) -> bool>(&mut self, pred: F) -> DrainFilter<'_, T, F> {
+        DrainFilter {
+            cursor: self.cursor_front_mut(),
+            pred,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front_prev: ptr::null_mut(),
+            front: self.head,
+            back_next: ptr::null_mut(),
+            back: self.tail,
+            remaining: self.len,
+            _list: PhantomData,
+        }
+    }
+
+    pub fn cursor_front(&self) -> Cursor<'_, T> {
+        Cursor {
+            prev: ptr::null_mut(),
+            current: self.head,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back(&self) -> Cursor<'_, T> {
+        let mut cursor = Cursor {
+            prev: ptr::null_mut(),
+            current: ptr::null_mut(),
+            list: self,
+        };
+        cursor.move_prev();
+        cursor
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            prev: ptr::null_mut(),
+            current: self.head,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        let mut cursor = CursorMut {
+            prev: ptr::null_mut(),
+            current: ptr::null_mut(),
+            list: self,
+        };
+        cursor.move_prev();
+        cursor
+    }
+
+    /// SAFETY: `prev` and `next` must be adjacent (either may be the null
+    /// ghost position that sits between tail and head).
+    unsafe fn insert_between(
+        &mut self,
+        prev: *mut Node<T>,
+        next: *mut Node<T>,
+        value: T,
+    ) -> *mut Node<T> {
+        let node = Box::into_raw(Box::new(Node {
+            value,
+            link: addr(prev) ^ addr(next),
+        }));
+        relink(prev, next, node);
+        relink(next, prev, node);
+        if prev.is_null() {
+            self.head = node;
+        }
+        if next.is_null() {
+            self.tail = node;
+        }
+        self.len += 1;
+        node
+    }
+
+    /// Remove `node`, whose predecessor is `prev`, and return its value.
+    ///
+    /// SAFETY: `node` must be live and `prev` its predecessor.
+    unsafe fn unlink(&mut self, prev: *mut Node<T>, node: *mut Node<T>) -> T {
+        let next = other_neighbour(node, prev);
+        relink(prev, node, next);
+        relink(next, node, prev);
+        if prev.is_null() {
+            self.head = next;
+        }
+        if next.is_null() {
+            self.tail = prev;
+        }
+        self.len -= 1;
+        Box::from_raw(node).value
+    }
+}
+
+impl<T> Drop for XorList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Default for XorList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for XorList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for XorList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for XorList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> Extend<T> for XorList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for XorList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = XorList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+/// Iterator returned by `XorList::drain`.
+pub struct Drain<'a, T> {
+    list: &'a mut XorList<T>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        self.list.clear();
+    }
+}
+
+/// Iterator returned by `XorList::drain_filter`.
+pub struct DrainFilter<'a, T, F: FnMut(&mut T) -> bool> {
+    cursor: CursorMut<'a, T>,
+    pred: F,
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Iterator for DrainFilter<'_, T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while let Some(value) = self.cursor.current_mut() {
+            if (self.pred)(value) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+        None
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Drop for DrainFilter<'_, T, F> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+pub struct Iter<'a, T> {
+    front_prev: *mut Node<T>,
+    front: *mut Node<T>,
+    back_next: *mut Node<T>,
+    back: *mut Node<T>,
+    remaining: usize,
+    _list: PhantomData<&'a XorList<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.front;
+        // SAFETY: `remaining > 0`, so `front` is a live node of the borrowed list
+        unsafe {
+            self.front = other_neighbour(node, self.front_prev);
+            self.front_prev = node;
+            Some(&(*node).value)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.back;
+        // SAFETY: as in `next`
+        unsafe {
+            self.back = other_neighbour(node, self.back_next);
+            self.back_next = node;
+            Some(&(*node).value)
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// A read-only position in an `XorList`.
+///
+/// Like the cursors of `alloc::collections::LinkedList`, it may also point to
+/// a "ghost" element between the back and the front of the list.
+pub struct Cursor<'a, T> {
+    prev: *mut Node<T>,
+    current: *mut Node<T>,
+    list: &'a XorList<T>,
+}
+
+/// A position in an `XorList` that allows editing the list around it.
+pub struct CursorMut<'a, T> {
+    prev: *mut Node<T>,
+    current: *mut Node<T>,
+    list: &'a mut XorList<T>,
+}
+
+macro_rules! cursor_movement {
+    () => {
+        pub fn move_next(&mut self) {
+            if self.current.is_null() {
+                self.prev = ptr::null_mut();
+                self.current = self.list.head;
+            } else {
+                // SAFETY: `current` is a live node of the list
+                let next = unsafe { other_neighbour(self.current, self.prev) };
+                self.prev = self.current;
+                self.current = next;
+            }
+        }
+
+        pub fn move_prev(&mut self) {
+            if self.current.is_null() {
+                self.current = self.list.tail;
+                self.prev = if self.current.is_null() {
+                    ptr::null_mut()
+                } else {
+                    // SAFETY: the tail is live and followed by the ghost
+                    unsafe { other_neighbour(self.current, ptr::null_mut()) }
+                };
+            } else {
+                let old = self.current;
+                self.current = self.prev;
+                if !self.current.is_null() {
+                    // SAFETY: `prev` is a live node of the list
+                    self.prev = unsafe { other_neighbour(self.current, old) };
+                }
+            }
+        }
+
+        pub fn current(&self) -> Option<&T> {
+            // SAFETY: `current` is null or a live node of the list
+            unsafe { self.current.as_ref().map(|n| &n.value) }
+        }
+
+        pub fn peek_next(&self) -> Option<&T> {
+            let next = if self.current.is_null() {
+                self.list.head
+            } else {
+                // SAFETY: `current` is a live node of the list
+                unsafe { other_neighbour(self.current, self.prev) }
+            };
+            // SAFETY: `next` is null or a live node of the list
+            unsafe { next.as_ref().map(|n| &n.value) }
+        }
+
+        pub fn peek_prev(&self) -> Option<&T> {
+            let prev = if self.current.is_null() {
+                self.list.tail
+            } else {
+                self.prev
+            };
+            // SAFETY: `prev` is null or a live node of the list
+            unsafe { prev.as_ref().map(|n| &n.value) }
+        }
+    };
+}
+
+impl<T> Cursor<'_, T> {
+    cursor_movement!();
+}
+
+impl<T> CursorMut<'_, T> {
+    cursor_movement!();
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: `current` is null or a live node, and we borrow the list
+        // mutably
+        unsafe { self.current.as_mut().map(|n| &mut n.value) }
+    }
+
+    /// Insert after the current element (at the front if at the ghost).
+    pub fn insert_after(&mut self, value: T) {
+        if self.current.is_null() {
+            return self.list.push_front(value);
+        }
+        // SAFETY: `current` and its successor are adjacent
+        unsafe {
+            let next = other_neighbour(self.current, self.prev);
+            self.list.insert_between(self.current, next, value);
+        }
+    }
+
+    /// Insert before the current element (at the back if at the ghost).
+    pub fn insert_before(&mut self, value: T) {
+        if self.current.is_null() {
+            return self.list.push_back(value);
+        }
+        // SAFETY: `prev` and `current` are adjacent; the new node becomes
+        // our predecessor
+        self.prev = unsafe { self.list.insert_between(self.prev, self.current, value) };
+    }
+
+    /// Remove the current element and move to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.current.is_null() {
+            return None;
+        }
+        let node = self.current;
+        // SAFETY: `node` is live and `prev` is its predecessor
+        unsafe {
+            self.current = other_neighbour(node, self.prev);
+            Some(self.list.unlink(self.prev, node))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn items<T: Clone>(list: &XorList<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn empty_list() {
+        let mut list = XorList::<i32>::new();
+        assert!(list.is_empty());
+        assert_eq!(list.front(), None);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.cursor_front().current(), None);
+    }
+
+    #[test]
+    fn push_and_pop_both_ends() {
+        let mut list = XorList::new();
+        list.push_back(2);
+        list.push_back(3);
+        list.push_front(1);
+        assert_eq!(items(&list), vec![1, 2, 3]);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&3));
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn iterate_in_both_directions() {
+        let list: XorList<_> = (0..6).collect();
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            vec![5, 4, 3, 2, 1, 0]
+        );
+        let mut it = list.iter();
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next_back(), Some(&5));
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.next_back(), Some(&4));
+        assert_eq!(it.len(), 2);
+        assert_eq!(it.collect::<Vec<_>>(), vec![&2, &3]);
+    }
+
+    #[test]
+    fn cursor_walks_both_ways() {
+        let list: XorList<_> = (1..=3).collect();
+        let mut c = list.cursor_front();
+        assert_eq!(c.current(), Some(&1));
+        assert_eq!(c.peek_prev(), None);
+        c.move_next();
+        c.move_next();
+        assert_eq!(c.current(), Some(&3));
+        c.move_next();
+        assert_eq!(c.current(), None);
+        assert_eq!(c.peek_next(), Some(&1));
+        assert_eq!(c.peek_prev(), Some(&3));
+        c.move_prev();
+        c.move_prev();
+        assert_eq!(c.current(), Some(&2));
+        assert_eq!(c.peek_prev(), Some(&1));
+        assert_eq!(c.peek_next(), Some(&3));
+        let mut c = list.cursor_back();
+        assert_eq!(c.current(), Some(&3));
+        c.move_prev();
+        c.move_prev();
+        c.move_prev();
+        assert_eq!(c.current(), None);
+    }
+
+    #[test]
+    fn cursor_edits() {
+        let mut list: XorList<_> = [1, 3, 5].into_iter().collect();
+        let mut c = list.cursor_front_mut();
+        c.insert_before(0);
+        c.insert_after(2);
+        c.move_next();
+        assert_eq!(c.current(), Some(&2));
+        c.move_next();
+        *c.current_mut().unwrap() = 30;
+        c.insert_before(25);
+        assert_eq!(c.peek_prev(), Some(&25));
+        c.move_prev();
+        c.move_prev();
+        assert_eq!(c.current(), Some(&2));
+        c.move_next();
+        c.move_next();
+        assert_eq!(c.remove_current(), Some(30));
+        assert_eq!(c.current(), Some(&5));
+        assert_eq!(c.remove_current(), Some(5));
+        assert_eq!(c.current(), None);
+        c.insert_before(6);
+        assert_eq!(items(&list), vec![0, 1, 2, 25, 6]);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            vec![6, 25, 2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn values_are_dropped() {
+        let item = Rc::new(());
+        {
+            let mut list = XorList::new();
+            for _ in 0..10 {
+                list.push_back(item.clone());
+            }
+            list.pop_back();
+            let mut c = list.cursor_front_mut();
+            c.move_next();
+            c.remove_current();
+            assert_eq!(Rc::strong_count(&item), 9);
+        }
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn drain_and_drain_filter() {
+        let mut list: XorList<_> = (0..10).collect();
+        let removed: Vec<_> = list.drain_filter(|x| *x % 3 == 0).take(2).collect();
+        assert_eq!(removed, vec![0, 3]);
+        assert_eq!(items(&list), vec![1, 2, 4, 5, 7, 8]);
+        assert_eq!(list.back(), Some(&8));
+        assert_eq!(list.drain().next(), Some(1));
+        assert!(list.is_empty());
+    }
+}
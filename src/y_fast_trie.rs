@@ -0,0 +1,435 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    min: u64,
+    max: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    prev: Option<u64>,
+    next: Option<u64>,
+}
+
+/// An x-fast trie over the universe `0..2^bits`. Every prefix of every key is
+/// stored in a map per level, so predecessor queries binary search over the
+/// prefix length with O(log log U) map lookups, at the cost of O(n log U)
+/// space.
+#[derive(Debug, Clone)]
+pub struct XFastTrie {
+    bits: u32,
+    levels: Vec<BTreeMap<u64, Span>>,
+    links: BTreeMap<u64, Link>,
+}
+
+impl XFastTrie {
+    pub fn new(bits: u32) -> Self {
+        assert!((1..=64).contains(&bits), "universe must have 1 to 64 bits");
+        XFastTrie {
+            bits,
+            levels: vec![BTreeMap::new(); bits as usize + 1],
+            links: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        self.links.contains_key(&x)
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.levels[0].get(&0).map(|span| span.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        self.levels[0].get(&0).map(|span| span.max)
+    }
+
+    /// Largest key less than or equal to `x`.
+    pub fn predecessor_or_equal(&self, x: u64) -> Option<u64> {
+        if self.contains(x) {
+            return Some(x);
+        }
+        let (level, span) = self.longest_prefix(x)?;
+        if self.bit(x, level) == 1 {
+            Some(span.max)
+        } else {
+            self.links[&span.min].prev
+        }
+    }
+
+    /// Smallest key strictly greater than `x`.
+    pub fn successor(&self, x: u64) -> Option<u64> {
+        if let Some(link) = self.links.get(&x) {
+            return link.next;
+        }
+        let (level, span) = self.longest_prefix(x)?;
+        if self.bit(x, level) == 1 {
+            self.links[&span.max].next
+        } else {
+            Some(span.min)
+        }
+    }
+
+    pub fn insert(&mut self, x: u64) -> bool {
+        assert!(self.in_universe(x), "key outside of universe");
+        if self.contains(x) {
+            return false;
+        }
+        let prev = self.predecessor_or_equal(x);
+        let next = self.successor(x);
+        if let Some(p) = prev {
+            self.links.get_mut(&p).unwrap().next = Some(x);
+        }
+        if let Some(n) = next {
+            self.links.get_mut(&n).unwrap().prev = Some(x);
+        }
+        self.links.insert(x, Link { prev, next });
+
+        for level in 0..=self.bits {
+            let prefix = self.prefix(x, level);
+            let span = self.levels[level as usize]
+                .entry(prefix)
+                .or_insert(Span { min: x, max: x });
+            span.min = span.min.min(x);
+            span.max = span.max.max(x);
+        }
+        true
+    }
+
+    pub fn remove(&mut self, x: u64) -> bool {
+        let Some(link) = self.links.remove(&x) else {
+            return false;
+        };
+        if let Some(p) = link.prev {
+            self.links.get_mut(&p).unwrap().next = link.next;
+        }
+        if let Some(n) = link.next {
+            self.links.get_mut(&n).unwrap().prev = link.prev;
+        }
+
+        self.levels[self.bits as usize].remove(&x);
+        for level in (0..self.bits).rev() {
+            let prefix = self.prefix(x, level);
+            let children = &self.levels[level as usize + 1];
+            let left = children.get(&(prefix << 1)).copied();
+            let right = children.get(&((prefix << 1) | 1)).copied();
+            let span = match (left, right) {
+                (None, None) => {
+                    self.levels[level as usize].remove(&prefix);
+                    continue;
+                }
+                (Some(l), None) => l,
+                (None, Some(r)) => r,
+                (Some(l), Some(r)) => Span {
+                    min: l.min,
+                    max: r.max,
+                },
+            };
+            self.levels[level as usize].insert(prefix, span);
+        }
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        core::iter::successors(self.min(), move |x| self.links[x].next)
+    }
+
+    fn in_universe(&self, x: u64) -> bool {
+        self.bits == 64 || x >> self.bits == 0
+    }
+
+    fn prefix(&self, x: u64, level: u32) -> u64 {
+        if level == 0 {
+            0
+        } else {
+            x >> (self.bits - level)
+        }
+    }
+
+    /// The bit of `x` that selects the child below a node at `level`.
+    fn bit(&self, x: u64, level: u32) -> u64 {
+        (x >> (self.bits - level - 1)) & 1
+    }
+
+    fn longest_prefix(&self, x: u64) -> Option<(u32, Span)> {
+        let root = *self.levels[0].get(&0)?;
+        let (mut lo, mut hi) = (0, self.bits);
+        let mut best = (0, root);
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            match self.levels[mid as usize].get(&self.prefix(x, mid)) {
+                Some(span) => {
+                    best = (mid, *span);
+                    lo = mid;
+                }
+                None => hi = mid - 1,
+            }
+        }
+        Some(best)
+    }
+}
+
+/// A y-fast trie over the universe `0..2^bits`: keys are kept in balanced
+/// buckets of about `bits` elements, and only one representative per bucket
+/// is stored in an x-fast trie. Predecessor and successor queries take
+/// O(log log U) map lookups, in O(n) space.
+#[derive(Debug, Clone)]
+pub struct YFastTrie {
+    bucket_size: usize,
+    representatives: XFastTrie,
+    buckets: BTreeMap<u64, BTreeSet<u64>>,
+    len: usize,
+}
+
+impl YFastTrie {
+    pub fn new(bits: u32) -> Self {
+        YFastTrie {
+            bucket_size: bits as usize,
+            representatives: XFastTrie::new(bits),
+            buckets: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        match self.representatives.predecessor_or_equal(x) {
+            None => false,
+            Some(rep) => self.buckets[&rep].contains(&x),
+        }
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.representatives.min()
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        let rep = self.representatives.max()?;
+        self.buckets[&rep].last().copied()
+    }
+
+    pub fn insert(&mut self, x: u64) -> bool {
+        assert!(
+            self.representatives.in_universe(x),
+            "key outside of universe"
+        );
+        let rep = match self.representatives.predecessor_or_equal(x) {
+            Some(rep) => rep,
+            None => match self.representatives.min() {
+                None => {
+                    self.representatives.insert(x);
+                    self.buckets.insert(x, BTreeSet::from([x]));
+                    self.len += 1;
+                    return true;
+                }
+                Some(first) => self.rekey(first, x),
+            },
+        };
+
+        if !self.buckets.get_mut(&rep).unwrap().insert(x) {
+            return false;
+        }
+        self.len += 1;
+        if self.buckets[&rep].len() > 2 * self.bucket_size {
+            self.split_bucket(rep);
+        }
+        true
+    }
+
+    pub fn remove(&mut self, x: u64) -> bool {
+        let Some(rep) = self.representatives.predecessor_or_equal(x) else {
+            return false;
+        };
+        let bucket = self.buckets.get_mut(&rep).unwrap();
+        if !bucket.remove(&x) {
+            return false;
+        }
+        self.len -= 1;
+
+        let rep = match bucket.first().copied() {
+            None => {
+                self.buckets.remove(&rep);
+                self.representatives.remove(rep);
+                return true;
+            }
+            Some(first) if first != rep => self.rekey(rep, first),
+            Some(_) => rep,
+        };
+
+        if self.buckets[&rep].len() < self.bucket_size / 2 {
+            self.merge_bucket(rep);
+        }
+        true
+    }
+
+    /// Largest key strictly smaller than `x`.
+    pub fn predecessor(&self, x: u64) -> Option<u64> {
+        let y = x.checked_sub(1)?;
+        let rep = self.representatives.predecessor_or_equal(y)?;
+        self.buckets[&rep].range(..=y).next_back().copied()
+    }
+
+    /// Smallest key strictly greater than `x`.
+    pub fn successor(&self, x: u64) -> Option<u64> {
+        if let Some(rep) = self.representatives.predecessor_or_equal(x) {
+            let next = x.checked_add(1)?;
+            if let Some(&y) = self.buckets[&rep].range(next..).next() {
+                return Some(y);
+            }
+        }
+        self.representatives.successor(x)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.representatives
+            .iter()
+            .flat_map(move |rep| self.buckets[&rep].iter().copied())
+    }
+
+    fn rekey(&mut self, old: u64, new: u64) -> u64 {
+        let bucket = self.buckets.remove(&old).unwrap();
+        self.buckets.insert(new, bucket);
+        self.representatives.remove(old);
+        self.representatives.insert(new);
+        new
+    }
+
+    fn split_bucket(&mut self, rep: u64) {
+        let bucket = self.buckets.get_mut(&rep).unwrap();
+        let median = *bucket.iter().nth(bucket.len() / 2).unwrap();
+        let upper = bucket.split_off(&median);
+        self.representatives.insert(median);
+        self.buckets.insert(median, upper);
+    }
+
+    fn merge_bucket(&mut self, rep: u64) {
+        let (left, right) = match self.representatives.successor(rep) {
+            Some(next) => (rep, next),
+            None if rep == 0 => return,
+            None => match self.representatives.predecessor_or_equal(rep - 1) {
+                Some(prev) => (prev, rep),
+                None => return,
+            },
+        };
+        let mut upper = self.buckets.remove(&right).unwrap();
+        self.representatives.remove(right);
+        let bucket = self.buckets.get_mut(&left).unwrap();
+        bucket.append(&mut upper);
+        if bucket.len() > 2 * self.bucket_size {
+            self.split_bucket(left);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x_fast_predecessor_and_successor() {
+        let mut t = XFastTrie::new(8);
+        for x in [10, 20, 30, 200] {
+            t.insert(x);
+        }
+        assert_eq!(t.predecessor_or_equal(5), None);
+        assert_eq!(t.predecessor_or_equal(10), Some(10));
+        assert_eq!(t.predecessor_or_equal(25), Some(20));
+        assert_eq!(t.predecessor_or_equal(255), Some(200));
+        assert_eq!(t.successor(0), Some(10));
+        assert_eq!(t.successor(30), Some(200));
+        assert_eq!(t.successor(200), None);
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![10, 20, 30, 200]);
+    }
+
+    #[test]
+    fn x_fast_remove() {
+        let mut t = XFastTrie::new(8);
+        for x in [1, 2, 3] {
+            t.insert(x);
+        }
+        assert!(t.remove(2));
+        assert!(!t.remove(2));
+        assert_eq!(t.successor(1), Some(3));
+        assert!(t.remove(1));
+        assert!(t.remove(3));
+        assert!(t.is_empty());
+        assert_eq!(t.min(), None);
+    }
+
+    #[test]
+    fn empty_y_fast_trie() {
+        let t = YFastTrie::new(32);
+        assert!(t.is_empty());
+        assert!(!t.contains(0));
+        assert_eq!(t.predecessor(10), None);
+        assert_eq!(t.successor(10), None);
+    }
+
+    #[test]
+    fn y_fast_basic_operations() {
+        let mut t = YFastTrie::new(16);
+        assert!(t.insert(100));
+        assert!(t.insert(50));
+        assert!(!t.insert(50));
+        assert!(t.contains(100));
+        assert!(!t.contains(75));
+        assert_eq!(t.predecessor(100), Some(50));
+        assert_eq!(t.successor(50), Some(100));
+        assert_eq!(t.min(), Some(50));
+        assert_eq!(t.max(), Some(100));
+        assert!(t.remove(50));
+        assert_eq!(t.min(), Some(100));
+    }
+
+    #[test]
+    fn y_fast_matches_btreeset() {
+        let mut t = YFastTrie::new(16);
+        let mut reference = BTreeSet::new();
+        let mut x = 1u64;
+        for i in 0..5000 {
+            x = (x * 75 + 74) % 65537 % 65536;
+            if i % 4 == 3 {
+                assert_eq!(t.remove(x), reference.remove(&x));
+            } else {
+                assert_eq!(t.insert(x), reference.insert(x));
+            }
+            let q = (x * 31) % 65536;
+            assert_eq!(t.predecessor(q), reference.range(..q).next_back().copied());
+            assert_eq!(t.successor(q), reference.range(q + 1..).next().copied());
+        }
+        assert_eq!(t.len(), reference.len());
+        assert!(t.iter().eq(reference.iter().copied()));
+        for &y in reference.clone().iter() {
+            assert!(t.remove(y));
+        }
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn y_fast_full_universe() {
+        let mut t = YFastTrie::new(64);
+        t.insert(u64::MAX);
+        t.insert(0);
+        assert_eq!(t.successor(0), Some(u64::MAX));
+        assert_eq!(t.successor(u64::MAX), None);
+        assert_eq!(t.predecessor(u64::MAX), Some(0));
+    }
+}